@@ -0,0 +1,69 @@
+//! Compares the deserialization of large embedding results through the library (which uses simd-json when the `simd-json` feature is enabled) against plain serde_json.
+//!
+//! Run with `cargo bench --bench json` and `cargo bench --bench json --features simd-json` to measure the difference.
+
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::{executor::block_on, stream, TryStreamExt};
+use libopenai::{embeddings::EmbeddingResult, error::Error, OpenAiStream};
+use std::hint::black_box;
+
+/// Dimensions of the embeddings of `text-embedding-3-small`
+const DIMENSIONS: usize = 1536;
+
+/// Builds an embedding result with `inputs` embeddings
+fn body(inputs: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(inputs);
+    for i in 0..inputs {
+        let embedding = (0..DIMENSIONS)
+            .map(|j| format!("{:.9}", ((i * DIMENSIONS + j) as f64).sin() / 10.0))
+            .collect::<Vec<_>>()
+            .join(",");
+        data.push(format!(
+            "{{\"object\":\"embedding\",\"index\":{i},\"embedding\":[{embedding}]}}"
+        ));
+    }
+
+    return format!(
+        "{{\"object\":\"list\",\"data\":[{}],\"model\":\"text-embedding-3-small\",\"usage\":{{\"prompt_tokens\":{inputs},\"total_tokens\":{inputs}}}}}",
+        data.join(",")
+    )
+    .into_bytes();
+}
+
+/// Deserializes `body` with the library's JSON parser, by sending it as the single event of a stream
+fn parse_library(body: &[u8]) -> EmbeddingResult {
+    let mut event = Vec::with_capacity(body.len() + 8);
+    event.extend_from_slice(b"data: ");
+    event.extend_from_slice(body);
+    event.extend_from_slice(b"\n\n");
+
+    let mut stream =
+        OpenAiStream::<EmbeddingResult>::from_bytes_stream(stream::iter([Ok::<_, Error>(
+            Bytes::from(event),
+        )]));
+    return block_on(stream.try_next())
+        .expect("embedding result should be parsed")
+        .expect("stream should have an event");
+}
+
+fn embedding_results(c: &mut Criterion) {
+    let mut group = c.benchmark_group("embedding_result");
+    group.sample_size(20);
+
+    for inputs in [1, 16, 128] {
+        let body = body(inputs);
+        group.throughput(Throughput::Bytes(body.len() as u64));
+
+        group.bench_with_input(BenchmarkId::new("serde_json", inputs), &body, |b, body| {
+            b.iter(|| black_box(serde_json::from_slice::<EmbeddingResult>(body).unwrap()))
+        });
+        group.bench_with_input(BenchmarkId::new("libopenai", inputs), &body, |b, body| {
+            b.iter(|| black_box(parse_library(body)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, embedding_results);
+criterion_main!(benches);
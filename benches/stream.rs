@@ -0,0 +1,63 @@
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use futures::{executor::block_on, stream, TryStreamExt};
+use libopenai::{completion::Completion, error::Error, OpenAiStream};
+use std::hint::black_box;
+
+/// Builds a completion stream body with `events` chunks
+fn body(events: usize) -> Vec<u8> {
+    let mut body = Vec::new();
+    for i in 0..events {
+        body.extend_from_slice(
+            format!(
+                "data: {{\"id\":\"cmpl-bench\",\"object\":\"text_completion\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo-instruct\",\"choices\":[{{\"text\":\"token {i}\",\"index\":0,\"logprobs\":null,\"finish_reason\":null}}]}}\n\n"
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(b"data: [DONE]\n\n");
+    return body;
+}
+
+/// Parses `body`, received in chunks of `chunk_size` bytes
+fn parse(body: &[u8], chunk_size: usize) -> usize {
+    let chunks = body
+        .chunks(chunk_size)
+        .map(|chunk| Ok::<_, Error>(Bytes::copy_from_slice(chunk)))
+        .collect::<Vec<_>>();
+
+    let stream = OpenAiStream::<Completion>::from_bytes_stream(stream::iter(chunks));
+    return block_on(stream.try_fold(0, |count, completion| async move {
+        black_box(completion);
+        Ok(count + 1)
+    }))
+    .expect("stream should be parsed");
+}
+
+fn chunk_sizes(c: &mut Criterion) {
+    let body = body(100);
+    let mut group = c.benchmark_group("stream_chunk_size");
+    group.throughput(Throughput::Bytes(body.len() as u64));
+
+    // Events split across many small chunks, and many events per chunk
+    for chunk_size in [16, 256, 4096] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(chunk_size),
+            &chunk_size,
+            |b, &chunk_size| b.iter(|| parse(&body, chunk_size)),
+        );
+    }
+    group.finish();
+}
+
+fn large_stream(c: &mut Criterion) {
+    let body = body(10_000);
+    let mut group = c.benchmark_group("stream_large");
+    group.throughput(Throughput::Bytes(body.len() as u64));
+    group.sample_size(20);
+    group.bench_function("10000_events", |b| b.iter(|| parse(&body, 1024)));
+    group.finish();
+}
+
+criterion_group!(benches, chunk_sizes, large_stream);
+criterion_main!(benches);
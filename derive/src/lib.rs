@@ -0,0 +1,286 @@
+//! Derive macros for [libopenai](https://docs.rs/libopenai), re-exported by its `derive` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    meta::ParseNestedMeta, parse_macro_input, token, Attribute, Data, DeriveInput, Error, Expr,
+    ExprLit, Fields, Lit, LitStr, Meta, Result, Token,
+};
+
+/// Implements `ToolSchema` for a struct with named fields, or an enum with unit variants.
+///
+/// Doc comments of the fields are used as the descriptions of their properties.
+#[proc_macro_derive(ToolSchema, attributes(tool))]
+pub fn derive_tool_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    return tool_schema(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into();
+}
+
+/// Implements `ToolSchema` and `ToolArgs` for a struct with named fields.
+///
+/// The function is named after the struct in snake case, and described by its doc comment, unless overriden with `#[tool(name = "...", description = "...")]`.
+#[proc_macro_derive(ToolArgs, attributes(tool))]
+pub fn derive_tool_args(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    return tool_schema(&input)
+        .and_then(|schema| {
+            let args = tool_args(&input)?;
+            return Ok(quote! { #schema #args });
+        })
+        .unwrap_or_else(Error::into_compile_error)
+        .into();
+}
+
+fn tool_schema(input: &DeriveInput) -> Result<TokenStream2> {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let rename_all = serde_rename_all(&input.attrs)?;
+
+    let body = match input.data {
+        Data::Struct(ref data) => {
+            let Fields::Named(ref fields) = data.fields else {
+                return Err(Error::new_spanned(
+                    &data.fields,
+                    "only structs with named fields can be derived",
+                ));
+            };
+
+            let mut properties = Vec::with_capacity(fields.named.len());
+            for field in fields.named.iter() {
+                let serde = SerdeAttrs::parse(&field.attrs)?;
+                if serde.skip {
+                    continue;
+                }
+                let name = match serde.rename {
+                    Some(name) => name,
+                    None => {
+                        let ident = field.ident.as_ref().unwrap().to_string();
+                        let ident = ident.trim_start_matches("r#");
+                        rename(&split_snake(ident), rename_all.as_deref())?
+                    }
+                };
+
+                let ty = &field.ty;
+                let description = option(docs(&field.attrs));
+                let default = serde.default;
+                properties.push(quote! {
+                    (
+                        #name,
+                        __private::property::<#ty>(#description),
+                        !#default && !<#ty as ToolSchema>::OPTIONAL,
+                    )
+                });
+            }
+            quote! { __private::object(::std::vec![#(#properties),*]) }
+        }
+
+        Data::Enum(ref data) => {
+            let mut variants = Vec::with_capacity(data.variants.len());
+            for variant in data.variants.iter() {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(Error::new_spanned(
+                        variant,
+                        "only enums with unit variants can be derived",
+                    ));
+                }
+                variants.push(match SerdeAttrs::parse(&variant.attrs)?.rename {
+                    Some(name) => name,
+                    None => rename(
+                        &split_pascal(&variant.ident.to_string()),
+                        Some(rename_all.as_deref().unwrap_or("PascalCase")),
+                    )?,
+                });
+            }
+            quote! { __private::string_enum(&[#(#variants),*]) }
+        }
+
+        Data::Union(_) => return Err(Error::new_spanned(input, "unions can't be derived")),
+    };
+
+    return Ok(quote! {
+        const _: () = {
+            use ::libopenai::chat::tool::schema::{__private, ToolSchema};
+
+            impl #impl_generics ToolSchema for #ident #ty_generics #where_clause {
+                fn schema() -> __private::Value {
+                    #body
+                }
+            }
+        };
+    });
+}
+
+fn tool_args(input: &DeriveInput) -> Result<TokenStream2> {
+    if !matches!(input.data, Data::Struct(_)) {
+        return Err(Error::new_spanned(
+            input,
+            "only structs can be the arguments of a tool",
+        ));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut name = rename(&split_pascal(&ident.to_string()), Some("snake_case"))?;
+    let mut description = docs(&input.attrs);
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("tool"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("description") {
+                description = Some(meta.value()?.parse::<LitStr>()?.value());
+            } else {
+                return Err(meta.error("expected `name` or `description`"));
+            }
+            return Ok(());
+        })?;
+    }
+
+    let description = option(description);
+    return Ok(quote! {
+        impl #impl_generics ::libopenai::chat::tool::schema::ToolArgs for #ident #ty_generics #where_clause {
+            const NAME: &'static str = #name;
+            const DESCRIPTION: ::std::option::Option<&'static str> = #description;
+        }
+    });
+}
+
+/// Serde attributes of a field or variant that change its schema
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<String>,
+    default: bool,
+    skip: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut result = Self::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    result.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    result.default = true;
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                    result.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    return Err(meta.error("flattened fields aren't supported"));
+                }
+                // Other attributes (i.e. `skip_serializing_if`) don't change the schema
+                return skip_meta(&meta);
+            })?;
+        }
+        return Ok(result);
+    }
+}
+
+/// Returns the `rename_all` case of a container's serde attributes
+fn serde_rename_all(attrs: &[Attribute]) -> Result<Option<String>> {
+    let mut rename_all = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename_all") {
+                rename_all = Some(meta.value()?.parse::<LitStr>()?.value());
+                return Ok(());
+            }
+            return skip_meta(&meta);
+        })?;
+    }
+    return Ok(rename_all);
+}
+
+/// Skips the value of an attribute that doesn't change the schema, i.e. `= "..."` or `(...)`
+fn skip_meta(meta: &ParseNestedMeta) -> Result<()> {
+    if meta.input.peek(Token![=]) {
+        meta.value()?.parse::<Expr>()?;
+    } else if meta.input.peek(token::Paren) {
+        meta.parse_nested_meta(|meta| skip_meta(&meta))?;
+    }
+    return Ok(());
+}
+
+/// Joins the doc comments of an item, if it has any
+fn docs(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter_map(|attr| match attr.meta {
+            Meta::NameValue(ref meta) if meta.path.is_ident("doc") => match meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(ref doc),
+                    ..
+                }) => Some(doc.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let docs = lines.join("\n").trim().to_string();
+    return (!docs.is_empty()).then_some(docs);
+}
+
+fn option(value: Option<String>) -> TokenStream2 {
+    return match value {
+        Some(value) => quote! { ::std::option::Option::Some(#value) },
+        None => quote! { ::std::option::Option::None },
+    };
+}
+
+fn split_snake(ident: &str) -> Vec<String> {
+    return ident
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+}
+
+fn split_pascal(ident: &str) -> Vec<String> {
+    let mut words = Vec::<String>::new();
+    for c in ident.chars() {
+        match words.last_mut() {
+            Some(word) if !c.is_uppercase() => word.push(c),
+            _ => words.push(c.to_lowercase().collect()),
+        }
+    }
+    return words;
+}
+
+/// Joins lowercase words with one of serde's `rename_all` cases (snake case if `None`)
+fn rename(words: &[String], case: Option<&str>) -> Result<String> {
+    let capitalize = |word: &String| {
+        let mut chars = word.chars();
+        return chars
+            .next()
+            .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default();
+    };
+
+    return Ok(match case.unwrap_or("snake_case") {
+        "lowercase" => words.concat(),
+        "UPPERCASE" => words.concat().to_uppercase(),
+        "PascalCase" => words.iter().map(capitalize).collect(),
+        "camelCase" => {
+            let mut words = words.iter();
+            let first = words.next().cloned().unwrap_or_default();
+            first + &words.map(capitalize).collect::<String>()
+        }
+        "snake_case" => words.join("_"),
+        "SCREAMING_SNAKE_CASE" => words.join("_").to_uppercase(),
+        "kebab-case" => words.join("-"),
+        "SCREAMING-KEBAB-CASE" => words.join("-").to_uppercase(),
+        other => {
+            return Err(Error::new(
+                Span::call_site(),
+                format!("unknown `rename_all` case '{other}'"),
+            ))
+        }
+    });
+}
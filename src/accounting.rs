@@ -0,0 +1,530 @@
+use crate::{
+    common::{cache_hit_rate, Usage},
+    error::{BudgetExceeded, Result},
+    model::info::Pricing,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    ops::{Add, AddAssign},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Token usage and request counts accumulated by a [`Client`](crate::Client), per endpoint and model
+#[derive(Debug, Clone, Default)]
+pub struct UsageReport {
+    entries: HashMap<UsageKey, UsageEntry>,
+}
+
+/// Endpoint and model a [`UsageEntry`] was accumulated for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct UsageKey {
+    /// Relative URL of the endpoint (e.g. `/v1/chat/completions`)
+    pub endpoint: String,
+    /// Model requested to the endpoint
+    pub model: String,
+}
+
+/// Accumulated token usage and request count
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct UsageEntry {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    /// Prompt tokens read from the prompt cache
+    pub cached_tokens: u64,
+}
+
+/// Prompt cache statistics of a conversation, accumulated from the [`Usage`] of each of its requests.
+///
+/// OpenAI caches the prompts of at least 1024 tokens automatically, and reuses the longest prefix a new prompt shares with a cached one.
+/// A low hit rate usually means the start of the prompt changes between requests (see [`order_for_prompt_cache`](crate::prompt::order_for_prompt_cache)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PromptCacheStats {
+    requests: u64,
+    prompt_tokens: u64,
+    cached_tokens: u64,
+}
+
+/// Limits on the requests a [`Client`](crate::Client) is allowed to send.
+///
+/// Requests that would exceed the budget fail with [`BudgetExceeded`] before the API is called.
+///
+/// Only the text endpoints (chat, completions, edits, embeddings and responses) are checked against it. Image and audio requests are neither limited nor counted.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Budget {
+    max_tokens_per_request: Option<u64>,
+    max_requests_per_minute: Option<u32>,
+    max_cost_per_hour: Option<f64>,
+}
+
+/// Rate limits of a model, which a [`Client`](crate::Client) enforces by waiting before sending the requests that would exceed them.
+///
+/// Like OpenAI's own limits, they're token buckets that refill continuously over a minute. The tokens of a request are its `max_tokens` plus, with the `tokens` feature, its estimated prompt tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RateLimit {
+    tokens_per_minute: Option<u64>,
+    requests_per_minute: Option<u32>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct UsageTracker {
+    report: Mutex<UsageReport>,
+}
+
+impl UsageTracker {
+    /// Counts a request sent to the endpoint
+    pub fn record_request(&self, endpoint: &str, model: &str) {
+        self.with_entry(endpoint, model, |entry| entry.requests += 1);
+    }
+
+    /// Adds the usage of a request to the endpoint, without counting the request itself
+    pub fn record_usage(&self, endpoint: &str, model: &str, usage: &Usage) {
+        self.with_entry(endpoint, model, |entry| {
+            entry.prompt_tokens += usage.prompt_tokens;
+            entry.completion_tokens += usage.completion_tokens;
+            entry.total_tokens += usage.total_tokens;
+            entry.cached_tokens += usage.cached_tokens();
+        });
+    }
+
+    #[inline]
+    pub fn report(&self) -> UsageReport {
+        return self.lock().clone();
+    }
+
+    #[inline]
+    pub fn reset(&self) -> UsageReport {
+        return core::mem::take(&mut *self.lock());
+    }
+
+    fn with_entry(&self, endpoint: &str, model: &str, f: impl FnOnce(&mut UsageEntry)) {
+        let key = UsageKey {
+            endpoint: endpoint.to_string(),
+            model: model.to_string(),
+        };
+        f(self.lock().entries.entry(key).or_default());
+    }
+
+    #[inline]
+    fn lock(&self) -> std::sync::MutexGuard<'_, UsageReport> {
+        return self.report.lock().unwrap_or_else(|e| e.into_inner());
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct BudgetTracker {
+    budget: Budget,
+    window: Mutex<BudgetWindow>,
+}
+
+#[derive(Debug, Default)]
+struct BudgetWindow {
+    requests: VecDeque<Instant>,
+    costs: VecDeque<(Instant, f64)>,
+}
+
+impl Budget {
+    /// Creates a new budget without any limits
+    #[inline]
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Maximum number of tokens a single request may use, counting the requested `max_tokens` and (with the `tokens` feature) the estimated prompt tokens
+    pub fn max_tokens_per_request(mut self, max_tokens: u64) -> Self {
+        self.max_tokens_per_request = Some(max_tokens);
+        self
+    }
+
+    /// Maximum number of requests that may be sent within any one-minute window
+    pub fn max_requests_per_minute(mut self, max_requests: u32) -> Self {
+        self.max_requests_per_minute = Some(max_requests);
+        self
+    }
+
+    /// Maximum estimated cost, in US dollars, that may be spent within any one-hour window.
+    ///
+    /// Spending is estimated from the usage reported by the API and the models' [`Pricing`].
+    /// The cost of a request is only known once it's answered, so requests are allowed while the spending is under the limit, and the last one may go over it.
+    pub fn max_cost_per_hour(mut self, max_cost: f64) -> Self {
+        self.max_cost_per_hour = Some(max_cost);
+        self
+    }
+}
+
+impl RateLimit {
+    /// Creates a new rate limit without any limits
+    #[inline]
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Maximum number of tokens that may be requested per minute
+    pub fn tokens_per_minute(mut self, tokens: u64) -> Self {
+        self.tokens_per_minute = Some(tokens);
+        self
+    }
+
+    /// Maximum number of requests that may be sent per minute
+    pub fn requests_per_minute(mut self, requests: u32) -> Self {
+        self.requests_per_minute = Some(requests);
+        self
+    }
+}
+
+/// Token buckets of the [`RateLimit`]s of each model, shared between the clones of a client
+#[derive(Debug, Default)]
+pub(crate) struct Throttle {
+    buckets: Mutex<HashMap<String, RateBuckets>>,
+}
+
+#[derive(Debug)]
+struct RateBuckets {
+    tokens: Option<TokenBucket>,
+    requests: Option<TokenBucket>,
+}
+
+/// A bucket that holds up to a minute's worth of capacity, refilled continuously
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    updated: Instant,
+}
+
+impl Throttle {
+    /// Sets the rate limit of `model`. If it already had one, what's been taken from its buckets is kept.
+    pub fn set_limit(&self, model: String, limit: RateLimit) {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let buckets = buckets.entry(model).or_insert(RateBuckets {
+            tokens: None,
+            requests: None,
+        });
+        TokenBucket::resize(
+            &mut buckets.tokens,
+            limit.tokens_per_minute.map(|x| x as f64),
+        );
+        TokenBucket::resize(
+            &mut buckets.requests,
+            limit.requests_per_minute.map(|x| x as f64),
+        );
+    }
+
+    /// Waits until a request of `tokens` to `model` is allowed by its rate limit, if any, and takes it from the buckets
+    pub async fn acquire(&self, model: &str, tokens: u64) {
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+                let buckets = match buckets.get_mut(model) {
+                    Some(buckets) => buckets,
+                    None => return,
+                };
+
+                let now = Instant::now();
+                let wait = [
+                    (&mut buckets.tokens, tokens as f64),
+                    (&mut buckets.requests, 1.0),
+                ]
+                .into_iter()
+                .filter_map(|(bucket, amount)| Some(bucket.as_mut()?.wait(now, amount)))
+                .max()
+                .unwrap_or_default();
+
+                if wait.is_zero() {
+                    for (bucket, amount) in [
+                        (&mut buckets.tokens, tokens as f64),
+                        (&mut buckets.requests, 1.0),
+                    ] {
+                        if let Some(bucket) = bucket {
+                            bucket.take(amount);
+                        }
+                    }
+                    return;
+                }
+                wait
+            };
+
+            crate::rt::sleep(wait).await;
+        }
+    }
+}
+
+impl TokenBucket {
+    const REFILL_PERIOD: Duration = Duration::from_secs(60);
+
+    #[inline]
+    fn new(capacity: f64) -> Self {
+        return Self {
+            capacity,
+            available: capacity,
+            updated: Instant::now(),
+        };
+    }
+
+    /// Refills the bucket, returning how long to wait until it holds `amount` (or all its capacity, if it's larger)
+    fn wait(&mut self, now: Instant, amount: f64) -> Duration {
+        let rate = self.capacity / Self::REFILL_PERIOD.as_secs_f64();
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * rate).min(self.capacity);
+        self.updated = now;
+
+        let missing = amount.min(self.capacity) - self.available;
+        return match missing > 0.0 && rate > 0.0 {
+            true => Duration::from_secs_f64(missing / rate),
+            false => Duration::ZERO,
+        };
+    }
+
+    /// Changes the capacity of `bucket`, keeping what's been taken from it
+    fn resize(bucket: &mut Option<Self>, capacity: Option<f64>) {
+        *bucket = match (bucket.take(), capacity) {
+            (Some(mut bucket), Some(capacity)) => {
+                bucket.available += capacity - bucket.capacity;
+                bucket.capacity = capacity;
+                Some(bucket)
+            }
+            (None, Some(capacity)) => Some(Self::new(capacity)),
+            (_, None) => None,
+        };
+    }
+
+    #[inline]
+    fn take(&mut self, amount: f64) {
+        self.available -= amount.min(self.capacity);
+    }
+}
+
+impl BudgetTracker {
+    const MINUTE: Duration = Duration::from_secs(60);
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    #[inline]
+    pub fn new(budget: Budget) -> Self {
+        return Self {
+            budget,
+            window: Mutex::default(),
+        };
+    }
+
+    /// Checks a request against the budget, counting it if it's allowed
+    pub fn check(&self, tokens: Option<u64>) -> Result<(), BudgetExceeded> {
+        if let (Some(limit), Some(requested)) = (self.budget.max_tokens_per_request, tokens) {
+            if requested > limit {
+                return Err(BudgetExceeded::TokensPerRequest { requested, limit });
+            }
+        }
+
+        let now = Instant::now();
+        let mut window = self.lock();
+        window.evict(now);
+
+        if let Some(limit) = self.budget.max_requests_per_minute {
+            if window.requests.len() >= limit as usize {
+                return Err(BudgetExceeded::RequestsPerMinute { limit });
+            }
+        }
+
+        if let Some(limit) = self.budget.max_cost_per_hour {
+            let spent = window.costs.iter().map(|(_, cost)| cost).sum::<f64>();
+            if spent >= limit {
+                return Err(BudgetExceeded::CostPerHour { spent, limit });
+            }
+        }
+
+        window.requests.push_back(now);
+        return Ok(());
+    }
+
+    /// Adds the estimated cost of a request's usage to the spending window
+    pub fn record_usage(&self, model: &str, usage: &Usage) {
+        if self.budget.max_cost_per_hour.is_none() {
+            return;
+        }
+
+        if let Some(pricing) = Pricing::of(model) {
+            let cost = pricing.cost(usage.prompt_tokens, usage.completion_tokens);
+            self.lock().costs.push_back((Instant::now(), cost));
+        }
+    }
+
+    #[inline]
+    fn lock(&self) -> std::sync::MutexGuard<'_, BudgetWindow> {
+        return self.window.lock().unwrap_or_else(|e| e.into_inner());
+    }
+}
+
+impl BudgetWindow {
+    fn evict(&mut self, now: Instant) {
+        while matches!(self.requests.front(), Some(t) if now.duration_since(*t) >= BudgetTracker::MINUTE)
+        {
+            self.requests.pop_front();
+        }
+        while matches!(self.costs.front(), Some((t, _)) if now.duration_since(*t) >= BudgetTracker::HOUR)
+        {
+            self.costs.pop_front();
+        }
+    }
+}
+
+impl UsageReport {
+    /// Returns an iterator over the accumulated entries
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&UsageKey, &UsageEntry)> {
+        return self.entries.iter();
+    }
+
+    /// Returns the usage accumulated for the specified endpoint and model
+    pub fn get(&self, endpoint: impl AsRef<str>, model: impl AsRef<str>) -> Option<&UsageEntry> {
+        let (endpoint, model) = (endpoint.as_ref(), model.as_ref());
+        return self
+            .entries
+            .iter()
+            .find(|(key, _)| key.endpoint == endpoint && key.model == model)
+            .map(|(_, entry)| entry);
+    }
+
+    /// Returns the usage accumulated across every endpoint and model
+    #[inline]
+    pub fn total(&self) -> UsageEntry {
+        return self
+            .entries
+            .values()
+            .copied()
+            .fold(UsageEntry::default(), Add::add);
+    }
+
+    /// Returns the usage accumulated for each model, across every endpoint
+    pub fn by_model(&self) -> HashMap<&str, UsageEntry> {
+        let mut result = HashMap::<&str, UsageEntry>::new();
+        for (key, entry) in self.entries.iter() {
+            *result.entry(&key.model).or_default() += *entry;
+        }
+        return result;
+    }
+
+    /// Returns the usage accumulated for each endpoint, across every model
+    pub fn by_endpoint(&self) -> HashMap<&str, UsageEntry> {
+        let mut result = HashMap::<&str, UsageEntry>::new();
+        for (key, entry) in self.entries.iter() {
+            *result.entry(&key.endpoint).or_default() += *entry;
+        }
+        return result;
+    }
+
+    /// Returns the estimated cost of the accumulated usage, in US dollars.
+    ///
+    /// Usage of models whose [`Pricing`] isn't known is not included.
+    pub fn estimated_cost(&self) -> f64 {
+        return self
+            .entries
+            .iter()
+            .filter_map(|(key, entry)| entry.cost(&key.model))
+            .sum();
+    }
+
+    /// Returns the estimated cost of the usage accumulated for each model, in US dollars, across every endpoint.
+    ///
+    /// Models whose [`Pricing`] isn't known are not included.
+    pub fn cost_by_model(&self) -> HashMap<&str, f64> {
+        return self
+            .by_model()
+            .into_iter()
+            .filter_map(|(model, entry)| Some((model, entry.cost(model)?)))
+            .collect();
+    }
+
+    /// Returns `true` if no usage has been accumulated
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        return self.entries.is_empty();
+    }
+}
+
+impl UsageEntry {
+    /// Returns the cost of the accumulated tokens when sent to `model`, in US dollars, or `None` if the model's [`Pricing`] isn't known
+    #[inline]
+    pub fn cost(&self, model: impl AsRef<str>) -> Option<f64> {
+        return Pricing::of(model)
+            .map(|pricing| pricing.cost(self.prompt_tokens, self.completion_tokens));
+    }
+
+    /// Returns the fraction of the prompt tokens read from the prompt cache, between 0 and 1
+    #[inline]
+    pub fn cache_hit_rate(&self) -> f64 {
+        return cache_hit_rate(self.cached_tokens, self.prompt_tokens);
+    }
+}
+
+impl PromptCacheStats {
+    #[inline]
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Adds the usage of a request of the conversation
+    #[inline]
+    pub fn record(&mut self, usage: &Usage) {
+        self.requests += 1;
+        self.prompt_tokens += usage.prompt_tokens;
+        self.cached_tokens += usage.cached_tokens();
+    }
+
+    /// Number of requests recorded
+    #[inline]
+    pub fn requests(&self) -> u64 {
+        return self.requests;
+    }
+
+    /// Prompt tokens sent across every request
+    #[inline]
+    pub fn prompt_tokens(&self) -> u64 {
+        return self.prompt_tokens;
+    }
+
+    /// Prompt tokens read from the prompt cache across every request
+    #[inline]
+    pub fn cached_tokens(&self) -> u64 {
+        return self.cached_tokens;
+    }
+
+    /// Returns the fraction of the prompt tokens read from the prompt cache, between 0 and 1
+    #[inline]
+    pub fn hit_rate(&self) -> f64 {
+        return cache_hit_rate(self.cached_tokens, self.prompt_tokens);
+    }
+}
+
+impl Add for UsageEntry {
+    type Output = Self;
+
+    #[inline]
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for UsageEntry {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        self.requests += rhs.requests;
+        self.prompt_tokens += rhs.prompt_tokens;
+        self.completion_tokens += rhs.completion_tokens;
+        self.total_tokens += rhs.total_tokens;
+        self.cached_tokens += rhs.cached_tokens;
+    }
+}
+
+impl<'a> IntoIterator for &'a UsageReport {
+    type Item = (&'a UsageKey, &'a UsageEntry);
+    type IntoIter = std::collections::hash_map::Iter<'a, UsageKey, UsageEntry>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
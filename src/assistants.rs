@@ -0,0 +1,185 @@
+use self::{
+    message::{thread_messages, ThreadMessage},
+    run::{Run, RunStatus, ToolOutput},
+    thread::Thread,
+};
+use crate::{
+    chat::Role,
+    error::{Error, Result},
+    Client, Str,
+};
+use futures::future::{try_join_all, BoxFuture};
+use std::{collections::HashMap, fmt::Debug, future::Future, sync::Arc, time::Duration};
+
+/// Messages posted to threads, by users or assistants.
+pub mod message;
+/// Executions of an assistant on a thread.
+pub mod run;
+/// Inspect the steps an assistant took while executing a run.
+pub mod step;
+/// Conversation sessions between an assistant and a user.
+pub mod thread;
+
+/// Handler invoked with the (JSON-encoded) arguments of a tool call, returning the tool's output
+pub type ToolHandler = Arc<dyn Fn(String) -> BoxFuture<'static, Result<String>> + Send + Sync>;
+
+/// Asks a question to an assistant, handling threads, runs and tool calls.
+#[derive(Clone)]
+pub struct AskBuilder<'a> {
+    assistant_id: Str<'a>,
+    thread_id: Option<Str<'a>>,
+    handlers: HashMap<String, ToolHandler>,
+    poll_interval: Duration,
+}
+
+/// Answer of an assistant, returned by [`AskBuilder`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Answer {
+    /// Thread the conversation took place in. It can be reused to ask follow-up questions.
+    pub thread_id: String,
+    /// The completed run
+    pub run: Run,
+    /// Messages posted by the assistant during the run, in chronological order
+    pub messages: Vec<ThreadMessage>,
+}
+
+/// Asks a question to an assistant, handling threads, runs and tool calls.
+#[inline]
+pub fn ask<'a>(assistant_id: impl Into<Str<'a>>) -> AskBuilder<'a> {
+    return AskBuilder::new(assistant_id);
+}
+
+impl<'a> AskBuilder<'a> {
+    /// Creates a new ask builder for the specified assistant
+    #[inline]
+    pub fn new(assistant_id: impl Into<Str<'a>>) -> Self {
+        return Self {
+            assistant_id: assistant_id.into(),
+            thread_id: None,
+            handlers: HashMap::new(),
+            poll_interval: Duration::from_millis(500),
+        };
+    }
+
+    /// Posts the question to an existing thread, instead of creating a new one.
+    #[inline]
+    pub fn thread(mut self, thread_id: impl Into<Str<'a>>) -> Self {
+        self.thread_id = Some(thread_id.into());
+        self
+    }
+
+    /// Registers the handler for the function tool named `name`.
+    ///
+    /// The handler receives the JSON-encoded arguments of the call, and returns the output to be sent back to the assistant.
+    pub fn tool<F, Fut>(mut self, name: impl Into<String>, handler: F) -> Self
+    where
+        F: 'static + Send + Sync + Fn(String) -> Fut,
+        Fut: 'static + Send + Future<Output = Result<String>>,
+    {
+        self.handlers
+            .insert(name.into(), Arc::new(move |args| Box::pin(handler(args))));
+        self
+    }
+
+    /// Interval between checks of the run's status. Defaults to 500 milliseconds.
+    #[inline]
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Posts `content` as a user message and waits for the assistant to answer, invoking the registered tool handlers as requested.
+    pub async fn send(
+        self,
+        content: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Answer> {
+        let client = client.as_ref();
+
+        let thread_id = match self.thread_id {
+            Some(thread_id) => thread_id.into_owned(),
+            None => Thread::new(client).await?.id,
+        };
+
+        ThreadMessage::new(&thread_id, content, client).await?;
+        let mut run = Run::new(&thread_id, &self.assistant_id, client).await?;
+
+        loop {
+            match run.status {
+                RunStatus::Completed => break,
+                RunStatus::RequiresAction => {
+                    let outputs = try_join_all(run.pending_tool_calls().iter().map(|call| {
+                        let handler = self.handlers.get(&call.function.name).cloned();
+                        let name = call.function.name.clone();
+                        let arguments = call.function.arguments.clone();
+                        let id = call.id.clone();
+
+                        async move {
+                            return match handler {
+                                Some(handler) => Ok(ToolOutput::new(id, handler(arguments).await?)),
+                                None => Err(Error::msg(format!(
+                                    "No handler registered for tool '{name}'"
+                                ))),
+                            };
+                        }
+                    }))
+                    .await;
+
+                    run = match outputs {
+                        Ok(outputs) => run.submit_tool_outputs(outputs, client).await?,
+                        Err(e) => {
+                            let _ = run.cancel(client).await;
+                            return Err(e);
+                        }
+                    };
+                }
+                status if run.is_finished() => {
+                    return Err(match run.last_error {
+                        Some(e) => Error::msg(format!("Run ended as {status:?}: {}", e.message)),
+                        None => Error::msg(format!("Run ended as {status:?}")),
+                    });
+                }
+                _ => {
+                    crate::rt::sleep(self.poll_interval).await;
+                    run = run.refresh(client).await?;
+                }
+            }
+        }
+
+        let messages = thread_messages(&thread_id, Some(&run.id), client)
+            .await?
+            .into_iter()
+            .filter(|x| x.role == Role::Assistant)
+            .collect();
+
+        return Ok(Answer {
+            thread_id,
+            run,
+            messages,
+        });
+    }
+}
+
+impl Answer {
+    /// Returns the text of the assistant's answer
+    pub fn text(&self) -> String {
+        return self
+            .messages
+            .iter()
+            .map(ThreadMessage::text)
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+}
+
+impl Debug for AskBuilder<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AskBuilder")
+            .field("assistant_id", &self.assistant_id)
+            .field("thread_id", &self.thread_id)
+            .field("tools", &self.handlers.keys().collect::<Vec<_>>())
+            .field("poll_interval", &self.poll_interval)
+            .finish()
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    chat::Role,
+    common::{ListParams, Order, Paginator},
+    endpoint::Endpoint,
+    error::{FallibleResponse, Result},
+    logging::{RequestBuilderExt, ResponseExt},
+    Client, Str,
+};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A message within a [`Thread`](super::thread::Thread).
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ThreadMessage {
+    pub id: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    pub thread_id: String,
+    pub role: Role,
+    pub content: Vec<MessageContent>,
+    #[serde(default)]
+    pub assistant_id: Option<String>,
+    #[serde(default)]
+    pub run_id: Option<String>,
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Content of a [`ThreadMessage`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum MessageContent {
+    /// Text created by the user or the assistant
+    Text { text: TextContent },
+    /// An image file in the content of the message
+    ImageFile { image_file: ImageFileContent },
+    /// The assistant refused to answer
+    Refusal { refusal: String },
+    /// Content type not supported by the library
+    #[serde(other)]
+    Unknown,
+}
+
+/// Text content of a [`ThreadMessage`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct TextContent {
+    pub value: String,
+    #[serde(default)]
+    pub annotations: Vec<serde_json::Value>,
+}
+
+/// Image file content of a [`ThreadMessage`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ImageFileContent {
+    pub file_id: String,
+}
+
+impl ThreadMessage {
+    /// Creates a user message on the specified thread.
+    pub async fn new(
+        thread_id: impl AsRef<str>,
+        content: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        #[derive(Debug, Serialize)]
+        struct Body<'a> {
+            role: Role,
+            content: Str<'a>,
+        }
+
+        let message = client
+            .as_ref()
+            .post(client.as_ref().url(
+                Endpoint::Threads,
+                format!("/{}/messages", thread_id.as_ref()),
+            ))
+            .header("OpenAI-Beta", "assistants=v2")
+            .json_body(&Body {
+                role: Role::User,
+                content: Str::Borrowed(content.as_ref()),
+            })
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(message);
+    }
+
+    /// Retrieves a message.
+    pub async fn retreive(
+        thread_id: impl AsRef<str>,
+        message_id: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        let message = client
+            .as_ref()
+            .get(client.as_ref().url(
+                Endpoint::Threads,
+                format!("/{}/messages/{}", thread_id.as_ref(), message_id.as_ref()),
+            ))
+            .header("OpenAI-Beta", "assistants=v2")
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(message);
+    }
+}
+
+impl ThreadMessage {
+    /// Returns the text of the message, joining all its text contents
+    pub fn text(&self) -> String {
+        let mut result = String::new();
+        for content in self.content.iter() {
+            if let MessageContent::Text { text } = content {
+                if !result.is_empty() {
+                    result.push('\n');
+                }
+                result.push_str(&text.value);
+            }
+        }
+        return result;
+    }
+}
+
+/// Returns a list of messages for a given thread, in chronological order.
+///
+/// If `run_id` is specified, only the messages generated by that run are returned.
+///
+/// Every page of the thread is fetched, which may take many requests for long threads. Use [`list_thread_messages`] to stop early.
+pub async fn thread_messages(
+    thread_id: impl AsRef<str>,
+    run_id: Option<&str>,
+    client: impl AsRef<Client>,
+) -> Result<Vec<ThreadMessage>> {
+    let params = ListParams::new().order(Order::Asc).limit(100);
+    return list_thread_messages(thread_id, run_id, params, client)
+        .try_collect()
+        .await;
+}
+
+/// Returns a stream over the messages of a given thread, fetching them one page at a time.
+///
+/// If `run_id` is specified, only the messages generated by that run are returned.
+pub fn list_thread_messages(
+    thread_id: impl AsRef<str>,
+    run_id: Option<&str>,
+    params: ListParams<'_>,
+    client: impl AsRef<Client>,
+) -> Paginator<ThreadMessage> {
+    let mut req = client
+        .as_ref()
+        .get(client.as_ref().url(
+            Endpoint::Threads,
+            format!("/{}/messages", thread_id.as_ref()),
+        ))
+        .header("OpenAI-Beta", "assistants=v2");
+
+    if let Some(run_id) = run_id {
+        req = req.query(&[("run_id", run_id)]);
+    }
+
+    return Paginator::new(req, params);
+}
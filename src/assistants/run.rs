@@ -0,0 +1,239 @@
+use super::step::RunStepError;
+use crate::{
+    common::Usage,
+    endpoint::Endpoint,
+    error::{FallibleResponse, Result},
+    logging::{RequestBuilderExt, ResponseExt},
+    Client, Str,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// An execution of an assistant on a [`Thread`](super::thread::Thread).
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct Run {
+    pub id: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    pub thread_id: String,
+    pub assistant_id: String,
+    pub status: RunStatus,
+    #[serde(default)]
+    pub required_action: Option<RequiredAction>,
+    #[serde(default)]
+    pub last_error: Option<RunStepError>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Status of a [`Run`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RunStatus {
+    Queued,
+    InProgress,
+    RequiresAction,
+    Cancelling,
+    Cancelled,
+    Failed,
+    Completed,
+    Incomplete,
+    Expired,
+}
+
+/// Action required to continue a [`Run`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RequiredAction {
+    /// The run is waiting for the outputs of the specified tool calls
+    SubmitToolOutputs {
+        submit_tool_outputs: SubmitToolOutputs,
+    },
+}
+
+/// Tool calls whose outputs must be submitted to continue a [`Run`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct SubmitToolOutputs {
+    pub tool_calls: Vec<RunToolCall>,
+}
+
+/// A function call requested by a [`Run`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct RunToolCall {
+    pub id: String,
+    pub function: RunFunctionCall,
+}
+
+/// Name and arguments of a function requested by a [`Run`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct RunFunctionCall {
+    pub name: String,
+    pub arguments: String,
+}
+
+/// Output of a tool call, submitted to continue a [`Run`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolOutput<'a> {
+    pub tool_call_id: Str<'a>,
+    pub output: Str<'a>,
+}
+
+impl Run {
+    /// Creates a run of the specified assistant on a thread.
+    pub async fn new(
+        thread_id: impl AsRef<str>,
+        assistant_id: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        let run = client
+            .as_ref()
+            .post(
+                client
+                    .as_ref()
+                    .url(Endpoint::Threads, format!("/{}/runs", thread_id.as_ref())),
+            )
+            .header("OpenAI-Beta", "assistants=v2")
+            .json_body(&serde_json::json!({ "assistant_id": assistant_id.as_ref() }))
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(run);
+    }
+
+    /// Retrieves a run.
+    pub async fn retreive(
+        thread_id: impl AsRef<str>,
+        run_id: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        let run = client
+            .as_ref()
+            .get(client.as_ref().url(
+                Endpoint::Threads,
+                format!("/{}/runs/{}", thread_id.as_ref(), run_id.as_ref()),
+            ))
+            .header("OpenAI-Beta", "assistants=v2")
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(run);
+    }
+}
+
+impl Run {
+    /// Returns `true` if the run has reached a final state
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        return matches!(
+            self.status,
+            RunStatus::Cancelled
+                | RunStatus::Failed
+                | RunStatus::Completed
+                | RunStatus::Incomplete
+                | RunStatus::Expired
+        );
+    }
+
+    /// Returns the tool calls whose outputs the run is waiting for
+    #[inline]
+    pub fn pending_tool_calls(&self) -> &[RunToolCall] {
+        match self.required_action {
+            Some(RequiredAction::SubmitToolOutputs {
+                ref submit_tool_outputs,
+            }) => &submit_tool_outputs.tool_calls,
+            None => &[],
+        }
+    }
+
+    /// Fetches the current state of the run.
+    #[inline]
+    pub async fn refresh(&self, client: impl AsRef<Client>) -> Result<Self> {
+        return Self::retreive(&self.thread_id, &self.id, client).await;
+    }
+
+    /// Submits the outputs of the tool calls the run is waiting for.
+    pub async fn submit_tool_outputs<'a, I: IntoIterator<Item = ToolOutput<'a>>>(
+        &self,
+        outputs: I,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        #[derive(Debug, Serialize)]
+        struct Body<'a> {
+            tool_outputs: Vec<ToolOutput<'a>>,
+        }
+
+        let run = client
+            .as_ref()
+            .post(client.as_ref().url(
+                Endpoint::Threads,
+                format!("/{}/runs/{}/submit_tool_outputs", self.thread_id, self.id),
+            ))
+            .header("OpenAI-Beta", "assistants=v2")
+            .json_body(&Body {
+                tool_outputs: outputs.into_iter().collect(),
+            })
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(run);
+    }
+
+    /// Cancels the run.
+    #[inline]
+    pub async fn cancel(self, client: impl AsRef<Client>) -> Result<Self> {
+        return cancel_run(self.thread_id, self.id, client).await;
+    }
+}
+
+impl<'a> ToolOutput<'a> {
+    /// Creates a new tool output
+    #[inline]
+    pub fn new(tool_call_id: impl Into<Str<'a>>, output: impl Into<Str<'a>>) -> Self {
+        return Self {
+            tool_call_id: tool_call_id.into(),
+            output: output.into(),
+        };
+    }
+}
+
+/// Cancels a run that is in progress.
+pub async fn cancel_run(
+    thread_id: impl AsRef<str>,
+    run_id: impl AsRef<str>,
+    client: impl AsRef<Client>,
+) -> Result<Run> {
+    let run = client
+        .as_ref()
+        .post(client.as_ref().url(
+            Endpoint::Threads,
+            format!("/{}/runs/{}/cancel", thread_id.as_ref(), run_id.as_ref()),
+        ))
+        .header("OpenAI-Beta", "assistants=v2")
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Run>>()
+        .await?
+        .into_result()?;
+
+    return Ok(run);
+}
@@ -0,0 +1,262 @@
+use crate::{
+    common::{ListParams, Order, Paginator, Usage},
+    endpoint::Endpoint,
+    error::{FallibleResponse, Result},
+    logging::ResponseExt,
+    Client,
+};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Include the content of file search results in the returned step details
+const INCLUDE_FILE_SEARCH_CONTENT: &str =
+    "step_details.tool_calls[*].file_search.results[*].content";
+
+/// A detailed list of steps the assistant took as part of a run.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct RunStep {
+    pub id: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    pub assistant_id: String,
+    pub thread_id: String,
+    pub run_id: String,
+    pub status: RunStepStatus,
+    pub step_details: StepDetails,
+    #[serde(default)]
+    pub last_error: Option<RunStepError>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub expired_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub cancelled_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub failed_at: Option<DateTime<Utc>>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Status of a [`RunStep`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RunStepStatus {
+    InProgress,
+    Cancelled,
+    Failed,
+    Completed,
+    Expired,
+}
+
+/// The last error associated with a [`RunStep`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct RunStepError {
+    pub code: String,
+    pub message: String,
+}
+
+/// The details of a [`RunStep`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum StepDetails {
+    /// The assistant created a message
+    MessageCreation { message_creation: MessageCreation },
+    /// The assistant called one or more tools
+    ToolCalls { tool_calls: Vec<StepToolCall> },
+}
+
+/// Details of the message created by a [`RunStep`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct MessageCreation {
+    pub message_id: String,
+}
+
+/// A tool call made by the assistant during a [`RunStep`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum StepToolCall {
+    CodeInterpreter {
+        id: String,
+        code_interpreter: CodeInterpreterCall,
+    },
+    FileSearch {
+        id: String,
+        file_search: FileSearchCall,
+    },
+    Function {
+        id: String,
+        function: FunctionCall,
+    },
+}
+
+/// Input and outputs of a code interpreter tool call
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct CodeInterpreterCall {
+    pub input: String,
+    #[serde(default)]
+    pub outputs: Vec<CodeInterpreterOutput>,
+}
+
+/// Output of a code interpreter tool call
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CodeInterpreterOutput {
+    /// Text output from the code interpreter
+    Logs { logs: String },
+    /// Image generated by the code interpreter
+    Image { image: CodeInterpreterImage },
+}
+
+/// Image generated by the code interpreter
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct CodeInterpreterImage {
+    pub file_id: String,
+}
+
+/// Results of a file search tool call
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct FileSearchCall {
+    #[serde(default)]
+    pub results: Vec<FileSearchResult>,
+}
+
+/// A file found by a file search tool call
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct FileSearchResult {
+    pub file_id: String,
+    pub file_name: String,
+    pub score: f64,
+    #[serde(default)]
+    pub content: Vec<FileSearchContent>,
+}
+
+/// Content of a file found by a file search tool call
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct FileSearchContent {
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+/// A function tool call
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: String,
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+impl RunStep {
+    /// Retrieves a run step.
+    pub async fn retreive(
+        thread_id: impl AsRef<str>,
+        run_id: impl AsRef<str>,
+        step_id: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        let step = client
+            .as_ref()
+            .get(client.as_ref().url(
+                Endpoint::Threads,
+                format!(
+                    "/{}/runs/{}/steps/{}",
+                    thread_id.as_ref(),
+                    run_id.as_ref(),
+                    step_id.as_ref()
+                ),
+            ))
+            .header("OpenAI-Beta", "assistants=v2")
+            .query(&[("include[]", INCLUDE_FILE_SEARCH_CONTENT)])
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(step);
+    }
+}
+
+impl RunStep {
+    /// Returns the id of the message created by this step, if any
+    #[inline]
+    pub fn message_id(&self) -> Option<&str> {
+        match self.step_details {
+            StepDetails::MessageCreation {
+                ref message_creation,
+            } => Some(&message_creation.message_id),
+            _ => None,
+        }
+    }
+
+    /// Returns the tool calls made during this step
+    #[inline]
+    pub fn tool_calls(&self) -> &[StepToolCall] {
+        match self.step_details {
+            StepDetails::ToolCalls { ref tool_calls } => tool_calls,
+            _ => &[],
+        }
+    }
+}
+
+impl StepToolCall {
+    /// Returns the id of the tool call
+    #[inline]
+    pub fn id(&self) -> &str {
+        match self {
+            StepToolCall::CodeInterpreter { id, .. } => id,
+            StepToolCall::FileSearch { id, .. } => id,
+            StepToolCall::Function { id, .. } => id,
+        }
+    }
+}
+
+/// Returns a list of run steps belonging to a run, fetching every page of them (see [`list_run_steps`]).
+pub async fn run_steps(
+    thread_id: impl AsRef<str>,
+    run_id: impl AsRef<str>,
+    client: impl AsRef<Client>,
+) -> Result<Vec<RunStep>> {
+    let params = ListParams::new().order(Order::Asc).limit(100);
+    return list_run_steps(thread_id, run_id, params, client)
+        .try_collect()
+        .await;
+}
+
+/// Returns a stream over the run steps belonging to a run, fetching them one page at a time.
+pub fn list_run_steps(
+    thread_id: impl AsRef<str>,
+    run_id: impl AsRef<str>,
+    params: ListParams<'_>,
+    client: impl AsRef<Client>,
+) -> Paginator<RunStep> {
+    let req = client
+        .as_ref()
+        .get(client.as_ref().url(
+            Endpoint::Threads,
+            format!("/{}/runs/{}/steps", thread_id.as_ref(), run_id.as_ref()),
+        ))
+        .header("OpenAI-Beta", "assistants=v2")
+        .query(&[("include[]", INCLUDE_FILE_SEARCH_CONTENT)]);
+
+    return Paginator::new(req, params);
+}
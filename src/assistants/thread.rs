@@ -0,0 +1,83 @@
+use crate::{
+    common::Delete,
+    endpoint::Endpoint,
+    error::{FallibleResponse, Result},
+    logging::{RequestBuilderExt, ResponseExt},
+    Client,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// A conversation session between an assistant and a user.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct Thread {
+    pub id: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+impl Thread {
+    /// Creates an empty thread.
+    pub async fn new(client: impl AsRef<Client>) -> Result<Self> {
+        let thread = client
+            .as_ref()
+            .post(client.as_ref().url(Endpoint::Threads, ""))
+            .header("OpenAI-Beta", "assistants=v2")
+            .json_body(&serde_json::json!({}))
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(thread);
+    }
+
+    /// Retrieves a thread.
+    pub async fn retreive(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Self> {
+        let thread = client
+            .as_ref()
+            .get(
+                client
+                    .as_ref()
+                    .url(Endpoint::Threads, format!("/{}", id.as_ref())),
+            )
+            .header("OpenAI-Beta", "assistants=v2")
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(thread);
+    }
+
+    /// Delete the thread.
+    #[inline]
+    pub async fn delete(self, client: impl AsRef<Client>) -> Result<Delete> {
+        return delete_thread(self.id, client).await;
+    }
+}
+
+/// Delete a thread.
+pub async fn delete_thread(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Delete> {
+    let delete = client
+        .as_ref()
+        .delete(
+            client
+                .as_ref()
+                .url(Endpoint::Threads, format!("/{}", id.as_ref())),
+        )
+        .header("OpenAI-Beta", "assistants=v2")
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Delete>>()
+        .await?
+        .into_result()?;
+
+    return Ok(delete);
+}
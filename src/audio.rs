@@ -1,13 +1,39 @@
-use crate::{error::Result, Str};
+use crate::{
+    chat::{tool::ToolCall, ChatCompletionBuilder, Message},
+    error::{BuilderError, Error, Result},
+    logging::ResponseExt,
+    rt, Client, Str,
+};
+use bytes::Bytes;
 use elor::Either;
-use serde::{Deserialize, Serialize};
+use futures::{Stream, StreamExt};
+use language::Language;
+use reqwest::multipart::Part;
+use serde::{Deserialize, Serialize, Serializer};
+use speech::SpeechBuilder;
 use srtlib::{Subtitle, Subtitles, Timestamp};
-use std::time::Duration;
+use std::{
+    ffi::OsStr,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use transcription::TranscriptionBuilder;
+use vtt::VttCue;
 
+/// Splits large audio files into chunks, to transcribe them separately.
+pub mod chunking;
+/// ISO-639-1 languages of the audio of transcriptions.
+pub mod language;
+/// Generates audio from text.
+pub mod speech;
 /// Transcribes audio into the input language.
 pub mod transcription;
 /// Translates audio into English.
 pub mod translation;
+/// Parses WebVTT subtitles, returned by the [`Vtt`](AudioResponseFormat::Vtt) response format.
+pub mod vtt;
 
 /// The format of the transcript/translation output.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize)]
@@ -19,7 +45,7 @@ pub enum AudioResponseFormat {
     Text,
     Srt,
     VerboseJson,
-    // Vtt,
+    Vtt,
 }
 
 /// Response to a transcript/translation
@@ -30,7 +56,32 @@ pub enum AudioResponse {
     Text(String),
     Srt(Vec<Subtitle>),
     VerboseJson(VerboseJsonResponse),
-    // Vtt
+    Vtt(Vec<VttCue>),
+}
+
+/// Turn of a spoken conversation, returned by [`converse`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct VoiceReply<'a> {
+    /// Transcript of the user's audio
+    pub transcript: String,
+    /// Text of the model's reply
+    pub reply: String,
+    /// Synthesized audio of the reply, or `None` if the reply has no text (e.g. the model only called tools)
+    pub audio: Option<Bytes>,
+    /// Tools called by the model, which aren't executed. Their results must be appended to `conversation` (see [`Message::tool`]) before the next turn
+    pub tool_calls: Vec<ToolCall>,
+    /// The conversation, with the transcript and the reply appended, to continue it with the next turn
+    pub conversation: ChatCompletionBuilder<'a>,
+}
+
+/// Options of [`transcribe_dir`]
+#[derive(Debug, Clone)]
+pub struct TranscribeDirOptions {
+    transcription: TranscriptionBuilder,
+    concurrency: usize,
+    retries: u32,
+    retry_delay: Duration,
 }
 
 /// A generic segment, independent of [response format](AudioResponseFormat)
@@ -43,33 +94,42 @@ pub struct GenericSegment<'a> {
 }
 
 /// Response for [`Json`](AudioResponseFormat::Json) response format
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct JsonResponse {
     pub text: String,
 }
 
 /// Response for [`VerboseJson`](AudioResponseFormat::VerboseJson) response format
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct VerboseJsonResponse {
     pub task: String,
-    pub language: String,
-    #[serde(deserialize_with = "crate::deserialize_duration_secs")]
+    pub language: Language,
+    #[serde(
+        serialize_with = "crate::serialize_duration_secs",
+        deserialize_with = "crate::deserialize_duration_secs"
+    )]
     pub duration: Duration,
     pub segments: Vec<VerboseJsonSegment>,
     pub text: String,
 }
 
 /// A [`VerboseJson`](AudioResponseFormat::VerboseJson) response segment
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct VerboseJsonSegment {
     pub id: u64,
     pub seek: u64,
-    #[serde(deserialize_with = "crate::deserialize_duration_secs")]
+    #[serde(
+        serialize_with = "crate::serialize_duration_secs",
+        deserialize_with = "crate::deserialize_duration_secs"
+    )]
     pub start: Duration,
-    #[serde(deserialize_with = "crate::deserialize_duration_secs")]
+    #[serde(
+        serialize_with = "crate::serialize_duration_secs",
+        deserialize_with = "crate::deserialize_duration_secs"
+    )]
     pub end: Duration,
     pub text: String,
     pub tokens: Vec<u64>,
@@ -80,6 +140,138 @@ pub struct VerboseJsonSegment {
     pub transient: bool,
 }
 
+/// Segments with a `no_speech_prob` above this threshold probably contain no speech (Whisper's default)
+pub const NO_SPEECH_THRESHOLD: f64 = 0.6;
+/// Segments with a `compression_ratio` above this threshold are probably repetitive hallucinations (Whisper's default)
+pub const COMPRESSION_RATIO_THRESHOLD: f64 = 2.4;
+
+/// Aggregated confidence of a [`VerboseJsonResponse`]'s segments, returned by [`VerboseJsonResponse::quality_report`]
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct QualityReport {
+    /// Number of segments of the transcript
+    pub segments: usize,
+    /// Mean of the segments' `avg_logprob`
+    pub mean_avg_logprob: f64,
+    /// Proportion of segments whose `no_speech_prob` is above [`NO_SPEECH_THRESHOLD`]
+    pub no_speech_ratio: f64,
+    /// Proportion of segments whose `compression_ratio` is above [`COMPRESSION_RATIO_THRESHOLD`]
+    pub compression_anomaly_ratio: f64,
+    /// Ids of the segments with no speech or a compression anomaly
+    pub flagged_segments: Vec<u64>,
+}
+
+impl QualityReport {
+    /// Returns a score between 0 and 1 of how reliable the transcript is, to flag the ones that need human review (e.g. below 0.5).
+    ///
+    /// It's the mean probability of the tokens (`exp(mean_avg_logprob)`), scaled down by the proportions of segments with no speech and compression anomalies. Transcripts without segments score 0.
+    pub fn quality_score(&self) -> f64 {
+        if self.segments == 0 {
+            return 0.0;
+        }
+
+        return (self.mean_avg_logprob.exp()
+            * (1.0 - self.no_speech_ratio)
+            * (1.0 - self.compression_anomaly_ratio))
+            .clamp(0.0, 1.0);
+    }
+}
+
+impl VerboseJsonResponse {
+    /// Returns an aggregated report of the confidence of the transcript's segments
+    pub fn quality_report(&self) -> QualityReport {
+        let segments = self.segments.len();
+        let ratio = |count: usize| match segments {
+            0 => 0.0,
+            _ => count as f64 / segments as f64,
+        };
+
+        let no_speech = self
+            .segments
+            .iter()
+            .filter(|x| x.no_speech_prob > NO_SPEECH_THRESHOLD)
+            .count();
+        let compression_anomalies = self
+            .segments
+            .iter()
+            .filter(|x| x.compression_ratio > COMPRESSION_RATIO_THRESHOLD)
+            .count();
+
+        return QualityReport {
+            segments,
+            mean_avg_logprob: match segments {
+                0 => 0.0,
+                _ => self.segments.iter().map(|x| x.avg_logprob).sum::<f64>() / segments as f64,
+            },
+            no_speech_ratio: ratio(no_speech),
+            compression_anomaly_ratio: ratio(compression_anomalies),
+            flagged_segments: self
+                .segments
+                .iter()
+                .filter(|x| {
+                    x.no_speech_prob > NO_SPEECH_THRESHOLD
+                        || x.compression_ratio > COMPRESSION_RATIO_THRESHOLD
+                })
+                .map(|x| x.id)
+                .collect(),
+        };
+    }
+
+    /// Returns the [quality score](QualityReport::quality_score) of the transcript
+    #[inline]
+    pub fn quality_score(&self) -> f64 {
+        return self.quality_report().quality_score();
+    }
+}
+
+impl AudioResponseFormat {
+    /// Returns the name of the format, as used by the API
+    #[inline]
+    pub fn as_str(self) -> &'static str {
+        return match self {
+            Self::Json => "json",
+            Self::Text => "text",
+            Self::Srt => "srt",
+            Self::VerboseJson => "verbose_json",
+            Self::Vtt => "vtt",
+        };
+    }
+
+    /// Returns `false` if the format is known not to be supported by `model`.
+    ///
+    /// The GPT-4o transcription models (e.g. `gpt-4o-transcribe`) only support [`Json`](Self::Json) and [`Text`](Self::Text).
+    #[inline]
+    pub fn is_supported_by(self, model: &str) -> bool {
+        return !model.starts_with("gpt-4o") || matches!(self, Self::Json | Self::Text);
+    }
+
+    /// Returns the format requested from `model` by default: [`VerboseJson`](Self::VerboseJson), or [`Json`](Self::Json) if it isn't supported
+    #[inline]
+    pub(crate) fn default_for(model: &str) -> Self {
+        return match Self::VerboseJson.is_supported_by(model) {
+            true => Self::VerboseJson,
+            false => Self::Json,
+        };
+    }
+
+    /// Checks that the format is supported by `model`
+    pub(crate) fn validate(self, model: &str) -> Result<()> {
+        if !self.is_supported_by(model) {
+            return Err(Error::msg(format!(
+                "the response format '{self}' isn't supported by the model '{model}'"
+            )));
+        }
+        return Ok(());
+    }
+}
+
+impl std::fmt::Display for AudioResponseFormat {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl AudioResponse {
     /// Returns the underlying text response
     #[inline]
@@ -101,12 +293,18 @@ impl AudioResponse {
                 Str::Owned(result)
             }
             AudioResponse::VerboseJson(x) => Str::Borrowed(&x.text),
+            AudioResponse::Vtt(cues) => Str::Owned(
+                cues.iter()
+                    .map(|cue| cue.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
         }
     }
 
-    /// Returns the language of the audio
+    /// Returns the language of the audio, as detected by the model
     #[inline]
-    pub fn language(&self) -> Option<&str> {
+    pub fn language(&self) -> Option<&Language> {
         match self {
             AudioResponse::VerboseJson(x) => Some(&x.language),
             _ => None,
@@ -123,6 +321,10 @@ impl AudioResponse {
                     .checked_sub(timestamp_to_duration(start.start_time)),
                 _ => Some(Duration::ZERO),
             },
+            AudioResponse::Vtt(x) => match (x.first(), x.last()) {
+                (Some(start), Some(end)) => end.end.checked_sub(start.start),
+                _ => Some(Duration::ZERO),
+            },
             _ => None,
         }
     }
@@ -131,17 +333,31 @@ impl AudioResponse {
     pub fn segments(&self) -> Option<impl Iterator<Item = GenericSegment<'_>>> {
         match self {
             AudioResponse::VerboseJson(x) => Some(
-                Either::Left(x.segments.iter().map(|x| GenericSegment {
-                    start: x.start,
-                    end: x.end,
-                    text: &x.text,
-                }))
+                Either::Left(
+                    Either::Left(x.segments.iter().map(|x| GenericSegment {
+                        start: x.start,
+                        end: x.end,
+                        text: &x.text,
+                    }))
+                    .into_same_iter(),
+                )
                 .into_same_iter(),
             ),
             AudioResponse::Srt(x) => Some(
+                Either::Left(
+                    Either::Right(x.iter().map(|x| GenericSegment {
+                        start: timestamp_to_duration(x.start_time),
+                        end: timestamp_to_duration(x.end_time),
+                        text: &x.text,
+                    }))
+                    .into_same_iter(),
+                )
+                .into_same_iter(),
+            ),
+            AudioResponse::Vtt(x) => Some(
                 Either::Right(x.iter().map(|x| GenericSegment {
-                    start: timestamp_to_duration(x.start_time),
-                    end: timestamp_to_duration(x.end_time),
+                    start: x.start,
+                    end: x.end,
                     text: &x.text,
                 }))
                 .into_same_iter(),
@@ -151,6 +367,19 @@ impl AudioResponse {
     }
 }
 
+impl Serialize for AudioResponse {
+    /// Serializes the response as it was returned by the API (SRT and VTT responses are serialized as a string)
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            AudioResponse::Json(x) => x.serialize(serializer),
+            AudioResponse::Text(x) => serializer.serialize_str(x),
+            AudioResponse::Srt(x) => serializer.collect_str(&Subtitles::new_from_vec(x.clone())),
+            AudioResponse::VerboseJson(x) => x.serialize(serializer),
+            AudioResponse::Vtt(x) => serializer.serialize_str(&vtt::format(x)),
+        }
+    }
+}
+
 impl GenericSegment<'_> {
     /// Returns the duration of the segment
     #[inline]
@@ -159,13 +388,125 @@ impl GenericSegment<'_> {
     }
 }
 
+/// Answers a spoken message: transcribes `audio`, appends the transcript to `conversation` as a user message, and voices the model's reply.
+///
+/// Replies that call tools are returned with their [`tool_calls`](VoiceReply::tool_calls), and only voiced if they also have text.
+///
+/// Audio is transcribed with `whisper-1` and synthesized with `tts-1` and the [`Alloy`](speech::Voice::Alloy) voice, in MP3. See [`converse_with`] to configure them.
+///
+/// The file name of `audio` must have the extension of its format (e.g. `Part::bytes(data).file_name("input.wav")`).
+#[inline]
+pub async fn converse<'a>(
+    audio: Part,
+    conversation: ChatCompletionBuilder<'a>,
+    client: impl AsRef<Client>,
+) -> Result<VoiceReply<'a>> {
+    return converse_with(
+        audio,
+        conversation,
+        TranscriptionBuilder::new(),
+        &SpeechBuilder::default(),
+        client,
+    )
+    .await;
+}
+
+/// Answers a spoken message like [`converse`], with the specified transcription and speech requests
+pub async fn converse_with<'a>(
+    audio: Part,
+    conversation: ChatCompletionBuilder<'a>,
+    transcription: TranscriptionBuilder,
+    speech: &SpeechBuilder<'_>,
+    client: impl AsRef<Client>,
+) -> Result<VoiceReply<'a>> {
+    let client = client.as_ref();
+    let transcript = transcription
+        .with_part(audio, client)
+        .await?
+        .text()
+        .trim()
+        .to_string();
+
+    let mut conversation = conversation.message(Message::user(transcript.clone()));
+    let reply = match conversation.clone().build(client).await?.into_first() {
+        Some(choice) => choice.message,
+        None => return Err(Error::msg("the chat completion has no choices")),
+    };
+
+    let audio = match reply.content.trim().is_empty() {
+        true => None,
+        false => Some(speech.build(&reply.content, client).await?),
+    };
+    let text = reply.content.to_string();
+    let tool_calls = reply.tool_calls.clone();
+    conversation = conversation.message(reply);
+
+    return Ok(VoiceReply {
+        transcript,
+        reply: text,
+        audio,
+        tool_calls,
+        conversation,
+    });
+}
+
+/// Transcribes every audio file matching the glob pattern `path_glob` (e.g. `podcasts/**/*.mp3`), returning a stream of the transcripts as they're completed.
+///
+/// Files are transcribed concurrently, and their transcripts are yielded in the order they're completed, with their paths. A file that can't be transcribed doesn't stop the rest, and its error is yielded in place of its transcript.
+/// Only files with the extension of a supported audio format (see [`AUDIO_EXTENSIONS`]) are transcribed.
+pub async fn transcribe_dir(
+    path_glob: impl AsRef<str>,
+    opts: TranscribeDirOptions,
+    client: impl AsRef<Client>,
+) -> Result<impl Stream<Item = (PathBuf, Result<AudioResponse>)>> {
+    let pattern = path_glob.as_ref().to_string();
+    let entries = rt::spawn_blocking(move || {
+        let entries = glob::glob(&pattern).map_err(Error::msg)?;
+        return Result::<_>::Ok(
+            entries
+                .filter(|entry| match entry {
+                    Ok(path) => path.is_file() && is_audio_file(path),
+                    Err(_) => true,
+                })
+                .collect::<Vec<_>>(),
+        );
+    })
+    .await?;
+
+    let client = client.as_ref().clone();
+    let concurrency = opts.concurrency;
+    let opts = Arc::new(opts);
+
+    return Ok(futures::stream::iter(entries)
+        .map(move |entry| {
+            let opts = opts.clone();
+            let client = client.clone();
+            async move {
+                return match entry {
+                    Ok(path) => {
+                        let result = opts.transcribe(&path, &client).await;
+                        (path, result)
+                    }
+                    Err(e) => {
+                        let error = io::Error::new(e.error().kind(), e.error().to_string());
+                        (e.path().to_path_buf(), Err(Error::Io(error)))
+                    }
+                };
+            }
+        })
+        .buffer_unordered(concurrency));
+}
+
 /// Parses a [`reqwest::Response`] into a response of the specified format.
 pub async fn parse_audio_response(
     resp: reqwest::Response,
     format: AudioResponseFormat,
 ) -> Result<AudioResponse> {
+    let resp = resp.check_status().await?;
     return match format {
-        AudioResponseFormat::Json => Ok(AudioResponse::Json(resp.json::<JsonResponse>().await?)),
+        AudioResponseFormat::Json => {
+            Ok(AudioResponse::Json(resp.json_body::<JsonResponse>().await?))
+        }
         AudioResponseFormat::Text => Ok(AudioResponse::Text(resp.text().await?)),
         AudioResponseFormat::Srt => {
             let text = resp.text().await?;
@@ -174,12 +515,93 @@ pub async fn parse_audio_response(
             ))
         }
         AudioResponseFormat::VerboseJson => Ok(AudioResponse::VerboseJson(
-            resp.json::<VerboseJsonResponse>().await?,
+            resp.json_body::<VerboseJsonResponse>().await?,
         )),
-        // AudioResponseFormat::Vtt => Err(Error::msg("Vtt is currently unsuported")),
+        AudioResponseFormat::Vtt => Ok(AudioResponse::Vtt(vtt::parse(&resp.text().await?)?)),
     };
 }
 
+impl TranscribeDirOptions {
+    /// Creates the default options: files are transcribed with the default [`TranscriptionBuilder`], 4 at a time, and retried up to 2 times after transient errors (see [`Error::is_transient`]), waiting 1 second before the first retry.
+    #[inline]
+    pub fn new() -> Self {
+        return Self {
+            transcription: TranscriptionBuilder::new(),
+            concurrency: 4,
+            retries: 2,
+            retry_delay: Duration::from_secs(1),
+        };
+    }
+
+    /// The request every file is transcribed with
+    #[inline]
+    pub fn transcription(mut self, transcription: TranscriptionBuilder) -> Self {
+        self.transcription = transcription;
+        self
+    }
+
+    /// Maximum number of files transcribed at the same time. Defaults to 4.
+    pub fn concurrency(mut self, concurrency: usize) -> Result<Self, BuilderError<Self>> {
+        if concurrency == 0 {
+            return Err(BuilderError::msg(self, "concurrency must be at least 1"));
+        }
+        self.concurrency = concurrency;
+        return Ok(self);
+    }
+
+    /// Number of times the transcription of a file is retried after a transient error. Defaults to 2.
+    #[inline]
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Time waited before the first retry of a file, doubled on every following retry. Defaults to 1 second.
+    #[inline]
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    async fn transcribe(&self, path: &Path, client: &Client) -> Result<AudioResponse> {
+        let mut delay = self.retry_delay;
+        let mut retries = 0;
+
+        loop {
+            match self.transcription.clone().with_file(path, client).await {
+                Err(e) if e.is_transient() && retries < self.retries => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("transcription of {} failed, retrying: {e}", path.display());
+                    rt::sleep(delay).await;
+                    delay *= 2;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Default for TranscribeDirOptions {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Extensions of the audio formats supported by the API, matched case-insensitively by [`transcribe_dir`]
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "flac", "m4a", "mp3", "mp4", "mpeg", "mpga", "oga", "ogg", "wav", "webm",
+];
+
+#[inline]
+fn is_audio_file(path: &Path) -> bool {
+    return path
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| AUDIO_EXTENSIONS.iter().any(|x| x.eq_ignore_ascii_case(ext)));
+}
+
 #[inline]
 fn timestamp_to_duration(ts: Timestamp) -> Duration {
     let (h, m, s, ms) = ts.get();
@@ -0,0 +1,321 @@
+use super::{transcription::TranscriptionBuilder, AudioResponse};
+use crate::{
+    error::{BuilderError, Error, Result},
+    multipart::FilePart,
+    rt, Client,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::{StreamExt, TryFutureExt, TryStreamExt};
+use std::{ops::Range, path::Path, time::Duration};
+
+/// Maximum size, in bytes, of the audio files accepted by the API
+pub const MAX_AUDIO_BYTES: usize = 25_000_000;
+
+/// How a [`TranscriptionBuilder`](super::transcription::TranscriptionBuilder) splits audio over a size limit into chunks, which are transcribed separately and stitched back together.
+///
+/// Only WAV audio can be split, since its samples aren't compressed. Audio in other formats must be converted (or split) beforehand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Chunking {
+    max_bytes: usize,
+    concurrency: usize,
+    split_on_silence: bool,
+}
+
+/// A chunk of audio, and the time it starts at
+#[derive(Debug, Clone)]
+struct AudioChunk {
+    data: Bytes,
+    offset: Duration,
+}
+
+/// Layout of a WAV file
+#[derive(Debug)]
+struct Wav<'a> {
+    /// The `fmt ` chunk, with its header
+    fmt: &'a [u8],
+    /// The samples of the `data` chunk
+    data: &'a [u8],
+    byte_rate: usize,
+    block_align: usize,
+    /// Whether the samples are 16-bit integers, which can be scanned for silences
+    pcm16: bool,
+}
+
+impl Chunking {
+    /// Creates the default chunking: chunks of up to 24 MB, transcribed one after the other, and split on the quietest moment near their end
+    #[inline]
+    pub fn new() -> Self {
+        return Self {
+            max_bytes: 24_000_000,
+            concurrency: 1,
+            split_on_silence: true,
+        };
+    }
+
+    /// Maximum size of a chunk, in bytes. Defaults to 24 MB, leaving room for the rest of the request under [`MAX_AUDIO_BYTES`].
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Maximum number of chunks transcribed at the same time. Defaults to 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Result<Self, BuilderError<Self>> {
+        if concurrency == 0 {
+            return Err(BuilderError::msg(self, "concurrency must be at least 1"));
+        }
+        self.concurrency = concurrency;
+        return Ok(self);
+    }
+
+    /// Whether to end chunks on the quietest moment of their last quarter, so that words aren't cut in half. Defaults to `true`.
+    ///
+    /// Only 16-bit PCM audio is scanned for silences; the rest is split at the size limit.
+    pub fn split_on_silence(mut self, split_on_silence: bool) -> Self {
+        self.split_on_silence = split_on_silence;
+        self
+    }
+
+    /// Transcribes the audio with `builder`, chunk by chunk, returning the stitched transcript
+    pub(crate) async fn transcribe(
+        &self,
+        builder: TranscriptionBuilder,
+        audio: Bytes,
+        file_name: String,
+        client: &Client,
+    ) -> Result<AudioResponse> {
+        let this = *self;
+        let mut chunks = rt::spawn_blocking(move || this.split(audio)).await?;
+        if chunks.len() == 1 {
+            let part = FilePart::bytes(chunks.remove(0).data).file_name(file_name);
+            return builder.with_part(part, client).await;
+        }
+
+        let file_name = Path::new(&file_name)
+            .with_extension("wav")
+            .to_string_lossy()
+            .into_owned();
+        let responses = futures::stream::iter(chunks)
+            .map(|chunk| {
+                let part = FilePart::bytes(chunk.data).file_name(file_name.clone());
+                builder
+                    .clone()
+                    .with_part(part, client)
+                    .map_ok(move |resp| (chunk.offset, resp))
+            })
+            .buffered(self.concurrency)
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        return stitch(responses);
+    }
+
+    /// Splits the audio into chunks of up to [`max_bytes`](Chunking::max_bytes), or returns it as is if it fits in one
+    fn split(&self, audio: Bytes) -> Result<Vec<AudioChunk>> {
+        if audio.len() <= self.max_bytes {
+            return Ok(vec![AudioChunk {
+                data: audio,
+                offset: Duration::ZERO,
+            }]);
+        }
+
+        let wav = Wav::parse(&audio).ok_or_else(|| {
+            Error::msg(format!(
+                "the audio is larger than {} bytes, and only WAV audio can be split into chunks",
+                self.max_bytes
+            ))
+        })?;
+
+        let header_len = 12 + wav.fmt.len() + 8;
+        let max_data =
+            self.max_bytes.saturating_sub(header_len) / wav.block_align * wav.block_align;
+        if max_data == 0 {
+            return Err(Error::msg(format!(
+                "chunks of {} bytes can't fit any audio",
+                self.max_bytes
+            )));
+        }
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        while start < wav.data.len() {
+            let mut end = wav.data.len().min(start + max_data);
+            if end < wav.data.len() && self.split_on_silence && wav.pcm16 {
+                end = wav
+                    .quietest_point(start + max_data / 4 * 3..end)
+                    .max(start + wav.block_align);
+            }
+
+            chunks.push(AudioChunk {
+                data: wav.chunk(start..end),
+                offset: Duration::from_secs_f64(start as f64 / wav.byte_rate as f64),
+            });
+            start = end;
+        }
+
+        return Ok(chunks);
+    }
+}
+
+impl Default for Chunking {
+    #[inline]
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+impl<'a> Wav<'a> {
+    /// Parses the layout of a WAV file, returning `None` if it isn't one
+    fn parse(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" {
+            return None;
+        }
+
+        let mut fmt = None;
+        let mut pos = 12;
+        while let Some(header) = bytes.get(pos..pos + 8) {
+            let len = u32::from_le_bytes(header[4..8].try_into().ok()?) as usize;
+            let body = pos + 8;
+
+            match &header[0..4] {
+                b"fmt " => fmt = Some(bytes.get(pos..body + len)?),
+                b"data" => {
+                    let fmt = fmt?;
+                    let format = u16::from_le_bytes(fmt.get(8..10)?.try_into().ok()?);
+                    let byte_rate = u32::from_le_bytes(fmt.get(16..20)?.try_into().ok()?) as usize;
+                    let block_align =
+                        u16::from_le_bytes(fmt.get(20..22)?.try_into().ok()?) as usize;
+                    let bits = u16::from_le_bytes(fmt.get(22..24)?.try_into().ok()?);
+                    if byte_rate == 0 || block_align == 0 {
+                        return None;
+                    }
+
+                    // Streamed files may not know the length of their data, which lasts until the end of the file
+                    let end = match len {
+                        0 | 0xFFFFFFFF => bytes.len(),
+                        len => bytes.len().min(body + len),
+                    };
+                    let data = bytes.get(body..end)?;
+
+                    return Some(Self {
+                        fmt,
+                        data: &data[..data.len() / block_align * block_align],
+                        byte_rate,
+                        block_align,
+                        pcm16: format == 1 && bits == 16,
+                    });
+                }
+                _ => {}
+            }
+
+            // Chunks are padded to an even length
+            pos = body + len + (len & 1);
+        }
+
+        return None;
+    }
+
+    /// Returns a WAV file with the samples in `range` of the data
+    fn chunk(&self, range: Range<usize>) -> Bytes {
+        let data = &self.data[range];
+        let mut result = BytesMut::with_capacity(12 + self.fmt.len() + 8 + data.len());
+        result.put_slice(b"RIFF");
+        result.put_u32_le((4 + self.fmt.len() + 8 + data.len()) as u32);
+        result.put_slice(b"WAVE");
+        result.put_slice(self.fmt);
+        result.put_slice(b"data");
+        result.put_u32_le(data.len() as u32);
+        result.put_slice(data);
+        return result.freeze();
+    }
+
+    /// Returns the middle of the quietest 20 milliseconds in the `range` of the data
+    fn quietest_point(&self, range: Range<usize>) -> usize {
+        let window = (self.byte_rate / 50 / self.block_align).max(1) * self.block_align;
+        let start = range.start / self.block_align * self.block_align;
+
+        return (start..range.end.saturating_sub(window))
+            .step_by(window)
+            .min_by_key(|&start| {
+                self.data[start..start + window]
+                    .chunks_exact(2)
+                    .map(|x| i16::from_le_bytes([x[0], x[1]]).unsigned_abs() as u64)
+                    .sum::<u64>()
+            })
+            .map_or(range.end, |start| {
+                start + window / 2 / self.block_align * self.block_align
+            });
+    }
+}
+
+/// Joins the responses of consecutive chunks into one, shifting their timestamps by the offsets of their chunks
+fn stitch(chunks: Vec<(Duration, AudioResponse)>) -> Result<AudioResponse> {
+    let mut chunks = chunks.into_iter();
+    let Some((_, mut result)) = chunks.next() else {
+        return Err(Error::msg("the audio has no chunks"));
+    };
+
+    for (offset, chunk) in chunks {
+        match (&mut result, chunk) {
+            (AudioResponse::Json(result), AudioResponse::Json(chunk)) => {
+                join_text(&mut result.text, &chunk.text)
+            }
+            (AudioResponse::Text(result), AudioResponse::Text(chunk)) => join_text(result, &chunk),
+            (AudioResponse::VerboseJson(result), AudioResponse::VerboseJson(chunk)) => {
+                let first_id = result.segments.len() as u64;
+                result
+                    .segments
+                    .extend(
+                        chunk
+                            .segments
+                            .into_iter()
+                            .enumerate()
+                            .map(|(i, mut segment)| {
+                                segment.id = first_id + i as u64;
+                                segment.start += offset;
+                                segment.end += offset;
+                                segment
+                            }),
+                    );
+                result.duration = offset + chunk.duration;
+                join_text(&mut result.text, &chunk.text);
+            }
+            (AudioResponse::Srt(result), AudioResponse::Srt(chunk)) => {
+                let millis = i32::try_from(offset.as_millis()).map_err(Error::msg)?;
+                let first_num = result.len();
+                result.extend(chunk.into_iter().enumerate().map(|(i, mut subtitle)| {
+                    subtitle.add_milliseconds(millis);
+                    subtitle.num = first_num + i + 1;
+                    subtitle
+                }));
+            }
+            (AudioResponse::Vtt(result), AudioResponse::Vtt(chunk)) => {
+                result.extend(chunk.into_iter().map(|mut cue| {
+                    cue.start += offset;
+                    cue.end += offset;
+                    cue
+                }));
+            }
+            _ => {
+                return Err(Error::msg(
+                    "the chunks of the audio were transcribed into different formats",
+                ))
+            }
+        }
+    }
+
+    return Ok(result);
+}
+
+/// Appends `text` to `result`, separated by a space
+fn join_text(result: &mut String, text: &str) {
+    let text = text.trim();
+    if text.is_empty() {
+        return;
+    }
+
+    result.truncate(result.trim_end().len());
+    if !result.is_empty() {
+        result.push(' ');
+    }
+    result.push_str(text);
+}
@@ -0,0 +1,303 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt::Display, str::FromStr};
+
+/// Language of the audio of a transcription, as supported by the model
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Language {
+    Afrikaans,
+    Arabic,
+    Armenian,
+    Azerbaijani,
+    Belarusian,
+    Bosnian,
+    Bulgarian,
+    Catalan,
+    Chinese,
+    Croatian,
+    Czech,
+    Danish,
+    Dutch,
+    English,
+    Estonian,
+    Finnish,
+    French,
+    Galician,
+    German,
+    Greek,
+    Hebrew,
+    Hindi,
+    Hungarian,
+    Icelandic,
+    Indonesian,
+    Italian,
+    Japanese,
+    Kannada,
+    Kazakh,
+    Korean,
+    Latvian,
+    Lithuanian,
+    Macedonian,
+    Malay,
+    Marathi,
+    Maori,
+    Nepali,
+    Norwegian,
+    Persian,
+    Polish,
+    Portuguese,
+    Romanian,
+    Russian,
+    Serbian,
+    Slovak,
+    Slovenian,
+    Spanish,
+    Swahili,
+    Swedish,
+    Tagalog,
+    Tamil,
+    Thai,
+    Turkish,
+    Ukrainian,
+    Urdu,
+    Vietnamese,
+    Welsh,
+    /// A language not known by the library, by its ISO-639-1 code (or by its name, if returned by the API)
+    Other(String),
+}
+
+impl Language {
+    /// Every language known by the library
+    pub const KNOWN: &'static [Language] = &[
+        Self::Afrikaans,
+        Self::Arabic,
+        Self::Armenian,
+        Self::Azerbaijani,
+        Self::Belarusian,
+        Self::Bosnian,
+        Self::Bulgarian,
+        Self::Catalan,
+        Self::Chinese,
+        Self::Croatian,
+        Self::Czech,
+        Self::Danish,
+        Self::Dutch,
+        Self::English,
+        Self::Estonian,
+        Self::Finnish,
+        Self::French,
+        Self::Galician,
+        Self::German,
+        Self::Greek,
+        Self::Hebrew,
+        Self::Hindi,
+        Self::Hungarian,
+        Self::Icelandic,
+        Self::Indonesian,
+        Self::Italian,
+        Self::Japanese,
+        Self::Kannada,
+        Self::Kazakh,
+        Self::Korean,
+        Self::Latvian,
+        Self::Lithuanian,
+        Self::Macedonian,
+        Self::Malay,
+        Self::Marathi,
+        Self::Maori,
+        Self::Nepali,
+        Self::Norwegian,
+        Self::Persian,
+        Self::Polish,
+        Self::Portuguese,
+        Self::Romanian,
+        Self::Russian,
+        Self::Serbian,
+        Self::Slovak,
+        Self::Slovenian,
+        Self::Spanish,
+        Self::Swahili,
+        Self::Swedish,
+        Self::Tagalog,
+        Self::Tamil,
+        Self::Thai,
+        Self::Turkish,
+        Self::Ukrainian,
+        Self::Urdu,
+        Self::Vietnamese,
+        Self::Welsh,
+    ];
+
+    /// Returns the ISO-639-1 code of the language
+    #[inline]
+    pub fn code(&self) -> &str {
+        return match self {
+            Self::Afrikaans => "af",
+            Self::Arabic => "ar",
+            Self::Armenian => "hy",
+            Self::Azerbaijani => "az",
+            Self::Belarusian => "be",
+            Self::Bosnian => "bs",
+            Self::Bulgarian => "bg",
+            Self::Catalan => "ca",
+            Self::Chinese => "zh",
+            Self::Croatian => "hr",
+            Self::Czech => "cs",
+            Self::Danish => "da",
+            Self::Dutch => "nl",
+            Self::English => "en",
+            Self::Estonian => "et",
+            Self::Finnish => "fi",
+            Self::French => "fr",
+            Self::Galician => "gl",
+            Self::German => "de",
+            Self::Greek => "el",
+            Self::Hebrew => "he",
+            Self::Hindi => "hi",
+            Self::Hungarian => "hu",
+            Self::Icelandic => "is",
+            Self::Indonesian => "id",
+            Self::Italian => "it",
+            Self::Japanese => "ja",
+            Self::Kannada => "kn",
+            Self::Kazakh => "kk",
+            Self::Korean => "ko",
+            Self::Latvian => "lv",
+            Self::Lithuanian => "lt",
+            Self::Macedonian => "mk",
+            Self::Malay => "ms",
+            Self::Marathi => "mr",
+            Self::Maori => "mi",
+            Self::Nepali => "ne",
+            Self::Norwegian => "no",
+            Self::Persian => "fa",
+            Self::Polish => "pl",
+            Self::Portuguese => "pt",
+            Self::Romanian => "ro",
+            Self::Russian => "ru",
+            Self::Serbian => "sr",
+            Self::Slovak => "sk",
+            Self::Slovenian => "sl",
+            Self::Spanish => "es",
+            Self::Swahili => "sw",
+            Self::Swedish => "sv",
+            Self::Tagalog => "tl",
+            Self::Tamil => "ta",
+            Self::Thai => "th",
+            Self::Turkish => "tr",
+            Self::Ukrainian => "uk",
+            Self::Urdu => "ur",
+            Self::Vietnamese => "vi",
+            Self::Welsh => "cy",
+            Self::Other(other) => other,
+        };
+    }
+
+    /// Returns the English name of the language, in lowercase, as returned by the API
+    #[inline]
+    pub fn name(&self) -> &str {
+        return match self {
+            Self::Afrikaans => "afrikaans",
+            Self::Arabic => "arabic",
+            Self::Armenian => "armenian",
+            Self::Azerbaijani => "azerbaijani",
+            Self::Belarusian => "belarusian",
+            Self::Bosnian => "bosnian",
+            Self::Bulgarian => "bulgarian",
+            Self::Catalan => "catalan",
+            Self::Chinese => "chinese",
+            Self::Croatian => "croatian",
+            Self::Czech => "czech",
+            Self::Danish => "danish",
+            Self::Dutch => "dutch",
+            Self::English => "english",
+            Self::Estonian => "estonian",
+            Self::Finnish => "finnish",
+            Self::French => "french",
+            Self::Galician => "galician",
+            Self::German => "german",
+            Self::Greek => "greek",
+            Self::Hebrew => "hebrew",
+            Self::Hindi => "hindi",
+            Self::Hungarian => "hungarian",
+            Self::Icelandic => "icelandic",
+            Self::Indonesian => "indonesian",
+            Self::Italian => "italian",
+            Self::Japanese => "japanese",
+            Self::Kannada => "kannada",
+            Self::Kazakh => "kazakh",
+            Self::Korean => "korean",
+            Self::Latvian => "latvian",
+            Self::Lithuanian => "lithuanian",
+            Self::Macedonian => "macedonian",
+            Self::Malay => "malay",
+            Self::Marathi => "marathi",
+            Self::Maori => "maori",
+            Self::Nepali => "nepali",
+            Self::Norwegian => "norwegian",
+            Self::Persian => "persian",
+            Self::Polish => "polish",
+            Self::Portuguese => "portuguese",
+            Self::Romanian => "romanian",
+            Self::Russian => "russian",
+            Self::Serbian => "serbian",
+            Self::Slovak => "slovak",
+            Self::Slovenian => "slovenian",
+            Self::Spanish => "spanish",
+            Self::Swahili => "swahili",
+            Self::Swedish => "swedish",
+            Self::Tagalog => "tagalog",
+            Self::Tamil => "tamil",
+            Self::Thai => "thai",
+            Self::Turkish => "turkish",
+            Self::Ukrainian => "ukrainian",
+            Self::Urdu => "urdu",
+            Self::Vietnamese => "vietnamese",
+            Self::Welsh => "welsh",
+            Self::Other(other) => other,
+        };
+    }
+
+    /// Returns the known language with the ISO-639-1 code or English name `language`, ignoring case
+    pub fn from_known(language: &str) -> Option<Self> {
+        return Self::KNOWN
+            .iter()
+            .find(|known| {
+                known.code().eq_ignore_ascii_case(language)
+                    || known.name().eq_ignore_ascii_case(language)
+            })
+            .cloned();
+    }
+}
+
+impl FromStr for Language {
+    type Err = Error;
+
+    /// Parses a known language from its ISO-639-1 code or English name. Unknown languages must be built explicitly with [`Language::Other`].
+    fn from_str(s: &str) -> Result<Self> {
+        return Self::from_known(s.trim())
+            .ok_or_else(|| Error::msg(format!("'{s}' isn't a known ISO-639-1 language")));
+    }
+}
+
+impl Display for Language {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl Serialize for Language {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Language {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let language = String::deserialize(deserializer)?;
+        return Ok(Self::from_known(&language).unwrap_or(Self::Other(language)));
+    }
+}
@@ -0,0 +1,314 @@
+use crate::{
+    endpoint::Endpoint,
+    error::{BuilderError, Error, Result},
+    logging::{RequestBuilderExt, ResponseExt},
+    rt, Client, Str,
+};
+use bytes::Bytes;
+use futures::{AsyncWriteExt, Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ops::RangeInclusive, path::Path};
+
+/// Voice of the generated audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Voice {
+    Alloy,
+    Ash,
+    /// Only supported by `gpt-4o-mini-tts`
+    Ballad,
+    Coral,
+    Echo,
+    Fable,
+    Onyx,
+    Nova,
+    Sage,
+    Shimmer,
+    /// Only supported by `gpt-4o-mini-tts`
+    Verse,
+}
+
+/// Format of the generated audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum SpeechFormat {
+    #[default]
+    Mp3,
+    Opus,
+    Aac,
+    Flac,
+    Wav,
+    /// Raw samples, 24kHz 16-bit signed little-endian, without a header
+    Pcm,
+}
+
+/// Generates audio from the input text.
+///
+/// The text is given when the request is sent, so a builder can be reused to voice many replies the same way.
+#[derive(Debug, Clone, Serialize)]
+pub struct SpeechBuilder<'a> {
+    model: Str<'a>,
+    voice: Voice,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<SpeechFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speed: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<Str<'a>>,
+    /// Parameters not supported by the builder, sent as is
+    #[serde(flatten)]
+    extra: HashMap<Str<'a>, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct SpeechRequest<'a, 'b> {
+    #[serde(flatten)]
+    builder: &'b SpeechBuilder<'a>,
+    input: &'b str,
+}
+
+impl<'a> SpeechBuilder<'a> {
+    /// Creates a new speech request builder, with the specified TTS model (e.g. `tts-1`) and voice
+    #[inline]
+    pub fn new(model: impl Into<Str<'a>>, voice: Voice) -> Self {
+        return Self {
+            model: model.into(),
+            voice,
+            response_format: None,
+            speed: None,
+            instructions: None,
+            extra: HashMap::new(),
+        };
+    }
+
+    /// Adds a parameter the builder doesn't have yet to the speech request
+    #[inline]
+    pub fn extra_param(
+        mut self,
+        key: impl Into<Str<'a>>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
+    /// The format of the audio. Defaults to [`Mp3`](SpeechFormat::Mp3).
+    pub fn response_format(mut self, response_format: SpeechFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    /// The speed of the generated audio, between 0.25 and 4. Defaults to 1.
+    pub fn speed(mut self, speed: f64) -> Result<Self, BuilderError<Self>> {
+        const RANGE: RangeInclusive<f64> = 0.25f64..=4f64;
+        match RANGE.contains(&speed) {
+            true => {
+                self.speed = Some(speed);
+                Ok(self)
+            }
+            false => Err(BuilderError::msg(
+                self,
+                format!("speed out of range ({RANGE:?})"),
+            )),
+        }
+    }
+
+    /// Instructions to control the voice of the generated audio (e.g. its tone or accent). Not supported by `tts-1` and `tts-1-hd`.
+    pub fn instructions(mut self, instructions: impl Into<Str<'a>>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Generates the audio of `input`
+    pub async fn build(&self, input: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Bytes> {
+        return Ok(self
+            .send(input.as_ref(), client.as_ref())
+            .await?
+            .bytes()
+            .await?);
+    }
+
+    /// Generates the audio of `input`, returning it in chunks as they're received
+    pub async fn build_stream(
+        &self,
+        input: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<impl Stream<Item = Result<Bytes>>> {
+        let resp = self.send(input.as_ref(), client.as_ref()).await?;
+        return Ok(resp.bytes_stream().map_err(Error::from));
+    }
+
+    /// Generates the audio of `input`, and saves it into the file at `path` (which is created, or truncated if it exists) as it's received.
+    ///
+    /// The file is only created once the API accepts the request.
+    pub async fn save_to(
+        &self,
+        input: impl AsRef<str>,
+        path: impl AsRef<Path>,
+        client: impl AsRef<Client>,
+    ) -> Result<()> {
+        let stream = self.build_stream(input, client).await?;
+        let mut w = rt::create_file(path).await?;
+        write_stream(stream, &mut w).await?;
+        w.close().await?;
+        return Ok(());
+    }
+
+    /// Generates the audio of `input`, and writes it into the specified [`futures::io::AsyncWrite`] writer as it's received
+    pub async fn write_into<W: ?Sized + Unpin + futures::io::AsyncWrite>(
+        &self,
+        input: impl AsRef<str>,
+        w: &mut W,
+        client: impl AsRef<Client>,
+    ) -> Result<()> {
+        let stream = self.build_stream(input, client).await?;
+        return write_stream(stream, w).await;
+    }
+
+    /// Generates the audio of `input`, and writes it into the specified [`tokio::io::AsyncWrite`] writer as it's received
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    #[cfg(feature = "tokio")]
+    pub async fn write_into_tokio<W: ?Sized + Unpin + tokio::io::AsyncWrite>(
+        &self,
+        input: impl AsRef<str>,
+        w: &mut W,
+        client: impl AsRef<Client>,
+    ) -> Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let stream = self.build_stream(input, client).await?;
+        futures::pin_mut!(stream);
+        while let Some(chunk) = stream.try_next().await? {
+            w.write_all(&chunk).await?;
+        }
+        w.flush().await?;
+        return Ok(());
+    }
+
+    /// Returns the format of the generated audio
+    #[inline]
+    pub fn format(&self) -> SpeechFormat {
+        return self.response_format.unwrap_or_default();
+    }
+
+    /// Checks that the model supports the voice and the instructions
+    fn validate(&self) -> Result<()> {
+        if !self.voice.is_supported_by(&self.model) {
+            return Err(Error::msg(format!(
+                "the voice '{}' isn't supported by the model '{}'",
+                self.voice, self.model
+            )));
+        }
+        if self.instructions.is_some() && is_classic_tts(&self.model) {
+            return Err(Error::msg(format!(
+                "instructions aren't supported by the model '{}'",
+                self.model
+            )));
+        }
+        return Ok(());
+    }
+
+    async fn send(&self, input: &str, client: &Client) -> Result<reqwest::Response> {
+        self.validate()?;
+        return client
+            .post(client.url(Endpoint::Audio, "/speech"))
+            .json_body(&SpeechRequest {
+                builder: self,
+                input,
+            })
+            .send()
+            .await?
+            .check_status()
+            .await;
+    }
+}
+
+impl Default for SpeechBuilder<'_> {
+    /// Creates a builder with the `tts-1` model and the [`Alloy`](Voice::Alloy) voice
+    #[inline]
+    fn default() -> Self {
+        return Self::new("tts-1", Voice::Alloy);
+    }
+}
+
+impl Voice {
+    /// Returns the name of the voice, as used by the API
+    #[inline]
+    pub fn as_str(self) -> &'static str {
+        return match self {
+            Self::Alloy => "alloy",
+            Self::Ash => "ash",
+            Self::Ballad => "ballad",
+            Self::Coral => "coral",
+            Self::Echo => "echo",
+            Self::Fable => "fable",
+            Self::Onyx => "onyx",
+            Self::Nova => "nova",
+            Self::Sage => "sage",
+            Self::Shimmer => "shimmer",
+            Self::Verse => "verse",
+        };
+    }
+
+    /// Returns `false` if the voice is known not to be supported by `model`
+    #[inline]
+    pub fn is_supported_by(self, model: &str) -> bool {
+        return !(is_classic_tts(model) && matches!(self, Self::Ballad | Self::Verse));
+    }
+}
+
+impl SpeechFormat {
+    /// Returns the file extension of the format
+    #[inline]
+    pub fn extension(self) -> &'static str {
+        return match self {
+            Self::Mp3 => "mp3",
+            Self::Opus => "opus",
+            Self::Aac => "aac",
+            Self::Flac => "flac",
+            Self::Wav => "wav",
+            Self::Pcm => "pcm",
+        };
+    }
+
+    /// Returns the MIME type of the format
+    #[inline]
+    pub fn content_type(self) -> &'static str {
+        return match self {
+            Self::Mp3 => "audio/mpeg",
+            Self::Opus => "audio/ogg",
+            Self::Aac => "audio/aac",
+            Self::Flac => "audio/flac",
+            Self::Wav => "audio/wav",
+            Self::Pcm => "audio/pcm",
+        };
+    }
+}
+
+impl std::fmt::Display for Voice {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Writes the chunks of `stream` into `w`, flushing it at the end
+async fn write_stream<W: ?Sized + Unpin + futures::io::AsyncWrite>(
+    stream: impl Stream<Item = Result<Bytes>>,
+    w: &mut W,
+) -> Result<()> {
+    futures::pin_mut!(stream);
+    while let Some(chunk) = stream.try_next().await? {
+        w.write_all(&chunk).await?;
+    }
+    w.flush().await?;
+    return Ok(());
+}
+
+/// Returns `true` for `tts-1` and `tts-1-hd`, which only support the original voices and no instructions
+#[inline]
+fn is_classic_tts(model: &str) -> bool {
+    return model == "tts-1" || model.starts_with("tts-1-");
+}
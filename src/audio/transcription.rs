@@ -1,50 +1,79 @@
-use super::{parse_audio_response, AudioResponse, AudioResponseFormat};
+use super::{
+    chunking::Chunking, language::Language, parse_audio_response, AudioResponse,
+    AudioResponseFormat,
+};
 use crate::{
+    common::estimate_tokens,
+    endpoint::Endpoint,
     error::{BuilderError, Error, Result},
-    Client,
+    multipart::{FilePart, MultipartRequest},
+    rt, Client, OpenAiStream,
 };
 use bytes::Bytes;
-use futures::TryStream;
+use futures::{TryStream, TryStreamExt};
 use rand::random;
-use reqwest::{
-    multipart::{Form, Part},
-    Body,
-};
+use reqwest::{multipart::Part, Body};
+use serde::Deserialize;
 use std::{borrow::Cow, ffi::OsStr, ops::RangeInclusive, path::Path};
+#[cfg(feature = "tokio")]
 use tokio_util::io::ReaderStream;
 
 /// Transcribes audio into the input language.
 #[derive(Debug, Clone)]
 pub struct TranscriptionBuilder {
+    model: String,
     prompt: Option<String>,
-    response_format: AudioResponseFormat,
+    response_format: Option<AudioResponseFormat>,
     temperature: Option<f64>,
-    language: Option<String>,
+    language: Option<Language>,
+    chunking: Option<Chunking>,
 }
 
 impl TranscriptionBuilder {
     #[inline]
     pub fn new() -> Self {
         return Self {
+            model: String::from("whisper-1"),
             prompt: None,
-            response_format: AudioResponseFormat::VerboseJson,
+            response_format: None,
             temperature: None,
             language: None,
+            chunking: None,
         };
     }
 
+    /// The model used to transcribe the audio (e.g. `gpt-4o-transcribe`). Defaults to `whisper-1`.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
     /// An optional text to guide the model's style or continue a previous audio segment. The prompt should match the audio language.
     pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
         self.prompt = Some(prompt.into());
         self
     }
 
-    /// The format of the transcript output
+    /// Guides the model with the vocabulary hints and previous context of a [`TranscriptionPrompt`].
+    #[inline]
+    pub fn prompt_hints(self, prompt: &TranscriptionPrompt) -> Self {
+        return self.prompt(prompt.build());
+    }
+
+    /// The format of the transcript output. Defaults to [`VerboseJson`](AudioResponseFormat::VerboseJson), or to [`Json`](AudioResponseFormat::Json) for the models that don't support it.
     pub fn response_format(mut self, response_format: AudioResponseFormat) -> Self {
-        self.response_format = response_format;
+        self.response_format = Some(response_format);
         self
     }
 
+    /// Returns the format of the transcript output
+    #[inline]
+    pub fn format(&self) -> AudioResponseFormat {
+        return self
+            .response_format
+            .unwrap_or_else(|| AudioResponseFormat::default_for(&self.model));
+    }
+
     /// The sampling temperature, between 0 and 1. Higher values like 0.8 will make the output more random, while lower values like 0.2 will make it more focused and deterministic. If set to 0, the model will use log probability to automatically increase the temperature until certain thresholds are hit.
     pub fn temperature(mut self, temperature: f64) -> Result<Self, BuilderError<Self>> {
         const RANGE: RangeInclusive<f64> = 0f64..=1f64;
@@ -60,9 +89,17 @@ impl TranscriptionBuilder {
         }
     }
 
-    /// The language of the input audio. Supplying the input language in ISO-639-1 format will improve accuracy and latency.
-    pub fn language(mut self, language: impl Into<String>) -> Self {
-        self.language = Some(language.into());
+    /// The language of the input audio. Supplying the input language will improve accuracy and latency.
+    pub fn language(mut self, language: Language) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    /// Splits audio larger than the limit of `chunking` into chunks, which are transcribed separately and stitched back together, with their timestamps shifted to match the whole audio.
+    ///
+    /// Only the audio sent with [`with_file`](TranscriptionBuilder::with_file) and [`with_bytes`](TranscriptionBuilder::with_bytes) is split, and it must be in WAV if it's over the limit.
+    pub fn chunking(mut self, chunking: Chunking) -> Self {
+        self.chunking = Some(chunking);
         self
     }
 
@@ -73,19 +110,42 @@ impl TranscriptionBuilder {
         client: impl AsRef<Client>,
     ) -> Result<AudioResponse> {
         let image = image.as_ref();
-        let name = image
-            .file_name()
-            .map(OsStr::to_string_lossy)
-            .map(Cow::into_owned)
-            .ok_or_else(|| Error::msg("File name not found"))?;
+        let name = file_name(image)?;
 
-        let image = Body::from(tokio::fs::File::open(image).await?);
-        let image = Part::stream(Body::from(image)).file_name(name);
+        if let Some(chunking) = self.chunking {
+            let audio = Bytes::from(rt::read(image).await?);
+            return chunking
+                .transcribe(self, audio, name, client.as_ref())
+                .await;
+        }
 
-        return self.with_part(image, client).await;
+        return self
+            .with_part(FilePart::file(image).file_name(name), client)
+            .await;
+    }
+
+    /// Sends the request with the specified audio.
+    pub async fn with_bytes(
+        self,
+        audio: impl Into<Bytes>,
+        extension: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<AudioResponse> {
+        let name = format!("{}.{}", random::<u64>(), extension.as_ref());
+        if let Some(chunking) = self.chunking {
+            return chunking
+                .transcribe(self, audio.into(), name, client.as_ref())
+                .await;
+        }
+
+        return self
+            .with_part(FilePart::bytes(audio).file_name(name), client)
+            .await;
     }
 
     /// Sends the request with the specified file.
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    #[cfg(feature = "tokio")]
     pub async fn with_tokio_reader<I>(
         self,
         image: I,
@@ -133,35 +193,242 @@ impl TranscriptionBuilder {
     }
 
     /// Sends the request with the specified file.
-    pub async fn with_part(self, file: Part, client: impl AsRef<Client>) -> Result<AudioResponse> {
-        let mut body = Form::new()
-            .text("model", "whisper-1")
-            .text(
-                "response_format",
-                match serde_json::to_value(&self.response_format)? {
-                    serde_json::Value::String(x) => x,
-                    _ => return Err(Error::msg("Unexpected error")),
-                },
-            )
-            .part("file", file);
+    ///
+    /// Unlike the other files, a [`Part`] can only be sent once, so the request isn't retried.
+    pub async fn with_part(
+        self,
+        file: impl Into<FilePart>,
+        client: impl AsRef<Client>,
+    ) -> Result<AudioResponse> {
+        let response_format = self.format();
+        let resp = self.into_request(file)?.send(client).await?;
+        return parse_audio_response(resp, response_format).await;
+    }
+
+    /// Sends the stream request with the specified file, which yields the text of the transcript as it's generated.
+    ///
+    /// Streaming isn't supported by `whisper-1`, use `gpt-4o-transcribe` instead.
+    pub async fn stream_file(
+        self,
+        file: impl AsRef<Path>,
+        client: impl AsRef<Client>,
+    ) -> Result<TranscriptStream> {
+        let file = file.as_ref();
+        let part = FilePart::file(file).file_name(file_name(file)?);
+        return self.stream_part(part, client).await;
+    }
+
+    /// Sends the stream request with the specified file, which yields the text of the transcript as it's generated.
+    ///
+    /// Streaming isn't supported by `whisper-1`, use `gpt-4o-transcribe` instead.
+    pub async fn stream_part(
+        self,
+        file: impl Into<FilePart>,
+        client: impl AsRef<Client>,
+    ) -> Result<TranscriptStream> {
+        if self.model.starts_with("whisper") {
+            return Err(Error::msg(format!(
+                "streaming isn't supported by the model '{}'",
+                self.model
+            )));
+        }
+
+        let resp = self
+            .into_request(file)?
+            .text("stream", "true")
+            .send(client)
+            .await?;
+        return Ok(TranscriptStream::from_bytes_stream(resp.bytes_stream()));
+    }
+
+    /// Creates the request with the specified file, to add fields to it before it's sent.
+    ///
+    /// Its response can be parsed with [`parse_audio_response`], in the request's [format](TranscriptionBuilder::format).
+    pub fn into_request(self, file: impl Into<FilePart>) -> Result<MultipartRequest> {
+        let response_format = self.format();
+        response_format.validate(&self.model)?;
+
+        return MultipartRequest::new(Endpoint::Audio, "/transcriptions")
+            .text("model", self.model)
+            .field("response_format", &response_format)?
+            .part("file", file)
+            .field_opt("prompt", self.prompt.as_ref())?
+            .field_opt("temperature", self.temperature.as_ref())?
+            .field_opt("language", self.language.as_ref());
+    }
+}
+
+/// A [`Stream`](futures::Stream) of [`TranscriptEvent`]s, emitted while audio is transcribed
+pub type TranscriptStream = OpenAiStream<TranscriptEvent>;
+
+/// An event emitted while audio is transcribed
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+#[non_exhaustive]
+pub enum TranscriptEvent {
+    /// Text appended to the transcript
+    #[serde(rename = "transcript.text.delta")]
+    Delta { delta: String },
+    /// The whole transcript, once the audio has been transcribed
+    #[serde(rename = "transcript.text.done")]
+    Done {
+        text: String,
+        /// Tokens used by the transcription, as reported by the API
+        #[serde(default)]
+        usage: Option<serde_json::Value>,
+    },
+    /// Event type not supported by the library
+    #[serde(other)]
+    Unknown,
+}
+
+impl TranscriptEvent {
+    /// Returns the text appended to the transcript by the event, if any
+    #[inline]
+    pub fn delta(&self) -> Option<&str> {
+        return match self {
+            TranscriptEvent::Delta { delta } => Some(delta),
+            _ => None,
+        };
+    }
+}
 
-        if let Some(prompt) = self.prompt {
-            body = body.text("prompt", prompt)
+impl TranscriptStream {
+    /// Consumes the stream, skipping the deltas and returning the whole transcript
+    pub async fn into_text(mut self) -> Result<String> {
+        while let Some(event) = self.try_next().await? {
+            if let TranscriptEvent::Done { text, .. } = event {
+                return Ok(text);
+            }
         }
-        if let Some(temperature) = self.temperature {
-            body = body.text("temperature", format!("{temperature}"))
+        return Err(Error::msg(
+            "Stream ended before the transcript was completed",
+        ));
+    }
+}
+
+/// Returns the file name of `path`
+fn file_name(path: &Path) -> Result<String> {
+    return path
+        .file_name()
+        .map(OsStr::to_string_lossy)
+        .map(Cow::into_owned)
+        .ok_or_else(|| Error::msg("File name not found"));
+}
+
+/// Tokens of the prompt considered by the transcription model. Earlier tokens are ignored.
+const MAX_PROMPT_TOKENS: u64 = 224;
+/// Model whose tokenizer approximates the transcription model's
+const TOKENIZER_MODEL: &str = "gpt2";
+
+/// Assembles the prompt of a transcription from the spellings of domain-specific terms and the transcript of the previous audio segment.
+///
+/// The prompt is truncated to the window considered by the model, dropping the earliest words of the context, and then the last vocabulary terms.
+#[derive(Debug, Clone)]
+pub struct TranscriptionPrompt {
+    vocabulary: Vec<String>,
+    context: Option<String>,
+    max_tokens: u64,
+}
+
+impl TranscriptionPrompt {
+    #[inline]
+    pub fn new() -> Self {
+        return Self {
+            vocabulary: Vec::new(),
+            context: None,
+            max_tokens: MAX_PROMPT_TOKENS,
+        };
+    }
+
+    /// Adds a term to the vocabulary, spelled the way it should be transcribed (e.g. names, acronyms or jargon). Earlier terms are kept first when the prompt is truncated.
+    pub fn term(mut self, term: impl Into<String>) -> Self {
+        self.vocabulary.push(term.into());
+        self
+    }
+
+    /// Adds terms to the vocabulary. See [`term`](TranscriptionPrompt::term)
+    pub fn vocabulary<I: IntoIterator<Item = T>, T: Into<String>>(mut self, terms: I) -> Self {
+        self.vocabulary.extend(terms.into_iter().map(Into::into));
+        self
+    }
+
+    /// The transcript of the previous audio segment, which the transcription continues
+    pub fn context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Maximum number of tokens of the prompt. Defaults to 224, the window considered by the model.
+    pub fn max_tokens(mut self, max_tokens: u64) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Builds the prompt, truncated to fit within [`max_tokens`](TranscriptionPrompt::max_tokens)
+    pub fn build(&self) -> String {
+        let mut terms = self.vocabulary.len();
+        loop {
+            let glossary = match terms {
+                0 => String::new(),
+                _ => format!("Glossary: {}.", self.vocabulary[..terms].join(", ")),
+            };
+
+            let remaining = self
+                .max_tokens
+                .saturating_sub(estimate_tokens(TOKENIZER_MODEL, &glossary));
+            if terms > 0 && remaining == 0 {
+                terms -= 1;
+                continue;
+            }
+
+            let context = match self.context {
+                Some(ref context) => Self::tail(context, remaining),
+                None => "",
+            };
+            return match (glossary.is_empty(), context.is_empty()) {
+                (_, true) => glossary,
+                (true, false) => context.to_string(),
+                (false, false) => {
+                    let prompt = format!("{glossary} {context}");
+                    match estimate_tokens(TOKENIZER_MODEL, &prompt) <= self.max_tokens {
+                        true => prompt,
+                        // Joining the glossary and the context may add a token
+                        false => format!(
+                            "{glossary} {}",
+                            Self::tail(context, remaining.saturating_sub(1))
+                        ),
+                    }
+                }
+            };
         }
-        if let Some(language) = self.language {
-            body = body.text("language", language)
+    }
+
+    /// Returns the longest suffix of `text` that starts at a word and fits within `max_tokens`
+    fn tail(text: &str, max_tokens: u64) -> &str {
+        let text = text.trim();
+        if estimate_tokens(TOKENIZER_MODEL, text) <= max_tokens {
+            return text;
         }
 
-        let resp = client
-            .as_ref()
-            .post("https://api.openai.com/v1/audio/transcriptions")
-            .multipart(body)
-            .send()
-            .await?;
+        let mut starts = text
+            .char_indices()
+            .filter(|&(i, c)| i > 0 && c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .collect::<Vec<_>>();
+        starts.push(text.len());
 
-        return parse_audio_response(resp, self.response_format).await;
+        // Fewer tokens are left as the suffix starts later
+        let first = starts.partition_point(|&start| {
+            estimate_tokens(TOKENIZER_MODEL, &text[start..]) > max_tokens
+        });
+        return text[starts[first]..].trim_start();
+    }
+}
+
+impl Default for TranscriptionPrompt {
+    #[inline]
+    fn default() -> Self {
+        return Self::new();
     }
 }
@@ -1,23 +1,24 @@
 use super::{parse_audio_response, AudioResponse, AudioResponseFormat};
 use crate::{
+    endpoint::Endpoint,
     error::{BuilderError, Error, Result},
+    multipart::{FilePart, MultipartRequest},
     Client,
 };
 use bytes::Bytes;
 use futures::TryStream;
 use rand::random;
-use reqwest::{
-    multipart::{Form, Part},
-    Body,
-};
+use reqwest::{multipart::Part, Body};
 use std::{borrow::Cow, ffi::OsStr, ops::RangeInclusive, path::Path};
+#[cfg(feature = "tokio")]
 use tokio_util::io::ReaderStream;
 
 /// Translates audio into English.
 #[derive(Debug, Clone)]
 pub struct TranslationBuilder {
+    model: String,
     prompt: Option<String>,
-    response_format: AudioResponseFormat,
+    response_format: Option<AudioResponseFormat>,
     temperature: Option<f64>,
 }
 
@@ -25,24 +26,39 @@ impl TranslationBuilder {
     #[inline]
     pub fn new() -> Self {
         return Self {
+            model: String::from("whisper-1"),
             prompt: None,
-            response_format: AudioResponseFormat::VerboseJson,
+            response_format: None,
             temperature: None,
         };
     }
 
+    /// The model used to translate the audio. Defaults to `whisper-1`.
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
     /// An optional text to guide the model's style or continue a previous audio segment. The prompt should be in English.
     pub fn prompt(mut self, prompt: impl Into<String>) -> Self {
         self.prompt = Some(prompt.into());
         self
     }
 
-    /// The format of the transcript output.
+    /// The format of the translation output. Defaults to [`VerboseJson`](AudioResponseFormat::VerboseJson), or to [`Json`](AudioResponseFormat::Json) for the models that don't support it.
     pub fn response_format(mut self, response_format: AudioResponseFormat) -> Self {
-        self.response_format = response_format;
+        self.response_format = Some(response_format);
         self
     }
 
+    /// Returns the format of the translation output
+    #[inline]
+    pub fn format(&self) -> AudioResponseFormat {
+        return self
+            .response_format
+            .unwrap_or_else(|| AudioResponseFormat::default_for(&self.model));
+    }
+
     /// The sampling temperature, between 0 and 1. Higher values like 0.8 will make the output more random, while lower values like 0.2 will make it more focused and deterministic. If set to 0, the model will use log probability to automatically increase the temperature until certain thresholds are hit.
     pub fn temperature(mut self, temperature: f64) -> Result<Self, BuilderError<Self>> {
         const RANGE: RangeInclusive<f64> = 0f64..=1f64;
@@ -71,13 +87,14 @@ impl TranslationBuilder {
             .map(Cow::into_owned)
             .ok_or_else(|| Error::msg("File name not found"))?;
 
-        let image = Body::from(tokio::fs::File::open(image).await?);
-        let image = Part::stream(Body::from(image)).file_name(name);
-
-        return self.with_part(image, client).await;
+        return self
+            .with_part(FilePart::file(image).file_name(name), client)
+            .await;
     }
 
     /// Sends the request with the specified file.
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    #[cfg(feature = "tokio")]
     pub async fn with_tokio_reader<I>(
         self,
         image: I,
@@ -125,32 +142,30 @@ impl TranslationBuilder {
     }
 
     /// Sends the request with the specified file.
-    pub async fn with_part(self, file: Part, client: impl AsRef<Client>) -> Result<AudioResponse> {
-        let mut body = Form::new()
-            .text("model", "whisper-1")
-            .text(
-                "response_format",
-                match serde_json::to_value(&self.response_format)? {
-                    serde_json::Value::String(x) => x,
-                    _ => return Err(Error::msg("Unexpected error")),
-                },
-            )
-            .part("file", file);
-
-        if let Some(prompt) = self.prompt {
-            body = body.text("prompt", prompt)
-        }
-        if let Some(temperature) = self.temperature {
-            body = body.text("temperature", format!("{temperature}"))
-        }
+    ///
+    /// Unlike the other files, a [`Part`] can only be sent once, so the request isn't retried.
+    pub async fn with_part(
+        self,
+        file: impl Into<FilePart>,
+        client: impl AsRef<Client>,
+    ) -> Result<AudioResponse> {
+        let response_format = self.format();
+        let resp = self.into_request(file)?.send(client).await?;
+        return parse_audio_response(resp, response_format).await;
+    }
 
-        let resp = client
-            .as_ref()
-            .post("https://api.openai.com/v1/audio/translations")
-            .multipart(body)
-            .send()
-            .await?;
+    /// Creates the request with the specified file, to add fields to it before it's sent.
+    ///
+    /// Its response can be parsed with [`parse_audio_response`], in the request's [format](TranslationBuilder::format).
+    pub fn into_request(self, file: impl Into<FilePart>) -> Result<MultipartRequest> {
+        let response_format = self.format();
+        response_format.validate(&self.model)?;
 
-        return parse_audio_response(resp, self.response_format).await;
+        return MultipartRequest::new(Endpoint::Audio, "/translations")
+            .text("model", self.model)
+            .field("response_format", &response_format)?
+            .part("file", file)
+            .field_opt("prompt", self.prompt.as_ref())?
+            .field_opt("temperature", self.temperature.as_ref());
     }
 }
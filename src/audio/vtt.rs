@@ -0,0 +1,146 @@
+use crate::error::{Error, Result};
+use std::{fmt::Write, time::Duration};
+
+/// A cue of a WebVTT document, with the text shown between two times
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VttCue {
+    /// Identifier of the cue, if it has one
+    pub identifier: Option<String>,
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+impl VttCue {
+    /// Creates a new cue without an identifier
+    #[inline]
+    pub fn new(start: Duration, end: Duration, text: impl Into<String>) -> Self {
+        return Self {
+            identifier: None,
+            start,
+            end,
+            text: text.into(),
+        };
+    }
+
+    /// Returns the duration of the cue
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        return self.end.saturating_sub(self.start);
+    }
+}
+
+/// Parses the cues of a WebVTT document.
+///
+/// Comments, styles and regions are skipped, as are the settings of the cues.
+pub fn parse(text: &str) -> Result<Vec<VttCue>> {
+    let text = text
+        .trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+    let mut lines = text.lines();
+
+    let header = lines.next().unwrap_or_default();
+    if header != "WEBVTT" && !header.starts_with("WEBVTT ") && !header.starts_with("WEBVTT\t") {
+        return Err(Error::msg("the WebVTT document has no 'WEBVTT' header"));
+    }
+    // The header may be followed by metadata, up to the first blank line
+    lines.by_ref().take_while(|line| !line.is_empty()).count();
+
+    let mut cues = Vec::new();
+    let mut block = Vec::new();
+    for line in lines.chain([""]) {
+        if !line.trim().is_empty() {
+            block.push(line);
+            continue;
+        }
+        if let Some(cue) = parse_block(&block)? {
+            cues.push(cue);
+        }
+        block.clear();
+    }
+
+    return Ok(cues);
+}
+
+/// Writes cues into a WebVTT document
+pub fn format(cues: &[VttCue]) -> String {
+    let mut result = String::from("WEBVTT\n");
+    for cue in cues {
+        result.push('\n');
+        if let Some(ref identifier) = cue.identifier {
+            result.push_str(identifier);
+            result.push('\n');
+        }
+        let _ = writeln!(
+            result,
+            "{} --> {}",
+            format_timestamp(cue.start),
+            format_timestamp(cue.end)
+        );
+        result.push_str(&cue.text);
+        result.push('\n');
+    }
+    return result;
+}
+
+/// Parses a block of lines, returning `None` if it isn't a cue
+fn parse_block(block: &[&str]) -> Result<Option<VttCue>> {
+    let (identifier, timing, text) = match block {
+        [] => return Ok(None),
+        [timing, text @ ..] if timing.contains("-->") => (None, *timing, text),
+        [first, ..]
+            if ["NOTE", "STYLE", "REGION"]
+                .iter()
+                .any(|x| first.starts_with(x)) =>
+        {
+            return Ok(None)
+        }
+        [identifier, timing, text @ ..] => (Some(identifier.to_string()), *timing, text),
+        [line] => return Err(Error::msg(format!("invalid WebVTT cue '{line}'"))),
+    };
+
+    let invalid_timing = || Error::msg(format!("invalid WebVTT cue timing '{timing}'"));
+    let (start, end) = timing.split_once("-->").ok_or_else(invalid_timing)?;
+    // The end time may be followed by the settings of the cue
+    let end = end.split_whitespace().next().unwrap_or_default();
+
+    return Ok(Some(VttCue {
+        identifier,
+        start: parse_timestamp(start.trim()).ok_or_else(invalid_timing)?,
+        end: parse_timestamp(end).ok_or_else(invalid_timing)?,
+        text: text.join("\n"),
+    }));
+}
+
+/// Parses a timestamp of the form `hh:mm:ss.ttt`, where the hours are optional
+fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let (time, millis) = timestamp.split_once('.')?;
+    let parts = time.split(':').collect::<Vec<_>>();
+    if !(2..=3).contains(&parts.len()) || millis.len() != 3 {
+        return None;
+    }
+
+    let number = |x: &str| match !x.is_empty() && x.bytes().all(|b| b.is_ascii_digit()) {
+        true => x.parse::<u64>().ok(),
+        false => None,
+    };
+
+    let mut secs = 0;
+    for part in parts {
+        secs = secs * 60 + number(part)?;
+    }
+    return Some(Duration::from_secs(secs) + Duration::from_millis(number(millis)?));
+}
+
+fn format_timestamp(timestamp: Duration) -> String {
+    let millis = timestamp.as_millis();
+    return format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    );
+}
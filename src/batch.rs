@@ -0,0 +1,472 @@
+use crate::{
+    chat::ChatCompletionBuilder,
+    common::{ListParams, Paginator},
+    completion::CompletionBuilder,
+    embeddings::EmbeddingBuilder,
+    endpoint::Endpoint,
+    error::{Error, FallibleResponse, OpenAiError, Result},
+    file::{
+        jsonl::{JsonlSummary, JsonlWriter},
+        retreive_file_content, File, FileContent,
+    },
+    logging::{RequestBuilderExt, ResponseExt},
+    Client, Str,
+};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Create large batches of API requests for asynchronous processing.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct Batch {
+    pub id: String,
+    pub endpoint: String,
+    pub input_file_id: String,
+    pub completion_window: String,
+    pub status: BatchStatus,
+    #[serde(default)]
+    pub output_file_id: Option<String>,
+    #[serde(default)]
+    pub error_file_id: Option<String>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub completed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub request_counts: Option<RequestCounts>,
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Status of a [`Batch`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum BatchStatus {
+    Validating,
+    Failed,
+    InProgress,
+    Finalizing,
+    Completed,
+    Expired,
+    Cancelling,
+    Cancelled,
+}
+
+/// The request counts for different statuses within a [`Batch`]
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[non_exhaustive]
+pub struct RequestCounts {
+    pub total: u64,
+    pub completed: u64,
+    pub failed: u64,
+}
+
+/// A line of a batch's output or error file
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct BatchOutputLine<T = serde_json::Value> {
+    pub id: String,
+    pub custom_id: String,
+    #[serde(default)]
+    pub response: Option<BatchResponse<T>>,
+    #[serde(default)]
+    pub error: Option<BatchError>,
+}
+
+impl<T> FileContent for BatchOutputLine<T>
+where
+    Self: DeserializeOwned,
+{
+    const PURPOSES: &'static [&'static str] = &["batch_output"];
+    const NAME: &'static str = "batch output line";
+}
+
+/// The response to a request of a [`Batch`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct BatchResponse<T = serde_json::Value> {
+    pub status_code: u16,
+    pub request_id: String,
+    pub body: T,
+}
+
+/// Error of a request of a [`Batch`] that failed before receiving a response
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct BatchError {
+    pub code: String,
+    pub message: String,
+}
+
+/// A request builder that can be sent as part of a [`Batch`]
+pub trait BatchRequest: Serialize {
+    /// Relative URL of the request's endpoint
+    const URL: &'static str;
+}
+
+impl BatchRequest for ChatCompletionBuilder<'_> {
+    const URL: &'static str = "/v1/chat/completions";
+}
+
+impl BatchRequest for CompletionBuilder<'_> {
+    const URL: &'static str = "/v1/completions";
+}
+
+impl BatchRequest for EmbeddingBuilder<'_> {
+    const URL: &'static str = "/v1/embeddings";
+}
+
+/// Builder of a batch's JSONL input file
+#[derive(Debug, Clone)]
+pub struct BatchInput {
+    endpoint: Option<&'static str>,
+    lines: Vec<u8>,
+    len: usize,
+}
+
+/// Writer of a batch's JSONL input file, which uploads the requests as they're written instead of keeping them in memory like [`BatchInput`]
+#[derive(Debug)]
+pub struct BatchWriter<R> {
+    writer: JsonlWriter<R>,
+}
+
+/// Line of a batch's input file
+#[derive(Debug, Serialize)]
+struct BatchLine<'a, R> {
+    custom_id: &'a str,
+    method: &'static str,
+    url: &'static str,
+    body: &'a R,
+}
+
+impl Batch {
+    /// Creates and executes a batch from an uploaded file of requests.
+    pub async fn new(
+        input_file_id: impl AsRef<str>,
+        endpoint: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        #[derive(Debug, Serialize)]
+        struct Body<'a> {
+            input_file_id: &'a str,
+            endpoint: &'a str,
+            completion_window: &'a str,
+        }
+
+        let batch = client
+            .as_ref()
+            .post(client.as_ref().url(Endpoint::Batches, ""))
+            .json_body(&Body {
+                input_file_id: input_file_id.as_ref(),
+                endpoint: endpoint.as_ref(),
+                completion_window: "24h",
+            })
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(batch);
+    }
+
+    /// Retrieves a batch.
+    pub async fn retreive(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Self> {
+        let batch = client
+            .as_ref()
+            .get_json::<Self>(
+                client
+                    .as_ref()
+                    .url(Endpoint::Batches, format!("/{}", id.as_ref())),
+            )
+            .await?;
+
+        return Ok(batch);
+    }
+
+    /// Creates a new batch input builder
+    #[inline]
+    pub fn input() -> BatchInput {
+        return BatchInput::new();
+    }
+
+    /// Starts the upload of a batch input, which requests of type `R` are streamed into. See [`BatchWriter`]
+    #[inline]
+    pub fn writer<R: BatchRequest>(
+        filename: Option<String>,
+        client: impl AsRef<Client>,
+    ) -> BatchWriter<R> {
+        return BatchWriter {
+            writer: JsonlWriter::upload(filename, "batch", client),
+        };
+    }
+}
+
+impl Batch {
+    /// Cancels an in-progress batch.
+    #[inline]
+    pub async fn cancel(self, client: impl AsRef<Client>) -> Result<Self> {
+        return cancel_batch(self.id, client).await;
+    }
+
+    /// Downloads the batch's output and error files, returning the result of every request keyed by its `custom_id`.
+    ///
+    /// Requests that haven't finished yet are not included in the result.
+    pub async fn results<T: DeserializeOwned>(
+        &self,
+        client: impl AsRef<Client>,
+    ) -> Result<HashMap<String, Result<T>>> {
+        let client = client.as_ref();
+        let mut results = HashMap::new();
+
+        for file_id in [&self.output_file_id, &self.error_file_id]
+            .into_iter()
+            .flatten()
+        {
+            let mut lines = Box::pin(
+                retreive_file_content::<BatchOutputLine<serde_json::Value>>(file_id, client)
+                    .await?,
+            );
+
+            while let Some(line) = lines.try_next().await? {
+                results.insert(line.custom_id.clone(), line.into_result());
+            }
+        }
+
+        return Ok(results);
+    }
+}
+
+impl<T> BatchOutputLine<T> {
+    /// Returns `true` if the request received a successful response
+    #[inline]
+    pub fn is_success(&self) -> bool {
+        return matches!(self.response, Some(ref resp) if (200..300).contains(&resp.status_code));
+    }
+}
+
+impl BatchOutputLine<serde_json::Value> {
+    /// Parses the line into the response body of the request, or the error it failed with
+    pub fn into_result<T: DeserializeOwned>(self) -> Result<T> {
+        #[derive(Debug, Deserialize)]
+        struct ErrorBody {
+            error: OpenAiError,
+        }
+
+        if let Some(error) = self.error {
+            return Err(Error::msg(format!("{}: {}", error.code, error.message)));
+        }
+
+        return match self.response {
+            Some(resp) if (200..300).contains(&resp.status_code) => {
+                Ok(serde_json::from_value::<T>(resp.body)?)
+            }
+            Some(resp) => match serde_json::from_value::<ErrorBody>(resp.body) {
+                Ok(ErrorBody { error }) => Err(Error::from(error)),
+                Err(_) => Err(Error::msg(format!(
+                    "Request failed with status code {}",
+                    resp.status_code
+                ))),
+            },
+            None => Err(Error::msg("Batch line has neither a response nor an error")),
+        };
+    }
+}
+
+impl BatchInput {
+    /// Creates an empty batch input
+    #[inline]
+    pub fn new() -> Self {
+        return Self {
+            endpoint: None,
+            lines: Vec::new(),
+            len: 0,
+        };
+    }
+
+    /// Adds a request to the batch input, identified by `custom_id`.
+    ///
+    /// All the requests of a batch must target the same endpoint.
+    pub fn push<R: BatchRequest>(&mut self, custom_id: impl AsRef<str>, request: &R) -> Result<()> {
+        match self.endpoint {
+            Some(endpoint) if endpoint != R::URL => {
+                return Err(Error::msg(format!(
+                    "Batch requests must target a single endpoint (found '{endpoint}' and '{}')",
+                    R::URL
+                )))
+            }
+            _ => self.endpoint = Some(R::URL),
+        }
+
+        serde_json::to_writer(
+            &mut self.lines,
+            &BatchLine::new(custom_id.as_ref(), request),
+        )?;
+        self.lines.push(b'\n');
+        self.len += 1;
+
+        return Ok(());
+    }
+
+    /// Adds a request to the batch input, identified by `custom_id`.
+    #[inline]
+    pub fn with<R: BatchRequest>(
+        mut self,
+        custom_id: impl AsRef<str>,
+        request: &R,
+    ) -> Result<Self> {
+        self.push(custom_id, request)?;
+        return Ok(self);
+    }
+
+    /// Creates a batch input from an iterator of `(custom_id, request)` pairs
+    pub fn from_requests<K, R, I>(requests: I) -> Result<Self>
+    where
+        K: AsRef<str>,
+        R: BatchRequest,
+        I: IntoIterator<Item = (K, R)>,
+    {
+        let mut this = Self::new();
+        for (custom_id, request) in requests {
+            this.push(custom_id, &request)?;
+        }
+        return Ok(this);
+    }
+
+    /// Returns the number of requests in the batch input
+    #[inline]
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+
+    /// Returns `true` if the batch input has no requests
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+
+    /// Returns the endpoint targeted by the batch input's requests
+    #[inline]
+    pub fn endpoint(&self) -> Option<&'static str> {
+        return self.endpoint;
+    }
+
+    /// Returns the JSONL contents of the batch input
+    #[inline]
+    pub fn into_bytes(self) -> Vec<u8> {
+        return self.lines;
+    }
+
+    /// Uploads the batch input as a file with the `batch` purpose.
+    pub async fn upload(
+        self,
+        filename: Option<String>,
+        client: impl AsRef<Client>,
+    ) -> Result<File> {
+        let stream = futures::stream::once(std::future::ready(Result::<_>::Ok(self.lines)));
+        return File::upload_byte_stream(stream, filename, Str::Borrowed("batch"), client).await;
+    }
+
+    /// Uploads the batch input and creates a batch that executes it.
+    pub async fn send(self, client: impl AsRef<Client>) -> Result<Batch> {
+        let client = client.as_ref();
+        let endpoint = self
+            .endpoint
+            .ok_or_else(|| Error::msg("Batch input has no requests"))?;
+
+        let file = self.upload(None, client).await?;
+        return Batch::new(&file.id, endpoint, client).await;
+    }
+}
+
+impl Default for BatchInput {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cancels an in-progress batch.
+pub async fn cancel_batch(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Batch> {
+    let batch = client
+        .as_ref()
+        .post(
+            client
+                .as_ref()
+                .url(Endpoint::Batches, format!("/{}/cancel", id.as_ref())),
+        )
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Batch>>()
+        .await?
+        .into_result()?;
+
+    return Ok(batch);
+}
+
+/// List all of your organization's batches. Use [`list_batches`] to fetch them one page at a time.
+pub async fn batches(client: impl AsRef<Client>) -> Result<Vec<Batch>> {
+    return list_batches(ListParams::new(), client).try_collect().await;
+}
+
+/// Returns a stream over your organization's batches, fetching them one page at a time.
+pub fn list_batches(params: ListParams<'_>, client: impl AsRef<Client>) -> Paginator<Batch> {
+    let req = client
+        .as_ref()
+        .get(client.as_ref().url(Endpoint::Batches, ""));
+    return Paginator::new(req, params);
+}
+
+impl<R: BatchRequest> BatchWriter<R> {
+    /// Writes a request into the batch input, identified by `custom_id`
+    #[inline]
+    pub async fn push(&mut self, custom_id: impl AsRef<str>, request: &R) -> Result<()> {
+        return self
+            .writer
+            .write_record(&BatchLine::new(custom_id.as_ref(), request))
+            .await;
+    }
+
+    /// Returns the number of requests written
+    #[inline]
+    pub fn len(&self) -> u64 {
+        return self.writer.records();
+    }
+
+    /// Returns `true` if no request has been written
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        return self.len() == 0;
+    }
+
+    /// Finishes the upload of the batch input, returning the uploaded file and the size of the input
+    #[inline]
+    pub async fn upload(self) -> Result<JsonlSummary> {
+        return self.writer.finish().await;
+    }
+
+    /// Finishes the upload of the batch input and creates a batch that executes it.
+    pub async fn send(self, client: impl AsRef<Client>) -> Result<Batch> {
+        if self.is_empty() {
+            return Err(Error::msg("Batch input has no requests"));
+        }
+
+        let file = self.upload().await?.into_file()?;
+        return Batch::new(&file.id, R::URL, client).await;
+    }
+}
+
+impl<'a, R: BatchRequest> BatchLine<'a, R> {
+    #[inline]
+    fn new(custom_id: &'a str, body: &'a R) -> Self {
+        return Self {
+            custom_id,
+            method: "POST",
+            url: R::URL,
+            body,
+        };
+    }
+}
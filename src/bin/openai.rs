@@ -0,0 +1,507 @@
+//! Command line interface to OpenAI's API, built on top of `libopenai`.
+//!
+//! Install with `cargo install libopenai --features cli`, and run `openai --help` to list the commands.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use futures::{Stream, TryStreamExt};
+use libopenai::{
+    audio::{language::Language, transcription::TranscriptionBuilder, AudioResponse},
+    chat::{ChatCompletion, ChatCompletionChunk, Message},
+    completion::Completion,
+    embeddings::Embedding,
+    error::Result,
+    file::{delete_file, files, retreive_raw_file_content, File},
+    finetune::{fine_tunes, FineTune, FineTuneEvent},
+    image::{ImageData, Images, Naming, Size},
+    Client,
+};
+use serde::Serialize;
+use std::{io::Write, path::PathBuf};
+
+/// Command line interface to OpenAI's API
+#[derive(Debug, Parser)]
+#[command(name = "openai", version)]
+struct Cli {
+    /// API key. Defaults to the environment variable `OPENAI_API_KEY`
+    #[arg(long, global = true, env = "OPENAI_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+    /// Organization the requests are sent on behalf of
+    #[arg(long, global = true, env = "OPENAI_ORGANIZATION")]
+    organization: Option<String>,
+    /// Base URL of the API (e.g. a proxy or a compatible API)
+    #[arg(long, global = true, env = "OPENAI_BASE_URL")]
+    base_url: Option<String>,
+    /// Prints the responses of the API as JSON (one object per line when streaming)
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Sends a message to a chat model, streaming its reply
+    Chat {
+        /// The message
+        prompt: String,
+        #[arg(short, long, default_value = "gpt-4o-mini")]
+        model: String,
+        /// System message that precedes the prompt
+        #[arg(short, long)]
+        system: Option<String>,
+        #[command(flatten)]
+        sampling: Sampling,
+    },
+    /// Completes a prompt with a completion model, streaming the completion
+    Complete {
+        prompt: String,
+        #[arg(short, long, default_value = "gpt-3.5-turbo-instruct")]
+        model: String,
+        #[command(flatten)]
+        sampling: Sampling,
+    },
+    /// Creates an embedding vector for every input
+    Embed {
+        #[arg(required = true)]
+        inputs: Vec<String>,
+        #[arg(short, long, default_value = "text-embedding-3-small")]
+        model: String,
+    },
+    /// Transcribes an audio file
+    Transcribe {
+        file: PathBuf,
+        /// ISO-639-1 code or English name of the audio's language
+        #[arg(short, long)]
+        language: Option<Language>,
+        /// Text to guide the style of the transcript or continue a previous segment
+        #[arg(short, long)]
+        prompt: Option<String>,
+    },
+    /// Generates images from a prompt
+    Image {
+        prompt: String,
+        /// Number of images to generate
+        #[arg(short, default_value_t = 1)]
+        n: u64,
+        #[arg(short, long, value_enum, default_value_t = ImageSize::P1024)]
+        size: ImageSize,
+        /// Directory the images are saved into. The URLs of the images are printed if missing
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Manages uploaded files
+    #[command(subcommand)]
+    Files(FilesCommand),
+    /// Manages fine-tuning jobs
+    #[command(subcommand, name = "fine-tune")]
+    FineTune(FineTuneCommand),
+}
+
+#[derive(Debug, Args)]
+struct Sampling {
+    #[arg(long)]
+    max_tokens: Option<u64>,
+    #[arg(short, long)]
+    temperature: Option<f64>,
+    /// Waits for the whole response instead of streaming it
+    #[arg(long)]
+    no_stream: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum FilesCommand {
+    /// Lists the uploaded files
+    List,
+    /// Uploads a file
+    Upload {
+        path: PathBuf,
+        #[arg(short, long, default_value = "fine-tune")]
+        purpose: String,
+    },
+    /// Prints the metadata of a file
+    Get { id: String },
+    /// Writes the contents of a file to stdout
+    Content { id: String },
+    /// Deletes a file
+    Delete { id: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum FineTuneCommand {
+    /// Creates a fine-tuning job from an uploaded training file
+    Create {
+        training_file: String,
+        #[arg(short, long)]
+        model: Option<String>,
+        #[arg(long)]
+        validation_file: Option<String>,
+    },
+    /// Lists the fine-tuning jobs
+    List,
+    /// Prints a fine-tuning job
+    Get { id: String },
+    /// Prints the events of a fine-tuning job
+    Events {
+        id: String,
+        /// Keeps printing the events as they happen, until the job ends
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Cancels a fine-tuning job
+    Cancel { id: String },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ImageSize {
+    #[value(name = "256")]
+    P256,
+    #[value(name = "512")]
+    P512,
+    #[value(name = "1024")]
+    P1024,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let mut client = Client::builder();
+    if let Some(api_key) = cli.api_key {
+        client = client.api_key(api_key);
+    }
+    if let Some(organization) = cli.organization {
+        client = client.organization(organization);
+    }
+    if let Some(base_url) = cli.base_url {
+        client = client.base_url(base_url);
+    }
+    let client = client.build()?;
+    let json = cli.json;
+
+    match cli.command {
+        Command::Chat {
+            prompt,
+            model,
+            system,
+            sampling,
+        } => {
+            let mut messages = Vec::with_capacity(2);
+            if let Some(system) = system {
+                messages.push(Message::system(system));
+            }
+            messages.push(Message::user(prompt));
+
+            let mut builder = ChatCompletion::builder(model, messages);
+            if let Some(max_tokens) = sampling.max_tokens {
+                builder = builder.max_tokens(max_tokens);
+            }
+            if let Some(temperature) = sampling.temperature {
+                builder = builder.temperature(temperature)?;
+            }
+
+            match sampling.no_stream {
+                true => {
+                    let completion = builder.build(&client).await?;
+                    match json {
+                        true => print_json(&completion)?,
+                        false => println!(
+                            "{}",
+                            completion
+                                .first()
+                                .map(|x| x.message.content.as_ref())
+                                .unwrap_or_default()
+                        ),
+                    }
+                }
+                false => {
+                    let stream = builder.build_stream(&client).await?;
+                    print_stream(stream, json, |chunk: &ChatCompletionChunk| {
+                        chunk.first().and_then(|x| x.delta.content.clone())
+                    })
+                    .await?
+                }
+            }
+        }
+
+        Command::Complete {
+            prompt,
+            model,
+            sampling,
+        } => {
+            let mut builder = Completion::builder(model, prompt);
+            if let Some(max_tokens) = sampling.max_tokens {
+                builder = builder.max_tokens(max_tokens);
+            }
+            if let Some(temperature) = sampling.temperature {
+                builder = builder.temperature(temperature)?;
+            }
+
+            match sampling.no_stream {
+                true => {
+                    let completion = builder.build(&client).await?;
+                    match json {
+                        true => print_json(&completion)?,
+                        false => println!(
+                            "{}",
+                            completion
+                                .first()
+                                .map(|x| x.text.as_str())
+                                .unwrap_or_default()
+                        ),
+                    }
+                }
+                false => {
+                    let stream = builder.build_stream(&client).await?;
+                    print_stream(stream, json, |chunk: &Completion| {
+                        chunk.first().map(|x| x.text.clone())
+                    })
+                    .await?
+                }
+            }
+        }
+
+        Command::Embed { inputs, model } => {
+            let result = Embedding::batched(model, inputs.iter(), &client).await?;
+            match json {
+                true => print_json(&result)?,
+                false => {
+                    for embedding in result.data {
+                        println!("{}", serde_json::to_string(&embedding.embedding)?);
+                    }
+                }
+            }
+        }
+
+        Command::Transcribe {
+            file,
+            language,
+            prompt,
+        } => {
+            let mut builder = TranscriptionBuilder::new();
+            if let Some(language) = language {
+                builder = builder.language(language);
+            }
+            if let Some(prompt) = prompt {
+                builder = builder.prompt(prompt);
+            }
+
+            let transcription = builder.with_file(file, &client).await?;
+            match (json, transcription) {
+                (true, AudioResponse::VerboseJson(verbose)) => print_json(&verbose)?,
+                (_, transcription) => println!("{}", transcription.text()),
+            }
+        }
+
+        Command::Image {
+            prompt,
+            n,
+            size,
+            output,
+        } => {
+            let images = Images::create(prompt.as_str())?
+                .n(n)?
+                .size(size.into())
+                .build(&client)
+                .await?;
+
+            match output {
+                Some(output) => {
+                    for path in images.save_at_with(output, Naming::Prompt(prompt)).await? {
+                        println!("{}", path.display());
+                    }
+                }
+                None if json => print_json(&images)?,
+                None => {
+                    for image in images.data {
+                        if let ImageData::Url(url) = image {
+                            println!("{url}");
+                        }
+                    }
+                }
+            }
+        }
+
+        Command::Files(command) => files_command(command, json, &client).await?,
+        Command::FineTune(command) => fine_tune_command(command, json, &client).await?,
+    }
+
+    return Ok(());
+}
+
+async fn files_command(command: FilesCommand, json: bool, client: &Client) -> Result<()> {
+    match command {
+        FilesCommand::List => {
+            let files = files(client).await?;
+            match json {
+                true => print_json(&files)?,
+                false => {
+                    for file in files {
+                        println!(
+                            "{}\t{}\t{}\t{} bytes",
+                            file.id, file.purpose, file.filename, file.bytes
+                        );
+                    }
+                }
+            }
+        }
+        FilesCommand::Upload { path, purpose } => {
+            let file = File::upload(path, purpose, client).await?;
+            print_file(&file, json)?;
+        }
+        FilesCommand::Get { id } => print_file(&File::retreive(id, client).await?, json)?,
+        FilesCommand::Content { id } => {
+            let mut content = retreive_raw_file_content(id, client).await?.bytes_stream();
+            let mut stdout = std::io::stdout().lock();
+            while let Some(chunk) = content.try_next().await? {
+                stdout.write_all(&chunk)?;
+            }
+            stdout.flush()?;
+        }
+        FilesCommand::Delete { id } => {
+            let delete = delete_file(&id, client).await?;
+            match json {
+                true => print_json(&delete)?,
+                false => println!("Deleted {id}"),
+            }
+        }
+    }
+
+    return Ok(());
+}
+
+async fn fine_tune_command(command: FineTuneCommand, json: bool, client: &Client) -> Result<()> {
+    match command {
+        FineTuneCommand::Create {
+            training_file,
+            model,
+            validation_file,
+        } => {
+            let mut builder = FineTune::builder(training_file);
+            if let Some(model) = model {
+                builder = builder.model(model);
+            }
+            if let Some(validation_file) = validation_file {
+                builder = builder.validation_file(validation_file);
+            }
+            print_fine_tune(&builder.build(client).await?, json)?;
+        }
+        FineTuneCommand::List => {
+            let fine_tunes = fine_tunes(client).await?;
+            match json {
+                true => print_json(&fine_tunes)?,
+                false => {
+                    for fine_tune in fine_tunes.iter() {
+                        print_fine_tune(fine_tune, false)?;
+                    }
+                }
+            }
+        }
+        FineTuneCommand::Get { id } => {
+            print_fine_tune(&FineTune::retreive(id, client).await?, json)?
+        }
+        FineTuneCommand::Events { id, follow } => {
+            let fine_tune = FineTune::retreive(id, client).await?;
+            let print = |event: &FineTuneEvent| match json {
+                true => print_json(event),
+                false => {
+                    println!("{}\t{}\t{}", event.created_at, event.level, event.message);
+                    Ok(())
+                }
+            };
+
+            match follow {
+                true => {
+                    let mut events = fine_tune.event_stream(client).await?;
+                    while let Some(event) = events.try_next().await? {
+                        print(&event)?;
+                    }
+                }
+                false => {
+                    for event in fine_tune.events(client).await? {
+                        print(&event)?;
+                    }
+                }
+            }
+        }
+        FineTuneCommand::Cancel { id } => print_fine_tune(
+            &FineTune::retreive(id, client).await?.cancel(client).await?,
+            json,
+        )?,
+    }
+
+    return Ok(());
+}
+
+fn print_file(file: &File, json: bool) -> Result<()> {
+    return match json {
+        true => print_json(file),
+        false => {
+            println!("{}\t{}\t{}", file.id, file.purpose, file.filename);
+            Ok(())
+        }
+    };
+}
+
+fn print_fine_tune(fine_tune: &FineTune, json: bool) -> Result<()> {
+    return match json {
+        true => print_json(fine_tune),
+        false => {
+            println!(
+                "{}\t{}\t{}\t{}",
+                fine_tune.id,
+                fine_tune.status,
+                fine_tune.model,
+                fine_tune.fine_tuned_model.as_deref().unwrap_or("-")
+            );
+            Ok(())
+        }
+    };
+}
+
+fn print_json<T: ?Sized + Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string(value)?);
+    return Ok(());
+}
+
+/// Prints the text of every chunk as it arrives, or every chunk as a line of JSON
+async fn print_stream<T, S, F>(mut stream: S, json: bool, text: F) -> Result<()>
+where
+    T: Serialize,
+    S: Unpin + Stream<Item = Result<T>>,
+    F: Fn(&T) -> Option<String>,
+{
+    let mut stdout = std::io::stdout();
+    while let Some(chunk) = stream.try_next().await? {
+        match json {
+            true => print_json(&chunk)?,
+            false => {
+                if let Some(text) = text(&chunk) {
+                    stdout.write_all(text.as_bytes())?;
+                    stdout.flush()?;
+                }
+            }
+        }
+    }
+
+    if !json {
+        writeln!(stdout)?;
+    }
+    return Ok(());
+}
+
+impl From<ImageSize> for Size {
+    #[inline]
+    fn from(value: ImageSize) -> Self {
+        return match value {
+            ImageSize::P256 => Size::P256,
+            ImageSize::P512 => Size::P512,
+            ImageSize::P1024 => Size::P1024,
+        };
+    }
+}
@@ -0,0 +1,194 @@
+use bytes::Bytes;
+use futures::{future::BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Storage of the responses cached by a [`Client`](crate::Client).
+///
+/// Responses are keyed by their URL, so a store shouldn't be shared between clients with different API keys or organizations.
+pub trait CacheStore: Debug + Send + Sync {
+    /// Returns the response cached for `url`, if any
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<CachedResponse>>;
+
+    /// Caches the response of `url`, replacing the previous one
+    fn put<'a>(&'a self, url: &'a str, response: CachedResponse) -> BoxFuture<'a, ()>;
+}
+
+/// A response cached with the validators the API returned for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CachedResponse {
+    /// Value of the `ETag` header
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` header
+    pub last_modified: Option<String>,
+    pub body: Bytes,
+}
+
+/// A [`CacheStore`] that keeps the responses in memory
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    responses: Mutex<HashMap<String, CachedResponse>>,
+}
+
+/// A [`CacheStore`] that keeps the responses as files in a directory, so that they outlive the process
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+/// Validators of a response stored by a [`DiskCache`], next to its body
+#[derive(Debug, Serialize, Deserialize)]
+struct DiskEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl CachedResponse {
+    #[inline]
+    pub fn new(body: Bytes, etag: Option<String>, last_modified: Option<String>) -> Self {
+        return Self {
+            etag,
+            last_modified,
+            body,
+        };
+    }
+
+    /// Returns `true` if the response can be revalidated with the API
+    #[inline]
+    pub fn has_validators(&self) -> bool {
+        return self.etag.is_some() || self.last_modified.is_some();
+    }
+}
+
+impl MemoryCache {
+    #[inline]
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Removes all the cached responses
+    #[inline]
+    pub fn clear(&self) {
+        self.responses.lock().unwrap().clear()
+    }
+}
+
+impl CacheStore for MemoryCache {
+    #[inline]
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<CachedResponse>> {
+        let response = self.responses.lock().unwrap().get(url).cloned();
+        return futures::future::ready(response).boxed();
+    }
+
+    #[inline]
+    fn put<'a>(&'a self, url: &'a str, response: CachedResponse) -> BoxFuture<'a, ()> {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), response);
+        return futures::future::ready(()).boxed();
+    }
+}
+
+impl DiskCache {
+    /// Creates a cache that stores its responses in `dir`, which is created if it doesn't exist
+    #[inline]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        return Self { dir: dir.into() };
+    }
+
+    /// Returns the directory the responses are stored in
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        return &self.dir;
+    }
+
+    /// Removes all the cached responses
+    pub async fn clear(&self) -> std::io::Result<()> {
+        return match crate::rt::remove_dir_all(&self.dir).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+
+    /// Returns the paths of the entry and the body of `url`
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        // FNV-1a, which (unlike the standard library's hasher) is stable across Rust versions
+        let mut hash = 0xcbf29ce484222325u64;
+        for byte in url.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+
+        return (
+            self.dir.join(format!("{hash:016x}.json")),
+            self.dir.join(format!("{hash:016x}.body")),
+        );
+    }
+
+    async fn read(&self, url: &str) -> std::io::Result<Option<CachedResponse>> {
+        let (entry_path, body_path) = self.paths(url);
+        let entry = match crate::rt::read(&entry_path).await {
+            Ok(entry) => serde_json::from_slice::<DiskEntry>(&entry)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        // Different URLs may share a hash
+        if entry.url != url {
+            return Ok(None);
+        }
+
+        let body = crate::rt::read(&body_path).await?;
+        return Ok(Some(CachedResponse {
+            etag: entry.etag,
+            last_modified: entry.last_modified,
+            body: Bytes::from(body),
+        }));
+    }
+
+    async fn write(&self, url: &str, response: CachedResponse) -> std::io::Result<()> {
+        let (entry_path, body_path) = self.paths(url);
+        let entry = serde_json::to_vec(&DiskEntry {
+            url: url.to_string(),
+            etag: response.etag,
+            last_modified: response.last_modified,
+        })?;
+
+        crate::rt::create_dir_all(&self.dir).await?;
+        crate::rt::write(&body_path, &response.body).await?;
+        crate::rt::write(&entry_path, entry).await?;
+        return Ok(());
+    }
+}
+
+impl CacheStore for DiskCache {
+    fn get<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Option<CachedResponse>> {
+        return async move {
+            let response = self.read(url).await;
+            #[cfg(feature = "tracing")]
+            if let Err(ref e) = response {
+                tracing::warn!("error reading cached response of {url}: {e}");
+            }
+            response.ok().flatten()
+        }
+        .boxed();
+    }
+
+    fn put<'a>(&'a self, url: &'a str, response: CachedResponse) -> BoxFuture<'a, ()> {
+        return async move {
+            if let Err(_e) = self.write(url, response).await {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("error caching response of {url}: {_e}");
+            }
+        }
+        .boxed();
+    }
+}
@@ -1,18 +1,51 @@
 use super::{
-    common::Usage,
-    error::{BuilderError, Result},
+    common::{
+        best_by, estimate_tokens, majority_vote, tally, HasUsage, LogitBias, ResponseBody,
+        StopSequences, StreamOptions, Truncated, Truncation, Usage, MAX_STOP_SEQUENCES,
+        MAX_TRUNCATION_RETRIES,
+    },
+    error::{BuilderError, Error, Result},
     Str,
 };
-use crate::{error::FallibleResponse, Client, OpenAiStream};
+use crate::{
+    check_len, check_range,
+    endpoint::Endpoint,
+    error::FallibleResponse,
+    logging::{RequestBuilderExt, ResponseExt},
+    model::info::ModelInfo,
+    Client, OpenAiStream,
+};
 use chrono::{DateTime, Utc};
 
 use futures::{Stream, TryStreamExt};
 use reqwest::Response;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
 use std::{
-    borrow::Cow, collections::HashMap, future::ready, marker::PhantomData, ops::RangeInclusive,
+    borrow::Cow,
+    collections::HashMap,
+    future::ready,
+    ops::{Range, RangeInclusive},
+};
+use tool::{
+    FunctionCall, FunctionCallDelta, FunctionChoice, FunctionDefinition, Tool, ToolCall,
+    ToolCallDelta, ToolChoice, ToolRegistry,
 };
 
+pub use conversation::Conversation;
+
+/// Chat sessions that can be saved and resumed, and the stores they're saved into.
+pub mod conversation;
+/// Export of chat transcripts into fine-tuning and evals datasets.
+pub mod export;
+/// Export and import of requests in the JSON format of the OpenAI Playground and `curl` examples.
+pub mod playground;
+/// Structured outputs, with the JSON schemas of Rust types.
+#[cfg_attr(docsrs, doc(cfg(feature = "schemars")))]
+#[cfg(feature = "schemars")]
+pub mod structured;
+/// Tools the model may call, and a registry to execute their calls.
+pub mod tool;
+
 /// Message role
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -22,25 +55,44 @@ pub enum Role {
     User,
     System,
     Assistant,
+    Tool,
+    /// Result of a [function call](Message::function_call), for the legacy function calling API
+    Function,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message<'a> {
     pub role: Role,
+    #[serde(default, deserialize_with = "deserialize_content")]
     pub content: Str<'a>,
+    /// The tool calls generated by the model
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// The tool call this message is responding to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<Str<'a>>,
+    /// The function called by the model, for the legacy function calling API (see [`ChatCompletionBuilder::functions`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<FunctionCall>,
+    /// Name of the author of the message, which is required for [`Function`](Role::Function) messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<Str<'a>>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct ChatChoice {
     pub message: Message<'static>,
     pub index: u64,
     #[serde(default)]
     pub finish_reason: Option<String>,
+    /// Fields returned by the API that aren't known by the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
 /// Given a chat conversation, the model will return a chat completion response.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct ChatCompletion {
     pub id: String,
@@ -50,16 +102,116 @@ pub struct ChatCompletion {
     pub choices: Vec<ChatChoice>,
     #[serde(default)]
     pub usage: Option<Usage>,
+    /// Fields returned by the API that aren't known by the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-/// Given a chat conversation, the model will return a chat completion response.
-pub type ChatCompletionStream = OpenAiStream<ChatCompletion>;
+/// A chunk of a [`ChatCompletionStream`], with the part of the message generated since the previous one.
+///
+/// Chunks can be accumulated into the complete [`ChatCompletion`] with [`ChatCompletion::append`] or [`ChatCompletionStream::collect_full`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ChatCompletionChunk {
+    pub id: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created: DateTime<Utc>,
+    pub model: String,
+    /// Empty in the last chunk of a stream with usage (see [`Client::with_usage_tracking`](crate::Client::with_usage_tracking))
+    #[serde(default)]
+    pub choices: Vec<ChatChunkChoice>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+    /// Fields returned by the API that aren't known by the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// A choice of a [`ChatCompletionChunk`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ChatChunkChoice {
+    pub delta: ChoiceDelta,
+    pub index: u64,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+    /// Fields returned by the API that aren't known by the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Part of a message generated by a [`ChatCompletionStream`]. Every field is only present in the chunks that add to it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ChoiceDelta {
+    /// Only sent in the first chunk of the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCallDelta>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<FunctionCallDelta>,
+}
+
+/// A [`ChatCompletion`] that borrows its strings from a [`ResponseBody`], returned by [`ChatCompletionBuilder::build_body`].
+///
+/// Useful to process many responses without allocating their strings. Fields returned by the API that aren't known by the library are ignored.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ChatCompletionRef<'a> {
+    pub id: &'a str,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created: DateTime<Utc>,
+    pub model: &'a str,
+    #[serde(borrow)]
+    pub choices: Vec<ChatChoiceRef<'a>>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
+}
+
+/// A [`ChatChoice`] that borrows its strings from a [`ResponseBody`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ChatChoiceRef<'a> {
+    #[serde(borrow)]
+    pub message: MessageRef<'a>,
+    pub index: u64,
+    #[serde(default)]
+    pub finish_reason: Option<&'a str>,
+}
+
+/// A [`Message`] that borrows its strings from a [`ResponseBody`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct MessageRef<'a> {
+    pub role: Role,
+    /// Only allocated if the content has escape sequences. `None` if the model only called tools.
+    #[serde(
+        default,
+        borrow,
+        deserialize_with = "crate::serde_cow::deserialize_option"
+    )]
+    pub content: Option<Cow<'a, str>>,
+    #[serde(default)]
+    pub tool_calls: Vec<ToolCall>,
+    #[serde(default)]
+    pub function_call: Option<FunctionCall>,
+}
 
-/// [`ChatCompletion`]/[`ChatCompletionBuilder`] request builder
-#[derive(Debug, Clone, Serialize)]
+/// Given a chat conversation, the model will return a chat completion response, streamed in [chunks](ChatCompletionChunk).
+pub type ChatCompletionStream = OpenAiStream<ChatCompletionChunk>;
+
+/// [`ChatCompletion`]/[`ChatCompletionBuilder`] request builder.
+///
+/// A preset (e.g. the model and sampling parameters of an assistant, loaded from a configuration file) can be deserialized into a builder. Its parameters are checked when the request is sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionBuilder<'a> {
     model: Str<'a>,
+    #[serde(default)]
     messages: Vec<Message<'a>>,
+    #[serde(skip_deserializing)]
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u64>,
@@ -70,15 +222,107 @@ pub struct ChatCompletionBuilder<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     n: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    stop: Option<Vec<Str<'a>>>,
+    stop: Option<StopSequences<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     frequency_penalty: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     presence_penalty: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    logit_bias: Option<HashMap<Str<'a>, f64>>,
+    logit_bias: Option<LogitBias>,
     #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<Str<'a>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Tool<'a>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<ToolChoice<'a>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat<'a>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    functions: Option<Vec<FunctionDefinition<'a>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<FunctionChoice<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none", skip_deserializing)]
+    stream_options: Option<StreamOptions>,
+    #[serde(skip)]
+    include_usage: bool,
+    /// Parameters not supported by the builder, sent as is
+    #[serde(flatten, deserialize_with = "crate::deserialize_extra_params")]
+    extra: HashMap<Str<'a>, serde_json::Value>,
+}
+
+/// Format of the messages generated by the model. See [`ChatCompletionBuilder::response_format`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ResponseFormat<'a> {
+    /// Plain text (the default)
+    Text,
+    /// A valid JSON object. The messages must also ask the model to answer with JSON.
+    JsonObject,
+    /// A JSON value that follows a schema
+    JsonSchema { json_schema: JsonSchemaFormat<'a> },
+}
+
+/// JSON Schema the messages generated by the model follow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct JsonSchemaFormat<'a> {
+    /// Name of the format, made of letters, digits, underscores and dashes
+    pub name: Str<'a>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<Str<'a>>,
+    pub schema: serde_json::Value,
+    /// Whether the model follows the schema exactly, which only supports a subset of JSON Schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// Limits of an agent loop, run by [`run_agent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AgentLimits {
+    max_iterations: u32,
+    max_tokens: Option<u64>,
+}
+
+/// Reason an agent loop stopped.
+///
+/// If it stops on a limit, the tool calls of the last message aren't executed, but they're answered with a [`Tool`](Role::Tool) message saying so, so that the transcript can be resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AgentStop {
+    /// The model answered without calling any tools
+    Answered,
+    /// The maximum number of requests was reached
+    MaxIterations,
+    /// The maximum number of tokens was reached
+    MaxTokens,
+}
+
+/// Result of an agent loop, run by [`run_agent`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AgentRun<'a> {
+    /// Every message of the conversation, including the ones of the initial request
+    pub transcript: Vec<Message<'a>>,
+    /// The last message generated by the model
+    pub final_message: Message<'static>,
+    pub stop: AgentStop,
+    /// Number of requests sent
+    pub iterations: u32,
+    /// Tokens used by all the requests
+    pub total_tokens: u64,
+}
+
+/// Answer agreed on by most of the completions sampled by [`self_consistent`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Consensus {
+    /// The most common answer
+    pub answer: String,
+    /// Every answer with its number of votes, from most to least voted
+    pub votes: Vec<(String, usize)>,
+    /// Number of choices sampled, including the ones without an answer
+    pub samples: usize,
 }
 
 impl<'a> Message<'a> {
@@ -88,6 +332,10 @@ impl<'a> Message<'a> {
         return Self {
             role,
             content: content.into(),
+            tool_calls: Vec::new(),
+            tool_call_id: None,
+            function_call: None,
+            name: None,
         };
     }
 
@@ -108,6 +356,30 @@ impl<'a> Message<'a> {
     pub fn assistant(content: impl Into<Str<'a>>) -> Self {
         return Self::new(Role::Assistant, content);
     }
+
+    /// Creates a new message with a role of [`Tool`](Role::Tool), with the result of a tool call
+    #[inline]
+    pub fn tool(tool_call_id: impl Into<Str<'a>>, content: impl Into<Str<'a>>) -> Self {
+        return Self {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Self::new(Role::Tool, content)
+        };
+    }
+
+    /// Creates a new message with a role of [`Function`](Role::Function), with the result of a call to the function `name`
+    #[inline]
+    pub fn function(name: impl Into<Str<'a>>, content: impl Into<Str<'a>>) -> Self {
+        return Self {
+            name: Some(name.into()),
+            ..Self::new(Role::Function, content)
+        };
+    }
+
+    /// Parses the JSON value in the content of the message. See [`extract_json`]
+    #[inline]
+    pub fn parse_json<T: DeserializeOwned>(&self) -> Result<T> {
+        return extract_json(&self.content);
+    }
 }
 
 impl ChatCompletion {
@@ -142,6 +414,51 @@ impl ChatCompletion {
 }
 
 impl ChatCompletion {
+    /// Appends a chunk of a [`ChatCompletionStream`] to the completion, e.g. to show the chunks as they arrive and keep the complete message
+    pub fn append(&mut self, chunk: ChatCompletionChunk) {
+        self.id = chunk.id;
+        self.created = chunk.created;
+        self.model = chunk.model;
+        self.usage = chunk.usage.or(self.usage.take());
+        self.extra.extend(chunk.extra);
+
+        for chunk in chunk.choices {
+            let choice = match self.choices.iter_mut().find(|x| x.index == chunk.index) {
+                Some(choice) => choice,
+                None => {
+                    self.choices.push(ChatChoice {
+                        message: Message::assistant(""),
+                        index: chunk.index,
+                        finish_reason: None,
+                        extra: HashMap::new(),
+                    });
+                    self.choices.last_mut().unwrap()
+                }
+            };
+
+            choice.finish_reason = chunk.finish_reason.or(choice.finish_reason.take());
+            choice.extra.extend(chunk.extra);
+
+            let (message, delta) = (&mut choice.message, chunk.delta);
+            if let Some(role) = delta.role {
+                message.role = role;
+            }
+            if let Some(content) = delta.content {
+                message.content.to_mut().push_str(&content);
+            }
+            for delta in delta.tool_calls {
+                delta.append_to(&mut message.tool_calls);
+            }
+            if let Some(delta) = delta.function_call {
+                delta.append_to(
+                    message
+                        .function_call
+                        .get_or_insert_with(FunctionCall::default),
+                );
+            }
+        }
+    }
+
     /// Returns a reference to the first [`ChatChoice`]
     #[inline]
     pub fn first(&self) -> Option<&ChatChoice> {
@@ -153,6 +470,43 @@ impl ChatCompletion {
     pub fn into_first(self) -> Option<ChatChoice> {
         return self.choices.into_iter().next();
     }
+
+    /// Returns the choice with the longest message content, or the first of them if there's a tie
+    #[inline]
+    pub fn longest(&self) -> Option<&ChatChoice> {
+        return self.best_by(|choice| choice.message.content.chars().count());
+    }
+
+    /// Returns the choice with the shortest message content, or the first of them if there's a tie
+    #[inline]
+    pub fn shortest(&self) -> Option<&ChatChoice> {
+        return self.best_by(|choice| std::cmp::Reverse(choice.message.content.chars().count()));
+    }
+
+    /// Returns the choice with the highest score, or the first of them if there's a tie
+    #[inline]
+    pub fn best_by<K: PartialOrd, F: FnMut(&ChatChoice) -> K>(
+        &self,
+        score: F,
+    ) -> Option<&ChatChoice> {
+        return best_by(&self.choices, score);
+    }
+
+    /// Returns the most common message content among the choices, with the number of choices that agree on it.
+    ///
+    /// Answers are compared ignoring case and surrounding whitespace, which is useful for classification prompts. Ties are won by the answer that appears first.
+    #[inline]
+    pub fn majority_vote(&self) -> Option<(&str, usize)> {
+        return majority_vote(self.choices.iter().map(|choice| &*choice.message.content));
+    }
+}
+
+impl<'a> ChatCompletionRef<'a> {
+    /// Returns a reference to the first [`ChatChoiceRef`]
+    #[inline]
+    pub fn first(&self) -> Option<&ChatChoiceRef<'a>> {
+        return self.choices.first();
+    }
 }
 
 impl<'a> ChatCompletionBuilder<'a> {
@@ -174,9 +528,40 @@ impl<'a> ChatCompletionBuilder<'a> {
             logit_bias: None,
             user: None,
             stop: None,
+            tools: None,
+            tool_choice: None,
+            response_format: None,
+            functions: None,
+            function_call: None,
+            stream_options: None,
+            include_usage: false,
+            extra: HashMap::new(),
         };
     }
 
+    /// Adds a parameter the builder doesn't have yet to the chat request. It's sent as is, next to the builder's own parameters.
+    #[inline]
+    pub fn extra_param(
+        mut self,
+        key: impl Into<Str<'a>>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
+    /// Appends a message to the conversation
+    pub fn message(mut self, message: Message<'a>) -> Self {
+        self.messages.push(message);
+        self
+    }
+
+    /// Appends messages to the conversation
+    pub fn messages<I: IntoIterator<Item = Message<'a>>>(mut self, messages: I) -> Self {
+        self.messages.extend(messages);
+        self
+    }
+
     /// The maximum number of tokens to generate in the chat completion.
     ///
     /// The total length of input tokens and generated tokens is limited by the model's context length.
@@ -221,23 +606,19 @@ impl<'a> ChatCompletionBuilder<'a> {
     where
         I::Item: Into<Str<'a>>,
     {
-        const MAX_SIZE: usize = 4;
-
-        let mut stop = stop.into_iter();
-        let mut result = Vec::with_capacity(MAX_SIZE);
-
-        while let Some(next) = stop.next() {
-            if result.len() == result.capacity() {
-                return Err(BuilderError::msg(
-                    self,
-                    format!("Interator exceeds size limit of {MAX_SIZE}"),
-                ));
+        return match StopSequences::new(stop) {
+            Ok(stop) => {
+                self.stop = Some(stop);
+                Ok(self)
             }
-            result.push(next.into());
-        }
+            Err(e) => Err(BuilderError::new(self, e)),
+        };
+    }
 
-        self.stop = Some(result);
-        return Ok(self);
+    /// Up to 4 sequences where the API will stop generating further tokens, checked when they're created (see [`StopSequences`])
+    pub fn stop_sequences(mut self, stop: impl Into<StopSequences<'a>>) -> Self {
+        self.stop = Some(stop.into());
+        self
     }
 
     /// Number between -2.0 and 2.0. Positive values penalize new tokens based on whether they appear in the text so far, increasing the model's likelihood to talk about new topics.
@@ -270,15 +651,9 @@ impl<'a> ChatCompletionBuilder<'a> {
         };
     }
 
-    /// Modify the likelihood of specified tokens appearing in the completion.
-    ///
-    /// Accepts a json object that maps tokens (specified by their token ID in the tokenizer) to an associated bias value from -100 to 100. Mathematically, the bias is added to the logits generated by the model prior to sampling. The exact effect will vary per model, but values between -1 and 1 should decrease or increase likelihood of selection; values like -100 or 100 should result in a ban or exclusive selection of the relevant token.
-    pub fn logit_bias<K, I>(mut self, logit_bias: I) -> Self
-    where
-        K: Into<Str<'a>>,
-        I: IntoIterator<Item = (K, f64)>,
-    {
-        self.logit_bias = Some(logit_bias.into_iter().map(|(k, v)| (k.into(), v)).collect());
+    /// Modify the likelihood of specified tokens appearing in the completion. See [`LogitBias`]
+    pub fn logit_bias(mut self, logit_bias: LogitBias) -> Self {
+        self.logit_bias = Some(logit_bias);
         self
     }
 
@@ -288,35 +663,503 @@ impl<'a> ChatCompletionBuilder<'a> {
         self
     }
 
+    /// Whether streams (see [`build_stream`](Self::build_stream)) end with an additional chunk with the usage of the whole request, which is read by [`OpenAiStream::usage`](crate::OpenAiStream::usage).
+    ///
+    /// Always enabled by clients that track their usage or budget.
+    pub fn include_usage(mut self, include_usage: bool) -> Self {
+        self.include_usage = include_usage;
+        self
+    }
+
+    /// A list of tools the model may call.
+    pub fn tools<I: IntoIterator<Item = Tool<'a>>>(mut self, tools: I) -> Self {
+        self.tools = Some(tools.into_iter().collect());
+        self
+    }
+
+    /// Controls whether (and which of) the [`tools`](ChatCompletionBuilder::tools) the model calls. Defaults to [`Auto`](ToolChoice::Auto) when tools are present.
+    pub fn tool_choice(mut self, tool_choice: impl Into<ToolChoice<'a>>) -> Self {
+        self.tool_choice = Some(tool_choice.into());
+        self
+    }
+
+    /// The format of the messages generated by the model, e.g. to make it answer with JSON that [`build_json`](ChatCompletionBuilder::build_json) parses
+    pub fn response_format(mut self, response_format: ResponseFormat<'a>) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    /// A list of functions the model may generate JSON inputs for, with the legacy function calling API. Prefer [`tools`](ChatCompletionBuilder::tools) for new code.
+    pub fn functions<I: IntoIterator<Item = FunctionDefinition<'a>>>(
+        mut self,
+        functions: I,
+    ) -> Self {
+        self.functions = Some(functions.into_iter().collect());
+        self
+    }
+
+    /// Controls whether (and which of) the [`functions`](ChatCompletionBuilder::functions) the model calls. Defaults to [`Auto`](FunctionChoice::Auto) when functions are present.
+    pub fn function_call(mut self, function_call: impl Into<FunctionChoice<'a>>) -> Self {
+        self.function_call = Some(function_call.into());
+        self
+    }
+
+    /// Estimates the number of tokens the request's messages take up in the prompt
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokens")))]
+    #[cfg(feature = "tokens")]
+    #[inline]
+    pub fn estimated_prompt_tokens(&self) -> Result<usize> {
+        return crate::tokens::count_message_tokens(&self.model, self.messages.iter());
+    }
+
+    /// Sets `max_tokens` to every token the model's context window has left after the messages (up to its output limit). See [`remaining_tokens`](crate::tokens::remaining_tokens)
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokens")))]
+    #[cfg(feature = "tokens")]
+    pub fn max_tokens_auto(self) -> Result<Self, BuilderError<Self>> {
+        return crate::tokens::max_tokens_auto(
+            self,
+            |this| crate::tokens::remaining_tokens(&this.model, this.estimated_prompt_tokens()?),
+            Self::max_tokens,
+        );
+    }
+
+    /// Estimates the maximum cost of the request, in US dollars, before sending it.
+    ///
+    /// The prompt is priced by its estimated token count, and the completion as if it used every token allowed by `max_tokens` (or the model's output limit, if not set).
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokens")))]
+    #[cfg(feature = "tokens")]
+    pub fn estimated_cost(&self) -> Result<f64> {
+        let pricing = crate::model::info::Pricing::of(&self.model).ok_or_else(|| {
+            crate::error::Error::msg(format!("Unknown pricing for model '{}'", self.model))
+        })?;
+
+        let max_tokens = match self.max_tokens {
+            Some(max_tokens) => max_tokens,
+            None => ModelInfo::of(&self.model)
+                .map(|info| info.max_output_tokens)
+                .unwrap_or_default(),
+        };
+
+        let prompt_tokens = self.estimated_prompt_tokens()? as u64;
+        return Ok(pricing.cost(prompt_tokens, max_tokens * self.n.unwrap_or(1)));
+    }
+
+    /// Returns the number of tokens the request may use, if known
+    fn requested_tokens(&self) -> Option<u64> {
+        #[cfg(feature = "tokens")]
+        if let Ok(prompt_tokens) = self.estimated_prompt_tokens() {
+            return Some(prompt_tokens as u64 + self.max_tokens.unwrap_or_default());
+        }
+        return self.max_tokens;
+    }
+
+    /// Checks the request's parameters, which may have been deserialized without validation
+    fn validate(&self) -> Result<()> {
+        check_range("temperature", self.temperature, 0f64..=2f64)?;
+        check_range("top_p", self.top_p, 0f64..=1f64)?;
+        check_range("n", self.n, 1..=128)?;
+        check_range("presence_penalty", self.presence_penalty, -2f64..=2f64)?;
+        check_range("frequency_penalty", self.frequency_penalty, -2f64..=2f64)?;
+        check_len("stop", self.stop.as_deref(), MAX_STOP_SEQUENCES)?;
+        if let Some(ref logit_bias) = self.logit_bias {
+            logit_bias.validate()?;
+        }
+
+        // Check `max_tokens` against the model's output limit, if the model is known
+        if let (Some(max_tokens), Some(info)) = (self.max_tokens, ModelInfo::of(&self.model)) {
+            info.check_max_tokens(max_tokens)?;
+        }
+        return Ok(());
+    }
+
     /// Sends the request
-    pub async fn build(self, client: impl AsRef<Client>) -> Result<ChatCompletion> {
+    pub async fn build(mut self, client: impl AsRef<Client>) -> Result<ChatCompletion> {
+        self.validate()?;
+        let client = client.as_ref();
+        client.default_user(&mut self.user);
+        let tokens = self.requested_tokens();
+        client.check_budget(tokens)?;
+        client.throttle(&self.model, tokens).await;
         let resp = client
-            .as_ref()
-            .post("https://api.openai.com/v1/chat/completions")
-            .json(&self)
+            .post(client.url(Endpoint::Chat, ""))
+            .json_body(&self)
             .send()
             .await?
-            .json::<FallibleResponse<ChatCompletion>>()
+            .json_body::<FallibleResponse<ChatCompletion>>()
             .await?
             .into_result()?;
 
+        client.record("/v1/chat/completions", &self.model, resp.usage.as_ref());
         return Ok(resp);
     }
 
+    /// Sends the request, dropping messages from the conversation and retrying it whenever it exceeds the model's context window (up to 5 times).
+    ///
+    /// Enough messages are dropped to fit the excess reported by the API, or a single one if it isn't reported. System messages and the last message are never dropped, and tool results are dropped along with the message that called them.
+    pub async fn build_truncating(
+        mut self,
+        truncation: Truncation,
+        client: impl AsRef<Client>,
+    ) -> Result<Truncated<ChatCompletion, Message<'a>>> {
+        let client = client.as_ref();
+        let mut dropped = Vec::new();
+        let mut retries = 0;
+
+        loop {
+            let messages = match self.clone().build(client).await {
+                Ok(value) => {
+                    return Ok(Truncated {
+                        value,
+                        dropped,
+                        retries,
+                    })
+                }
+                Err(Error::OpenAI(e))
+                    if e.is_context_length_exceeded() && retries < MAX_TRUNCATION_RETRIES =>
+                {
+                    match self.drop_messages(truncation, e.excess_tokens()) {
+                        Some(messages) => messages,
+                        None => return Err(Error::OpenAI(e)),
+                    }
+                }
+                Err(e) => return Err(e),
+            };
+
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "context length exceeded, retrying without {} messages",
+                messages.len()
+            );
+            dropped.extend(messages);
+            retries += 1;
+        }
+    }
+
+    /// Removes the messages that free up `excess` tokens (or a single turn, if unknown), returning them
+    fn drop_messages(
+        &mut self,
+        truncation: Truncation,
+        excess: Option<u64>,
+    ) -> Option<Vec<Message<'a>>> {
+        // Tool results are dropped along with the message that called them, as a single turn
+        let mut turns = Vec::<Range<usize>>::new();
+        for (i, message) in self.messages.iter().enumerate() {
+            match turns.last_mut() {
+                Some(turn) if message.role == Role::Tool => turn.end = i + 1,
+                _ => turns.push(i..i + 1),
+            }
+        }
+
+        let last = self.messages.len().saturating_sub(1);
+        let mut candidates = turns
+            .into_iter()
+            .filter(|turn| turn.end <= last && self.messages[turn.start].role != Role::System);
+        if truncation == Truncation::Middle {
+            candidates.next();
+        }
+
+        let mut remove = Vec::new();
+        let mut freed = 0;
+        for turn in candidates {
+            let enough = match excess {
+                Some(excess) => freed >= excess,
+                None => true,
+            };
+            if enough && !remove.is_empty() {
+                break;
+            }
+            for i in turn {
+                // Plus the tokens of the message's role and delimiters
+                freed += estimate_tokens(&self.model, &self.messages[i].content) + 4;
+                remove.push(i);
+            }
+        }
+
+        if remove.is_empty() {
+            return None;
+        }
+
+        let mut dropped = Vec::with_capacity(remove.len());
+        for i in remove.into_iter().rev() {
+            dropped.push(self.messages.remove(i));
+        }
+        dropped.reverse();
+        return Some(dropped);
+    }
+
+    /// Sends the request, returning its raw body to deserialize a borrowed [`ChatCompletionRef`] from
+    pub async fn build_body(mut self, client: impl AsRef<Client>) -> Result<ResponseBody> {
+        self.validate()?;
+        let client = client.as_ref();
+        client.default_user(&mut self.user);
+        let tokens = self.requested_tokens();
+        client.check_budget(tokens)?;
+        client.throttle(&self.model, tokens).await;
+        let resp = client
+            .post(client.url(Endpoint::Chat, ""))
+            .json_body(&self)
+            .send()
+            .await?;
+
+        let body = ResponseBody::read(resp).await?;
+        if client.wants_usage() {
+            let usage = body.parse::<ChatCompletionRef>()?.usage;
+            client.record("/v1/chat/completions", &self.model, usage.as_ref());
+        }
+        return Ok(body);
+    }
+
+    /// Sends the request, and parses the JSON value in the content of the first choice (see [`extract_json`]).
+    ///
+    /// If `retry` is `true` and the value can't be parsed, the model is asked once to fix its response.
+    pub async fn build_json<T: DeserializeOwned>(
+        mut self,
+        retry: bool,
+        client: impl AsRef<Client>,
+    ) -> Result<T> {
+        let client = client.as_ref();
+        let message = first_message(self.clone().build(client).await?)?;
+
+        let err = match message.parse_json::<T>() {
+            Ok(value) => return Ok(value),
+            Err(e) if retry => e,
+            Err(e) => return Err(e),
+        };
+
+        self.messages.push(message);
+        self.messages.push(Message::user(format!(
+            "Your response couldn't be parsed as JSON ({err}). Reply with only the corrected JSON."
+        )));
+
+        return first_message(self.build(client).await?)?.parse_json::<T>();
+    }
+
     /// Sends the stream request
     pub async fn build_stream(
         mut self,
         client: impl AsRef<Client>,
     ) -> Result<ChatCompletionStream> {
+        self.validate()?;
+        let client = client.as_ref();
+        client.default_user(&mut self.user);
+        let tokens = self.requested_tokens();
+        client.check_budget(tokens)?;
+        client.throttle(&self.model, tokens).await;
         self.stream = true;
+        if self.include_usage || client.wants_usage() {
+            self.stream_options = Some(StreamOptions {
+                include_usage: true,
+            });
+        }
+
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
         let resp = client
-            .as_ref()
-            .post("https://api.openai.com/v1/chat/completions")
-            .json(&self)
+            .post(client.url(Endpoint::Chat, ""))
+            .json_body(&self)
             .send()
+            .await?
+            .check_status()
             .await?;
 
-        return Ok(ChatCompletionStream::create(resp));
+        let mut stream = ChatCompletionStream::create(resp);
+        stream.inspect = client.record_stream(
+            "/v1/chat/completions",
+            &self.model,
+            |chunk: &ChatCompletionChunk| chunk.usage.clone(),
+        );
+        #[cfg(feature = "tracing")]
+        stream.trace("/v1/chat/completions", &self.model, start);
+        return Ok(stream);
+    }
+}
+
+impl<'a> ResponseFormat<'a> {
+    /// Creates a format of JSON values that follow `schema`, which is followed exactly if `strict` is `true`
+    #[inline]
+    pub fn json_schema(name: impl Into<Str<'a>>, schema: serde_json::Value, strict: bool) -> Self {
+        return Self::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: name.into(),
+                description: None,
+                schema,
+                strict: Some(strict),
+            },
+        };
+    }
+}
+
+impl AgentLimits {
+    #[inline]
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Maximum number of requests to send, which must be at least 1. Defaults to 10.
+    pub fn max_iterations(mut self, max_iterations: u32) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Maximum number of tokens to use across all requests. Unlimited by default.
+    pub fn max_tokens(mut self, max_tokens: u64) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+}
+
+impl Default for AgentLimits {
+    #[inline]
+    fn default() -> Self {
+        return Self {
+            max_iterations: 10,
+            max_tokens: None,
+        };
+    }
+}
+
+/// Result of the tool calls left pending when an agent loop stops on a limit
+const AGENT_LIMIT_REACHED: &str = "not executed: limit reached";
+
+/// Runs an agent loop: sends the request, executes the tool calls of the response with `registry`, appends their results to the conversation and sends it again, until the model answers without calling any tools or one of the `limits` is reached.
+///
+/// The tools of `registry` are added to the ones already in `builder`.
+pub async fn run_agent<'a>(
+    mut builder: ChatCompletionBuilder<'a>,
+    registry: &ToolRegistry,
+    client: impl AsRef<Client>,
+    limits: AgentLimits,
+) -> Result<AgentRun<'a>> {
+    if limits.max_iterations == 0 {
+        return Err(Error::msg(
+            "the agent must be allowed at least one iteration",
+        ));
+    }
+
+    let client = client.as_ref();
+    let tools = builder.tools.get_or_insert_with(Vec::new);
+    for tool in registry.tools() {
+        tools.push(tool.clone());
+    }
+
+    let mut iterations = 0;
+    let mut total_tokens = 0;
+
+    loop {
+        let completion = builder.clone().build(client).await?;
+        iterations += 1;
+        total_tokens += completion
+            .usage
+            .as_ref()
+            .map(|usage| usage.total_tokens)
+            .unwrap_or_default();
+
+        let message = match completion.first() {
+            Some(choice) => choice.message.clone(),
+            None => return Err(Error::msg("the chat completion has no choices")),
+        };
+        builder.messages.push(message.clone());
+
+        let stop = if message.tool_calls.is_empty() {
+            Some(AgentStop::Answered)
+        } else if iterations >= limits.max_iterations {
+            Some(AgentStop::MaxIterations)
+        } else if limits.max_tokens.is_some_and(|max| total_tokens >= max) {
+            Some(AgentStop::MaxTokens)
+        } else {
+            None
+        };
+
+        match stop {
+            Some(AgentStop::Answered) => {}
+            // Tool calls can't be left unanswered
+            Some(_) => builder.messages.extend(
+                message
+                    .tool_calls
+                    .iter()
+                    .map(|call| Message::tool(call.id.clone(), AGENT_LIMIT_REACHED)),
+            ),
+            None => builder
+                .messages
+                .extend(registry.execute(&completion).await?),
+        }
+
+        if let Some(stop) = stop {
+            return Ok(AgentRun {
+                transcript: builder.messages,
+                final_message: message,
+                stop,
+                iterations,
+                total_tokens,
+            });
+        }
+    }
+}
+
+/// Sampling temperature of [`self_consistent`], if the builder doesn't set one
+const SELF_CONSISTENCY_TEMPERATURE: f64 = 0.8;
+
+/// Samples `k` completions of the request (as the choices of a single request) and returns the answer most of them agree on, a technique known as [self-consistency](https://arxiv.org/abs/2203.11171) that improves the accuracy of reasoning prompts.
+///
+/// `extract_answer` returns the final answer of a message's content (e.g. the number after "Answer:"), or `None` if it has none. Answers are compared ignoring case and surrounding whitespace.
+/// Completions are sampled with a temperature of 0.8, unless the builder sets its own.
+pub async fn self_consistent<F>(
+    mut builder: ChatCompletionBuilder<'_>,
+    k: usize,
+    mut extract_answer: F,
+    client: impl AsRef<Client>,
+) -> Result<Consensus>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    if k == 0 {
+        return Err(Error::msg("at least one completion must be sampled"));
+    }
+
+    builder
+        .temperature
+        .get_or_insert(SELF_CONSISTENCY_TEMPERATURE);
+    builder.n = Some(k as u64);
+
+    // The prompt is only sent (and paid for) once
+    let completion = builder.build(client).await?;
+    let choices = &completion.choices;
+    let answers = choices
+        .iter()
+        .filter_map(|choice| extract_answer(&choice.message.content))
+        .collect::<Vec<_>>();
+
+    let mut votes = tally(answers.iter().map(String::as_str))
+        .into_iter()
+        .map(|(answer, votes)| (answer.to_string(), votes))
+        .collect::<Vec<_>>();
+    // Stable, so ties keep the answer that appeared first
+    votes.sort_by_key(|(_, votes)| std::cmp::Reverse(*votes));
+
+    return match votes.first() {
+        Some((answer, _)) => Ok(Consensus {
+            answer: answer.clone(),
+            votes,
+            samples: choices.len(),
+        }),
+        None => Err(Error::msg("none of the sampled completions has an answer")),
+    };
+}
+
+impl Consensus {
+    /// Returns the number of votes of the most common answer
+    #[inline]
+    pub fn answer_votes(&self) -> usize {
+        return self.votes.first().map_or(0, |(_, votes)| *votes);
+    }
+
+    /// Returns the fraction of the samples that agree on the answer, between 0 and 1
+    #[inline]
+    pub fn agreement(&self) -> f64 {
+        return match self.samples {
+            0 => 0.0,
+            samples => self.answer_votes() as f64 / samples as f64,
+        };
     }
 }
 
@@ -335,26 +1178,152 @@ impl ChatCompletionStream {
 
     #[inline]
     fn create(resp: Response) -> Self {
-        return Self {
-            inner: Box::pin(resp.bytes_stream()),
-            current_chunk: None,
-            _phtm: PhantomData,
-        };
+        return Self::from_bytes_stream(resp.bytes_stream());
     }
 }
 
 impl ChatCompletionStream {
-    /// Converts [`Stream<Item = Result<Completion>>`] into [`Stream<Item = Result<Message<'static>>>`]
-    pub fn into_message_stream(self) -> impl Stream<Item = Result<Message<'static>>> {
+    /// Converts [`Stream<Item = Result<ChatCompletionChunk>>`] into [`Stream<Item = Result<ChoiceDelta>>`], with the deltas of the first choice
+    pub fn into_delta_stream(self) -> impl Stream<Item = Result<ChoiceDelta>> {
         return self
             .try_filter_map(|x| ready(Ok(x.choices.into_iter().next())))
-            .map_ok(|x| x.message);
+            .map_ok(|x| x.delta);
     }
 
-    /// Converts [`Stream<Item = Result<Completion>>`] into [`Stream<Item = Result<Cow<'static, str>>>`]
+    /// Converts [`Stream<Item = Result<ChatCompletionChunk>>`] into [`Stream<Item = Result<Cow<'static, str>>>`], with the content generated for the first choice
     pub fn into_text_stream(self) -> impl Stream<Item = Result<Str<'static>>> {
         return self
-            .try_filter_map(|x| ready(Ok(x.choices.into_iter().next())))
-            .map_ok(|x| x.message.content);
+            .into_delta_stream()
+            .try_filter_map(|x| ready(Ok(x.content.map(Str::Owned))));
     }
+
+    /// Reads the stream to its end, reassembling its chunks into the complete [`ChatCompletion`], with the message of every choice (including its tool calls) and their usage (if sent)
+    pub async fn collect_full(mut self) -> Result<ChatCompletion> {
+        let mut completion = match self.try_next().await? {
+            Some(chunk) => ChatCompletion::from(chunk),
+            None => return Err(Error::msg("the chat completion stream has no chunks")),
+        };
+        while let Some(chunk) = self.try_next().await? {
+            completion.append(chunk);
+        }
+        completion.choices.sort_by_key(|choice| choice.index);
+        return Ok(completion);
+    }
+}
+
+impl ChatCompletionChunk {
+    /// Returns a reference to the first [`ChatChunkChoice`]
+    #[inline]
+    pub fn first(&self) -> Option<&ChatChunkChoice> {
+        return self.choices.first();
+    }
+}
+
+impl HasUsage for ChatCompletionChunk {
+    #[inline]
+    fn usage(&self) -> Option<&Usage> {
+        return self.usage.as_ref();
+    }
+}
+
+impl From<ChatCompletionChunk> for ChatCompletion {
+    /// Starts a completion from the first chunk of a [`ChatCompletionStream`]. See [`ChatCompletion::append`]
+    fn from(chunk: ChatCompletionChunk) -> Self {
+        let mut completion = Self {
+            id: String::new(),
+            created: chunk.created,
+            model: String::new(),
+            choices: Vec::new(),
+            usage: None,
+            extra: HashMap::new(),
+        };
+        completion.append(chunk);
+        return completion;
+    }
+}
+
+/// The content of assistant messages with tool calls may be `null`
+fn deserialize_content<'de, 'a, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Str<'a>, D::Error> {
+    return Ok(Option::<Str<'a>>::deserialize(deserializer)?.unwrap_or_default());
+}
+
+/// Extracts and parses the first JSON value of a model's output, even when it's wrapped in markdown code fences or prose.
+///
+/// The output is scanned once for balanced objects and arrays (ignoring brackets inside their strings), and the first one that parses as `T` is returned.
+pub fn extract_json<T: DeserializeOwned>(text: &str) -> Result<T> {
+    let text = text.trim();
+    let mut err = match serde_json::from_str::<T>(text) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+
+    // Closing brackets of the candidate being scanned, and where it started
+    let mut closers = Vec::new();
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    // Brackets and quotes are ASCII, so their byte positions are character boundaries
+    for (i, c) in text.bytes().enumerate() {
+        if closers.is_empty() {
+            if let Some(closer) = closer_of(c) {
+                start = i;
+                closers.push(closer);
+            }
+            continue;
+        }
+
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match c {
+            b'"' => in_string = true,
+            b'{' | b'[' => closers.extend(closer_of(c)),
+            b'}' | b']' => match closers.pop() {
+                // Unbalanced brackets aren't JSON, so the candidate is dropped
+                Some(closer) if closer != c => closers.clear(),
+                _ if !closers.is_empty() => {}
+                _ => match serde_json::from_str::<T>(&text[start..=i]) {
+                    Ok(value) => return Ok(value),
+                    Err(e) => err = e,
+                },
+            },
+            _ => {}
+        }
+    }
+
+    // A value cut short (e.g. by `max_tokens`) reports where it ends
+    if !closers.is_empty() {
+        if let Err(e) = serde_json::from_str::<T>(&text[start..]) {
+            err = e;
+        }
+    }
+
+    return Err(Error::Json(err));
+}
+
+/// Returns the closing bracket of an opening one
+#[inline]
+fn closer_of(c: u8) -> Option<u8> {
+    return match c {
+        b'{' => Some(b'}'),
+        b'[' => Some(b']'),
+        _ => None,
+    };
+}
+
+fn first_message(completion: ChatCompletion) -> Result<Message<'static>> {
+    return completion
+        .into_first()
+        .map(|choice| choice.message)
+        .ok_or_else(|| Error::msg("the chat completion has no choices"));
 }
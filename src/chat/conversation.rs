@@ -0,0 +1,370 @@
+use super::{first_message, ChatCompletionBuilder, Message};
+use crate::{
+    error::{Error, Result},
+    Client, Str,
+};
+use chrono::{DateTime, Utc};
+use futures::{future::BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
+
+/// A chat session that keeps its messages between requests, and can be saved into a [`ConversationStore`] to be resumed by its id (e.g. after the process restarts)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Conversation {
+    /// Id the conversation is saved with
+    pub id: String,
+    /// Request the conversation is continued with, including its messages
+    pub builder: ChatCompletionBuilder<'static>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Storage of [`Conversation`]s, keyed by their id
+pub trait ConversationStore: Debug + Send + Sync {
+    /// Returns the conversation saved with `id`, if any
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Conversation>>>;
+
+    /// Saves a conversation, replacing the previous one with the same id
+    fn save<'a>(&'a self, conversation: &'a Conversation) -> BoxFuture<'a, Result<()>>;
+
+    /// Deletes the conversation saved with `id`, returning `false` if there was none
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<bool>>;
+
+    /// Returns the ids of the saved conversations
+    fn ids(&self) -> BoxFuture<'_, Result<Vec<String>>>;
+}
+
+/// A [`ConversationStore`] that keeps each conversation as a JSON file (`{id}.json`) in a directory
+#[derive(Debug, Clone)]
+pub struct JsonFileStore {
+    dir: PathBuf,
+}
+
+/// A [`ConversationStore`] backed by a [SQLite](https://www.sqlite.org) database
+#[cfg_attr(docsrs, doc(cfg(feature = "sqlite")))]
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    conn: std::sync::Arc<std::sync::Mutex<rusqlite::Connection>>,
+}
+
+impl Conversation {
+    /// Starts a conversation with a random id, continuing the request of `builder`
+    #[inline]
+    pub fn new(builder: ChatCompletionBuilder<'static>) -> Self {
+        return Self::with_id(format!("conv-{:016x}", rand::random::<u64>()), builder);
+    }
+
+    /// Starts a conversation with the specified id, continuing the request of `builder`
+    #[inline]
+    pub fn with_id(id: impl Into<String>, builder: ChatCompletionBuilder<'static>) -> Self {
+        let now = Utc::now();
+        return Self {
+            id: id.into(),
+            builder,
+            created_at: now,
+            updated_at: now,
+        };
+    }
+
+    /// Returns the messages of the conversation
+    #[inline]
+    pub fn messages(&self) -> &[Message<'static>] {
+        return &self.builder.messages;
+    }
+
+    /// Appends a message to the conversation, without sending it
+    #[inline]
+    pub fn push(&mut self, message: Message<'static>) {
+        self.builder.messages.push(message);
+        self.updated_at = Utc::now();
+    }
+
+    /// Sends a user message, appending it and the model's reply to the conversation.
+    ///
+    /// If the request fails, the conversation is left as it was.
+    pub async fn send(
+        &mut self,
+        content: impl Into<Str<'static>>,
+        client: impl AsRef<Client>,
+    ) -> Result<&Message<'static>> {
+        self.builder.messages.push(Message::user(content));
+        let reply = match self.builder.clone().build(client).await {
+            Ok(completion) => first_message(completion),
+            Err(e) => Err(e),
+        };
+
+        match reply {
+            Ok(reply) => self.push(reply),
+            Err(e) => {
+                self.builder.messages.pop();
+                return Err(e);
+            }
+        }
+
+        return Ok(self.builder.messages.last().unwrap());
+    }
+
+    /// Loads the conversation saved with `id` from `store`, if any
+    #[inline]
+    pub async fn load(id: &str, store: &(impl ?Sized + ConversationStore)) -> Result<Option<Self>> {
+        return store.load(id).await;
+    }
+
+    /// Loads the conversation saved with `id` from `store`, or starts a new one with that id
+    pub async fn resume(
+        id: &str,
+        builder: ChatCompletionBuilder<'static>,
+        store: &(impl ?Sized + ConversationStore),
+    ) -> Result<Self> {
+        return match store.load(id).await? {
+            Some(conversation) => Ok(conversation),
+            None => Ok(Self::with_id(id, builder)),
+        };
+    }
+
+    /// Saves the conversation into `store`
+    #[inline]
+    pub async fn save(&self, store: &(impl ?Sized + ConversationStore)) -> Result<()> {
+        return store.save(self).await;
+    }
+}
+
+impl JsonFileStore {
+    /// Creates a store that keeps its conversations in `dir`, which is created if it doesn't exist
+    #[inline]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        return Self { dir: dir.into() };
+    }
+
+    /// Returns the directory the conversations are stored in
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        return &self.dir;
+    }
+
+    /// Returns the path of the conversation with `id`, which must be usable as a file name
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        let valid = !id.is_empty()
+            && !id.starts_with('.')
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+        if !valid {
+            return Err(Error::msg(format!(
+                "invalid conversation id '{id}': only ASCII letters, digits, '-', '_' and '.' are allowed"
+            )));
+        }
+        return Ok(self.dir.join(format!("{id}.json")));
+    }
+
+    async fn read(&self, id: &str) -> Result<Option<Conversation>> {
+        let path = self.path(id)?;
+        return match crate::rt::read(&path).await {
+            Ok(json) => Ok(Some(serde_json::from_slice(&json)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    async fn write(&self, conversation: &Conversation) -> Result<()> {
+        let path = self.path(&conversation.id)?;
+        let json = serde_json::to_vec(conversation)?;
+
+        // Written to a temporary file first, so that a crash doesn't leave a truncated conversation
+        let tmp = path.with_extension("json.tmp");
+        crate::rt::create_dir_all(&self.dir).await?;
+        crate::rt::write(&tmp, json).await?;
+        crate::rt::rename(&tmp, &path).await?;
+        return Ok(());
+    }
+
+    async fn remove(&self, id: &str) -> Result<bool> {
+        let path = self.path(id)?;
+        return match crate::rt::remove_file(&path).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    async fn list(&self) -> Result<Vec<String>> {
+        let dir = self.dir.clone();
+        return crate::rt::spawn_blocking(move || {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => return Err(e.into()),
+            };
+
+            let mut ids = Vec::new();
+            for entry in entries {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    if let Some(id) = path.file_stem().and_then(|x| x.to_str()) {
+                        ids.push(id.to_string());
+                    }
+                }
+            }
+
+            ids.sort_unstable();
+            return Ok(ids);
+        })
+        .await;
+    }
+}
+
+impl ConversationStore for JsonFileStore {
+    #[inline]
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Conversation>>> {
+        return self.read(id).boxed();
+    }
+
+    #[inline]
+    fn save<'a>(&'a self, conversation: &'a Conversation) -> BoxFuture<'a, Result<()>> {
+        return self.write(conversation).boxed();
+    }
+
+    #[inline]
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        return self.remove(id).boxed();
+    }
+
+    #[inline]
+    fn ids(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        return self.list().boxed();
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    /// Opens (or creates) the database at `path`, creating the `conversations` table if it doesn't exist
+    pub async fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let conn = crate::rt::spawn_blocking(move || rusqlite::Connection::open(path))
+            .await
+            .map_err(Error::msg)?;
+        return Self::with_connection(conn).await;
+    }
+
+    /// Creates a store in an in-memory database, which is lost when the store is dropped
+    pub async fn in_memory() -> Result<Self> {
+        let conn = rusqlite::Connection::open_in_memory().map_err(Error::msg)?;
+        return Self::with_connection(conn).await;
+    }
+
+    /// Creates a store with an open connection, creating the `conversations` table if it doesn't exist
+    pub async fn with_connection(conn: rusqlite::Connection) -> Result<Self> {
+        let store = Self {
+            conn: std::sync::Arc::new(std::sync::Mutex::new(conn)),
+        };
+
+        store
+            .with_conn(|conn| {
+                conn.execute(
+                    "CREATE TABLE IF NOT EXISTS conversations (
+                        id TEXT PRIMARY KEY NOT NULL,
+                        data TEXT NOT NULL,
+                        updated_at INTEGER NOT NULL
+                    )",
+                    (),
+                )?;
+                Ok(())
+            })
+            .await?;
+
+        return Ok(store);
+    }
+
+    /// Runs `f` with the connection, on a thread where blocking is acceptable
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        F: 'static + Send + FnOnce(&rusqlite::Connection) -> rusqlite::Result<T>,
+        T: 'static + Send,
+    {
+        let conn = self.conn.clone();
+        return crate::rt::spawn_blocking(move || {
+            let conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+            f(&conn)
+        })
+        .await
+        .map_err(Error::msg);
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl ConversationStore for SqliteStore {
+    fn load<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Conversation>>> {
+        use rusqlite::OptionalExtension;
+
+        let id = id.to_string();
+        return async move {
+            let data = self
+                .with_conn(move |conn| {
+                    conn.query_row(
+                        "SELECT data FROM conversations WHERE id = ?1",
+                        [id],
+                        |row| row.get::<_, String>(0),
+                    )
+                    .optional()
+                })
+                .await?;
+
+            return match data {
+                Some(data) => Ok(Some(serde_json::from_str(&data)?)),
+                None => Ok(None),
+            };
+        }
+        .boxed();
+    }
+
+    fn save<'a>(&'a self, conversation: &'a Conversation) -> BoxFuture<'a, Result<()>> {
+        return async move {
+            let id = conversation.id.clone();
+            let updated_at = conversation.updated_at.timestamp();
+            let data = serde_json::to_string(conversation)?;
+
+            self.with_conn(move |conn| {
+                conn.execute(
+                    "INSERT INTO conversations (id, data, updated_at) VALUES (?1, ?2, ?3)
+                    ON CONFLICT(id) DO UPDATE SET data = excluded.data, updated_at = excluded.updated_at",
+                    rusqlite::params![id, data, updated_at],
+                )?;
+                Ok(())
+            })
+            .await
+        }
+        .boxed();
+    }
+
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<bool>> {
+        let id = id.to_string();
+        return async move {
+            self.with_conn(move |conn| {
+                conn.execute("DELETE FROM conversations WHERE id = ?1", [id])
+                    .map(|deleted| deleted > 0)
+            })
+            .await
+        }
+        .boxed();
+    }
+
+    fn ids(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        return self
+            .with_conn(|conn| {
+                let mut stmt = conn.prepare("SELECT id FROM conversations ORDER BY id")?;
+                let ids = stmt
+                    .query_map((), |row| row.get::<_, String>(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>();
+                ids
+            })
+            .boxed();
+    }
+}
@@ -0,0 +1,190 @@
+use super::{conversation::ConversationStore, ChatCompletion, Conversation, Message, Role};
+use crate::{error::Result, file::jsonl::JsonlWriter};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+/// Datasets a [`TranscriptExporter`] writes lines of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum TranscriptFormat {
+    /// Chat fine-tuning examples (`{"messages": [...]}`)
+    #[default]
+    FineTune,
+    /// Evals items (`{"item": {"input": [...], "ideal": "..."}}`), whose ideal answer is the last reply of the assistant
+    Evals,
+}
+
+/// A line of a dataset written by a [`TranscriptExporter`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TranscriptLine {
+    FineTune { messages: Vec<Message<'static>> },
+    Evals { item: EvalItem },
+}
+
+/// A conversation and the answer expected from the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct EvalItem {
+    /// Messages that precede the answer
+    pub input: Vec<Message<'static>>,
+    /// Content of the answer
+    pub ideal: String,
+}
+
+/// Rewrites the text of the messages being exported
+type Scrubber = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Converts chat transcripts (e.g. saved [`Conversation`]s, or the messages of a [`ChatCompletion`] request and its reply) into the JSONL datasets accepted by the fine-tuning and evals APIs.
+#[derive(Clone, Default)]
+pub struct TranscriptExporter {
+    format: TranscriptFormat,
+    roles: HashMap<Role, Option<Role>>,
+    scrub: Option<Scrubber>,
+}
+
+impl TranscriptExporter {
+    /// Creates an exporter of the specified format, which keeps messages as they are
+    #[inline]
+    pub fn new(format: TranscriptFormat) -> Self {
+        return Self {
+            format,
+            ..Self::default()
+        };
+    }
+
+    /// Converts the messages with role `from` into messages with role `to`, or drops them if `to` is `None`
+    #[inline]
+    pub fn role(mut self, from: Role, to: Option<Role>) -> Self {
+        self.roles.insert(from, to);
+        self
+    }
+
+    /// Rewrites the content of every message (and the arguments of its tool calls) with `scrub`, e.g. to remove personally identifiable information
+    #[inline]
+    pub fn scrub(mut self, scrub: impl 'static + Fn(&str) -> String + Send + Sync) -> Self {
+        self.scrub = Some(Arc::new(scrub));
+        self
+    }
+
+    /// Returns the purpose files of the exporter's format are uploaded with
+    #[inline]
+    pub fn purpose(&self) -> &'static str {
+        return match self.format {
+            TranscriptFormat::FineTune => "fine-tune",
+            TranscriptFormat::Evals => "evals",
+        };
+    }
+
+    /// Converts a transcript into a line of the dataset.
+    ///
+    /// Returns `None` if no message is left after mapping their roles, or if an evals item has no reply of the assistant to expect.
+    pub fn line(&self, messages: &[Message<'_>]) -> Option<TranscriptLine> {
+        let mut messages = messages
+            .iter()
+            .filter_map(|message| self.message(message))
+            .collect::<Vec<_>>();
+
+        return match self.format {
+            TranscriptFormat::FineTune if messages.is_empty() => None,
+            TranscriptFormat::FineTune => Some(TranscriptLine::FineTune { messages }),
+            TranscriptFormat::Evals => {
+                let ideal = messages.pop().filter(|m| m.role == Role::Assistant)?;
+                Some(TranscriptLine::Evals {
+                    item: EvalItem {
+                        input: messages,
+                        ideal: ideal.content.into_owned(),
+                    },
+                })
+            }
+        };
+    }
+
+    /// Converts the messages of a request and the first choice of its completion into a line of the dataset
+    pub fn completion_line(
+        &self,
+        messages: &[Message<'_>],
+        completion: &ChatCompletion,
+    ) -> Option<TranscriptLine> {
+        let mut messages = messages.to_vec();
+        if let Some(choice) = completion.choices.first() {
+            messages.push(choice.message.clone());
+        }
+        return self.line(&messages);
+    }
+
+    /// Writes a line for every conversation, returning the number of lines written
+    pub async fn export<'a, I>(
+        &self,
+        conversations: I,
+        writer: &mut JsonlWriter<TranscriptLine>,
+    ) -> Result<u64>
+    where
+        I: IntoIterator<Item = &'a Conversation>,
+    {
+        let mut lines = 0;
+        for conversation in conversations {
+            if let Some(line) = self.line(conversation.messages()) {
+                writer.write(&line).await?;
+                lines += 1;
+            }
+        }
+        return Ok(lines);
+    }
+
+    /// Writes a line for every conversation saved in `store`, returning the number of lines written
+    pub async fn export_store(
+        &self,
+        store: &(impl ?Sized + ConversationStore),
+        writer: &mut JsonlWriter<TranscriptLine>,
+    ) -> Result<u64> {
+        let mut lines = 0;
+        for id in store.ids().await? {
+            if let Some(conversation) = store.load(&id).await? {
+                lines += self.export([&conversation], writer).await?;
+            }
+        }
+        return Ok(lines);
+    }
+
+    /// Maps the role of a message and scrubs its content, or returns `None` if it's dropped
+    fn message(&self, message: &Message<'_>) -> Option<Message<'static>> {
+        let role = match self.roles.get(&message.role) {
+            Some(role) => (*role)?,
+            None => message.role,
+        };
+
+        let mut message = Message {
+            role,
+            content: message.content.to_string().into(),
+            tool_calls: message.tool_calls.clone(),
+            tool_call_id: message
+                .tool_call_id
+                .as_ref()
+                .map(|id| id.to_string().into()),
+            function_call: message.function_call.clone(),
+            name: message.name.as_ref().map(|name| name.to_string().into()),
+        };
+
+        if let Some(ref scrub) = self.scrub {
+            message.content = scrub(&message.content).into();
+            for call in message.tool_calls.iter_mut() {
+                call.function.arguments = scrub(&call.function.arguments);
+            }
+            if let Some(ref mut call) = message.function_call {
+                call.arguments = scrub(&call.arguments);
+            }
+        }
+        return Some(message);
+    }
+}
+
+impl Debug for TranscriptExporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TranscriptExporter")
+            .field("format", &self.format)
+            .field("roles", &self.roles)
+            .field("scrub", &self.scrub.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,121 @@
+use super::{ChatCompletionBuilder, Conversation};
+use crate::{
+    endpoint::Endpoint,
+    error::{Error, Result},
+    Client,
+};
+
+impl<'a> ChatCompletionBuilder<'a> {
+    /// Returns the request as the JSON payload used by the OpenAI Playground and the `curl` examples of the API reference
+    pub fn to_playground(&self) -> Result<String> {
+        let mut payload = serde_json::to_value(self)?;
+        if let Some(payload) = payload.as_object_mut() {
+            if !self.stream {
+                payload.remove("stream");
+            }
+        }
+        return Ok(serde_json::to_string_pretty(&payload)?);
+    }
+
+    /// Returns a `curl` command that sends the request to the chat endpoint of `client`, authenticated with the `OPENAI_API_KEY` environment variable
+    pub fn to_curl(&self, client: &Client) -> Result<String> {
+        let payload = self.to_playground()?;
+        return Ok(format!(
+            "curl {} \\\n  -H \"Content-Type: application/json\" \\\n  -H \"Authorization: Bearer $OPENAI_API_KEY\" \\\n  -d '{}'",
+            client.url(Endpoint::Chat, ""),
+            payload.replace('\'', "'\\''")
+        ));
+    }
+}
+
+impl ChatCompletionBuilder<'static> {
+    /// Parses a request from a Playground JSON payload, or from a `curl` command that sends one.
+    ///
+    /// Fields the builder doesn't support are kept as [extra parameters](ChatCompletionBuilder::extra_param), except for `stream` and `stream_options`.
+    pub fn from_playground(input: &str) -> Result<Self> {
+        let input = input.trim();
+        if !input.starts_with("curl") {
+            return Ok(serde_json::from_str(input)?);
+        }
+
+        let payload = curl_data(input)
+            .ok_or_else(|| Error::msg("The curl command doesn't send a request body"))?;
+        return Ok(serde_json::from_str(&payload)?);
+    }
+}
+
+impl Conversation {
+    /// Returns the conversation as a Playground JSON payload. See [`ChatCompletionBuilder::to_playground`]
+    #[inline]
+    pub fn to_playground(&self) -> Result<String> {
+        return self.builder.to_playground();
+    }
+
+    /// Starts a conversation (with a random id) from a Playground JSON payload or `curl` command. See [`ChatCompletionBuilder::from_playground`]
+    #[inline]
+    pub fn from_playground(input: &str) -> Result<Self> {
+        return ChatCompletionBuilder::from_playground(input).map(Self::new);
+    }
+}
+
+/// Returns the body sent by a `curl` command (the argument of `-d`, `--data` or `--data-raw`)
+fn curl_data(command: &str) -> Option<String> {
+    let args = shell_words(command);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-d" | "--data" | "--data-raw" => return args.next().cloned(),
+            _ => {
+                if let Some(data) = arg
+                    .strip_prefix("--data=")
+                    .or_else(|| arg.strip_prefix("--data-raw="))
+                {
+                    return Some(data.to_string());
+                }
+            }
+        }
+    }
+    return None;
+}
+
+/// Splits a POSIX shell command into its words, handling quotes, escapes and line continuations
+fn shell_words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                word.extend(chars.by_ref().take_while(|&c| c != '\''));
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some('\n') | None => {}
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') | None => {}
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+            },
+            c if c.is_whitespace() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    words.extend(word);
+    return words;
+}
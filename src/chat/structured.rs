@@ -0,0 +1,77 @@
+use super::{first_message, ChatCompletionBuilder, JsonSchemaFormat, ResponseFormat};
+use crate::{error::Result, Client};
+use schemars::{gen::SchemaSettings, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Maximum length of the name of a [`JsonSchemaFormat`]
+const MAX_NAME_LEN: usize = 64;
+
+impl<'a> ChatCompletionBuilder<'a> {
+    /// Makes the model answer with a JSON value that follows the schema of `T` exactly, with a [`JsonSchema`](ResponseFormat::JsonSchema) format in strict mode.
+    ///
+    /// The schema is adapted to strict mode, which requires every property (optional ones can be `null`) and forbids the ones not in the schema.
+    pub fn response_schema<T: JsonSchema>(self) -> Result<Self> {
+        let schema = SchemaSettings::draft2019_09()
+            .into_generator()
+            .into_root_schema_for::<T>();
+
+        let mut schema = serde_json::to_value(schema)?;
+        if let Some(schema) = schema.as_object_mut() {
+            schema.remove("$schema");
+        }
+        make_strict(&mut schema);
+
+        return Ok(self.response_format(ResponseFormat::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: schema_name(&T::schema_name()).into(),
+                description: None,
+                schema,
+                strict: Some(true),
+            },
+        }));
+    }
+
+    /// Sends the request, and parses the content of the first choice into `T`.
+    ///
+    /// Unless the builder already has a [response format](ChatCompletionBuilder::response_format), the model is asked to follow the schema of `T` (see [`response_schema`](ChatCompletionBuilder::response_schema)).
+    pub async fn build_typed<T: JsonSchema + DeserializeOwned>(
+        mut self,
+        client: impl AsRef<Client>,
+    ) -> Result<T> {
+        if self.response_format.is_none() {
+            self = self.response_schema::<T>()?;
+        }
+
+        let message = first_message(self.build(client).await?)?;
+        return Ok(serde_json::from_str(&message.content)?);
+    }
+}
+
+/// Closes the objects of `schema` (and its subschemas), requiring all their properties
+fn make_strict(schema: &mut Value) {
+    match schema {
+        Value::Object(schema) => {
+            if let Some(Value::Object(properties)) = schema.get("properties") {
+                let required = properties.keys().cloned().map(Value::String).collect();
+                schema.insert("required".to_string(), Value::Array(required));
+                schema.insert("additionalProperties".to_string(), Value::Bool(false));
+            }
+            schema.values_mut().for_each(make_strict);
+        }
+        Value::Array(schemas) => schemas.iter_mut().for_each(make_strict),
+        _ => {}
+    }
+}
+
+/// Returns the name of a schema with only the characters allowed by the API (letters, digits, underscores and dashes)
+fn schema_name(name: &str) -> String {
+    return name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '-' {
+            true => c,
+            false => '_',
+        })
+        .take(MAX_NAME_LEN)
+        .collect();
+}
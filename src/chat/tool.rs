@@ -0,0 +1,491 @@
+use super::{ChatCompletion, Message};
+use crate::{
+    error::{Error, Result},
+    Str,
+};
+use futures::{
+    future::{join_all, BoxFuture},
+    FutureExt,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Debug, future::Future};
+
+pub use schema::{ToolArgs, ToolSchema};
+
+/// JSON Schemas of the arguments of tools, which can be derived with the `derive` feature.
+pub mod schema;
+
+/// A tool the model may call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Tool<'a> {
+    /// A custom function defined by you.
+    Function { function: FunctionDefinition<'a> },
+}
+
+/// Definition of a function the model may call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FunctionDefinition<'a> {
+    pub name: Str<'a>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<Str<'a>>,
+    /// JSON Schema of the function's arguments
+    pub parameters: serde_json::Value,
+}
+
+/// A tool call generated by the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub function: FunctionCall,
+}
+
+/// Part of a [`ToolCall`] generated by a [`ChatCompletionStream`](super::ChatCompletionStream)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ToolCallDelta {
+    /// Position of the call in the message's tool calls
+    pub index: usize,
+    /// Only sent in the first chunk of the call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<FunctionCallDelta>,
+}
+
+/// Part of a [`FunctionCall`] generated by a [`ChatCompletionStream`](super::ChatCompletionStream)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FunctionCallDelta {
+    /// Only sent in the first chunk of the call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+}
+
+/// Function called by a [`ToolCall`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FunctionCall {
+    pub name: String,
+    /// Arguments of the call, as a JSON string generated by the model (which may not be valid JSON)
+    pub arguments: String,
+}
+
+/// Whether (and which) function the model calls, with the legacy function calling API. See [`ChatCompletionBuilder::function_call`](super::ChatCompletionBuilder::function_call)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FunctionChoice<'a> {
+    /// The model doesn't call any function, and generates a message instead
+    None,
+    /// The model picks between generating a message or calling a function
+    Auto,
+    /// The model calls the function with the specified name
+    Function(Str<'a>),
+}
+
+/// Whether (and which) tool the model calls. See [`ChatCompletionBuilder::tool_choice`](super::ChatCompletionBuilder::tool_choice)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ToolChoice<'a> {
+    /// The model doesn't call any tool, and generates a message instead
+    None,
+    /// The model picks between generating a message or calling one or more tools
+    Auto,
+    /// The model calls one or more tools
+    Required,
+    /// The model calls the function with the specified name
+    Function(Str<'a>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum FunctionChoiceRepr<'a> {
+    Mode(Str<'a>),
+    Function(FunctionName<'a>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ToolChoiceRepr<'a> {
+    Mode(Str<'a>),
+    Function {
+        #[serde(rename = "type")]
+        ty: Str<'a>,
+        function: FunctionName<'a>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+struct FunctionName<'a> {
+    name: Str<'a>,
+}
+
+type Handler = Box<
+    dyn Fn(&str) -> std::result::Result<BoxFuture<'static, Result<String>>, serde_json::Error>
+        + Send
+        + Sync,
+>;
+
+struct RegisteredTool {
+    definition: Tool<'static>,
+    handler: Handler,
+}
+
+/// Maps tool names to the handlers that execute them.
+///
+/// Given a [`ChatCompletion`] with tool calls, the registry calls the matching handlers with their deserialized arguments, and returns the [`Tool`](super::Role::Tool) messages with their results.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, RegisteredTool>,
+    concurrent: bool,
+}
+
+impl<'a> Tool<'a> {
+    /// Creates a function tool
+    #[inline]
+    pub fn function(
+        name: impl Into<Str<'a>>,
+        description: Option<Str<'a>>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        return Self::Function {
+            function: FunctionDefinition::new(name, description, parameters),
+        };
+    }
+
+    /// Returns the name of the tool
+    #[inline]
+    pub fn name(&self) -> &str {
+        match self {
+            Tool::Function { function } => &function.name,
+        }
+    }
+}
+
+impl<'a> FunctionDefinition<'a> {
+    /// Creates a function definition, with the JSON Schema of its arguments
+    #[inline]
+    pub fn new(
+        name: impl Into<Str<'a>>,
+        description: Option<Str<'a>>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        return Self {
+            name: name.into(),
+            description,
+            parameters,
+        };
+    }
+}
+
+impl ToolCall {
+    /// Returns the name of the called function
+    #[inline]
+    pub fn name(&self) -> &str {
+        return &self.function.name;
+    }
+
+    /// Deserializes the arguments of the call. See [`FunctionCall::parse_arguments`]
+    #[inline]
+    pub fn parse_arguments<T: DeserializeOwned>(&self) -> Result<T> {
+        return self.function.parse_arguments();
+    }
+}
+
+impl ToolCallDelta {
+    /// Appends the delta to the call at its index of `calls`, adding it if it's a new one
+    pub(crate) fn append_to(self, calls: &mut Vec<ToolCall>) {
+        while calls.len() <= self.index {
+            calls.push(ToolCall {
+                id: String::new(),
+                ty: String::new(),
+                function: FunctionCall::default(),
+            });
+        }
+
+        let call = &mut calls[self.index];
+        if let Some(id) = self.id {
+            call.id = id;
+        }
+        if let Some(ty) = self.ty {
+            call.ty = ty;
+        }
+        if let Some(function) = self.function {
+            function.append_to(&mut call.function);
+        }
+    }
+}
+
+impl FunctionCallDelta {
+    /// Appends the delta to `call`
+    pub(crate) fn append_to(self, call: &mut FunctionCall) {
+        if let Some(name) = self.name {
+            call.name.push_str(&name);
+        }
+        if let Some(arguments) = self.arguments {
+            call.arguments.push_str(&arguments);
+        }
+    }
+}
+
+impl FunctionCall {
+    /// Deserializes the arguments of the call
+    #[inline]
+    pub fn parse_arguments<T: DeserializeOwned>(&self) -> Result<T> {
+        return Ok(serde_json::from_str(&self.arguments)?);
+    }
+}
+
+impl<'a> From<&'a str> for FunctionChoice<'a> {
+    /// Calls the function with the specified name
+    #[inline]
+    fn from(name: &'a str) -> Self {
+        return Self::Function(name.into());
+    }
+}
+
+impl From<String> for FunctionChoice<'_> {
+    /// Calls the function with the specified name
+    #[inline]
+    fn from(name: String) -> Self {
+        return Self::Function(name.into());
+    }
+}
+
+impl Serialize for FunctionChoice<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let repr = match self {
+            FunctionChoice::None => FunctionChoiceRepr::Mode("none".into()),
+            FunctionChoice::Auto => FunctionChoiceRepr::Mode("auto".into()),
+            FunctionChoice::Function(name) => FunctionChoiceRepr::Function(FunctionName {
+                name: Str::Borrowed(name),
+            }),
+        };
+        return repr.serialize(serializer);
+    }
+}
+
+impl<'de> Deserialize<'de> for FunctionChoice<'_> {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        return match FunctionChoiceRepr::deserialize(deserializer)? {
+            FunctionChoiceRepr::Mode(mode) => match mode.as_ref() {
+                "none" => Ok(Self::None),
+                "auto" => Ok(Self::Auto),
+                other => Err(serde::de::Error::unknown_variant(other, &["none", "auto"])),
+            },
+            FunctionChoiceRepr::Function(FunctionName { name }) => {
+                Ok(Self::Function(Str::Owned(name.into_owned())))
+            }
+        };
+    }
+}
+
+impl<'a> From<&'a str> for ToolChoice<'a> {
+    /// Calls the function with the specified name
+    #[inline]
+    fn from(name: &'a str) -> Self {
+        return Self::Function(name.into());
+    }
+}
+
+impl From<String> for ToolChoice<'_> {
+    /// Calls the function with the specified name
+    #[inline]
+    fn from(name: String) -> Self {
+        return Self::Function(name.into());
+    }
+}
+
+impl Serialize for ToolChoice<'_> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let repr = match self {
+            ToolChoice::None => ToolChoiceRepr::Mode("none".into()),
+            ToolChoice::Auto => ToolChoiceRepr::Mode("auto".into()),
+            ToolChoice::Required => ToolChoiceRepr::Mode("required".into()),
+            ToolChoice::Function(name) => ToolChoiceRepr::Function {
+                ty: "function".into(),
+                function: FunctionName {
+                    name: Str::Borrowed(name),
+                },
+            },
+        };
+        return repr.serialize(serializer);
+    }
+}
+
+impl<'de> Deserialize<'de> for ToolChoice<'_> {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        return match ToolChoiceRepr::deserialize(deserializer)? {
+            ToolChoiceRepr::Mode(mode) => match mode.as_ref() {
+                "none" => Ok(Self::None),
+                "auto" => Ok(Self::Auto),
+                "required" => Ok(Self::Required),
+                other => Err(serde::de::Error::unknown_variant(
+                    other,
+                    &["none", "auto", "required"],
+                )),
+            },
+            ToolChoiceRepr::Function { function, .. } => {
+                Ok(Self::Function(Str::Owned(function.name.into_owned())))
+            }
+        };
+    }
+}
+
+impl ToolRegistry {
+    #[inline]
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Registers a function tool.
+    ///
+    /// The arguments generated by the model are deserialized into `A` before calling `handler`. If its output serializes into a JSON string, the string is sent to the model as is. Otherwise, it's sent as JSON.
+    pub fn register<A, F, Fut, R>(
+        mut self,
+        name: impl Into<String>,
+        description: Option<&str>,
+        parameters: serde_json::Value,
+        handler: F,
+    ) -> Self
+    where
+        A: DeserializeOwned,
+        F: 'static + Send + Sync + Fn(A) -> Fut,
+        Fut: 'static + Send + Future<Output = Result<R>>,
+        R: Serialize,
+    {
+        let name = name.into();
+        let definition = Tool::function(
+            name.clone(),
+            description.map(|x| Str::Owned(x.to_string())),
+            parameters,
+        );
+
+        let handler: Handler = Box::new(move |args| {
+            let args = serde_json::from_str::<A>(args)?;
+            let fut = handler(args);
+            return Ok(async move {
+                let output = serde_json::to_value(fut.await?)?;
+                return Ok::<_, Error>(match output {
+                    serde_json::Value::String(output) => output,
+                    output => output.to_string(),
+                });
+            }
+            .boxed());
+        });
+
+        self.tools.insert(
+            name,
+            RegisteredTool {
+                definition,
+                handler,
+            },
+        );
+        self
+    }
+
+    /// Registers a function tool, whose name, description and parameters are the ones of its arguments. See [`ToolArgs`]
+    #[inline]
+    pub fn register_args<A, F, Fut, R>(self, handler: F) -> Self
+    where
+        A: ToolArgs,
+        F: 'static + Send + Sync + Fn(A) -> Fut,
+        Fut: 'static + Send + Future<Output = Result<R>>,
+        R: Serialize,
+    {
+        return self.register(A::NAME, A::DESCRIPTION, A::schema(), handler);
+    }
+
+    /// Whether to execute the tool calls of a completion concurrently. Defaults to `false`.
+    pub fn concurrent(mut self, concurrent: bool) -> Self {
+        self.concurrent = concurrent;
+        self
+    }
+
+    /// Returns the definitions of the registered tools, to be sent with the request.
+    ///
+    /// Tools are sorted by name, so that requests (and their cached prompt prefixes) are the same between runs.
+    #[inline]
+    pub fn tools(&self) -> impl Iterator<Item = &Tool<'static>> {
+        return self.tools.values().map(|tool| &tool.definition);
+    }
+
+    /// Returns `true` if a tool with the specified name is registered
+    #[inline]
+    pub fn contains(&self, name: impl AsRef<str>) -> bool {
+        return self.tools.contains_key(name.as_ref());
+    }
+
+    /// Executes a tool call, returning the message with its result.
+    ///
+    /// Mistakes of the model (calling an unknown tool or passing invalid arguments) are reported back to it in the returned message, while errors of the handler are returned.
+    pub async fn call(&self, call: &ToolCall) -> Result<Message<'static>> {
+        let content = match self.tools.get(&call.function.name) {
+            Some(tool) => match (tool.handler)(&call.function.arguments) {
+                Ok(fut) => fut.await?,
+                Err(e) => format!("Error: invalid arguments for '{}': {e}", call.function.name),
+            },
+            None => format!("Error: unknown tool '{}'", call.function.name),
+        };
+
+        return Ok(Message::tool(call.id.clone(), content));
+    }
+
+    /// Executes the tool calls of the first choice of `completion`, returning the messages with their results in the same order.
+    ///
+    /// The assistant message with the tool calls must be appended to the conversation before the returned messages.
+    pub async fn execute(&self, completion: &ChatCompletion) -> Result<Vec<Message<'static>>> {
+        let calls = match completion.first() {
+            Some(choice) => &choice.message.tool_calls[..],
+            None => &[],
+        };
+
+        if self.concurrent {
+            return join_all(calls.iter().map(|call| self.call(call)))
+                .await
+                .into_iter()
+                .collect();
+        }
+
+        let mut result = Vec::with_capacity(calls.len());
+        for call in calls {
+            result.push(self.call(call).await?);
+        }
+        return Ok(result);
+    }
+}
+
+impl Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolRegistry")
+            .field(
+                "tools",
+                &self
+                    .tools
+                    .values()
+                    .map(|x| &x.definition)
+                    .collect::<Vec<_>>(),
+            )
+            .field("concurrent", &self.concurrent)
+            .finish()
+    }
+}
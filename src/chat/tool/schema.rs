@@ -0,0 +1,221 @@
+use super::{FunctionDefinition, Tool};
+use crate::{error::Result, Str};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    rc::Rc,
+    sync::Arc,
+};
+
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+#[cfg(feature = "derive")]
+pub use libopenai_derive::{ToolArgs, ToolSchema};
+
+/// Types with a JSON Schema, which the model follows when generating them.
+///
+/// With the `derive` feature, it can be derived for structs with named fields (whose doc comments describe their properties) and enums with unit variants.
+pub trait ToolSchema {
+    /// Whether the value may be omitted from its object, e.g. for [`Option`]s
+    const OPTIONAL: bool = false;
+
+    /// Returns the JSON Schema of the type
+    fn schema() -> Value;
+}
+
+/// Arguments of a function tool, which describe the function and are parsed from its calls.
+///
+/// With the `derive` feature, it can be derived for structs with named fields that implement [`Deserialize`](serde::Deserialize):
+///
+/// ```rust,ignore
+/// /// Returns the current weather of a city
+/// #[derive(Deserialize, ToolArgs)]
+/// struct GetWeather {
+///     /// Name of the city, e.g. "Paris"
+///     city: String,
+///     unit: Option<Unit>,
+/// }
+/// ```
+///
+/// The function is named after the struct in snake case (`get_weather`), and described by its doc comment, unless overriden with `#[tool(name = "...", description = "...")]`.
+pub trait ToolArgs: ToolSchema + DeserializeOwned {
+    /// Name of the function
+    const NAME: &'static str;
+    /// Description of the function, used by the model to choose when and how to call it
+    const DESCRIPTION: Option<&'static str>;
+
+    /// Returns the definition of the function
+    #[inline]
+    fn definition() -> FunctionDefinition<'static> {
+        return FunctionDefinition::new(
+            Self::NAME,
+            Self::DESCRIPTION.map(Str::Borrowed),
+            Self::schema(),
+        );
+    }
+
+    /// Returns the function as a tool
+    #[inline]
+    fn tool() -> Tool<'static> {
+        return Tool::Function {
+            function: Self::definition(),
+        };
+    }
+
+    /// Parses the arguments of a call, as generated by the model
+    #[inline]
+    fn parse(arguments: &str) -> Result<Self> {
+        return Ok(serde_json::from_str(arguments)?);
+    }
+}
+
+macro_rules! impl_schema {
+    ($schema:tt => $($ty:ty),+) => {
+        $(
+            impl ToolSchema for $ty {
+                #[inline]
+                fn schema() -> Value {
+                    return json!($schema);
+                }
+            }
+        )+
+    };
+}
+
+impl_schema!({ "type": "boolean" } => bool);
+impl_schema!({ "type": "integer" } => i8, i16, i32, i64, i128, isize);
+impl_schema!({ "type": "integer", "minimum": 0 } => u8, u16, u32, u64, u128, usize);
+impl_schema!({ "type": "number" } => f32, f64);
+impl_schema!({ "type": "string" } => char, str, String);
+impl_schema!({} => Value);
+
+impl<T: ?Sized + ToolSchema> ToolSchema for &T {
+    const OPTIONAL: bool = T::OPTIONAL;
+
+    #[inline]
+    fn schema() -> Value {
+        return T::schema();
+    }
+}
+
+impl<T: ?Sized + ToOwned + ToolSchema> ToolSchema for Cow<'_, T> {
+    const OPTIONAL: bool = T::OPTIONAL;
+
+    #[inline]
+    fn schema() -> Value {
+        return T::schema();
+    }
+}
+
+macro_rules! impl_wrapper {
+    ($($ty:ident),+) => {
+        $(
+            impl<T: ?Sized + ToolSchema> ToolSchema for $ty<T> {
+                const OPTIONAL: bool = T::OPTIONAL;
+
+                #[inline]
+                fn schema() -> Value {
+                    return T::schema();
+                }
+            }
+        )+
+    };
+}
+
+impl_wrapper!(Box, Rc, Arc);
+
+impl<T: ToolSchema> ToolSchema for Option<T> {
+    const OPTIONAL: bool = true;
+
+    #[inline]
+    fn schema() -> Value {
+        return T::schema();
+    }
+}
+
+macro_rules! impl_array {
+    ($unique:literal => $($ty:ident),+) => {
+        $(
+            impl<T: ToolSchema> ToolSchema for $ty<T> {
+                #[inline]
+                fn schema() -> Value {
+                    return match $unique {
+                        true => json!({ "type": "array", "items": T::schema(), "uniqueItems": true }),
+                        false => json!({ "type": "array", "items": T::schema() }),
+                    };
+                }
+            }
+        )+
+    };
+}
+
+impl_array!(false => Vec, VecDeque);
+impl_array!(true => HashSet, BTreeSet);
+
+impl<T: ToolSchema> ToolSchema for [T] {
+    #[inline]
+    fn schema() -> Value {
+        return json!({ "type": "array", "items": T::schema() });
+    }
+}
+
+impl<T: ToolSchema, const N: usize> ToolSchema for [T; N] {
+    #[inline]
+    fn schema() -> Value {
+        return json!({ "type": "array", "items": T::schema(), "minItems": N, "maxItems": N });
+    }
+}
+
+impl<K, V: ToolSchema, S> ToolSchema for HashMap<K, V, S> {
+    #[inline]
+    fn schema() -> Value {
+        return json!({ "type": "object", "additionalProperties": V::schema() });
+    }
+}
+
+impl<K, V: ToolSchema> ToolSchema for BTreeMap<K, V> {
+    #[inline]
+    fn schema() -> Value {
+        return json!({ "type": "object", "additionalProperties": V::schema() });
+    }
+}
+
+/// Helpers of the code generated by the derive macros
+#[doc(hidden)]
+pub mod __private {
+    use super::ToolSchema;
+    use serde_json::{json, Map};
+
+    pub use serde_json::Value;
+
+    /// Returns the schema of a property, with its description
+    pub fn property<T: ?Sized + ToolSchema>(description: Option<&str>) -> Value {
+        let mut schema = T::schema();
+        if let (Some(description), Some(schema)) = (description, schema.as_object_mut()) {
+            schema.insert("description".to_string(), description.into());
+        }
+        return schema;
+    }
+
+    /// Returns the schema of an object with the specified properties (name, schema and whether they're required)
+    pub fn object(properties: Vec<(&str, Value, bool)>) -> Value {
+        let required = properties
+            .iter()
+            .filter(|(_, _, required)| *required)
+            .map(|(name, _, _)| Value::from(*name))
+            .collect::<Vec<_>>();
+
+        let properties = properties
+            .into_iter()
+            .map(|(name, schema, _)| (name.to_string(), schema))
+            .collect::<Map<_, _>>();
+
+        return json!({ "type": "object", "properties": properties, "required": required });
+    }
+
+    /// Returns the schema of a string with one of the specified values
+    pub fn string_enum(variants: &[&str]) -> Value {
+        return json!({ "type": "string", "enum": variants });
+    }
+}
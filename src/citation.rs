@@ -0,0 +1,377 @@
+use crate::{
+    assistants::{
+        message::{MessageContent, ThreadMessage},
+        step::{run_steps, StepToolCall},
+    },
+    error::Result,
+    file::File,
+    responses::{
+        output::{OutputContent, OutputItem},
+        Response,
+    },
+    Client,
+};
+use futures::future::join_all;
+use serde::Deserialize;
+use std::{collections::HashMap, ops::Range};
+
+/// A citation in the text generated by a model, with its source resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Citation {
+    /// Span of the annotated text, in characters, as reported by the API. Responses cite files at a position, so their span is empty.
+    pub span: Range<usize>,
+    /// Text of the span (e.g. the `【4:0†source】` marker inserted by an assistant)
+    pub text: String,
+    pub source: CitationSource,
+    /// Chunk of the source the citation is grounded on, if it's known
+    pub quote: Option<String>,
+}
+
+/// Source of a [`Citation`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CitationSource {
+    /// A file, searched by the file search tool or generated by the code interpreter
+    File {
+        file_id: String,
+        filename: Option<String>,
+    },
+    /// A web page, found by the web search tool
+    Url { url: String, title: Option<String> },
+}
+
+/// Annotation of the text content of a [`ThreadMessage`]
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum MessageAnnotation {
+    FileCitation {
+        text: String,
+        start_index: usize,
+        end_index: usize,
+        file_citation: FileCitation,
+    },
+    FilePath {
+        text: String,
+        start_index: usize,
+        end_index: usize,
+        file_path: FileCitation,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct FileCitation {
+    file_id: String,
+    #[serde(default)]
+    quote: Option<String>,
+}
+
+/// Annotation of the output text of a [`Response`]
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum OutputAnnotation {
+    FileCitation {
+        index: usize,
+        file_id: String,
+        #[serde(default)]
+        filename: Option<String>,
+    },
+    UrlCitation {
+        start_index: usize,
+        end_index: usize,
+        url: String,
+        #[serde(default)]
+        title: Option<String>,
+    },
+}
+
+/// A chunk of a file found by the file search tool
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    file_id: String,
+    #[serde(default)]
+    filename: Option<String>,
+    #[serde(default)]
+    text: Option<String>,
+}
+
+/// Names and chunks of the files cited by a model
+#[derive(Debug, Default)]
+struct Sources {
+    // File ID to its name, if known
+    filenames: HashMap<String, Option<String>>,
+    chunks: Vec<Chunk>,
+}
+
+/// A chunk of a file found by a search
+#[derive(Debug)]
+struct Chunk {
+    file_id: String,
+    /// Position of the chunk among the results of its search
+    index: usize,
+    text: String,
+}
+
+impl ThreadMessage {
+    /// Resolves the file citations and paths annotated in the text of the message.
+    ///
+    /// The chunks quoted by the citations are read from the file search results of the message's run, and the names of the files that weren't found by a search are fetched from the Files API.
+    /// Files that can't be fetched (e.g. because they were deleted) are cited without a name.
+    pub async fn citations(&self, client: impl AsRef<Client>) -> Result<Vec<Citation>> {
+        let client = client.as_ref();
+        let annotations = self
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                MessageContent::Text { text } => Some(text.annotations.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|annotation| MessageAnnotation::deserialize(annotation).ok())
+            .collect::<Vec<_>>();
+
+        let mut sources = Sources::default();
+        let cites_files = annotations
+            .iter()
+            .any(|annotation| matches!(annotation, MessageAnnotation::FileCitation { .. }));
+
+        if let (true, Some(run_id)) = (cites_files, &self.run_id) {
+            for step in run_steps(&self.thread_id, run_id, client).await? {
+                for call in step.tool_calls() {
+                    if let StepToolCall::FileSearch { file_search, .. } = call {
+                        for (i, result) in file_search.results.iter().enumerate() {
+                            let text = result.content.iter().find_map(|c| c.text.clone());
+                            sources.add(&result.file_id, Some(&result.file_name), i, text);
+                        }
+                    }
+                }
+            }
+        }
+
+        let file_ids = annotations.iter().map(|annotation| match annotation {
+            MessageAnnotation::FileCitation { file_citation, .. } => &file_citation.file_id,
+            MessageAnnotation::FilePath { file_path, .. } => &file_path.file_id,
+        });
+        sources.fetch_filenames(file_ids, client).await;
+
+        let mut citations = Vec::with_capacity(annotations.len());
+        for annotation in annotations {
+            let (text, span, file, cites) = match annotation {
+                MessageAnnotation::FileCitation {
+                    text,
+                    start_index,
+                    end_index,
+                    file_citation,
+                } => (text, start_index..end_index, file_citation, true),
+                MessageAnnotation::FilePath {
+                    text,
+                    start_index,
+                    end_index,
+                    file_path,
+                } => (text, start_index..end_index, file_path, false),
+            };
+
+            // Markers like `【4:0†source】` point at the search result they cite
+            let quote = match cites {
+                true => file
+                    .quote
+                    .or_else(|| sources.quote(&file.file_id, marker_index(&text))),
+                false => None,
+            };
+            citations.push(Citation {
+                span,
+                quote,
+                text,
+                source: CitationSource::File {
+                    filename: sources.filename(&file.file_id),
+                    file_id: file.file_id,
+                },
+            });
+        }
+        return Ok(citations);
+    }
+}
+
+impl Response {
+    /// Resolves the file and URL citations annotated in the output text of the response.
+    ///
+    /// The chunks quoted by file citations are read from the results of the response's file search calls, which are only returned if the request included `file_search_call.results`.
+    /// Since file citations don't tell which chunk they cite, they're only quoted if a single chunk of their file was found.
+    /// The names of the files that weren't found by a search are fetched from the Files API, or left empty if they can't be.
+    pub async fn citations(&self, client: impl AsRef<Client>) -> Result<Vec<Citation>> {
+        let client = client.as_ref();
+        let mut sources = Sources::default();
+        for item in self.output.iter() {
+            if let OutputItem::FileSearchCall {
+                results: Some(results),
+                ..
+            } = item
+            {
+                for (i, result) in results.iter().enumerate() {
+                    if let Ok(result) = SearchResult::deserialize(result) {
+                        sources.add(&result.file_id, result.filename.as_deref(), i, result.text);
+                    }
+                }
+            }
+        }
+
+        let annotations = self
+            .output
+            .iter()
+            .filter_map(|item| match item {
+                OutputItem::Message { content, .. } => Some(content.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|content| match content {
+                OutputContent::OutputText { annotations, .. } => Some(annotations.iter()),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(
+                |annotation| match OutputAnnotation::deserialize(annotation) {
+                    Ok(OutputAnnotation::FileCitation {
+                        file_id, filename, ..
+                    }) => Some((file_id, filename)),
+                    _ => None,
+                },
+            )
+            .collect::<Vec<_>>();
+        for (file_id, filename) in annotations.iter() {
+            if let Some(filename) = filename {
+                sources.add(file_id, Some(filename), 0, None);
+            }
+        }
+        sources
+            .fetch_filenames(annotations.iter().map(|(file_id, _)| file_id), client)
+            .await;
+
+        let mut citations = Vec::new();
+        for item in self.output.iter() {
+            let OutputItem::Message { content, .. } = item else {
+                continue;
+            };
+
+            for content in content.iter() {
+                let OutputContent::OutputText { text, annotations } = content else {
+                    continue;
+                };
+
+                for annotation in annotations {
+                    match OutputAnnotation::deserialize(annotation) {
+                        Ok(OutputAnnotation::FileCitation { index, file_id, .. }) => {
+                            citations.push(Citation {
+                                span: index..index,
+                                text: String::new(),
+                                // Responses don't tell which chunk of the file they cite
+                                quote: sources.quote(&file_id, None),
+                                source: CitationSource::File {
+                                    filename: sources.filename(&file_id),
+                                    file_id,
+                                },
+                            });
+                        }
+                        Ok(OutputAnnotation::UrlCitation {
+                            start_index,
+                            end_index,
+                            url,
+                            title,
+                        }) => citations.push(Citation {
+                            text: text
+                                .chars()
+                                .skip(start_index)
+                                .take(end_index.saturating_sub(start_index))
+                                .collect(),
+                            span: start_index..end_index,
+                            source: CitationSource::Url { url, title },
+                            quote: None,
+                        }),
+                        // Annotations not supported by the library
+                        Err(_) => continue,
+                    }
+                }
+            }
+        }
+        return Ok(citations);
+    }
+}
+
+impl Sources {
+    /// Adds a file found by a search, with the chunk found at position `index` of its results (if any)
+    fn add(&mut self, file_id: &str, filename: Option<&str>, index: usize, text: Option<String>) {
+        let entry = self.filenames.entry(file_id.to_string()).or_default();
+        if entry.is_none() {
+            *entry = filename.map(str::to_string);
+        }
+        if let Some(text) = text {
+            self.chunks.push(Chunk {
+                file_id: file_id.to_string(),
+                index,
+                text,
+            });
+        }
+    }
+
+    /// Fetches the names of the files that aren't known yet, all at once.
+    ///
+    /// Files that can't be retrieved (e.g. because they were deleted) are left without a name.
+    async fn fetch_filenames<'a>(
+        &mut self,
+        file_ids: impl IntoIterator<Item = &'a String>,
+        client: &Client,
+    ) {
+        let mut missing = file_ids
+            .into_iter()
+            .filter(|file_id| !matches!(self.filenames.get(*file_id), Some(Some(_))))
+            .collect::<Vec<_>>();
+        missing.sort_unstable();
+        missing.dedup();
+
+        let files = join_all(
+            missing
+                .iter()
+                .map(|file_id| File::retreive(file_id, client)),
+        )
+        .await;
+        for (file_id, file) in missing.into_iter().zip(files) {
+            let filename = match file {
+                Ok(file) => Some(file.filename),
+                Err(_e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("error retrieving the name of the cited file {file_id}: {_e}");
+                    None
+                }
+            };
+            self.filenames.insert(file_id.clone(), filename);
+        }
+    }
+
+    /// Returns the name of a file, if known
+    fn filename(&self, file_id: &str) -> Option<String> {
+        return self.filenames.get(file_id).cloned().flatten();
+    }
+
+    /// Returns the chunk of a file found at position `index` of the search results (or at any position, if `None`), as long as it's the only one that matches
+    fn quote(&self, file_id: &str, index: Option<usize>) -> Option<String> {
+        let mut chunks = self.chunks.iter().filter(|chunk| {
+            return chunk.file_id == file_id
+                && match index {
+                    Some(index) => chunk.index == index,
+                    None => true,
+                };
+        });
+
+        let quote = chunks.next()?;
+        if chunks.any(|chunk| chunk.text != quote.text) {
+            return None;
+        }
+        return Some(quote.text.clone());
+    }
+}
+
+/// Returns the position of the search result cited by an assistant's marker (e.g. `0` for `【4:0†source】`)
+fn marker_index(marker: &str) -> Option<usize> {
+    let (_, rest) = marker.split_once(':')?;
+    let (index, _) = rest.split_once('†')?;
+    return index.parse().ok();
+}
@@ -0,0 +1,65 @@
+use crate::error::{Error, Result};
+use bytes::Bytes;
+use futures::{
+    future::{BoxFuture, Shared},
+    FutureExt,
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+type SharedResponse = Shared<BoxFuture<'static, Result<Bytes, Arc<Error>>>>;
+
+/// Deduplicates concurrent identical GET requests, so that only one of them reaches the network and the rest share its body
+#[derive(Default)]
+pub(crate) struct Coalescer {
+    in_flight: Arc<Mutex<HashMap<String, SharedResponse>>>,
+}
+
+impl Coalescer {
+    /// Returns the body of the request to `url`, joining the request in flight to the same URL if there is one, or starting a new one with `request` otherwise
+    pub async fn get<F, Fut>(&self, url: String, request: F) -> Result<Bytes>
+    where
+        F: FnOnce() -> Fut,
+        Fut: 'static + Send + Future<Output = Result<Bytes>>,
+    {
+        let response = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&url) {
+                Some(response) => response.clone(),
+                None => {
+                    let map = self.in_flight.clone();
+                    let key = url.clone();
+                    let request = request();
+                    let response = async move {
+                        let result = request.await.map_err(Arc::new);
+                        // Later calls start a new request
+                        map.lock().unwrap().remove(&key);
+                        result
+                    }
+                    .boxed()
+                    .shared();
+
+                    in_flight.insert(url, response.clone());
+                    response
+                }
+            }
+        };
+
+        return response.await.map_err(|e| match Arc::try_unwrap(e) {
+            Ok(e) => e,
+            Err(e) => Error::Other(anyhow::Error::new(e)),
+        });
+    }
+}
+
+impl std::fmt::Debug for Coalescer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let in_flight = self.in_flight.lock().unwrap();
+        f.debug_struct("Coalescer")
+            .field("in_flight", &in_flight.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
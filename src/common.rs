@@ -1,6 +1,71 @@
-use serde::Deserialize;
+use crate::{
+    error::{BuilderError, Error, FallibleResponse, Result},
+    logging::ResponseExt,
+    model::info::Pricing,
+    rt, Str,
+};
+use bytes::Bytes;
+use futures::{ready, stream::BoxStream, Future, Stream, StreamExt, TryStreamExt};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    ops::{Deref, RangeInclusive},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+/// Rough number of characters per token of English text, to estimate token counts without a tokenizer
+const CHARS_PER_TOKEN: usize = 4;
+/// Times a request is shrunk and retried before its context length error is returned
+pub(crate) const MAX_TRUNCATION_RETRIES: u32 = 5;
+
+/// How a request that exceeds the model's context window is shrunk, before it's retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Truncation {
+    /// Drops the oldest messages of the conversation, or the start of the prompt
+    #[default]
+    Oldest,
+    /// Keeps the first message of the conversation (which usually states the task) and drops the oldest messages after it, or drops the middle of the prompt
+    Middle,
+}
+
+/// Response of a request that was retried after shrinking it to fit in the model's context window
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Truncated<T, D> {
+    pub value: T,
+    /// What was removed from the request, in the order it had in the request
+    pub dropped: Vec<D>,
+    /// Number of times the request was retried
+    pub retries: u32,
+}
+
+impl<T, D> Truncated<T, D> {
+    /// Returns `true` if the request had to be shrunk
+    #[inline]
+    pub fn is_truncated(&self) -> bool {
+        return !self.dropped.is_empty();
+    }
+
+    /// Returns the response
+    #[inline]
+    pub fn into_inner(self) -> T {
+        return self.value;
+    }
+}
+
+/// Estimates the number of tokens `text` is encoded into by the tokenizer of `model`, counting them if the `tokens` feature is enabled
+pub(crate) fn estimate_tokens(_model: &str, text: &str) -> u64 {
+    #[cfg(feature = "tokens")]
+    if let Ok(tokens) = crate::tokens::count_tokens(_model, text) {
+        return tokens as u64;
+    }
+    return text.len().div_ceil(CHARS_PER_TOKEN) as u64;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Usage {
     #[serde(default)]
@@ -8,13 +73,553 @@ pub struct Usage {
     #[serde(default)]
     pub completion_tokens: u64,
     pub total_tokens: u64,
+    /// Breakdown of the prompt tokens, if returned by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    /// Fields returned by the API that aren't known by the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// Breakdown of the prompt tokens of a request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct PromptTokensDetails {
+    /// Prompt tokens read from the [prompt cache](https://platform.openai.com/docs/guides/prompt-caching), which are billed at a discount
+    #[serde(default)]
+    pub cached_tokens: u64,
+    /// Fields returned by the API that aren't known by the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl Usage {
+    /// Returns the cost of the tokens used, in US dollars, or `None` if the model's [`Pricing`] isn't known.
+    ///
+    /// Prices set at runtime with [`Pricing::set`] take precedence over the ones known by the library.
+    #[inline]
+    pub fn cost(&self, model: impl AsRef<str>) -> Option<f64> {
+        return Pricing::of(model)
+            .map(|pricing| pricing.cost(self.prompt_tokens, self.completion_tokens));
+    }
+
+    /// Returns the estimated cost of the tokens used, in US dollars.
+    ///
+    /// Returns zero if the model's [`Pricing`] isn't known.
+    #[inline]
+    pub fn estimated_cost(&self, model: impl AsRef<str>) -> f64 {
+        return self.cost(model).unwrap_or_default();
+    }
+
+    /// Returns the number of prompt tokens read from the prompt cache
+    #[inline]
+    pub fn cached_tokens(&self) -> u64 {
+        return self
+            .prompt_tokens_details
+            .as_ref()
+            .map_or(0, |details| details.cached_tokens);
+    }
+
+    /// Returns the fraction of the prompt tokens read from the prompt cache, between 0 and 1
+    #[inline]
+    pub fn cache_hit_rate(&self) -> f64 {
+        return cache_hit_rate(self.cached_tokens(), self.prompt_tokens);
+    }
+}
+
+/// Returns the fraction of `prompt_tokens` that were `cached_tokens`, or 0 if there were none
+#[inline]
+pub(crate) fn cache_hit_rate(cached_tokens: u64, prompt_tokens: u64) -> f64 {
+    return match prompt_tokens {
+        0 => 0.0,
+        prompt_tokens => cached_tokens as f64 / prompt_tokens as f64,
+    };
+}
+
+/// Chunks of a stream that may carry the usage of the whole request
+pub trait HasUsage {
+    /// Returns the usage of the request, if the chunk has it
+    fn usage(&self) -> Option<&Usage>;
+}
+
+/// Usage of a streamed request, which resolves once the stream has received it. See [`OpenAiStream::usage`](crate::OpenAiStream::usage)
+///
+/// Resolves to `None` if the stream ends or is dropped without receiving it, e.g. because the request didn't [include it](crate::chat::ChatCompletionBuilder::include_usage).
+#[derive(Debug)]
+pub struct StreamUsage {
+    pub(crate) receiver: futures::channel::oneshot::Receiver<Usage>,
+}
+
+impl Future for StreamUsage {
+    type Output = Option<Usage>;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        return Poll::Ready(ready!(Pin::new(&mut self.receiver).poll(cx)).ok());
+    }
+}
+
+/// Options of a stream request
+#[derive(Debug, Clone, Copy, Serialize)]
+pub(crate) struct StreamOptions {
+    /// Sends an additional chunk with the usage of the whole request before the end of the stream
+    pub include_usage: bool,
 }
 
 /// Result of deleting a file
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Delete {
     pub id: String,
     pub object: String,
     pub deleted: bool,
 }
+
+/// The raw body of a response, kept around to deserialize borrowed views of it (e.g. [`CompletionRef`](crate::completion::CompletionRef)) without allocating their strings
+#[derive(Debug, Clone)]
+pub struct ResponseBody {
+    bytes: Bytes,
+}
+
+impl ResponseBody {
+    /// Reads the body of a response, returning the API's error if the request failed
+    pub(crate) async fn read(resp: reqwest::Response) -> Result<Self> {
+        let status = resp.status();
+        let bytes = resp.bytes().await?;
+        if !status.is_success() {
+            return Err(Error::from_status(status, &bytes));
+        }
+
+        return Ok(Self { bytes });
+    }
+
+    /// Deserializes a value that borrows from the body
+    #[inline]
+    pub fn parse<'a, T: Deserialize<'a>>(&'a self) -> Result<T> {
+        return Ok(serde_json::from_slice::<T>(&self.bytes)?);
+    }
+
+    /// Returns the raw bytes of the body
+    #[inline]
+    pub fn bytes(&self) -> &Bytes {
+        return &self.bytes;
+    }
+}
+
+/// Maximum number of stop sequences of a request
+pub(crate) const MAX_STOP_SEQUENCES: usize = 4;
+
+/// Up to 4 sequences where the API will stop generating further tokens.
+///
+/// Arrays are checked at compile time, so `StopSequences::from(["\n", "END"])` can't fail, while an array of 5 sequences doesn't compile.
+/// Other collections are checked by [`StopSequences::new`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StopSequences<'a> {
+    sequences: Vec<Str<'a>>,
+}
+
+/// Fails to compile if `N` stop sequences are over the limit
+struct AssertStopSequences<const N: usize>;
+
+impl<const N: usize> AssertStopSequences<N> {
+    const OK: () = assert!(
+        N <= MAX_STOP_SEQUENCES,
+        "there can't be more than 4 stop sequences"
+    );
+}
+
+impl<'a> StopSequences<'a> {
+    /// Creates a new list of stop sequences, failing if there are more than 4
+    pub fn new<I: IntoIterator>(sequences: I) -> Result<Self>
+    where
+        I::Item: Into<Str<'a>>,
+    {
+        let mut sequences = sequences.into_iter();
+        let mut result = Vec::with_capacity(MAX_STOP_SEQUENCES);
+
+        for next in sequences.by_ref() {
+            if result.len() == MAX_STOP_SEQUENCES {
+                return Err(Error::msg(format!(
+                    "stop exceeds size limit of {MAX_STOP_SEQUENCES}"
+                )));
+            }
+            result.push(next.into());
+        }
+
+        return Ok(Self { sequences: result });
+    }
+}
+
+impl<'a> Deref for StopSequences<'a> {
+    type Target = [Str<'a>];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.sequences
+    }
+}
+
+impl<'a, S: Into<Str<'a>>, const N: usize> From<[S; N]> for StopSequences<'a> {
+    #[inline]
+    fn from(value: [S; N]) -> Self {
+        let () = AssertStopSequences::<N>::OK;
+        return Self {
+            sequences: value.into_iter().map(Into::into).collect(),
+        };
+    }
+}
+
+/// Modifies the likelihood of the specified tokens appearing in a completion.
+///
+/// Every token is mapped to a bias from -100 to 100, which is added to the logits generated by the model prior to sampling. Values between -1 and 1 should decrease or increase the likelihood of the token, while values like -100 or 100 should result in its ban or exclusive selection.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct LogitBias {
+    biases: HashMap<u32, f64>,
+}
+
+impl LogitBias {
+    /// Range of the bias of a token
+    pub const RANGE: RangeInclusive<f64> = -100f64..=100f64;
+
+    #[inline]
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// Biases the token with the specified ID
+    pub fn token(mut self, token: u32, bias: f64) -> Result<Self, BuilderError<Self>> {
+        if !Self::RANGE.contains(&bias) {
+            return Err(BuilderError::msg(
+                self,
+                format!("logit bias out of range ({:?})", Self::RANGE),
+            ));
+        }
+
+        self.biases.insert(token, bias);
+        return Ok(self);
+    }
+
+    /// Biases every token `text` is encoded into by the tokenizer of `model`.
+    ///
+    /// Words are usually encoded with a leading space (e.g. `" Paris"`), which is a different token than the one without it.
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokens")))]
+    #[cfg(feature = "tokens")]
+    pub fn text(
+        mut self,
+        model: impl AsRef<str>,
+        text: impl AsRef<str>,
+        bias: f64,
+    ) -> Result<Self, BuilderError<Self>> {
+        let tokens = match crate::tokens::encode(model, text) {
+            Ok(tokens) => tokens,
+            Err(e) => return Err(BuilderError::new(self, e)),
+        };
+
+        for token in tokens {
+            self = self.token(token, bias)?;
+        }
+        return Ok(self);
+    }
+
+    /// Returns the bias of the token with the specified ID
+    #[inline]
+    pub fn get(&self, token: u32) -> Option<f64> {
+        return self.biases.get(&token).copied();
+    }
+
+    /// Returns an iterator over the token IDs and their biases
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (u32, f64)> + '_ {
+        return self.biases.iter().map(|(token, bias)| (*token, *bias));
+    }
+
+    /// Returns the number of biased tokens
+    #[inline]
+    pub fn len(&self) -> usize {
+        return self.biases.len();
+    }
+
+    /// Returns `true` if no token is biased
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        return self.biases.is_empty();
+    }
+
+    /// Checks the biases, which may have been deserialized without validation
+    pub(crate) fn validate(&self) -> Result<()> {
+        for bias in self.biases.values() {
+            crate::check_range("logit_bias", Some(*bias), Self::RANGE)?;
+        }
+        return Ok(());
+    }
+}
+
+/// Returns the item with the highest score, or the first of them if there's a tie
+pub(crate) fn best_by<T, K: PartialOrd>(items: &[T], mut score: impl FnMut(&T) -> K) -> Option<&T> {
+    let mut best: Option<(&T, K)> = None;
+    for item in items {
+        let item_score = score(item);
+        match best {
+            Some((_, ref best_score)) if item_score <= *best_score => {}
+            _ => best = Some((item, item_score)),
+        }
+    }
+    return best.map(|(item, _)| item);
+}
+
+/// Counts the votes of every answer, ignoring case and surrounding whitespace, in order of appearance
+pub(crate) fn tally<'a>(answers: impl IntoIterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    // (answer as it first appeared, votes)
+    let mut votes = Vec::<(&'a str, usize)>::new();
+    let mut indices = HashMap::<String, usize>::new();
+
+    for answer in answers {
+        let answer = answer.trim();
+        match indices.entry(answer.to_lowercase()) {
+            std::collections::hash_map::Entry::Occupied(entry) => votes[*entry.get()].1 += 1,
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                entry.insert(votes.len());
+                votes.push((answer, 1));
+            }
+        }
+    }
+    return votes;
+}
+
+/// Returns the most common answer, ignoring case and surrounding whitespace, with the number of times it appears. Ties are won by the answer that appears first
+pub(crate) fn majority_vote<'a>(
+    answers: impl IntoIterator<Item = &'a str>,
+) -> Option<(&'a str, usize)> {
+    return tally(answers)
+        .into_iter()
+        .reduce(|best, next| if next.1 > best.1 { next } else { best });
+}
+
+/// A page of a list endpoint
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ListResponse<T> {
+    pub data: Vec<T>,
+    #[serde(default)]
+    pub has_more: bool,
+    #[serde(default)]
+    pub first_id: Option<String>,
+    #[serde(default)]
+    pub last_id: Option<String>,
+}
+
+/// Sort order of the objects returned by a list endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+/// Paging parameters of a list endpoint
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ListParams<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    after: Option<Str<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    before: Option<Str<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order: Option<Order>,
+}
+
+impl<'a> ListParams<'a> {
+    #[inline]
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    /// A limit on the number of objects to be returned per page.
+    #[inline]
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// A cursor for use in pagination. Only objects after the one with this id are returned.
+    #[inline]
+    pub fn after(mut self, after: impl Into<Str<'a>>) -> Self {
+        self.after = Some(after.into());
+        self
+    }
+
+    /// A cursor for use in pagination. Only objects before the one with this id are returned.
+    #[inline]
+    pub fn before(mut self, before: impl Into<Str<'a>>) -> Self {
+        self.before = Some(before.into());
+        self
+    }
+
+    /// Sort order by the `created_at` timestamp of the objects.
+    #[inline]
+    pub fn order(mut self, order: Order) -> Self {
+        self.order = Some(order);
+        self
+    }
+
+    #[inline]
+    fn into_owned(self) -> ListParams<'static> {
+        return ListParams {
+            limit: self.limit,
+            after: self.after.map(|x| Str::Owned(x.into_owned())),
+            before: self.before.map(|x| Str::Owned(x.into_owned())),
+            order: self.order,
+        };
+    }
+}
+
+/// Stream that returns every object of a list endpoint, fetching new pages as needed
+pub struct Paginator<T> {
+    inner: BoxStream<'static, Result<T>>,
+}
+
+impl<T: 'static + Send + DeserializeOwned> Paginator<T> {
+    pub(crate) fn new(request: crate::request::RequestBuilder, params: ListParams<'_>) -> Self {
+        let pages =
+            futures::stream::try_unfold(Some((request, params.into_owned())), |state| async move {
+                let (request, mut params) = match state {
+                    Some(state) => state,
+                    None => return Ok(None),
+                };
+
+                let page = request
+                    .try_clone()
+                    .ok_or_else(|| Error::msg("list request can't be cloned"))?
+                    .query(&params)
+                    .send()
+                    .await?
+                    .json_body::<FallibleResponse<ListResponse<T>>>()
+                    .await?
+                    .into_result()?;
+
+                let next = match (page.has_more, page.last_id) {
+                    // when paging backwards, the next page is before the first object
+                    (true, _) if params.before.is_some() => match page.first_id {
+                        Some(first_id) => {
+                            params.before = Some(Str::Owned(first_id));
+                            Some((request, params))
+                        }
+                        None => None,
+                    },
+                    (true, Some(last_id)) => {
+                        params.after = Some(Str::Owned(last_id));
+                        Some((request, params))
+                    }
+                    _ => None,
+                };
+
+                return Ok::<_, Error>(Some((
+                    futures::stream::iter(page.data.into_iter().map(Ok)),
+                    next,
+                )));
+            });
+
+        return Self {
+            inner: pages.try_flatten().boxed(),
+        };
+    }
+}
+
+impl<T> Stream for Paginator<T> {
+    type Item = Result<T>;
+
+    #[inline]
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// An item of a stream with [heartbeats](crate::OpenAiStream::with_heartbeat)
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamEvent<T> {
+    /// An item of the inner stream
+    Data(T),
+    /// Sent by a local timer when no item has arrived for the heartbeat's interval. It doesn't come from the server, so it's also sent if the connection is stalled or dead.
+    KeepAlive,
+}
+
+pin_project_lite::pin_project! {
+    /// A [`Stream`] that yields a [`StreamEvent::KeepAlive`] every time its inner stream goes an interval without yielding an item
+    pub struct Heartbeat<S> {
+        #[pin]
+        inner: S,
+        interval: Duration,
+        timer: Pin<Box<dyn Future<Output = ()> + Send>>,
+    }
+}
+
+impl<T> StreamEvent<T> {
+    /// Returns the item of the inner stream, if any
+    #[inline]
+    pub fn data(self) -> Option<T> {
+        return match self {
+            Self::Data(data) => Some(data),
+            Self::KeepAlive => None,
+        };
+    }
+
+    /// Returns `true` if the event is a [`StreamEvent::KeepAlive`]
+    #[inline]
+    pub fn is_keep_alive(&self) -> bool {
+        return matches!(self, Self::KeepAlive);
+    }
+}
+
+impl<S> Heartbeat<S> {
+    #[inline]
+    pub(crate) fn new(inner: S, interval: Duration) -> Self {
+        return Self {
+            inner,
+            interval,
+            timer: Box::pin(rt::sleep(interval)),
+        };
+    }
+}
+
+impl<T, S: Stream<Item = Result<T>>> Stream for Heartbeat<S> {
+    type Item = Result<StreamEvent<T>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        match this.inner.poll_next(cx) {
+            Poll::Ready(Some(item)) => {
+                *this.timer = Box::pin(rt::sleep(*this.interval));
+                return Poll::Ready(Some(item.map(StreamEvent::Data)));
+            }
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => {}
+        }
+
+        ready!(this.timer.as_mut().poll(cx));
+        *this.timer = Box::pin(rt::sleep(*this.interval));
+        return Poll::Ready(Some(Ok(StreamEvent::KeepAlive)));
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Heartbeats may be added between any two items
+        return (self.inner.size_hint().0, None);
+    }
+}
+
+impl<S: std::fmt::Debug> std::fmt::Debug for Heartbeat<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Heartbeat")
+            .field("inner", &self.inner)
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
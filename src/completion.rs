@@ -1,28 +1,44 @@
 use super::{
-    common::Usage,
-    error::{BuilderError, Result},
+    common::{
+        best_by, estimate_tokens, majority_vote, HasUsage, LogitBias, ResponseBody, StopSequences,
+        StreamOptions, Truncated, Truncation, Usage, MAX_STOP_SEQUENCES, MAX_TRUNCATION_RETRIES,
+    },
+    error::{BuilderError, Error, Result},
     Str,
 };
-use crate::{error::FallibleResponse, Client, OpenAiStream};
+use crate::{
+    check_len, check_range,
+    endpoint::Endpoint,
+    error::FallibleResponse,
+    logging::{RequestBuilderExt, ResponseExt},
+    model::info::ModelInfo,
+    rt, Client, OpenAiStream,
+};
 use chrono::{DateTime, Utc};
-use futures::{future::ready, Stream, TryStreamExt};
+use futures::{
+    future::{ready, select, Either},
+    Stream, TryStreamExt,
+};
 use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, collections::HashMap, marker::PhantomData, ops::RangeInclusive};
+use std::{borrow::Cow, collections::HashMap, ops::RangeInclusive, pin::pin, time::Duration};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Choice {
-    #[serde(with = "crate::serde_trim_string")]
+    /// Generated text. Trimmed of the whitespace the model surrounds it with, except in the chunks of a [`CompletionStream`].
     pub text: String,
     pub index: u64,
     #[serde(default)]
     pub logprobs: Option<Logprobs>,
     #[serde(default)]
     pub finish_reason: Option<String>,
+    /// Fields returned by the API that aren't known by the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Logprobs {
     pub tokens: Vec<String>,
@@ -32,7 +48,7 @@ pub struct Logprobs {
 }
 
 /// Given a prompt, the model will return one or more predicted completions, and can also return the probabilities of alternative tokens at each position.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Completion {
     pub id: String,
@@ -42,15 +58,52 @@ pub struct Completion {
     pub choices: Vec<Choice>,
     #[serde(default)]
     pub usage: Option<Usage>,
+    /// Fields returned by the API that aren't known by the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// A [`Completion`] that borrows its strings from a [`ResponseBody`], returned by [`CompletionBuilder::build_body`].
+///
+/// Useful to process many responses without allocating their strings. Fields returned by the API that aren't known by the library are ignored.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct CompletionRef<'a> {
+    pub id: &'a str,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created: DateTime<Utc>,
+    pub model: &'a str,
+    #[serde(borrow)]
+    pub choices: Vec<ChoiceRef<'a>>,
+    #[serde(default)]
+    pub usage: Option<Usage>,
 }
 
+/// A [`Choice`] that borrows its strings from a [`ResponseBody`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ChoiceRef<'a> {
+    /// Only allocated if the text has escape sequences
+    #[serde(borrow, deserialize_with = "crate::serde_trim_string::deserialize_cow")]
+    pub text: Cow<'a, str>,
+    pub index: u64,
+    #[serde(default)]
+    pub finish_reason: Option<&'a str>,
+}
+
+/// `finish_reason` of the choices cut short by [`CompletionBuilder::build_stream_with_deadline`]
+pub const DEADLINE_FINISH_REASON: &str = "deadline";
+
 /// Given a prompt, the model will return one or more predicted completions, and can also return the probabilities of alternative tokens at each position.
 pub type CompletionStream = OpenAiStream<Completion>;
 
-/// [`Completion`]/[`CompletionStream`] request builder
-#[derive(Debug, Clone, Serialize)]
+/// [`Completion`]/[`CompletionStream`] request builder.
+///
+/// Can be deserialized from a preset of the model and its sampling parameters, which are checked against their valid ranges when the request is sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompletionBuilder<'a> {
     model: Str<'a>,
+    #[serde(skip_deserializing)]
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     prompt: Option<Vec<Str<'a>>>,
@@ -69,7 +122,7 @@ pub struct CompletionBuilder<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     echo: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    stop: Option<Vec<Str<'a>>>,
+    stop: Option<StopSequences<'a>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     frequency_penalty: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -77,9 +130,16 @@ pub struct CompletionBuilder<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     best_of: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    logit_bias: Option<HashMap<Str<'a>, f64>>,
+    logit_bias: Option<LogitBias>,
     #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<Str<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none", skip_deserializing)]
+    stream_options: Option<StreamOptions>,
+    #[serde(skip)]
+    include_usage: bool,
+    /// Parameters not supported by the builder, sent as is
+    #[serde(flatten, deserialize_with = "crate::deserialize_extra_params")]
+    extra: HashMap<Str<'a>, serde_json::Value>,
 }
 
 impl Completion {
@@ -133,6 +193,40 @@ impl Completion {
     pub fn into_first(self) -> Option<Choice> {
         return self.choices.into_iter().next();
     }
+
+    /// Returns the choice with the longest text, or the first of them if there's a tie
+    #[inline]
+    pub fn longest(&self) -> Option<&Choice> {
+        return self.best_by(|choice| choice.text.chars().count());
+    }
+
+    /// Returns the choice with the shortest text, or the first of them if there's a tie
+    #[inline]
+    pub fn shortest(&self) -> Option<&Choice> {
+        return self.best_by(|choice| std::cmp::Reverse(choice.text.chars().count()));
+    }
+
+    /// Returns the choice with the highest score, or the first of them if there's a tie
+    #[inline]
+    pub fn best_by<K: PartialOrd, F: FnMut(&Choice) -> K>(&self, score: F) -> Option<&Choice> {
+        return best_by(&self.choices, score);
+    }
+
+    /// Returns the most common text among the choices, with the number of choices that agree on it.
+    ///
+    /// Answers are compared ignoring case and surrounding whitespace, which is useful for classification prompts. Ties are won by the answer that appears first.
+    #[inline]
+    pub fn majority_vote(&self) -> Option<(&str, usize)> {
+        return majority_vote(self.choices.iter().map(|choice| &*choice.text));
+    }
+}
+
+impl<'a> CompletionRef<'a> {
+    /// Returns a reference to the completion's first choice
+    #[inline]
+    pub fn first(&self) -> Option<&ChoiceRef<'a>> {
+        return self.choices.first();
+    }
 }
 
 impl<'a> CompletionBuilder<'a> {
@@ -155,9 +249,23 @@ impl<'a> CompletionBuilder<'a> {
             logit_bias: None,
             user: None,
             stop: None,
+            stream_options: None,
+            include_usage: false,
+            extra: HashMap::new(),
         };
     }
 
+    /// Adds a parameter the builder doesn't have yet to the completion request
+    #[inline]
+    pub fn extra_param(
+        mut self,
+        key: impl Into<Str<'a>>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
     /// The prompt(s) to generate completions for, encoded as a string, array of strings, array of tokens, or array of token arrays.
     ///
     /// Note that <|endoftext|> is the document separator that the model sees during training, so if a prompt is not specified the model will generate as if from the beginning of a new document.
@@ -183,6 +291,31 @@ impl<'a> CompletionBuilder<'a> {
         self
     }
 
+    /// Sets `max_tokens` to every token the model's context window has left after the longest prompt (up to its output limit). See [`remaining_tokens`](crate::tokens::remaining_tokens)
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokens")))]
+    #[cfg(feature = "tokens")]
+    pub fn max_tokens_auto(self) -> Result<Self, BuilderError<Self>> {
+        return crate::tokens::max_tokens_auto(
+            self,
+            |this| crate::tokens::remaining_tokens(&this.model, this.estimated_prompt_tokens()?),
+            Self::max_tokens,
+        );
+    }
+
+    /// Estimates the number of tokens the longest prompt (with the suffix) takes up, as every prompt is completed separately
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokens")))]
+    #[cfg(feature = "tokens")]
+    pub fn estimated_prompt_tokens(&self) -> Result<usize> {
+        let mut tokens = 0;
+        for prompt in self.prompt.iter().flatten() {
+            tokens = tokens.max(crate::tokens::count_tokens(&self.model, prompt)?);
+        }
+        if let Some(ref suffix) = self.suffix {
+            tokens += crate::tokens::count_tokens(&self.model, suffix)?;
+        }
+        return Ok(tokens);
+    }
+
     /// What sampling temperature to use, between 0 and 2. Higher values like 0.8 will make the output more random, while lower values like 0.2 will make it more focused and deterministic.
     ///
     /// We generally recommend altering this or `top_p` but not both.
@@ -244,23 +377,19 @@ impl<'a> CompletionBuilder<'a> {
     where
         I::Item: Into<Str<'a>>,
     {
-        const MAX_SIZE: usize = 4;
-
-        let mut stop = stop.into_iter();
-        let mut result = Vec::with_capacity(MAX_SIZE);
-
-        while let Some(next) = stop.next() {
-            if result.len() == result.capacity() {
-                return Err(BuilderError::msg(
-                    self,
-                    format!("Interator exceeds size limit of {MAX_SIZE}"),
-                ));
+        return match StopSequences::new(stop) {
+            Ok(stop) => {
+                self.stop = Some(stop);
+                Ok(self)
             }
-            result.push(next.into());
-        }
+            Err(e) => Err(BuilderError::new(self, e)),
+        };
+    }
 
-        self.stop = Some(result);
-        return Ok(self);
+    /// Up to 4 sequences where the API will stop generating further tokens, checked when they're created (see [`StopSequences`])
+    pub fn stop_sequences(mut self, stop: impl Into<StopSequences<'a>>) -> Self {
+        self.stop = Some(stop.into());
+        self
     }
 
     /// Number between -2.0 and 2.0. Positive values penalize new tokens based on whether they appear in the text so far, increasing the model's likelihood to talk about new topics.
@@ -303,17 +432,9 @@ impl<'a> CompletionBuilder<'a> {
         self
     }
 
-    /// Modify the likelihood of specified tokens appearing in the completion.
-    ///
-    /// Accepts a json object that maps tokens (specified by their token ID in the GPT tokenizer) to an associated bias value from -100 to 100. You can use this tokenizer tool (which works for both GPT-2 and GPT-3) to convert text to token IDs. Mathematically, the bias is added to the logits generated by the model prior to sampling. The exact effect will vary per model, but values between -1 and 1 should decrease or increase likelihood of selection; values like -100 or 100 should result in a ban or exclusive selection of the relevant token.
-    ///
-    /// As an example, you can pass `{"50256": -100}` to prevent the <|endoftext|> token from being generated.
-    pub fn logit_bias<K, I>(mut self, logit_bias: I) -> Self
-    where
-        K: Into<Str<'a>>,
-        I: IntoIterator<Item = (K, f64)>,
-    {
-        self.logit_bias = Some(logit_bias.into_iter().map(|(k, v)| (k.into(), v)).collect());
+    /// Modify the likelihood of specified tokens appearing in the completion. See [`LogitBias`]
+    pub fn logit_bias(mut self, logit_bias: LogitBias) -> Self {
+        self.logit_bias = Some(logit_bias);
         self
     }
 
@@ -323,32 +444,322 @@ impl<'a> CompletionBuilder<'a> {
         self
     }
 
+    /// Whether streams (see [`build_stream`](Self::build_stream)) end with an additional chunk with the usage of the whole request, which is read by [`OpenAiStream::usage`](crate::OpenAiStream::usage).
+    ///
+    /// Always enabled by clients that track their usage or budget.
+    pub fn include_usage(mut self, include_usage: bool) -> Self {
+        self.include_usage = include_usage;
+        self
+    }
+
+    /// Returns the number of tokens the request may use, if known
+    fn requested_tokens(&self) -> Option<u64> {
+        #[cfg(feature = "tokens")]
+        if let Some(ref prompt) = self.prompt {
+            let prompt_tokens = prompt
+                .iter()
+                .map(|prompt| crate::tokens::count_tokens(&self.model, prompt))
+                .sum::<Result<usize>>();
+
+            if let Ok(prompt_tokens) = prompt_tokens {
+                return Some(prompt_tokens as u64 + self.max_tokens.unwrap_or_default());
+            }
+        }
+        return self.max_tokens;
+    }
+
+    /// Checks the request's parameters, which may have been deserialized without validation
+    fn validate(&self) -> Result<()> {
+        check_range("temperature", self.temperature, 0f64..=2f64)?;
+        check_range("top_p", self.top_p, 0f64..=1f64)?;
+        check_range("n", self.n, 1..=128)?;
+        check_range("logprobs", self.logprobs, 0..=5)?;
+        check_range("presence_penalty", self.presence_penalty, -2f64..=2f64)?;
+        check_range("frequency_penalty", self.frequency_penalty, -2f64..=2f64)?;
+        check_len("stop", self.stop.as_deref(), MAX_STOP_SEQUENCES)?;
+        if let Some(ref logit_bias) = self.logit_bias {
+            logit_bias.validate()?;
+        }
+
+        // Check `max_tokens` against the model's output limit, if the model is known
+        if let (Some(max_tokens), Some(info)) = (self.max_tokens, ModelInfo::of(&self.model)) {
+            info.check_max_tokens(max_tokens)?;
+        }
+        return Ok(());
+    }
+
     /// Sends the request
-    pub async fn build(self, client: impl AsRef<Client>) -> Result<Completion> {
+    pub async fn build(mut self, client: impl AsRef<Client>) -> Result<Completion> {
+        self.validate()?;
+        let client = client.as_ref();
+        client.default_user(&mut self.user);
+        let tokens = self.requested_tokens();
+        client.check_budget(tokens)?;
+        client.throttle(&self.model, tokens).await;
         let resp = client
-            .as_ref()
-            .post("https://api.openai.com/v1/completions")
-            .json(&self)
+            .post(client.url(Endpoint::Completions, ""))
+            .json_body(&self)
             .send()
             .await?
-            .json::<FallibleResponse<Completion>>()
+            .json_body::<FallibleResponse<Completion>>()
             .await?
             .into_result()?;
 
-        return Ok(resp);
+        client.record("/v1/completions", &self.model, resp.usage.as_ref());
+        return Ok(resp.trimmed());
+    }
+
+    /// Sends the request, trimming its longest prompt and retrying it whenever it exceeds the model's context window (up to 5 times).
+    ///
+    /// Enough of the prompt is trimmed to fit the excess reported by the API, or a quarter of it if it isn't reported.
+    pub async fn build_truncating(
+        mut self,
+        truncation: Truncation,
+        client: impl AsRef<Client>,
+    ) -> Result<Truncated<Completion, String>> {
+        let client = client.as_ref();
+        let mut dropped = Vec::new();
+        let mut retries = 0;
+
+        loop {
+            let text = match self.clone().build(client).await {
+                Ok(value) => {
+                    return Ok(Truncated {
+                        value,
+                        dropped,
+                        retries,
+                    })
+                }
+                Err(Error::OpenAI(e))
+                    if e.is_context_length_exceeded() && retries < MAX_TRUNCATION_RETRIES =>
+                {
+                    match self.trim_prompt(truncation, e.excess_tokens()) {
+                        Some(text) => text,
+                        None => return Err(Error::OpenAI(e)),
+                    }
+                }
+                Err(e) => return Err(e),
+            };
+
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "context length exceeded, retrying without {} bytes of the prompt",
+                text.len()
+            );
+            dropped.push(text);
+            retries += 1;
+        }
+    }
+
+    /// Removes the text that frees up `excess` tokens (or a quarter of the prompt, if unknown) from the longest prompt, returning it
+    fn trim_prompt(&mut self, truncation: Truncation, excess: Option<u64>) -> Option<String> {
+        let prompt = self.prompt.as_mut()?.iter_mut().max_by_key(|p| p.len())?;
+        if prompt.is_empty() {
+            return None;
+        }
+
+        let len = match excess {
+            // Scale the excess by the characters per token of the prompt itself
+            Some(excess) => {
+                let tokens = estimate_tokens(&self.model, prompt).max(1);
+                (excess as usize)
+                    .saturating_mul(prompt.len())
+                    .div_ceil(tokens as usize)
+            }
+            None => prompt.len().div_ceil(4),
+        }
+        .clamp(1, prompt.len());
+
+        let start = match truncation {
+            Truncation::Oldest => 0,
+            Truncation::Middle => (prompt.len() - len) / 2,
+        };
+
+        let start = floor_char_boundary(prompt, start);
+        let end = ceil_char_boundary(prompt, start + len);
+        // Don't leave any words cut in half
+        let start = match prompt[..start].rfind(char::is_whitespace) {
+            Some(idx) => ceil_char_boundary(prompt, idx + 1),
+            None => 0,
+        };
+        let end = match prompt[end..].find(char::is_whitespace) {
+            Some(offset) => {
+                let rest = &prompt[end + offset..];
+                prompt.len() - rest.trim_start().len()
+            }
+            None => prompt.len(),
+        };
+        let mut text = prompt.to_string();
+        let trimmed = text.drain(start..end).collect::<String>();
+        *prompt = Str::Owned(text);
+        return Some(trimmed);
+    }
+
+    /// Sends the request, returning its raw body to deserialize a borrowed [`CompletionRef`] from
+    pub async fn build_body(mut self, client: impl AsRef<Client>) -> Result<ResponseBody> {
+        self.validate()?;
+        let client = client.as_ref();
+        client.default_user(&mut self.user);
+        let tokens = self.requested_tokens();
+        client.check_budget(tokens)?;
+        client.throttle(&self.model, tokens).await;
+        let resp = client
+            .post(client.url(Endpoint::Completions, ""))
+            .json_body(&self)
+            .send()
+            .await?;
+
+        let body = ResponseBody::read(resp).await?;
+        if client.wants_usage() {
+            let usage = body.parse::<CompletionRef>()?.usage;
+            client.record("/v1/completions", &self.model, usage.as_ref());
+        }
+        return Ok(body);
     }
 
     /// Sends the request as a stream request
     pub async fn build_stream(mut self, client: impl AsRef<Client>) -> Result<CompletionStream> {
+        self.validate()?;
+        let client = client.as_ref();
+        client.default_user(&mut self.user);
+        let tokens = self.requested_tokens();
+        client.check_budget(tokens)?;
+        client.throttle(&self.model, tokens).await;
         self.stream = true;
+        if self.include_usage || client.wants_usage() {
+            self.stream_options = Some(StreamOptions {
+                include_usage: true,
+            });
+        }
+
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
         let resp = client
-            .as_ref()
-            .post("https://api.openai.com/v1/completions")
-            .json(&self)
+            .post(client.url(Endpoint::Completions, ""))
+            .json_body(&self)
             .send()
+            .await?
+            .check_status()
             .await?;
 
-        return Ok(CompletionStream::create(resp));
+        let mut stream = CompletionStream::create(resp);
+        stream.inspect =
+            client.record_stream("/v1/completions", &self.model, |chunk: &Completion| {
+                chunk.usage.clone()
+            });
+        #[cfg(feature = "tracing")]
+        stream.trace("/v1/completions", &self.model, start);
+        return Ok(stream);
+    }
+
+    /// Streams the completion until it's done or `deadline` elapses (including the time it takes to send the request), whichever happens first.
+    ///
+    /// If the deadline elapses, the request is aborted and the text generated so far is returned, with the unfinished choices' `finish_reason` set to [`DEADLINE_FINISH_REASON`].
+    pub async fn build_stream_with_deadline(
+        self,
+        deadline: Duration,
+        client: impl AsRef<Client>,
+    ) -> Result<Completion> {
+        let mut partial = Completion {
+            id: String::new(),
+            created: Utc::now(),
+            model: self.model.to_string(),
+            choices: (0..self.n.unwrap_or(1))
+                .map(|index| Choice {
+                    text: String::new(),
+                    index,
+                    logprobs: None,
+                    finish_reason: None,
+                    extra: HashMap::new(),
+                })
+                .collect(),
+            usage: None,
+            extra: HashMap::new(),
+        };
+
+        let stream = async {
+            let mut stream = self.build_stream(client).await?;
+            while let Some(chunk) = stream.try_next().await? {
+                partial.append(chunk);
+            }
+            return Ok::<_, Error>(());
+        };
+
+        // The request is aborted once the stream is dropped
+        let finished = match select(pin!(stream), pin!(rt::sleep(deadline))).await {
+            Either::Left((result, _)) => {
+                result?;
+                true
+            }
+            Either::Right(_) => false,
+        };
+
+        if !finished {
+            for choice in partial.choices.iter_mut() {
+                choice
+                    .finish_reason
+                    .get_or_insert_with(|| DEADLINE_FINISH_REASON.to_string());
+            }
+        }
+        return Ok(partial);
+    }
+}
+
+impl Completion {
+    /// Trims the text of the choices of the whitespace the model surrounds them with, which is only kept in streams so that their chunks can be concatenated
+    fn trimmed(mut self) -> Self {
+        for choice in self.choices.iter_mut() {
+            let trimmed = choice.text.trim();
+            if trimmed.len() != choice.text.len() {
+                choice.text = trimmed.to_string();
+            }
+        }
+        return self;
+    }
+
+    /// Appends a chunk of a [`CompletionStream`] to the completion, e.g. to show the chunks as they arrive and keep the complete text
+    pub fn append(&mut self, chunk: Completion) {
+        self.id = chunk.id;
+        self.created = chunk.created;
+        self.model = chunk.model;
+        self.usage = chunk.usage.or(self.usage.take());
+        self.extra.extend(chunk.extra);
+
+        for chunk in chunk.choices {
+            let choice = match self.choices.iter_mut().find(|x| x.index == chunk.index) {
+                Some(choice) => choice,
+                None => {
+                    self.choices.push(Choice {
+                        text: String::new(),
+                        index: chunk.index,
+                        logprobs: None,
+                        finish_reason: None,
+                        extra: HashMap::new(),
+                    });
+                    self.choices.last_mut().unwrap()
+                }
+            };
+
+            choice.text.push_str(&chunk.text);
+            choice.finish_reason = chunk.finish_reason.or(choice.finish_reason.take());
+            choice.extra.extend(chunk.extra);
+            match (&mut choice.logprobs, chunk.logprobs) {
+                (Some(logprobs), Some(chunk)) => {
+                    logprobs.tokens.extend(chunk.tokens);
+                    logprobs.token_logprobs.extend(chunk.token_logprobs);
+                    logprobs.top_logprobs.extend(chunk.top_logprobs);
+                    logprobs.text_offset.extend(chunk.text_offset);
+                }
+                (logprobs, chunk) => *logprobs = logprobs.take().or(chunk),
+            }
+        }
+    }
+}
+
+impl HasUsage for Completion {
+    #[inline]
+    fn usage(&self) -> Option<&Usage> {
+        return self.usage.as_ref();
     }
 }
 
@@ -367,11 +778,7 @@ impl CompletionStream {
 
     #[inline]
     fn create(resp: Response) -> Self {
-        return Self {
-            inner: Box::pin(resp.bytes_stream()),
-            current_chunk: None,
-            _phtm: PhantomData,
-        };
+        return Self::from_bytes_stream(resp.bytes_stream());
     }
 }
 
@@ -387,4 +794,33 @@ impl CompletionStream {
             .try_filter_map(|x| ready(Ok(x.choices.into_iter().next())))
             .map_ok(|x| x.text);
     }
+
+    /// Reads the stream to its end, reassembling its chunks into the complete [`Completion`], with the text of every choice and their usage (if sent)
+    pub async fn collect_full(mut self) -> Result<Completion> {
+        let mut completion = match self.try_next().await? {
+            Some(chunk) => chunk,
+            None => return Err(Error::msg("the completion stream has no chunks")),
+        };
+        while let Some(chunk) = self.try_next().await? {
+            completion.append(chunk);
+        }
+        completion.choices.sort_by_key(|choice| choice.index);
+        return Ok(completion);
+    }
+}
+
+#[inline]
+fn floor_char_boundary(str: &str, mut idx: usize) -> usize {
+    while !str.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    return idx;
+}
+
+#[inline]
+fn ceil_char_boundary(str: &str, mut idx: usize) -> usize {
+    while !str.is_char_boundary(idx) {
+        idx += 1;
+    }
+    return idx;
 }
@@ -0,0 +1,373 @@
+use crate::{
+    common::{Delete, ListParams, Paginator},
+    endpoint::Endpoint,
+    error::{FallibleResponse, Result},
+    logging::{RequestBuilderExt, ResponseExt},
+    multipart::{FilePart, MultipartRequest},
+    rt, Client, Str,
+};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use rand::random;
+use reqwest::Response;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ffi::OsStr, path::Path};
+
+/// A sandboxed environment where the code interpreter tool executes code.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct Container {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub last_active_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub expires_after: Option<ContainerExpiration>,
+}
+
+/// Expiration policy of a [`Container`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ContainerExpiration {
+    /// Time anchor of the expiration (i.e. `last_active_at`)
+    pub anchor: String,
+    pub minutes: u64,
+}
+
+/// A file stored inside a [`Container`], either uploaded or generated by the code interpreter.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ContainerFile {
+    pub id: String,
+    pub container_id: String,
+    /// Path of the file inside the container
+    pub path: String,
+    /// Origin of the file (i.e. `user` or `assistant`)
+    pub source: String,
+    #[serde(default)]
+    pub bytes: Option<u64>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// [`Container`] request builder
+#[derive(Debug, Clone, Serialize)]
+pub struct ContainerBuilder<'a> {
+    name: Str<'a>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    file_ids: Vec<Str<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_after: Option<ContainerExpiration>,
+    /// Parameters not supported by the builder, sent as is
+    #[serde(flatten)]
+    extra: HashMap<Str<'a>, serde_json::Value>,
+}
+
+impl Container {
+    /// Creates a new container
+    #[inline]
+    pub async fn new<'a>(name: impl Into<Str<'a>>, client: impl AsRef<Client>) -> Result<Self> {
+        return Self::builder(name).build(client).await;
+    }
+
+    /// Creates a new container request builder
+    #[inline]
+    pub fn builder<'a>(name: impl Into<Str<'a>>) -> ContainerBuilder<'a> {
+        return ContainerBuilder::new(name);
+    }
+
+    /// Retrieves a container.
+    pub async fn retreive(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Self> {
+        let container = client
+            .as_ref()
+            .get_json::<Self>(
+                client
+                    .as_ref()
+                    .url(Endpoint::Containers, format!("/{}", id.as_ref())),
+            )
+            .await?;
+
+        return Ok(container);
+    }
+}
+
+impl Container {
+    /// Uploads a local file into the container.
+    #[inline]
+    pub async fn upload(
+        &self,
+        file: impl AsRef<Path>,
+        client: impl AsRef<Client>,
+    ) -> Result<ContainerFile> {
+        return ContainerFile::upload(&self.id, file, client).await;
+    }
+
+    /// Returns the files stored inside the container.
+    #[inline]
+    pub async fn files(&self, client: impl AsRef<Client>) -> Result<Vec<ContainerFile>> {
+        return container_files(&self.id, client).await;
+    }
+
+    /// Deletes the container.
+    #[inline]
+    pub async fn delete(self, client: impl AsRef<Client>) -> Result<Delete> {
+        return delete_container(self.id, client).await;
+    }
+}
+
+impl ContainerFile {
+    /// Uploads a local file into a container.
+    pub async fn upload(
+        container_id: impl AsRef<str>,
+        file: impl AsRef<Path>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        let path: &Path = file.as_ref();
+        let filename = match path.file_name().map(OsStr::to_string_lossy) {
+            Some(x) => x.into_owned(),
+            None => format!("{}", random::<u64>()),
+        };
+
+        let file = FilePart::file(path).file_name(filename);
+        return Self::upload_part(container_id, file, client).await;
+    }
+
+    /// Uploads a file into a container.
+    ///
+    /// Unlike the other files, a [`Part`](reqwest::multipart::Part) can only be sent once, so the request isn't retried.
+    pub async fn upload_part(
+        container_id: impl AsRef<str>,
+        file: impl Into<FilePart>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        return MultipartRequest::new(
+            Endpoint::Containers,
+            format!("/{}/files", container_id.as_ref()),
+        )
+        .part("file", file)
+        .send_json::<Self>(client)
+        .await;
+    }
+
+    /// Copies a previously uploaded [`File`](crate::file::File) into a container.
+    pub async fn from_file(
+        container_id: impl AsRef<str>,
+        file_id: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        #[derive(Debug, Serialize)]
+        struct Body<'a> {
+            file_id: &'a str,
+        }
+
+        let file = client
+            .as_ref()
+            .post(client.as_ref().url(
+                Endpoint::Containers,
+                format!("/{}/files", container_id.as_ref()),
+            ))
+            .json_body(&Body {
+                file_id: file_id.as_ref(),
+            })
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(file);
+    }
+
+    /// Retrieves a file of a container.
+    pub async fn retreive(
+        container_id: impl AsRef<str>,
+        id: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        let file = client
+            .as_ref()
+            .get_json::<Self>(client.as_ref().url(
+                Endpoint::Containers,
+                format!("/{}/files/{}", container_id.as_ref(), id.as_ref()),
+            ))
+            .await?;
+
+        return Ok(file);
+    }
+}
+
+impl ContainerFile {
+    /// Returns the name of the file, without the path of its directory
+    #[inline]
+    pub fn filename(&self) -> &str {
+        return match self.path.rsplit_once('/') {
+            Some((_, name)) => name,
+            None => &self.path,
+        };
+    }
+
+    /// Downloads the contents of the file.
+    pub async fn content(&self, client: impl AsRef<Client>) -> Result<Bytes> {
+        let content = self.raw_content(client).await?.bytes().await?;
+        return Ok(content);
+    }
+
+    /// Returns the contents of the file.
+    #[inline]
+    pub async fn raw_content(&self, client: impl AsRef<Client>) -> Result<Response> {
+        return retreive_raw_container_file_content(&self.container_id, &self.id, client).await;
+    }
+
+    /// Downloads the contents of the file into the specified path.
+    pub async fn download(&self, path: impl AsRef<Path>, client: impl AsRef<Client>) -> Result<()> {
+        let content = self.content(client).await?;
+        rt::write(path, content).await?;
+        return Ok(());
+    }
+
+    /// Deletes the file from its container.
+    #[inline]
+    pub async fn delete(self, client: impl AsRef<Client>) -> Result<Delete> {
+        return delete_container_file(self.container_id, self.id, client).await;
+    }
+}
+
+impl<'a> ContainerBuilder<'a> {
+    /// Creates a new container request builder
+    pub fn new(name: impl Into<Str<'a>>) -> Self {
+        return Self {
+            name: name.into(),
+            file_ids: Vec::new(),
+            expires_after: None,
+            extra: HashMap::new(),
+        };
+    }
+
+    /// Adds a parameter the builder doesn't have yet to the container's creation request
+    #[inline]
+    pub fn extra_param(
+        mut self,
+        key: impl Into<Str<'a>>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
+    /// Copies a previously uploaded file into the container on creation.
+    pub fn file_id(mut self, file_id: impl Into<Str<'a>>) -> Self {
+        self.file_ids.push(file_id.into());
+        self
+    }
+
+    /// Expires the container after it has been inactive for the specified number of minutes.
+    pub fn expires_after(mut self, minutes: u64) -> Self {
+        self.expires_after = Some(ContainerExpiration {
+            anchor: String::from("last_active_at"),
+            minutes,
+        });
+        self
+    }
+
+    /// Sends the request
+    pub async fn build(self, client: impl AsRef<Client>) -> Result<Container> {
+        let container = client
+            .as_ref()
+            .post(client.as_ref().url(Endpoint::Containers, ""))
+            .json_body(&self)
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Container>>()
+            .await?
+            .into_result()?;
+
+        return Ok(container);
+    }
+}
+
+/// Returns the contents of the specified container file
+pub async fn retreive_raw_container_file_content(
+    container_id: impl AsRef<str>,
+    id: impl AsRef<str>,
+    client: impl AsRef<Client>,
+) -> Result<Response> {
+    let content = client
+        .as_ref()
+        .get(client.as_ref().url(
+            Endpoint::Containers,
+            format!("/{}/files/{}/content", container_id.as_ref(), id.as_ref()),
+        ))
+        .send()
+        .await?
+        .check_status()
+        .await?;
+
+    return Ok(content);
+}
+
+/// Deletes a file from a container.
+pub async fn delete_container_file(
+    container_id: impl AsRef<str>,
+    id: impl AsRef<str>,
+    client: impl AsRef<Client>,
+) -> Result<Delete> {
+    let delete = client
+        .as_ref()
+        .delete(client.as_ref().url(
+            Endpoint::Containers,
+            format!("/{}/files/{}", container_id.as_ref(), id.as_ref()),
+        ))
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Delete>>()
+        .await?
+        .into_result()?;
+
+    return Ok(delete);
+}
+
+/// Returns the files stored inside a container, all of them, 100 per request.
+pub async fn container_files(
+    container_id: impl AsRef<str>,
+    client: impl AsRef<Client>,
+) -> Result<Vec<ContainerFile>> {
+    let req = client.as_ref().get(client.as_ref().url(
+        Endpoint::Containers,
+        format!("/{}/files", container_id.as_ref()),
+    ));
+    return Paginator::new(req, ListParams::new().limit(100))
+        .try_collect()
+        .await;
+}
+
+/// Deletes a container.
+pub async fn delete_container(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Delete> {
+    let delete = client
+        .as_ref()
+        .delete(
+            client
+                .as_ref()
+                .url(Endpoint::Containers, format!("/{}", id.as_ref())),
+        )
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Delete>>()
+        .await?
+        .into_result()?;
+
+    return Ok(delete);
+}
+
+/// Lists every container of the project, 100 per request.
+pub async fn containers(client: impl AsRef<Client>) -> Result<Vec<Container>> {
+    let req = client
+        .as_ref()
+        .get(client.as_ref().url(Endpoint::Containers, ""));
+    return Paginator::new(req, ListParams::new().limit(100))
+        .try_collect()
+        .await;
+}
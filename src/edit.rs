@@ -1,16 +1,22 @@
 use super::{
     common::Usage,
     completion::Choice,
-    error::{BuilderError, Result},
+    error::{BuilderError, Error, Result},
     Str,
 };
-use crate::{error::FallibleResponse, Client};
+use crate::{
+    check_range,
+    endpoint::Endpoint,
+    error::FallibleResponse,
+    logging::{RequestBuilderExt, ResponseExt},
+    Client,
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::{borrow::Cow, ops::RangeInclusive};
+use std::{borrow::Cow, collections::HashMap, ops::RangeInclusive};
 
 /// Given a prompt and an instruction, the model will return an edited version of the prompt.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Edit {
     #[serde(with = "chrono::serde::ts_seconds")]
@@ -20,15 +26,21 @@ pub struct Edit {
     pub usage: Usage,
 }
 
-/// Builder for [`Edit`]
-#[derive(Debug, Clone, Serialize)]
+/// Builder for [`Edit`].
+///
+/// When deserialized, the instruction may be left out of the preset and set afterwards with [`instruction`](Self::instruction). It must be set before the request is sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EditBuilder<'a> {
     model: Str<'a>,
+    #[serde(default)]
     instruction: Str<'a>,
     input: Option<Str<'a>>,
     n: Option<u64>,
     temperature: Option<f64>,
     top_p: Option<f64>,
+    /// Parameters not supported by the builder, sent as is
+    #[serde(flatten)]
+    extra: HashMap<Str<'a>, serde_json::Value>,
 }
 
 impl Edit {
@@ -64,9 +76,27 @@ impl<'a> EditBuilder<'a> {
             n: None,
             temperature: None,
             top_p: None,
+            extra: HashMap::new(),
         };
     }
 
+    /// Adds a parameter the builder doesn't have to the edit request
+    #[inline]
+    pub fn extra_param(
+        mut self,
+        key: impl Into<Str<'a>>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
+    /// The instruction that tells the model how to edit the prompt.
+    pub fn instruction(mut self, instruction: impl Into<Str<'a>>) -> Self {
+        self.instruction = instruction.into();
+        self
+    }
+
     /// The input text to use as a starting point for the edit.
     pub fn input(mut self, input: impl Into<Str<'a>>) -> Self {
         self.input = Some(input.into());
@@ -106,16 +136,25 @@ impl<'a> EditBuilder<'a> {
 
     /// Sends the request.
     pub async fn build(self, client: impl AsRef<Client>) -> Result<Edit> {
+        if self.instruction.is_empty() {
+            return Err(Error::msg("instruction is empty"));
+        }
+        check_range("temperature", self.temperature, 0f64..=2f64)?;
+        check_range("top_p", self.top_p, 0f64..=1f64)?;
+        check_range("n", self.n, 1..=20)?;
+        let client = client.as_ref();
+        client.check_budget(None)?;
+        client.throttle(&self.model, None).await;
         let resp = client
-            .as_ref()
-            .post("https://api.openai.com/v1/edits")
-            .json(&self)
+            .post(client.url(Endpoint::Edits, ""))
+            .json_body(&self)
             .send()
             .await?
-            .json::<FallibleResponse<Edit>>()
+            .json_body::<FallibleResponse<Edit>>()
             .await?
             .into_result()?;
 
+        client.record("/v1/edits", &self.model, Some(&resp.usage));
         return Ok(resp);
     }
 }
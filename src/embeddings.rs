@@ -1,12 +1,15 @@
 use crate::{
-    common::Usage,
-    error::{FallibleResponse, Result},
+    common::{estimate_tokens, Usage},
+    endpoint::Endpoint,
+    error::{Error, FallibleResponse, Result},
+    logging::{RequestBuilderExt, ResponseExt},
     Client, Str,
 };
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ops::Range};
 
 /// Get a vector representation of a given input that can be easily consumed by machine learning models and algorithms.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Embedding {
     pub embedding: Vec<f64>,
@@ -14,7 +17,7 @@ pub struct Embedding {
 }
 
 /// A list of [`Embedding`]s
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct EmbeddingResult {
     pub data: Vec<Embedding>,
@@ -22,12 +25,29 @@ pub struct EmbeddingResult {
     pub usage: Usage,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// [`EmbeddingResult`] request builder.
+///
+/// When deserialized, the input may be left out of the preset and set afterwards with [`input`](Self::input). It must be set before the request is sent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingBuilder<'a> {
     model: Str<'a>,
+    #[serde(default)]
     input: Str<'a>,
     #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<Str<'a>>,
+    /// Parameters not supported by the builder, sent as is
+    #[serde(flatten)]
+    extra: HashMap<Str<'a>, serde_json::Value>,
+}
+
+/// How [`Embedding::batched_with`] packs many inputs into as few requests as possible.
+///
+/// Inputs are packed greedily, in order, by their estimated token count (counted exactly if the `tokens` feature is enabled).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EmbeddingPacking {
+    max_input_tokens: u64,
+    max_request_tokens: u64,
+    max_inputs: usize,
 }
 
 impl Embedding {
@@ -43,6 +63,96 @@ impl Embedding {
             .await;
     }
 
+    /// Creates an embedding vector for each of the inputs, packing them into as few requests as the API allows.
+    ///
+    /// The embeddings of the result are in the same order as the inputs, with their `index` pointing to them.
+    #[inline]
+    pub async fn batched<I>(
+        model: impl AsRef<str>,
+        inputs: I,
+        client: impl AsRef<Client>,
+    ) -> Result<EmbeddingResult>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        return Self::batched_with(model, inputs, EmbeddingPacking::new(), client).await;
+    }
+
+    /// Creates an embedding vector for each of the inputs, packing them into requests following `packing`.
+    ///
+    /// Fails before sending any request if an input is estimated to exceed the tokens allowed per input.
+    pub async fn batched_with<I>(
+        model: impl AsRef<str>,
+        inputs: I,
+        packing: EmbeddingPacking,
+        client: impl AsRef<Client>,
+    ) -> Result<EmbeddingResult>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        #[derive(Debug, Serialize)]
+        struct Body<'b> {
+            model: &'b str,
+            input: &'b [&'b str],
+            #[serde(skip_serializing_if = "Option::is_none")]
+            user: Option<String>,
+        }
+
+        let (model, client) = (model.as_ref(), client.as_ref());
+        let inputs = inputs.into_iter().collect::<Vec<_>>();
+        let inputs = inputs.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+
+        let mut user = None;
+        client.default_user(&mut user);
+
+        let mut result = EmbeddingResult {
+            data: Vec::with_capacity(inputs.len()),
+            model: model.to_string(),
+            usage: Usage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+                prompt_tokens_details: None,
+                extra: HashMap::new(),
+            },
+        };
+
+        for (range, tokens) in packing.pack_with_tokens(model, &inputs)? {
+            client.check_budget(Some(tokens))?;
+            client.throttle(model, Some(tokens)).await;
+            let body = Body {
+                model,
+                input: &inputs[range.clone()],
+                user: user.clone(),
+            };
+
+            let batch = client
+                .post(client.url(Endpoint::Embeddings, ""))
+                .json_body(&body)
+                .send()
+                .await?
+                .json_body::<FallibleResponse<EmbeddingResult>>()
+                .await?
+                .into_result()?;
+            client.record("/v1/embeddings", model, Some(&batch.usage));
+
+            result.model = batch.model;
+            result.usage.prompt_tokens += batch.usage.prompt_tokens;
+            result.usage.total_tokens += batch.usage.total_tokens;
+            result
+                .data
+                .extend(batch.data.into_iter().map(|embedding| Embedding {
+                    index: embedding.index + range.start as u64,
+                    ..embedding
+                }));
+        }
+
+        result.data.sort_by_key(|embedding| embedding.index);
+        return Ok(result);
+    }
+
     #[inline]
     pub fn builder<'a>(
         model: impl Into<Str<'a>>,
@@ -52,6 +162,94 @@ impl Embedding {
     }
 }
 
+impl EmbeddingPacking {
+    /// Creates a packing within the API limits: up to 8191 tokens per input, and 2048 inputs and 300000 tokens per request
+    #[inline]
+    pub fn new() -> Self {
+        return Self {
+            max_input_tokens: 8191,
+            max_request_tokens: 300_000,
+            max_inputs: 2048,
+        };
+    }
+
+    /// Maximum number of tokens of a single input
+    #[inline]
+    pub fn max_input_tokens(mut self, max_input_tokens: u64) -> Self {
+        self.max_input_tokens = max_input_tokens;
+        self
+    }
+
+    /// Maximum number of tokens of all the inputs of a request
+    #[inline]
+    pub fn max_request_tokens(mut self, max_request_tokens: u64) -> Self {
+        self.max_request_tokens = max_request_tokens;
+        self
+    }
+
+    /// Maximum number of inputs of a request
+    #[inline]
+    pub fn max_inputs(mut self, max_inputs: usize) -> Self {
+        self.max_inputs = max_inputs;
+        self
+    }
+
+    /// Splits `inputs` into the ranges of inputs sent by each request, in order.
+    ///
+    /// Fails if an input is estimated to exceed the tokens allowed per input.
+    pub fn pack<S: AsRef<str>>(
+        &self,
+        model: impl AsRef<str>,
+        inputs: &[S],
+    ) -> Result<Vec<Range<usize>>> {
+        return Ok(self
+            .pack_with_tokens(model.as_ref(), inputs)?
+            .into_iter()
+            .map(|(range, _)| range)
+            .collect());
+    }
+
+    /// Splits `inputs` into the ranges of inputs sent by each request, alongside their estimated token count
+    fn pack_with_tokens<S: AsRef<str>>(
+        &self,
+        model: &str,
+        inputs: &[S],
+    ) -> Result<Vec<(Range<usize>, u64)>> {
+        let mut batches = Vec::new();
+        let (mut start, mut tokens) = (0, 0);
+        for (i, input) in inputs.iter().enumerate() {
+            let input_tokens = estimate_tokens(model, input.as_ref());
+            if input_tokens > self.max_input_tokens {
+                return Err(Error::msg(format!(
+                    "Input {i} has about {input_tokens} tokens, more than the {} allowed per input",
+                    self.max_input_tokens
+                )));
+            }
+
+            // Inputs that don't fit in a request on their own are sent alone
+            let full =
+                i - start >= self.max_inputs || tokens + input_tokens > self.max_request_tokens;
+            if i > start && full {
+                batches.push((start..i, tokens));
+                (start, tokens) = (i, 0);
+            }
+            tokens += input_tokens;
+        }
+
+        if start < inputs.len() {
+            batches.push((start..inputs.len(), tokens));
+        }
+        return Ok(batches);
+    }
+}
+
+impl Default for EmbeddingPacking {
+    #[inline]
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
 impl<'a> EmbeddingBuilder<'a> {
     #[inline]
     pub fn new(model: impl Into<Str<'a>>, input: impl Into<Str<'a>>) -> Self {
@@ -59,9 +257,17 @@ impl<'a> EmbeddingBuilder<'a> {
             model: model.into(),
             input: input.into(),
             user: None,
+            extra: HashMap::new(),
         };
     }
 
+    /// Input text to get embeddings for
+    #[inline]
+    pub fn input(mut self, input: impl Into<Str<'a>>) -> Self {
+        self.input = input.into();
+        self
+    }
+
     /// A unique identifier representing your end-user, which can help OpenAI to monitor and detect abuse.
     #[inline]
     pub fn user(mut self, user: impl Into<Str<'a>>) -> Self {
@@ -69,18 +275,55 @@ impl<'a> EmbeddingBuilder<'a> {
         self
     }
 
+    /// Adds a parameter that isn't supported by the builder to the request, e.g. a new API parameter, or one of an OpenAI-compatible backend (like `input_type` task hints, such as `search_query` or `search_document`).
+    ///
+    /// Extra parameters are sent alongside the ones of the builder, so they shouldn't share their names.
+    #[inline]
+    pub fn extra_param(
+        mut self,
+        key: impl Into<Str<'a>>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds a field that isn't supported by OpenAI to the request, for OpenAI-compatible backends that accept it
+    #[deprecated(note = "use `extra_param`, which every builder has")]
+    #[inline]
+    pub fn extra(self, key: impl Into<Str<'a>>, value: impl Into<serde_json::Value>) -> Self {
+        return self.extra_param(key, value);
+    }
+
+    /// Returns the number of tokens the request may use, if known
+    fn requested_tokens(&self) -> Option<u64> {
+        #[cfg(feature = "tokens")]
+        if let Ok(tokens) = crate::tokens::count_tokens(&self.model, &self.input) {
+            return Some(tokens as u64);
+        }
+        return None;
+    }
+
     /// Sends the request
-    pub async fn build(self, client: impl AsRef<Client>) -> Result<EmbeddingResult> {
+    pub async fn build(mut self, client: impl AsRef<Client>) -> Result<EmbeddingResult> {
+        if self.input.is_empty() {
+            return Err(Error::msg("input is empty"));
+        }
+        let client = client.as_ref();
+        client.default_user(&mut self.user);
+        let tokens = self.requested_tokens();
+        client.check_budget(tokens)?;
+        client.throttle(&self.model, tokens).await;
         let result = client
-            .as_ref()
-            .post("https://api.openai.com/v1/embeddings")
-            .json(&self)
+            .post(client.url(Endpoint::Embeddings, ""))
+            .json_body(&self)
             .send()
             .await?
-            .json::<FallibleResponse<EmbeddingResult>>()
+            .json_body::<FallibleResponse<EmbeddingResult>>()
             .await?
             .into_result()?;
 
+        client.record("/v1/embeddings", &self.model, Some(&result.usage));
         return Ok(result);
     }
 }
@@ -0,0 +1,89 @@
+use reqwest::header::HeaderMap;
+
+/// A group of endpoints of the API, sharing the first segment of their paths.
+///
+/// The requests of every group can be sent to a different base URL with [`Client::with_endpoint_url`](crate::Client::with_endpoint_url), e.g. to route them through a proxy or gateway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Endpoint {
+    /// `/audio`
+    Audio,
+    /// `/batches`
+    Batches,
+    /// `/chat/completions`
+    Chat,
+    /// `/completions`
+    Completions,
+    /// `/containers`
+    Containers,
+    /// `/edits`
+    Edits,
+    /// `/embeddings`
+    Embeddings,
+    /// `/files`
+    Files,
+    /// `/fine-tunes`
+    FineTunes,
+    /// `/images`
+    Images,
+    /// `/models`
+    Models,
+    /// `/moderations`
+    Moderations,
+    /// `/organization`
+    Organization,
+    /// `/responses`
+    Responses,
+    /// `/threads`
+    Threads,
+}
+
+impl Endpoint {
+    /// Every group of endpoints
+    pub const ALL: &'static [Endpoint] = &[
+        Self::Audio,
+        Self::Batches,
+        Self::Chat,
+        Self::Completions,
+        Self::Containers,
+        Self::Edits,
+        Self::Embeddings,
+        Self::Files,
+        Self::FineTunes,
+        Self::Images,
+        Self::Models,
+        Self::Moderations,
+        Self::Organization,
+        Self::Responses,
+        Self::Threads,
+    ];
+
+    /// Returns the path of the endpoints, relative to the base URL
+    #[inline]
+    pub fn path(self) -> &'static str {
+        return match self {
+            Self::Audio => "/audio",
+            Self::Batches => "/batches",
+            Self::Chat => "/chat/completions",
+            Self::Completions => "/completions",
+            Self::Containers => "/containers",
+            Self::Edits => "/edits",
+            Self::Embeddings => "/embeddings",
+            Self::Files => "/files",
+            Self::FineTunes => "/fine-tunes",
+            Self::Images => "/images",
+            Self::Models => "/models",
+            Self::Moderations => "/moderations",
+            Self::Organization => "/organization",
+            Self::Responses => "/responses",
+            Self::Threads => "/threads",
+        };
+    }
+}
+
+/// Headers and query parameters added to every request of an [`Endpoint`]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EndpointDefaults {
+    pub headers: HeaderMap,
+    pub query: Vec<(String, String)>,
+}
@@ -19,6 +19,13 @@ pub enum Error {
     Env(#[from] std::env::VarError),
     #[error("Reqwest error: {0}")]
     Reqwest(#[from] reqwest::Error),
+    /// The API (or a proxy in front of it) answered with an error status, and a body that isn't an OpenAI error (e.g. an HTML error page)
+    #[error("HTTP error {status}: {body}")]
+    Status {
+        status: reqwest::StatusCode,
+        /// Start of the response's body
+        body: String,
+    },
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
     #[error("I/O error: {0}")]
@@ -29,10 +36,29 @@ pub enum Error {
     Image(#[from] image::error::ImageError),
     #[error("Srt error: {0}")]
     Srt(#[from] srtlib::ParsingError),
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(#[from] BudgetExceeded),
+    /// The client has been [shut down](crate::Client::shutdown)
+    #[error("The client has been shut down")]
+    ShutDown,
     #[error("Unknown error: {0}")]
     Other(#[from] anyhow::Error),
 }
 
+/// Error returned when a request would exceed the [`Budget`](crate::accounting::Budget) of its client
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum BudgetExceeded {
+    #[error(
+        "the request uses up to {requested} tokens, over the limit of {limit} tokens per request"
+    )]
+    TokensPerRequest { requested: u64, limit: u64 },
+    #[error("the limit of {limit} requests per minute has been reached")]
+    RequestsPerMinute { limit: u32 },
+    #[error("${spent:.4} spent within the last hour, over the limit of ${limit:.4}")]
+    CostPerHour { spent: f64, limit: f64 },
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(untagged)]
 pub(crate) enum FallibleResponse<T> {
@@ -57,6 +83,85 @@ impl Error {
     pub fn msg<M: Display + Debug + Send + Sync + 'static>(msg: M) -> Self {
         Self::Other(anyhow::Error::msg(msg))
     }
+
+    /// Creates the error of a failed response, from its status and body
+    pub(crate) fn from_status(status: reqwest::StatusCode, body: &[u8]) -> Self {
+        /// Characters of the body kept in the error
+        const MAX_BODY_LEN: usize = 512;
+
+        #[derive(Debug, Deserialize)]
+        struct ErrorBody {
+            error: OpenAiError,
+        }
+
+        if let Ok(ErrorBody { error }) = serde_json::from_slice::<ErrorBody>(body) {
+            return Self::OpenAI(error);
+        }
+
+        let body = String::from_utf8_lossy(body);
+        let body = body.trim();
+        return Self::Status {
+            status,
+            body: match body.char_indices().nth(MAX_BODY_LEN) {
+                Some((idx, _)) => format!("{}...", &body[..idx]),
+                None => body.to_string(),
+            },
+        };
+    }
+}
+
+impl Error {
+    /// Returns `true` if the request didn't fit in the model's context window
+    #[inline]
+    pub fn is_context_length_exceeded(&self) -> bool {
+        return matches!(self, Self::OpenAI(e) if e.is_context_length_exceeded());
+    }
+
+    /// Returns `true` if the request may succeed if it's sent again (e.g. it was rate limited, timed out or hit a server error)
+    pub fn is_transient(&self) -> bool {
+        return match self {
+            Self::OpenAI(e) => e.is_transient(),
+            Self::Reqwest(e) => e.is_timeout() || e.is_connect(),
+            Self::Status { status, .. } => {
+                *status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            _ => false,
+        };
+    }
+}
+
+impl OpenAiError {
+    /// Returns `true` if the request didn't fit in the model's context window
+    #[inline]
+    pub fn is_context_length_exceeded(&self) -> bool {
+        return self.code.as_deref() == Some("context_length_exceeded");
+    }
+
+    /// Returns `true` if the request was rate limited or failed because of the server, and may succeed if it's sent again
+    #[inline]
+    pub fn is_transient(&self) -> bool {
+        return self.code.as_deref() == Some("rate_limit_exceeded")
+            || matches!(self.ty.as_str(), "server_error" | "requests" | "tokens");
+    }
+
+    /// Returns the number of tokens the request went over the model's context window by, if the error's message reports it.
+    ///
+    /// The message reports the context length (e.g. "maximum context length is 4097 tokens") and the tokens requested (e.g. "you requested 4200 tokens", or "your messages resulted in 4200 tokens").
+    pub fn excess_tokens(&self) -> Option<u64> {
+        fn number_after(message: &str, pattern: &str) -> Option<u64> {
+            let rest = &message[message.find(pattern)? + pattern.len()..];
+            let rest = rest.trim_start();
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            return rest[..len].parse().ok();
+        }
+
+        let max = number_after(&self.message, "maximum context length is")?;
+        let requested = number_after(&self.message, "you requested")
+            .or_else(|| number_after(&self.message, "resulted in"))?;
+        return requested.checked_sub(max);
+    }
 }
 
 impl<T> BuilderError<T> {
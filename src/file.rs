@@ -1,19 +1,19 @@
 use crate::{
-    common::Delete,
-    error::{FallibleResponse, Result},
-    Client, Str,
+    common::{Delete, ListParams, Paginator},
+    endpoint::Endpoint,
+    error::{Error, FallibleResponse, Result},
+    logging::ResponseExt,
+    multipart::{FilePart, MultipartRequest},
+    rt, trim_ascii, Client, Str,
 };
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use futures::{ready, Stream, StreamExt, TryStream, TryStreamExt};
+use futures::{ready, stream, stream::BoxStream, Stream, StreamExt, TryStream, TryStreamExt};
 use rand::random;
-use reqwest::{
-    multipart::{Form, Part},
-    Body, Response,
-};
+use reqwest::{multipart::Part, Body, Response};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
-    collections::VecDeque,
+    collections::{HashMap, VecDeque},
     ffi::OsStr,
     future::ready,
     marker::PhantomData,
@@ -23,8 +23,11 @@ use std::{
     pin::Pin,
     task::Poll,
 };
+#[cfg(feature = "tokio")]
 use tokio_util::io::ReaderStream;
 
+pub mod jsonl;
+
 // File that is deleted when dropped
 #[derive(Debug)]
 pub struct TemporaryFile {
@@ -34,7 +37,7 @@ pub struct TemporaryFile {
 }
 
 /// Files are used to upload documents that can be used with features like **Fine-tuning**.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct File {
     pub id: String,
@@ -43,6 +46,17 @@ pub struct File {
     pub created_at: DateTime<Utc>,
     pub filename: String,
     pub purpose: String,
+    /// Fields returned by the API that aren't known by the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+/// A record of the JSONL files uploaded (or generated) with a specific purpose, which can be read with [`File::content_as`]
+pub trait FileContent: DeserializeOwned {
+    /// Purposes of the files whose lines are records of this type
+    const PURPOSES: &'static [&'static str];
+    /// Name of the record, used in error messages
+    const NAME: &'static str;
 }
 
 pin_project_lite::pin_project! {
@@ -50,6 +64,10 @@ pin_project_lite::pin_project! {
         #[pin]
         stream: S,
         buf: VecDeque<u8>,
+        // Number of lines read so far
+        lines: usize,
+        // File ID and record name, to describe the lines that can't be parsed
+        record: Option<(String, &'static str)>,
         _phtm: PhantomData<T>,
     }
 }
@@ -67,7 +85,7 @@ impl File {
             None => format!("{}.jsonl", random::<u64>()),
         };
 
-        let file = Part::stream(tokio::fs::File::open(path).await?).file_name(filename);
+        let file = FilePart::file(path).file_name(filename);
         return Self::upload_part(file, purpose, client).await;
     }
 
@@ -135,6 +153,8 @@ impl File {
     }
 
     /// Upload a file that contains document(s) to be used across various endpoints/features. Currently, the size of all the files uploaded by one organization can be up to 1 GB.
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    #[cfg(feature = "tokio")]
     pub async fn upload_tokio_reader<R>(
         reader: R,
         filename: Option<String>,
@@ -149,35 +169,39 @@ impl File {
     }
 
     /// Upload a file that contains document(s) to be used across various endpoints/features. Currently, the size of all the files uploaded by one organization can be up to 1 GB.
+    ///
+    /// Unlike the other files, a [`Part`] can only be sent once, so the request isn't retried.
     pub async fn upload_part(
-        file: Part,
+        file: impl Into<FilePart>,
         purpose: impl Into<Str<'static>>,
         client: impl AsRef<Client>,
     ) -> Result<Self> {
-        let body = Form::new().text("purpose", purpose).part("file", file);
-        let file = client
-            .as_ref()
-            .post("https://api.openai.com/v1/files")
-            .multipart(body)
-            .send()
-            .await?
-            .json::<FallibleResponse<File>>()
-            .await?
-            .into_result()?;
+        return Self::upload_request(file, purpose)
+            .send_json::<File>(client)
+            .await;
+    }
 
-        return Ok(file);
+    /// Creates the upload request of a file, to add fields to it before it's sent.
+    #[inline]
+    pub fn upload_request(
+        file: impl Into<FilePart>,
+        purpose: impl Into<Str<'static>>,
+    ) -> MultipartRequest {
+        return MultipartRequest::new(Endpoint::Files, "")
+            .text("purpose", purpose)
+            .part("file", file);
     }
 
     /// Returns information about a specific file.
     pub async fn retreive(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Self> {
         let file = client
             .as_ref()
-            .get(format!("https://api.openai.com/v1/files/{}", id.as_ref()))
-            .send()
-            .await?
-            .json::<FallibleResponse<Self>>()
-            .await?
-            .into_result()?;
+            .get_json::<Self>(
+                client
+                    .as_ref()
+                    .url(Endpoint::Files, format!("/{}", id.as_ref())),
+            )
+            .await?;
 
         return Ok(file);
     }
@@ -188,10 +212,31 @@ impl File {
         &self,
         client: impl AsRef<Client>,
     ) -> Result<impl Stream<Item = Result<T>>> {
-        let content = retreive_raw_file_content(&self.id, client).await?;
+        return retreive_file_content(self.id.clone(), client).await;
+    }
+
+    /// Returns the records of the file, checking that its purpose is one of the record's.
+    ///
+    /// Lines that can't be parsed fail with an error that points to their line number.
+    pub async fn content_as<T: FileContent>(
+        &self,
+        client: impl AsRef<Client>,
+    ) -> Result<impl Stream<Item = Result<T>>> {
+        if !T::PURPOSES.contains(&self.purpose.as_str()) {
+            return Err(Error::msg(format!(
+                "File '{}' has purpose '{}', but {}s are read from files with purpose '{}'",
+                self.id,
+                self.purpose,
+                T::NAME,
+                T::PURPOSES.join("' or '")
+            )));
+        }
+
         return Ok(Contents {
-            stream: content.bytes_stream(),
+            stream: file_content_stream(&self.id, client).await?,
             buf: VecDeque::new(),
+            lines: 0,
+            record: Some((self.id.clone(), T::NAME)),
             _phtm: PhantomData,
         });
     }
@@ -209,6 +254,30 @@ impl File {
     }
 }
 
+impl<S, T: DeserializeOwned> Contents<S, T> {
+    /// Parses the next line of the file, or returns `None` if it's blank
+    fn parse(
+        lines: &mut usize,
+        record: &Option<(String, &'static str)>,
+        line: &[u8],
+    ) -> Option<Result<T>> {
+        *lines += 1;
+        let line = trim_ascii(line);
+        if line.is_empty() {
+            return None;
+        }
+
+        return Some(match (serde_json::from_slice::<T>(line), record) {
+            (Ok(item), _) => Ok(item),
+            (Err(e), Some((id, name))) => Err(Error::msg(format!(
+                "Line {} of file '{id}' isn't a valid {name}: {e}",
+                *lines
+            ))),
+            (Err(e), None) => Err(e.into()),
+        });
+    }
+}
+
 impl<S: Stream<Item = reqwest::Result<Bytes>>, T: DeserializeOwned> Stream for Contents<S, T> {
     type Item = Result<T>;
 
@@ -219,16 +288,22 @@ impl<S: Stream<Item = reqwest::Result<Bytes>>, T: DeserializeOwned> Stream for C
         let mut this = self.project();
 
         loop {
-            if let Some((idx, _)) = this.buf.iter().enumerate().find(|(_, &x)| x == b'\n') {
-                let mut line = this.buf.split_off(idx);
-                let item = serde_json::from_slice::<T>(line.make_contiguous())?;
-                return Poll::Ready(Some(Ok(item)));
+            if let Some(idx) = this.buf.iter().position(|&x| x == b'\n') {
+                let line = this.buf.drain(..=idx).collect::<Vec<_>>();
+                match Self::parse(this.lines, this.record, &line) {
+                    Some(item) => return Poll::Ready(Some(item)),
+                    None => continue,
+                }
             }
 
             match ready!(this.stream.as_mut().poll_next(cx)) {
                 Some(Ok(x)) => this.buf.extend(x),
                 Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
-                None => return Poll::Ready(None),
+                None => {
+                    // The last line may not be terminated by a newline
+                    let line = this.buf.drain(..).collect::<Vec<_>>();
+                    return Poll::Ready(Self::parse(this.lines, this.record, &line));
+                }
             }
         }
     }
@@ -280,7 +355,7 @@ impl Drop for TemporaryFile {
     fn drop(&mut self) {
         if !self.deleting {
             self.deleting = true;
-            tokio::spawn(delete_file(self.id.clone(), self.client.clone()));
+            rt::detach(delete_file(self.id.clone(), self.client.clone()));
         }
     }
 }
@@ -290,14 +365,41 @@ pub async fn retreive_file_content<T: DeserializeOwned>(
     id: impl AsRef<str>,
     client: impl AsRef<Client>,
 ) -> Result<impl Stream<Item = Result<T>>> {
-    let content = retreive_raw_file_content(id, client).await?;
     return Ok(Contents {
-        stream: content.bytes_stream(),
+        stream: file_content_stream(id.as_ref(), client).await?,
         buf: VecDeque::new(),
+        lines: 0,
+        record: None,
         _phtm: PhantomData,
     });
 }
 
+/// Returns the bytes of the contents of the specified file
+async fn file_content_stream(
+    id: &str,
+    client: impl AsRef<Client>,
+) -> Result<BoxStream<'static, reqwest::Result<Bytes>>> {
+    let client = client.as_ref();
+    let url = client.url(Endpoint::Files, format!("/{id}/content"));
+
+    // Shared and cached contents are downloaded whole
+    let stream = match client.buffers_gets() {
+        true => {
+            let content = client.get_body(url).await?;
+            stream::once(ready(Ok(content))).boxed()
+        }
+        false => client
+            .get(url)
+            .send()
+            .await?
+            .check_status()
+            .await?
+            .bytes_stream()
+            .boxed(),
+    };
+    return Ok(stream);
+}
+
 /// Returns the contents of the specified file
 pub async fn retreive_raw_file_content(
     id: impl AsRef<str>,
@@ -305,11 +407,14 @@ pub async fn retreive_raw_file_content(
 ) -> Result<Response> {
     let content = client
         .as_ref()
-        .get(format!(
-            "https://api.openai.com/v1/files/{}/content",
-            id.as_ref()
-        ))
+        .get(
+            client
+                .as_ref()
+                .url(Endpoint::Files, format!("/{}/content", id.as_ref())),
+        )
         .send()
+        .await?
+        .check_status()
         .await?;
 
     return Ok(content);
@@ -319,31 +424,29 @@ pub async fn retreive_raw_file_content(
 pub async fn delete_file(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Delete> {
     let delete = client
         .as_ref()
-        .delete(format!("https://api.openai.com/v1/files/{}", id.as_ref()))
+        .delete(
+            client
+                .as_ref()
+                .url(Endpoint::Files, format!("/{}", id.as_ref())),
+        )
         .send()
         .await?
-        .json::<FallibleResponse<Delete>>()
+        .json_body::<FallibleResponse<Delete>>()
         .await?
         .into_result()?;
 
     return Ok(delete);
 }
 
-/// Returns a list of files that belong to the user's organization.
+/// Returns a list of all the files that belong to the user's organization. Use [`list_files`] to fetch them one page at a time.
 pub async fn files(client: impl AsRef<Client>) -> Result<Vec<File>> {
-    #[derive(Debug, Deserialize)]
-    struct Response {
-        data: Vec<File>,
-    }
+    return list_files(ListParams::new(), client).try_collect().await;
+}
 
-    let files = client
+/// Returns a stream over the files that belong to the user's organization, fetching them one page at a time.
+pub fn list_files(params: ListParams<'_>, client: impl AsRef<Client>) -> Paginator<File> {
+    let req = client
         .as_ref()
-        .get("https://api.openai.com/v1/files")
-        .send()
-        .await?
-        .json::<FallibleResponse<Response>>()
-        .await?
-        .into_result()?;
-
-    return Ok(files.data);
+        .get(client.as_ref().url(Endpoint::Files, ""));
+    return Paginator::new(req, params);
 }
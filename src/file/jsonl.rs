@@ -0,0 +1,243 @@
+use super::File;
+use crate::{
+    error::{Error, Result},
+    rt, Client, Str,
+};
+use bytes::Bytes;
+use futures::{
+    channel::mpsc,
+    future::{AbortHandle, Aborted, BoxFuture},
+    io::AsyncWrite,
+    AsyncWriteExt, FutureExt, SinkExt,
+};
+use rand::random;
+use serde::Serialize;
+use std::{
+    io,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+    pin::Pin,
+};
+
+/// Number of records that may be waiting to be uploaded before [`JsonlWriter::write`] waits for the upload to catch up
+const UPLOAD_BUFFER: usize = 64;
+
+/// Writes records as [JSON lines](https://jsonlines.org), one at a time, into a file or directly into the upload of a [`File`].
+///
+/// Records are sent to the API as they're written, so datasets never need to fit in memory. If the upload falls behind, [`write`](JsonlWriter::write) waits for it.
+/// A writer that's dropped before it's [finished](JsonlWriter::finish) cancels its upload.
+pub struct JsonlWriter<T: ?Sized> {
+    target: Option<Target>,
+    records: u64,
+    bytes: u64,
+    _phtm: PhantomData<fn(&T)>,
+}
+
+enum Target {
+    File {
+        writer: Pin<Box<dyn Send + AsyncWrite>>,
+        path: PathBuf,
+    },
+    Upload {
+        sender: mpsc::Sender<io::Result<Bytes>>,
+        upload: BoxFuture<'static, Result<Result<File>, Aborted>>,
+        abort: AbortHandle,
+    },
+}
+
+/// Summary of the records written by a [`JsonlWriter`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct JsonlSummary {
+    /// Number of records written
+    pub records: u64,
+    /// Number of bytes written, including the line breaks
+    pub bytes: u64,
+    /// Path of the file the records were written into, if any
+    pub path: Option<PathBuf>,
+    /// File the records were uploaded as, if any
+    pub file: Option<File>,
+}
+
+impl<T: ?Sized + Serialize> JsonlWriter<T> {
+    /// Creates (or truncates) a file at `path` to write the records into
+    pub async fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let writer = rt::create_file(path.clone()).await?;
+        return Ok(Self::new(Target::File {
+            writer: Box::pin(writer),
+            path,
+        }));
+    }
+
+    /// Creates a file with a random name in the temporary directory to write the records into.
+    ///
+    /// The file isn't deleted by the writer, and its path is returned by [`finish`](JsonlWriter::finish).
+    #[inline]
+    pub async fn temp_file() -> Result<Self> {
+        return Self::create(std::env::temp_dir().join(format!("{}.jsonl", random::<u64>()))).await;
+    }
+
+    /// Starts the upload of a file, which the records are streamed into. See [`File::upload_byte_stream`]
+    pub fn upload(
+        filename: Option<String>,
+        purpose: impl Into<Str<'static>>,
+        client: impl AsRef<Client>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(UPLOAD_BUFFER);
+        let purpose = purpose.into();
+        let client = client.as_ref().clone();
+
+        let (upload, abort) = futures::future::abortable(async move {
+            File::upload_byte_stream(receiver, filename, purpose, client).await
+        });
+
+        return Self::new(Target::Upload {
+            sender,
+            upload: rt::spawn(upload).boxed(),
+            abort,
+        });
+    }
+
+    #[inline]
+    fn new(target: Target) -> Self {
+        return Self {
+            target: Some(target),
+            records: 0,
+            bytes: 0,
+            _phtm: PhantomData,
+        };
+    }
+
+    /// Writes a record
+    #[inline]
+    pub async fn write(&mut self, record: &T) -> Result<()> {
+        return self.write_record(record).await;
+    }
+
+    /// Writes every record of an iterator
+    pub async fn write_all<'a, I>(&mut self, records: I) -> Result<()>
+    where
+        T: 'a,
+        I: IntoIterator<Item = &'a T>,
+    {
+        for record in records {
+            self.write(record).await?;
+        }
+        return Ok(());
+    }
+}
+
+impl<T: ?Sized> JsonlWriter<T> {
+    /// Writes a record of any type, for writers whose records wrap `T` (e.g. the lines of a batch input)
+    pub(crate) async fn write_record<U: ?Sized + Serialize>(&mut self, record: &U) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let len = line.len() as u64;
+
+        match self.target {
+            Some(Target::File { ref mut writer, .. }) => writer.write_all(&line).await?,
+            Some(Target::Upload { ref mut sender, .. }) => {
+                if sender.send(Ok(Bytes::from(line))).await.is_err() {
+                    return Err(self.upload_error().await);
+                }
+            }
+            None => return Err(Error::msg("The writer failed on a previous record")),
+        }
+
+        self.records += 1;
+        self.bytes += len;
+        return Ok(());
+    }
+
+    /// Returns the error the upload stopped with, before reading every record
+    async fn upload_error(&mut self) -> Error {
+        let target = self.target.take();
+        return match target.map(finish_target) {
+            Some(finish) => match finish.await {
+                Ok(_) => Error::msg("The upload finished before every record was written"),
+                Err(e) => e,
+            },
+            None => Error::msg("The writer failed on a previous record"),
+        };
+    }
+
+    /// Returns the number of records written
+    #[inline]
+    pub fn records(&self) -> u64 {
+        return self.records;
+    }
+
+    /// Returns the number of bytes written, including the line breaks
+    #[inline]
+    pub fn bytes(&self) -> u64 {
+        return self.bytes;
+    }
+
+    /// Flushes the records into their file, or waits for their upload to complete
+    pub async fn finish(mut self) -> Result<JsonlSummary> {
+        let mut summary = JsonlSummary {
+            records: self.records,
+            bytes: self.bytes,
+            path: None,
+            file: None,
+        };
+
+        match self.target.take() {
+            Some(target) => (summary.path, summary.file) = finish_target(target).await?,
+            None => return Err(Error::msg("The writer failed on a previous record")),
+        }
+        return Ok(summary);
+    }
+}
+
+impl<T: ?Sized> Drop for JsonlWriter<T> {
+    fn drop(&mut self) {
+        if let Some(Target::Upload { ref abort, .. }) = self.target {
+            abort.abort();
+        }
+    }
+}
+
+impl<T: ?Sized> std::fmt::Debug for JsonlWriter<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let target = match self.target {
+            Some(Target::File { ref path, .. }) => format!("{}", path.display()),
+            Some(Target::Upload { .. }) => "upload".to_string(),
+            None => "failed".to_string(),
+        };
+
+        f.debug_struct("JsonlWriter")
+            .field("target", &target)
+            .field("records", &self.records)
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
+
+impl JsonlSummary {
+    /// Returns the uploaded file, failing if the records were written into a local file instead
+    #[inline]
+    pub fn into_file(self) -> Result<File> {
+        return self
+            .file
+            .ok_or_else(|| Error::msg("The records weren't uploaded"));
+    }
+}
+
+async fn finish_target(target: Target) -> Result<(Option<PathBuf>, Option<File>)> {
+    return match target {
+        Target::File { mut writer, path } => {
+            writer.close().await?;
+            Ok((Some(path), None))
+        }
+        Target::Upload { sender, upload, .. } => {
+            // Closing the channel ends the body of the upload
+            drop(sender);
+            match upload.await {
+                Ok(file) => Ok((None, Some(file?))),
+                Err(Aborted) => Err(Error::msg("The upload was cancelled")),
+            }
+        }
+    };
+}
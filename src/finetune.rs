@@ -1,18 +1,20 @@
 use crate::{
-    common::Delete,
+    common::{Delete, ListResponse},
+    endpoint::Endpoint,
     error::{BuilderError, FallibleResponse, Result},
     file::File,
+    logging::{RequestBuilderExt, ResponseExt},
     prelude::Error,
     Client, OpenAiStream, Str,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::marker::PhantomData;
+use std::collections::HashMap;
 
 pub mod data;
 
 /// Manage fine-tuning jobs to tailor a model to your specific training data.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct FineTune {
     pub id: String,
@@ -30,9 +32,12 @@ pub struct FineTune {
     pub training_files: Vec<File>,
     #[serde(with = "chrono::serde::ts_seconds")]
     pub updated_at: DateTime<Utc>,
+    /// Fields returned by the API that aren't known by the library
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Hyperparams {
     #[serde(default)]
@@ -43,7 +48,7 @@ pub struct Hyperparams {
     pub prompt_loss_weight: f64,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct FineTuneEvent {
     #[serde(with = "chrono::serde::ts_seconds")]
@@ -79,6 +84,9 @@ pub struct Builder<'a> {
     classification_betas: Option<Vec<f64>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     suffix: Option<Str<'a>>,
+    /// Parameters not supported by the builder, sent as is
+    #[serde(flatten)]
+    extra: HashMap<Str<'a>, serde_json::Value>,
 }
 
 impl FineTune {
@@ -92,15 +100,12 @@ impl FineTune {
     pub async fn retreive(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Self> {
         let ft = client
             .as_ref()
-            .get(format!(
-                "https://api.openai.com/v1/fine-tunes/{}",
-                id.as_ref()
-            ))
-            .send()
-            .await?
-            .json::<FallibleResponse<Self>>()
-            .await?
-            .into_result()?;
+            .get_json::<Self>(
+                client
+                    .as_ref()
+                    .url(Endpoint::FineTunes, format!("/{}", id.as_ref())),
+            )
+            .await?;
 
         return Ok(ft);
     }
@@ -163,9 +168,21 @@ impl<'a> Builder<'a> {
             classification_positive_class: None,
             classification_betas: None,
             suffix: None,
+            extra: HashMap::new(),
         };
     }
 
+    /// Adds a parameter the builder doesn't have yet to the fine-tuning job
+    #[inline]
+    pub fn extra_param(
+        mut self,
+        key: impl Into<Str<'a>>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
     /// The ID of an uploaded file that contains validation data.
     ///
     /// If you provide this file, the data is used to generate validation metrics periodically during fine-tuning. These metrics can be viewed in the fine-tuning results file. Your train and validation data should be mutually exclusive.
@@ -275,11 +292,11 @@ impl<'a> Builder<'a> {
     pub async fn build(self, client: impl AsRef<Client>) -> Result<FineTune> {
         let finetune = client
             .as_ref()
-            .post("https://api.openai.com/v1/fine-tunes")
-            .json(&self)
+            .post(client.as_ref().url(Endpoint::FineTunes, ""))
+            .json_body(&self)
             .send()
             .await?
-            .json::<FallibleResponse<FineTune>>()
+            .json_body::<FallibleResponse<FineTune>>()
             .await?
             .into_result()?;
 
@@ -292,16 +309,15 @@ async fn fine_tune_events_inner(
     stream: bool,
     client: impl AsRef<Client>,
 ) -> Result<reqwest::Response> {
-    let resp = client
-        .as_ref()
-        .get(format!(
-            "https://api.openai.com/v1/fine-tunes/{}/events",
-            id.as_ref()
-        ))
+    let client = client.as_ref();
+    let url = client.url(Endpoint::FineTunes, format!("/{}/events", id.as_ref()));
+    return client
+        .get(&url)
         .query(&serde_json::json!({ "stream": stream }))
         .send()
-        .await?;
-    return Ok(resp);
+        .await?
+        .check_status()
+        .await;
 }
 
 /// Get fine-grained status updates for a fine-tune job.
@@ -309,14 +325,9 @@ pub async fn fine_tune_events(
     id: impl AsRef<str>,
     client: impl AsRef<Client>,
 ) -> Result<Vec<FineTuneEvent>> {
-    #[derive(Debug, Deserialize)]
-    struct Response {
-        data: Vec<FineTuneEvent>,
-    }
-
     let resp = fine_tune_events_inner(id, false, client)
         .await?
-        .json::<FallibleResponse<Response>>()
+        .json_body::<FallibleResponse<ListResponse<FineTuneEvent>>>()
         .await?
         .into_result()?
         .data;
@@ -333,24 +344,21 @@ pub async fn fine_tune_event_stream(
         .await?
         .bytes_stream();
 
-    return Ok(FineTuneEventStream {
-        inner: Box::pin(stream),
-        current_chunk: None,
-        _phtm: PhantomData,
-    });
+    return Ok(FineTuneEventStream::from_bytes_stream(stream));
 }
 
 /// Immediately cancel a fine-tune job.
 pub async fn cancel_fine_tune(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<FineTune> {
     let ft = client
         .as_ref()
-        .post(format!(
-            "https://api.openai.com/v1/fine-tunes/{}/cancel",
-            id.as_ref()
-        ))
+        .post(
+            client
+                .as_ref()
+                .url(Endpoint::FineTunes, format!("/{}/cancel", id.as_ref())),
+        )
         .send()
         .await?
-        .json::<FallibleResponse<FineTune>>()
+        .json_body::<FallibleResponse<FineTune>>()
         .await?
         .into_result()?;
 
@@ -364,13 +372,14 @@ pub async fn delete_fine_tune_model(
 ) -> Result<Delete> {
     let del = client
         .as_ref()
-        .delete(format!(
-            "https://api.openai.com/v1/models/{}",
-            model_id.as_ref()
-        ))
+        .delete(
+            client
+                .as_ref()
+                .url(Endpoint::Models, format!("/{}", model_id.as_ref())),
+        )
         .send()
         .await?
-        .json::<FallibleResponse<Delete>>()
+        .json_body::<FallibleResponse<Delete>>()
         .await?
         .into_result()?;
 
@@ -379,19 +388,10 @@ pub async fn delete_fine_tune_model(
 
 /// List your organization's fine-tuning jobs
 pub async fn fine_tunes(client: impl AsRef<Client>) -> Result<Vec<FineTune>> {
-    #[derive(Debug, Deserialize)]
-    struct Response {
-        data: Vec<FineTune>,
-    }
-
     let files = client
         .as_ref()
-        .get("https://api.openai.com/v1/fine-tunes")
-        .send()
-        .await?
-        .json::<FallibleResponse<Response>>()
-        .await?
-        .into_result()?;
+        .get_json::<ListResponse<FineTune>>(client.as_ref().url(Endpoint::FineTunes, ""))
+        .await?;
 
     return Ok(files.data);
 }
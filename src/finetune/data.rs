@@ -1,4 +1,8 @@
-use crate::{error::Result, file::File, Client};
+use crate::{
+    error::{Error, Result},
+    file::{jsonl::JsonlWriter, File, FileContent},
+    Client,
+};
 use futures::{Stream, StreamExt, TryStream, TryStreamExt};
 use serde::{Deserialize, Serialize};
 
@@ -8,6 +12,11 @@ pub struct TrainingData {
     pub completion: String,
 }
 
+impl FileContent for TrainingData {
+    const PURPOSES: &'static [&'static str] = &["fine-tune"];
+    const NAME: &'static str = "training example";
+}
+
 #[derive(Debug, Clone)]
 pub struct TrainingDataBuilder {
     filename: Option<String>,
@@ -113,19 +122,13 @@ impl TrainingDataBuilder {
     where
         S: 'static + Send + Sync + Stream<Item = TrainingData>,
     {
-        let data = data.map(move |mut x| {
-            if let Some(prefix) = self.prefix {
-                x.completion.insert_str(0, prefix)
-            }
-
-            if let Some(suffix) = self.suffix {
-                x.completion.push_str(suffix)
-            }
+        let mut writer = JsonlWriter::upload(self.filename.clone(), "fine-tune", client);
+        futures::pin_mut!(data);
+        while let Some(x) = data.next().await {
+            writer.write(&self.format(x)).await?;
+        }
 
-            return x;
-        });
-
-        return File::upload_stream(data, self.filename, "fine-tune", client).await;
+        return writer.finish().await?.into_file();
     }
 
     pub async fn try_save_stream<S>(self, data: S, client: impl AsRef<Client>) -> Result<File>
@@ -133,19 +136,28 @@ impl TrainingDataBuilder {
         S: 'static + Send + Sync + TryStream<Ok = TrainingData>,
         S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
     {
-        let data = data.map_ok(move |mut x| {
-            if let Some(prefix) = self.prefix {
-                x.completion.insert_str(0, prefix)
-            }
+        let mut writer = JsonlWriter::upload(self.filename.clone(), "fine-tune", client);
+        let data = data.into_stream();
+        futures::pin_mut!(data);
+        while let Some(x) = data.next().await {
+            let x = x.map_err(|e| Error::msg(e.into()))?;
+            writer.write(&self.format(x)).await?;
+        }
+
+        return writer.finish().await?.into_file();
+    }
 
-            if let Some(suffix) = self.suffix {
-                x.completion.push_str(suffix)
-            }
+    /// Adds the prefix and suffix to the completion of `data`
+    fn format(&self, mut data: TrainingData) -> TrainingData {
+        if let Some(prefix) = self.prefix {
+            data.completion.insert_str(0, prefix)
+        }
 
-            return x;
-        });
+        if let Some(suffix) = self.suffix {
+            data.completion.push_str(suffix)
+        }
 
-        return File::try_upload_stream(data, self.filename, "fine-tune", client).await;
+        return data;
     }
 }
 
@@ -0,0 +1,104 @@
+use crate::{endpoint::Endpoint, error::Error, rt, Client};
+use futures::future::{abortable, AbortHandle};
+use reqwest::StatusCode;
+use std::time::{Duration, Instant};
+
+/// Result of a [`Client::ping`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Health {
+    /// Time it took the API to answer, or to fail
+    pub latency: Duration,
+    pub status: HealthStatus,
+}
+
+/// State of the API, as seen by a client
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HealthStatus {
+    /// The API answered, and accepted the client's credentials
+    Healthy,
+    /// The API answered, but rejected the client's credentials
+    Unauthorized,
+    /// The API couldn't be reached, or answered with an error that isn't about the credentials
+    Unavailable {
+        /// Description of the error
+        reason: String,
+    },
+}
+
+/// Handle to the periodic health check started by [`Client::watch_health`], which stops when the handle is dropped
+#[derive(Debug)]
+pub struct HealthCheck {
+    abort: AbortHandle,
+}
+
+impl Health {
+    /// Returns `true` if the API answered, and accepted the client's credentials
+    #[inline]
+    pub fn is_healthy(&self) -> bool {
+        return self.status == HealthStatus::Healthy;
+    }
+}
+
+impl HealthCheck {
+    /// Stops the health check
+    #[inline]
+    pub fn stop(self) {}
+}
+
+impl Drop for HealthCheck {
+    #[inline]
+    fn drop(&mut self) {
+        self.abort.abort();
+    }
+}
+
+/// Lists the models available to the client, timing the request
+pub(crate) async fn ping(client: &Client) -> Health {
+    let start = Instant::now();
+    // Caches and coalescers are bypassed and pings aren't retried, so that every ping reaches the API
+    let result = client
+        .get(client.url(Endpoint::Models, ""))
+        .retry_policy(None)
+        .send()
+        .await;
+    let latency = start.elapsed();
+
+    let status = match result {
+        Ok(resp) => match resp.status() {
+            status if status.is_success() => HealthStatus::Healthy,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => HealthStatus::Unauthorized,
+            status => {
+                let body = resp.bytes().await.unwrap_or_default();
+                HealthStatus::Unavailable {
+                    reason: Error::from_status(status, &body).to_string(),
+                }
+            }
+        },
+        Err(e) => HealthStatus::Unavailable {
+            reason: e.to_string(),
+        },
+    };
+
+    return Health { latency, status };
+}
+
+/// Pings the API every `interval` in the background, calling `callback` with every result
+pub(crate) fn watch(
+    client: Client,
+    interval: Duration,
+    mut callback: impl 'static + Send + FnMut(&Health),
+) -> HealthCheck {
+    let lifecycle = client.lifecycle.clone();
+    let (task, abort) = abortable(async move {
+        loop {
+            callback(&ping(&client).await);
+            rt::sleep(interval).await;
+        }
+    });
+
+    lifecycle.register_task(abort.clone());
+    rt::detach(task);
+    return HealthCheck { abort };
+}
@@ -1,42 +1,70 @@
 use super::error::{Error, Result};
-use crate::error_to_io_error;
+use crate::{error_to_io_error, logging::ResponseExt, rt};
 use base64::Engine;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use elor::{Either, LeftRight};
-use futures::{stream::FuturesUnordered, StreamExt, TryFutureExt, TryStream, TryStreamExt};
-use image::{
-    codecs::png::PngDecoder, ExtendedColorType, GenericImage, GenericImageView, ImageBuffer,
-    ImageDecoder, ImageFormat, ImageOutputFormat, Rgba,
-};
-use image::{io::Reader as ImageReader, DynamicImage};
+use futures::{AsyncWriteExt, TryFutureExt, TryStream, TryStreamExt};
+use image::{DynamicImage, ImageFormat, ImageOutputFormat};
+use preprocess::ImagePreprocessor;
 use rand::{distributions::Standard, thread_rng, Rng};
 use reqwest::Body;
 use serde::{Deserialize, Serialize};
 use std::{
     future::ready,
-    io::{Cursor, Read, Seek, SeekFrom},
+    io::Cursor,
     ops::Deref,
-    panic::resume_unwind,
     path::{Path, PathBuf},
+    pin::Pin,
     sync::Arc,
 };
-use tokio::task::spawn_blocking;
+#[cfg(feature = "tokio")]
 use tokio_util::io::StreamReader;
 
 /// Creates an edited or extended image given an original image and a prompt.
 pub mod edit;
 /// Creates an image given a prompt.
 pub mod generate;
+/// Adapts images to the requirements of the API.
+pub mod preprocess;
 /// Creates a variation of a given image.
 pub mod variation;
 
 /// Result from an images request
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "ImagesRepr", into = "ImagesRepr")]
 pub struct Images {
-    #[serde(with = "chrono::serde::ts_seconds")]
     pub created: DateTime<Utc>,
     pub data: Vec<ImageData>,
+    /// Prompts the images were generated with, in the order of [`data`](Images::data), if the model rewrote the original one (e.g. `dall-e-3`)
+    pub revised_prompts: Vec<Option<String>>,
+    post_processing: PostProcessing,
+}
+
+/// [`Images`] as returned by the API, with the revised prompt next to the data of each image
+#[derive(Serialize, Deserialize)]
+struct ImagesRepr {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    created: DateTime<Utc>,
+    data: Vec<ImageDataRepr>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ImageDataRepr {
+    #[serde(flatten)]
+    data: ImageData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    revised_prompt: Option<String>,
+}
+
+/// A post-processing step, applied to the images before they're saved
+type Hook = Arc<dyn Send + Sync + Fn(DynamicImage) -> Result<DynamicImage>>;
+
+/// Post-processing of the images of an [`Images`], applied when they're saved
+#[derive(Clone, Default)]
+struct PostProcessing {
+    hooks: Vec<Hook>,
+    format: Option<ImageFormat>,
 }
 
 /// The size of the generated images.
@@ -52,6 +80,49 @@ pub enum Size {
     #[serde(rename = "1024x1024")]
     #[default]
     P1024,
+    /// 1792-by-1024 pixels (landscape), supported by `dall-e-3`
+    #[serde(rename = "1792x1024")]
+    P1792x1024,
+    /// 1024-by-1792 pixels (portrait), supported by `dall-e-3`
+    #[serde(rename = "1024x1792")]
+    P1024x1792,
+    /// 1536-by-1024 pixels (landscape), supported by `gpt-image-1`
+    #[serde(rename = "1536x1024")]
+    P1536x1024,
+    /// 1024-by-1536 pixels (portrait), supported by `gpt-image-1`
+    #[serde(rename = "1024x1536")]
+    P1024x1536,
+    /// Chosen by the model, supported by `gpt-image-1`
+    #[serde(rename = "auto")]
+    Auto,
+}
+
+impl Size {
+    /// Returns `false` if the size is known to be unsupported by the model.
+    ///
+    /// Unknown models (e.g. deployments) are assumed to support every size.
+    pub fn is_supported_by(self, model: &str) -> bool {
+        return match model {
+            "dall-e-2" => matches!(self, Size::P256 | Size::P512 | Size::P1024),
+            "dall-e-3" => matches!(self, Size::P1024 | Size::P1792x1024 | Size::P1024x1792),
+            model if model.starts_with("gpt-image") => matches!(
+                self,
+                Size::P1024 | Size::P1536x1024 | Size::P1024x1536 | Size::Auto
+            ),
+            _ => true,
+        };
+    }
+
+    /// Returns an error if the size is known to be unsupported by the model
+    pub(crate) fn validate(size: Option<Self>, model: &str) -> Result<()> {
+        return match size {
+            Some(size) if !size.is_supported_by(model) => Err(Error::msg(format!(
+                "the size {} isn't supported by the model '{model}'",
+                serde_json::to_value(size)?
+            ))),
+            _ => Ok(()),
+        };
+    }
 }
 
 /// The format in which the generated images are returned.
@@ -68,7 +139,7 @@ pub enum ImageResponseFormat {
 }
 
 /// Image data
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ImageData {
     /// URL that points to an image hosted by OpenAI
@@ -78,43 +149,228 @@ pub enum ImageData {
     B64Json(Arc<String>), // avoid making large string copies
 }
 
+impl From<ImagesRepr> for Images {
+    fn from(value: ImagesRepr) -> Self {
+        let (data, revised_prompts) = value
+            .data
+            .into_iter()
+            .map(|x| (x.data, x.revised_prompt))
+            .unzip();
+
+        return Self {
+            created: value.created,
+            data,
+            revised_prompts,
+            post_processing: PostProcessing::default(),
+        };
+    }
+}
+
+impl From<Images> for ImagesRepr {
+    fn from(value: Images) -> Self {
+        let revised_prompts = value
+            .revised_prompts
+            .into_iter()
+            .chain(std::iter::repeat(None));
+        return Self {
+            created: value.created,
+            data: value
+                .data
+                .into_iter()
+                .zip(revised_prompts)
+                .map(|(data, revised_prompt)| ImageDataRepr {
+                    data,
+                    revised_prompt,
+                })
+                .collect(),
+        };
+    }
+}
+
 impl Images {
-    /// Saves all the images in the response into the specified directory
+    /// Registers a post-processing step (e.g. resizing or watermarking the image), applied to every image before it's saved by [`save`](Images::save) or [`save_at`](Images::save_at).
+    ///
+    /// Steps are applied in the order they're registered, on a thread where blocking is acceptable.
+    pub fn post_process<F>(mut self, f: F) -> Self
+    where
+        F: 'static + Send + Sync + Fn(DynamicImage) -> Result<DynamicImage>,
+    {
+        self.post_processing.hooks.push(Arc::new(f));
+        self
+    }
+
+    /// Converts the images into the specified format (e.g. JPEG or WebP) when they're saved.
+    ///
+    /// By default, images saved by [`save_at`](Images::save_at) keep the format returned by the API, and post-processed images saved by [`save`](Images::save) are encoded in the format of their path's extension (or PNG, if unknown).
+    pub fn output_format(mut self, format: ImageFormat) -> Self {
+        self.post_processing.format = Some(format);
+        self
+    }
+
+    /// Saves all the images in the response into the specified directory, with random file names
+    #[inline]
     pub async fn save_at(self, path: impl AsRef<Path>) -> Result<()> {
+        self.save_at_with(path, Naming::Random).await?;
+        return Ok(());
+    }
+
+    /// Saves all the images in the response into the specified directory, named by `naming`, returning their paths in the order of [`data`](Images::data).
+    ///
+    /// Files are never overwritten: if a name is taken (e.g. by images saved concurrently), a counter is appended to it.
+    pub async fn save_at_with(
+        self,
+        path: impl AsRef<Path>,
+        naming: Naming,
+    ) -> Result<Vec<PathBuf>> {
         let mut rng = thread_rng();
+        let created = self.created.format("%Y%m%dT%H%M%S");
+        return self
+            .save_at_named(path, |i, _| match naming {
+                Naming::Random => format!("{}", rng.sample::<u64, _>(Standard)),
+                Naming::Timestamp => format!("{i}-{created}"),
+                Naming::Prompt(ref prompt) => format!("{}-{i}-{created}", slug(prompt)),
+            })
+            .await;
+    }
+
+    /// Saves all the images in the response into the specified directory, named by `f` from their index, returning their paths in the order of [`data`](Images::data).
+    ///
+    /// The extension of the files is the one of their [output format](Images::output_format), or of the format returned by the API, detected from their contents. As with [`save_at_with`](Images::save_at_with), files are never overwritten.
+    pub async fn save_at_named<F: FnMut(usize, &ImageData) -> String>(
+        self,
+        path: impl AsRef<Path>,
+        mut f: F,
+    ) -> Result<Vec<PathBuf>> {
         let path: &Path = path.as_ref();
+        let paths = self
+            .data
+            .iter()
+            .enumerate()
+            .map(|(i, data)| path.join(f(i, data)))
+            .collect::<Vec<_>>();
+
+        return self.save_all(paths, true).await;
+    }
+
+    /// Saves all the images in the response into the path provided for each one by `f`
+    pub async fn save<F: FnMut(&ImageData) -> PathBuf>(self, f: F) -> Result<()> {
+        let paths = self.data.iter().map(f).collect::<Vec<_>>();
+        self.save_all(paths, false).await?;
+        return Ok(());
+    }
+
+    /// Saves the images into `paths`. If `exclusive`, files aren't overwritten, and their extensions are set to the format they're saved in.
+    async fn save_all(
+        self,
+        paths: impl IntoIterator<Item = PathBuf>,
+        exclusive: bool,
+    ) -> Result<Vec<PathBuf>> {
+        let post_processing = self.post_processing;
+        let fut = self.data.into_iter().zip(paths).map(|(data, path)| {
+            let post_processing = post_processing.clone();
+            rt::spawn(async move {
+                // The format of the image is detected from its first bytes, so it must be downloaded before the file is named
+                let stream = data.into_stream().await?.into_stream();
+                futures::pin_mut!(stream);
+                let first = stream.try_next().await?.unwrap_or_default();
+
+                let (mut w, path) = match exclusive {
+                    true => {
+                        create_unique(path.with_extension(post_processing.extension_of(&first)))
+                            .await?
+                    }
+                    false => (Box::pin(rt::create_file(path.clone()).await?) as _, path),
+                };
+
+                if post_processing.is_empty() {
+                    w.write_all(&first).await?;
+                    while let Some(chunk) = stream.try_next().await? {
+                        w.write_all(&chunk).await?;
+                    }
+                } else {
+                    let mut bytes = first.to_vec();
+                    while let Some(chunk) = stream.try_next().await? {
+                        bytes.extend_from_slice(&chunk);
+                    }
 
-        let fut = self.save(|_| {
-            let id = rng.sample::<u64, _>(Standard);
-            path.join(format!("{id}")).with_extension("png")
+                    let format = post_processing.format_of(&path);
+                    let image = rt::spawn_blocking(move || {
+                        let image = image::load_from_memory(&bytes)?;
+                        return post_processing.apply(image, format);
+                    })
+                    .await?;
+                    w.write_all(&image).await?;
+                }
+                w.close().await?;
+                return Result::<PathBuf>::Ok(path);
+            })
         });
 
-        return fut.await;
+        return futures::future::try_join_all(fut).await;
     }
+}
 
-    /// Saves all the images in the response into the path provided for each one by `f`
-    pub async fn save<F: FnMut(&ImageData) -> PathBuf>(self, mut f: F) -> Result<()> {
-        let fut = futures::stream::iter(self.data.into_iter())
-            .map(|data| {
-                let path = f(&data);
-                tokio::spawn(async move {
-                    let mut w = tokio::fs::File::create(path).await?;
-                    data.write_into_tokio(&mut w).await?;
-                    return Result::<()>::Ok(());
-                })
-            })
-            .collect::<FuturesUnordered<_>>()
-            .await;
+/// How [`Images::save_at_with`] names the files of the images
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum Naming {
+    /// A random number (e.g. `8137562948223619513.png`)
+    #[default]
+    Random,
+    /// The index of the image and the time the images were created (e.g. `0-20230301T120000.png`)
+    Timestamp,
+    /// A slug of the prompt, the index of the image and the time the images were created (e.g. `a-cat-in-a-hat-0-20230301T120000.png`)
+    Prompt(String),
+}
 
-        fut.map(|x| match x {
-            Ok(x) => x,
-            Err(e) => resume_unwind(e.into_panic()),
-        })
-        .try_collect::<()>()
-        .await?;
+/// Creates the file at `path`, or at `path` with a counter appended if it's taken, returning the path of the created file
+async fn create_unique(
+    path: PathBuf,
+) -> Result<(Pin<Box<dyn Send + futures::io::AsyncWrite>>, PathBuf)> {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut candidate = path.clone();
+    for i in 1u32.. {
+        match rt::create_new_file(candidate.clone()).await {
+            Ok(w) => return Ok((Box::pin(w), candidate)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                candidate = path.with_file_name(format!("{stem}-{i}"));
+                if let Some(extension) = path.extension() {
+                    candidate.set_extension(extension);
+                }
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    unreachable!()
+}
 
-        return Ok(());
+/// Returns a lowercase, dash-separated version of `prompt`, usable in file names
+fn slug(prompt: &str) -> String {
+    /// Characters of the prompt kept in the slug
+    const MAX_LEN: usize = 48;
+
+    let mut slug = String::new();
+    for word in prompt
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        if slug.len() + word.len() + 1 > MAX_LEN && !slug.is_empty() {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.extend(word.chars().take(MAX_LEN).flat_map(char::to_lowercase));
     }
+
+    if slug.is_empty() {
+        slug.push_str("image");
+    }
+    return slug;
 }
 
 impl ImageData {
@@ -127,25 +383,40 @@ impl ImageData {
         }
     }
 
+    /// Downloads and decodes the image
+    pub async fn into_image(self) -> Result<DynamicImage> {
+        let bytes = self
+            .into_stream()
+            .await?
+            .try_fold(Vec::new(), |mut bytes, chunk| {
+                bytes.extend_from_slice(&chunk);
+                ready(Ok(bytes))
+            })
+            .await?;
+
+        return Ok(rt::spawn_blocking(move || image::load_from_memory(&bytes)).await?);
+    }
+
     /// Returns a bytes [`Stream`](futures::Stream) with the contents of the image
     pub async fn into_stream(self) -> Result<impl TryStream<Ok = Bytes, Error = Error>> {
         let v = match self {
             ImageData::Url(url) => Either::Left(
                 reqwest::get(url.deref())
+                    .await?
+                    .check_status()
                     .await?
                     .bytes_stream()
                     .map_err(Error::from),
             ),
             ImageData::B64Json(x) => {
                 let fut = async move {
-                    match spawn_blocking(move || {
+                    match rt::spawn_blocking(move || {
                         base64::engine::general_purpose::STANDARD.decode(x.deref())
                     })
                     .await
                     {
-                        Ok(Ok(x)) => return Ok(futures::stream::once(ready(Ok(Bytes::from(x))))),
-                        Ok(Err(e)) => return Err(Error::from(e)),
-                        Err(e) => std::panic::resume_unwind(e.into_panic()),
+                        Ok(x) => return Ok(futures::stream::once(ready(Ok(Bytes::from(x))))),
+                        Err(e) => return Err(Error::from(e)),
                     }
                 };
                 Either::Right(fut.try_flatten_stream())
@@ -161,13 +432,28 @@ impl ImageData {
         return Ok(stream.into_async_read());
     }
 
+    /// Writes the image's content into the specified [`futures::io::AsyncWrite`] writer
+    pub async fn write_into<W: ?Sized + Unpin + futures::io::AsyncWrite>(
+        self,
+        w: &mut W,
+    ) -> Result<()> {
+        let reader = self.into_futures_reader().await?;
+        futures::pin_mut!(reader);
+        futures::io::copy_buf(reader, w).await?;
+        return Ok(());
+    }
+
     /// Returns an [`tokio::io::AsyncBufRead`] with the contents of the image
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    #[cfg(feature = "tokio")]
     pub async fn into_tokio_reader(self) -> Result<impl tokio::io::AsyncBufRead> {
         let stream = self.into_stream().await?.map_err(error_to_io_error);
         return Ok(StreamReader::new(stream));
     }
 
     /// Writes the image's content into the specified [`tokio::io::AsyncWrite`] writer
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    #[cfg(feature = "tokio")]
     pub async fn write_into_tokio<W: ?Sized + Unpin + tokio::io::AsyncWrite>(
         self,
         w: &mut W,
@@ -190,81 +476,69 @@ impl ImageData {
     }
 }
 
-/// Loads the image from `path` and transforms it into a format valid to be sent to an OpenAI endpoint.
-///
-/// If the image is already in a valid format, no conversion will be done and it's byte stream will be directly returned.
-///
-/// > **Note**: This is a **blocking** method and should not be used in async contexts
-pub fn load_image(path: impl AsRef<Path>) -> Result<Body> {
-    let mut image = std::fs::File::open(path)?;
-
-    // Read file magic number and seek back to start
-    let mut magic = [0; 8];
-    image.read_exact(&mut magic)?;
-    image.seek(SeekFrom::Start(0))?;
-
-    return match image::guess_format(&magic) {
-        // Image is a PNG
-        Ok(ImageFormat::Png) => {
-            let decoder = PngDecoder::new(&mut image)?;
-            let (width, height) = decoder.dimensions();
-
-            // Make image square (by adding transparent background)
-            if width != height {
-                let size = u32::max(width, height);
-                let image = DynamicImage::from_decoder(decoder)?;
-
-                let mut extended = ImageBuffer::<Rgba<u8>, _>::new(size, size);
-                extended.copy_from(&image, (size - width) / 2, (size - height) / 2)?;
-
-                let mut result = Cursor::new(Vec::new());
-                extended.write_to(&mut result, ImageOutputFormat::Png)?;
-                return Ok(Body::from(result.into_inner()));
-            }
-
-            // Check image color type
-            match decoder.original_color_type() {
-                // Image has RGBA color, pass directly for streaming.
-                ExtendedColorType::Rgba8 => {
-                    image.seek(SeekFrom::Start(0))?;
-                    Ok(Body::from(tokio::fs::File::from_std(image)))
-                }
-
-                // Transform image to RGBA PNG
-                _ => {
-                    let image = DynamicImage::from_decoder(decoder)?.to_rgba8();
-                    let mut result = Cursor::new(Vec::new());
-                    image.write_to(&mut result, ImageOutputFormat::Png)?;
-                    Ok(Body::from(result.into_inner()))
-                }
-            }
-        }
+impl PostProcessing {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        return self.hooks.is_empty() && self.format.is_none();
+    }
 
-        // Image isn't a PNG
-        _ => {
-            let image = ImageReader::new(std::io::BufReader::new(image))
-                .with_guessed_format()?
-                .decode()?;
-            let (width, height) = image.dimensions();
+    /// Returns the extension of an image saved by [`Images::save_at`], whose contents start with `bytes`
+    #[inline]
+    fn extension_of(&self, bytes: &[u8]) -> &'static str {
+        return self
+            .format
+            .or_else(|| image::guess_format(bytes).ok())
+            .and_then(|format| format.extensions_str().first().copied())
+            .unwrap_or("png");
+    }
 
-            // Make image square (by adding transparent background)
-            if width != height {
-                let size = u32::max(width, height);
+    /// Returns the format an image saved at `path` is encoded in
+    #[inline]
+    fn format_of(&self, path: &Path) -> ImageFormat {
+        return self
+            .format
+            .or_else(|| ImageFormat::from_path(path).ok())
+            .unwrap_or(ImageFormat::Png);
+    }
 
-                let mut extended = ImageBuffer::<Rgba<u8>, _>::new(size, size);
-                extended.copy_from(&image, (size - width) / 2, (size - height) / 2)?;
+    /// Applies the hooks to `image` and encodes it
+    ///
+    /// > **Note**: This is a **blocking** method and should not be used in async contexts
+    fn apply(&self, mut image: DynamicImage, format: ImageFormat) -> Result<Vec<u8>> {
+        for hook in self.hooks.iter() {
+            image = hook(image)?;
+        }
 
-                let mut result = Cursor::new(Vec::new());
-                extended.write_to(&mut result, ImageOutputFormat::Png)?;
-                return Ok(Body::from(result.into_inner()));
+        // Neither encoder supports every color type (e.g. JPEG has no transparency)
+        image = match format {
+            ImageFormat::Jpeg if image.color().has_alpha() => image.to_rgb8().into(),
+            ImageFormat::WebP if !matches!(image, DynamicImage::ImageRgb8(_)) => {
+                image.to_rgba8().into()
             }
+            _ => image,
+        };
 
-            let mut output = Cursor::new(Vec::new());
-            image
-                .into_rgba8()
-                .write_to(&mut output, ImageOutputFormat::Png)?;
+        let mut result = Cursor::new(Vec::new());
+        image.write_to(&mut result, ImageOutputFormat::from(format))?;
+        return Ok(result.into_inner());
+    }
+}
 
-            Result::<Body>::Ok(Body::from(output.into_inner()))
-        }
-    };
+impl std::fmt::Debug for PostProcessing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostProcessing")
+            .field("hooks", &self.hooks.len())
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+/// Loads the image from `path` and transforms it into a format valid to be sent to an OpenAI endpoint.
+///
+/// If the image is already in a valid format, no conversion will be done and it's byte stream will be directly returned.
+///
+/// > **Note**: This is a **blocking** method and should not be used in async contexts
+#[deprecated(note = "use `ImagePreprocessor::load`, which doesn't block")]
+pub fn load_image(path: impl AsRef<Path>) -> Result<Body> {
+    return ImagePreprocessor::new().load_blocking(path.as_ref());
 }
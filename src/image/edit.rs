@@ -1,26 +1,48 @@
-use super::{load_image, ImageResponseFormat, Images, Size};
+use super::{
+    preprocess::{ImagePreprocessor, Resized},
+    ImageResponseFormat, Images, Size,
+};
 use crate::{
-    error::{BuilderError, Error, FallibleResponse, Result},
-    Client,
+    endpoint::Endpoint,
+    error::{BuilderError, Error, Result},
+    multipart::{FilePart, MultipartRequest},
+    rt, Client,
 };
 use bytes::Bytes;
-use futures::{future::try_join, FutureExt, TryStream};
+use futures::TryStream;
+use image::{
+    imageops::FilterType, GenericImage, GrayImage, ImageOutputFormat, Luma, Rgba, RgbaImage,
+};
 use rand::{distributions::Standard, random, thread_rng, Rng};
-use reqwest::{
-    multipart::{Form, Part},
-    Body,
+use reqwest::{multipart::Part, Body};
+use std::{
+    ffi::OsStr,
+    io::Cursor,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
 };
-use std::{ffi::OsStr, ops::RangeInclusive, path::PathBuf};
-use tokio::task::spawn_blocking;
+#[cfg(feature = "tokio")]
 use tokio_util::io::ReaderStream;
 
 #[derive(Debug, Clone)]
 pub struct ImageEditBuilder {
     prompt: String,
+    model: Option<String>,
+    mask: Option<MaskBuilder>,
     n: Option<u64>,
     size: Option<Size>,
     response_format: Option<ImageResponseFormat>,
     user: Option<String>,
+    preprocessor: ImagePreprocessor,
+}
+
+/// Builds the mask of an edit, whose transparent areas are the ones edited.
+///
+/// The mask must have the same size as the edited image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaskBuilder {
+    /// How much each pixel is edited, from 0 (kept) to 255 (edited)
+    edited: GrayImage,
 }
 
 impl Images {
@@ -34,17 +56,15 @@ impl Images {
 impl ImageEditBuilder {
     #[inline]
     pub fn new(prompt: impl Into<String>) -> Result<Self> {
-        let prompt: String = prompt.into();
-        if prompt.len() > 1000 {
-            return Err(Error::msg("Message excedes character limit of 1000"));
-        }
-
         return Ok(Self {
             prompt: prompt.into(),
+            model: None,
+            mask: None,
             n: None,
             size: None,
             response_format: None,
             user: None,
+            preprocessor: ImagePreprocessor::new(),
         });
     }
 
@@ -64,7 +84,23 @@ impl ImageEditBuilder {
         };
     }
 
-    /// The size of the generated images.
+    /// The model used to edit the images (e.g. `gpt-image-1`). Defaults to `dall-e-2`.
+    #[inline]
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// The mask of the image, used when no other mask is sent with it.
+    ///
+    /// The mask must have the size of the original image: if the image is sent with [`with_file`](ImageEditBuilder::with_file), the mask is padded and scaled along with it.
+    #[inline]
+    pub fn mask(mut self, mask: MaskBuilder) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// The size of the generated images, whose values depend on the model.
     #[inline]
     pub fn size(mut self, size: Size) -> Self {
         self.size = Some(size);
@@ -85,11 +121,18 @@ impl ImageEditBuilder {
         self
     }
 
+    /// How the files sent with [`with_file`](ImageEditBuilder::with_file) are adapted to the requirements of the API.
+    #[inline]
+    pub fn preprocessor(mut self, preprocessor: ImagePreprocessor) -> Self {
+        self.preprocessor = preprocessor;
+        self
+    }
+
     /// Sends the request with the specified files.
     ///
     /// If the images do not conform to OpenAI's requirements, they will be adapted before they are sent
     pub async fn with_file(
-        self,
+        mut self,
         image: impl Into<PathBuf>,
         mask: Option<PathBuf>,
         client: impl AsRef<Client>,
@@ -108,15 +151,13 @@ impl ImageEditBuilder {
                     None => format!("{}.png", rng.sample::<u64, _>(Standard)),
                 };
 
-                let (image, mask) = try_join(
-                    spawn_blocking(move || load_image(image)).map(Result::unwrap),
-                    spawn_blocking(move || load_image(mask)).map(Result::unwrap),
-                )
+                // The mask is padded and scaled along with the image
+                let (image, resized) = self.preprocessor.load_part(image).await?;
+                let mask = rt::spawn_blocking(move || {
+                    return MaskBuilder::open(&mask)?.fit(resized)?.into_part();
+                })
                 .await?;
-                (
-                    Part::stream(Body::from(image)).file_name(image_name),
-                    Some(Part::stream(Body::from(mask)).file_name(mask_name)),
-                )
+                (image.file_name(image_name), Some(mask.file_name(mask_name)))
             }
             None => {
                 let image: PathBuf = image.into();
@@ -125,15 +166,20 @@ impl ImageEditBuilder {
                     None => format!("{}.png", random::<u64>()),
                 };
 
-                let image = spawn_blocking(move || load_image(image)).await.unwrap()?;
-                (Part::stream(Body::from(image)).file_name(name), None)
+                let (image, resized) = self.preprocessor.load_part(image).await?;
+                if let Some(mask) = self.mask.take() {
+                    self.mask = Some(rt::spawn_blocking(move || mask.fit(resized)).await?);
+                }
+                (image.file_name(name), None)
             }
         };
 
-        return self.with_part(image, mask, client).await;
+        return self.send(image, mask, client).await;
     }
 
     /// Sends the request with the specified file.
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    #[cfg(feature = "tokio")]
     pub async fn with_tokio_reader<I>(self, image: I, client: impl AsRef<Client>) -> Result<Images>
     where
         I: 'static + Send + Sync + tokio::io::AsyncRead,
@@ -172,55 +218,207 @@ impl ImageEditBuilder {
     }
 
     /// Sends the request with the specified files.
+    ///
+    /// Requests sent with a [`Part`] aren't retried, since it can only be sent once (see [`FilePart`]).
     pub async fn with_part(
         self,
-        image: Part,
+        image: impl Into<FilePart>,
         mask: Option<Part>,
         client: impl AsRef<Client>,
     ) -> Result<Images> {
-        let mut body = Form::new().text("prompt", self.prompt).part("image", image);
+        return self
+            .send(image.into(), mask.map(FilePart::from), client)
+            .await;
+    }
+
+    async fn send(
+        mut self,
+        image: FilePart,
+        mask: Option<FilePart>,
+        client: impl AsRef<Client>,
+    ) -> Result<Images> {
+        client.as_ref().default_user(&mut self.user);
+        let mask = match (mask, self.mask.take()) {
+            (None, Some(builder)) => Some(rt::spawn_blocking(move || builder.into_part()).await?),
+            (mask, builder) => {
+                self.mask = builder;
+                mask
+            }
+        };
+
+        let resp = self
+            .request(image, mask)?
+            .send_json::<Images>(client)
+            .await?;
+
+        #[cfg(feature = "tracing")]
+        tracing::info!("Images generated");
+
+        return Ok(resp);
+    }
+
+    /// Creates the request with the specified files, to add fields to it before it's sent.
+    ///
+    /// The client's default user isn't applied to the request, so it must be set with [`user`](ImageEditBuilder::user).
+    ///
+    /// > **Note**: The builder's [`mask`](ImageEditBuilder::mask) is encoded by this method, which may block
+    pub fn into_request(
+        self,
+        image: impl Into<FilePart>,
+        mask: Option<Part>,
+    ) -> Result<MultipartRequest> {
+        return self.request(image.into(), mask.map(FilePart::from));
+    }
+
+    fn request(self, image: FilePart, mask: Option<FilePart>) -> Result<MultipartRequest> {
+        self.validate()?;
+        let mask = match (mask, self.mask) {
+            (Some(_), Some(_)) => {
+                return Err(Error::msg(
+                    "the edit has a mask builder, and another mask was sent",
+                ))
+            }
+            (None, Some(builder)) => Some(builder.into_part()?),
+            (mask, None) => mask,
+        };
+
+        let mut request = MultipartRequest::new(Endpoint::Images, "/edits")
+            .text("prompt", self.prompt)
+            .part("image", image);
 
         if let Some(mask) = mask {
-            body = body.part("mask", mask)
+            request = request.part("mask", mask)
         }
-        if let Some(n) = self.n {
-            body = body.text("n", format!("{n}"))
-        }
-        if let Some(size) = self.size {
-            body = body.text(
-                "size",
-                match serde_json::to_value(&size)? {
-                    serde_json::Value::String(x) => x,
-                    _ => return Err(Error::msg("Unexpected error")),
-                },
-            )
+
+        return request
+            .field_opt("model", self.model.as_ref())?
+            .field_opt("n", self.n.as_ref())?
+            .field_opt("size", self.size.as_ref())?
+            .field_opt("response_format", self.response_format.as_ref())?
+            .field_opt("user", self.user.as_ref());
+    }
+
+    /// Checks that the prompt and the size aren't known to be unsupported by the model
+    fn validate(&self) -> Result<()> {
+        let model = self.model.as_deref().unwrap_or("dall-e-2");
+        let max_prompt_chars = match model {
+            "dall-e-2" => 1000,
+            _ => 32000,
+        };
+        if self.prompt.chars().count() > max_prompt_chars {
+            return Err(Error::msg(format!(
+                "the prompt exceeds the character limit of '{model}' ({max_prompt_chars})"
+            )));
         }
-        if let Some(response_format) = self.response_format {
-            body = body.text(
-                "response_format",
-                match serde_json::to_value(&response_format)? {
-                    serde_json::Value::String(x) => x,
-                    _ => return Err(Error::msg("Unexpected error")),
-                },
-            )
+        return Size::validate(self.size, model);
+    }
+}
+
+impl MaskBuilder {
+    /// Creates a mask of `width` by `height` pixels, which keeps the whole image
+    #[inline]
+    pub fn new(width: u32, height: u32) -> Self {
+        return Self {
+            edited: GrayImage::new(width, height),
+        };
+    }
+
+    /// Creates a mask from a grayscale image, whose white pixels are edited and black pixels kept
+    #[inline]
+    pub fn from_gray(edited: GrayImage) -> Self {
+        return Self { edited };
+    }
+
+    /// Marks the rectangle of `width` by `height` pixels, with its top-left corner at (`x`, `y`), to be edited.
+    ///
+    /// The parts of the rectangle outside the mask are ignored.
+    pub fn rect(mut self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        let x_end = x.saturating_add(width).min(self.edited.width());
+        let y_end = y.saturating_add(height).min(self.edited.height());
+        for y in y..y_end {
+            for x in x..x_end {
+                self.edited.put_pixel(x, y, Luma([u8::MAX]));
+            }
         }
-        if let Some(user) = self.user {
-            body = body.text("user", user)
+        self
+    }
+
+    /// Width of the mask, in pixels
+    #[inline]
+    pub fn width(&self) -> u32 {
+        return self.edited.width();
+    }
+
+    /// Height of the mask, in pixels
+    #[inline]
+    pub fn height(&self) -> u32 {
+        return self.edited.height();
+    }
+
+    /// Encodes the mask into the RGBA PNG expected by the API, transparent where the image is edited
+    ///
+    /// > **Note**: This is a **blocking** method and should not be used in async contexts
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mask = RgbaImage::from_fn(self.width(), self.height(), |x, y| {
+            let Luma([edited]) = *self.edited.get_pixel(x, y);
+            Rgba([0, 0, 0, u8::MAX - edited])
+        });
+
+        let mut result = Cursor::new(Vec::new());
+        mask.write_to(&mut result, ImageOutputFormat::Png)?;
+        return Ok(result.into_inner());
+    }
+
+    /// Opens the mask at `path`, whose transparent pixels are edited
+    ///
+    /// > **Note**: This is a **blocking** method and should not be used in async contexts
+    fn open(path: &Path) -> Result<Self> {
+        let mask = image::open(path)?.into_rgba8();
+        let edited = GrayImage::from_fn(mask.width(), mask.height(), |x, y| {
+            let Rgba([_, _, _, alpha]) = *mask.get_pixel(x, y);
+            Luma([u8::MAX - alpha])
+        });
+        return Ok(Self { edited });
+    }
+
+    /// Pads and scales the mask the same way as the image it belongs to was preprocessed
+    ///
+    /// > **Note**: This is a **blocking** method and should not be used in async contexts
+    fn fit(self, resized: Resized) -> Result<Self> {
+        let (width, height) = resized.original;
+        if self.edited.dimensions() != resized.original {
+            return Err(Error::msg(format!(
+                "the mask is {}x{} pixels, but the image is {width}x{height}",
+                self.width(),
+                self.height()
+            )));
         }
 
-        let resp = client
-            .as_ref()
-            .post("https://api.openai.com/v1/images/edits")
-            .multipart(body)
-            .send()
-            .await?
-            .json::<FallibleResponse<Images>>()
-            .await?
-            .into_result()?;
+        // Images are only padded when they're made square, with the padding kept
+        let mut edited = self.edited;
+        let (processed_width, processed_height) = resized.processed;
+        if processed_width == processed_height && width != height {
+            let size = u32::max(width, height);
+            let mut padded = GrayImage::new(size, size);
+            padded.copy_from(&edited, (size - width) / 2, (size - height) / 2)?;
+            edited = padded;
+        }
 
-        #[cfg(feature = "tracing")]
-        tracing::info!("Images generated");
+        if edited.dimensions() != resized.processed {
+            edited = image::imageops::resize(
+                &edited,
+                processed_width,
+                processed_height,
+                FilterType::Triangle,
+            );
+        }
+        return Ok(Self { edited });
+    }
 
-        return Ok(resp);
+    /// Encodes the mask into a part of a multipart request
+    ///
+    /// > **Note**: This is a **blocking** method and should not be used in async contexts
+    fn into_part(self) -> Result<FilePart> {
+        return Ok(FilePart::bytes(self.to_png()?).file_name("mask.png"));
     }
 }
@@ -1,22 +1,70 @@
-use super::{ImageResponseFormat, Images, Size};
+use super::{ImageData, ImageResponseFormat, Images, Size};
 use crate::{
+    endpoint::Endpoint,
     error::{BuilderError, Error, FallibleResponse, Result},
-    Client, Str,
+    logging::{RequestBuilderExt, ResponseExt},
+    responses::ResponseUsage,
+    Client, OpenAiStream, Str,
 };
-use serde::Serialize;
-use std::ops::RangeInclusive;
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ops::RangeInclusive, sync::Arc};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct GenerateBuilder<'a> {
     prompt: Str<'a>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<Str<'a>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    partial_images: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     n: Option<u64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     size: Option<Size>,
     #[serde(skip_serializing_if = "Option::is_none")]
     response_format: Option<ImageResponseFormat>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    quality: Option<ImageQuality>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    style: Option<ImageStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     user: Option<Str<'a>>,
+    /// Parameters not supported by the builder, sent as is
+    #[serde(flatten)]
+    extra: HashMap<Str<'a>, serde_json::Value>,
+}
+
+/// Quality of the generated images
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ImageQuality {
+    /// Only supported by `dall-e-3`
+    Standard,
+    /// Finer details and greater consistency. Only supported by `dall-e-3`
+    Hd,
+    /// Only supported by `gpt-image-1`
+    Low,
+    /// Only supported by `gpt-image-1`
+    Medium,
+    /// Only supported by `gpt-image-1`
+    High,
+    /// Chosen by the model
+    Auto,
+}
+
+/// Style of the images generated by `dall-e-3`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ImageStyle {
+    /// Hyper-real and dramatic images
+    Vivid,
+    /// More natural, less hyper-real images
+    Natural,
 }
 
 impl Images {
@@ -36,20 +84,32 @@ impl Images {
 impl<'a> GenerateBuilder<'a> {
     #[inline]
     pub fn new(prompt: impl Into<Str<'a>>) -> Result<Self> {
-        let prompt: Str<'a> = prompt.into();
-        if prompt.len() > 1000 {
-            return Err(Error::msg("Message excedes character limit of 1000"));
-        }
-
         return Ok(Self {
             prompt: prompt.into(),
+            model: None,
+            stream: false,
+            partial_images: None,
             n: None,
             size: None,
             response_format: None,
+            quality: None,
+            style: None,
             user: None,
+            extra: HashMap::new(),
         });
     }
 
+    /// Adds a parameter the builder doesn't have yet to the generation request (e.g. one only supported by a newer model)
+    #[inline]
+    pub fn extra_param(
+        mut self,
+        key: impl Into<Str<'a>>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
     /// The number of images to generate. Must be between 1 and 10.
     #[inline]
     pub fn n(mut self, n: u64) -> Result<Self, BuilderError<Self>> {
@@ -66,7 +126,30 @@ impl<'a> GenerateBuilder<'a> {
         };
     }
 
-    /// The size of the generated images.
+    /// The model used to generate the images (e.g. `gpt-image-1`). Defaults to `dall-e-2`.
+    #[inline]
+    pub fn model(mut self, model: impl Into<Str<'a>>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// The number of partial images sent by [`build_stream`](GenerateBuilder::build_stream) before the final one. Must be between 0 and 3.
+    #[inline]
+    pub fn partial_images(mut self, partial_images: u64) -> Result<Self, BuilderError<Self>> {
+        const RANGE: RangeInclusive<u64> = 0..=3;
+        return match RANGE.contains(&partial_images) {
+            true => {
+                self.partial_images = Some(partial_images);
+                Ok(self)
+            }
+            false => Err(BuilderError::msg(
+                self,
+                format!("partial_images out of range ({RANGE:?})"),
+            )),
+        };
+    }
+
+    /// The size of the generated images, whose values depend on the model.
     #[inline]
     pub fn size(mut self, size: Size) -> Self {
         self.size = Some(size);
@@ -80,6 +163,20 @@ impl<'a> GenerateBuilder<'a> {
         self
     }
 
+    /// The quality of the generated images, whose values depend on the model
+    #[inline]
+    pub fn quality(mut self, quality: ImageQuality) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+    /// The style of the generated images. Only supported by `dall-e-3`.
+    #[inline]
+    pub fn style(mut self, style: ImageStyle) -> Self {
+        self.style = Some(style);
+        self
+    }
+
     /// A unique identifier representing your end-user, which can help OpenAI to monitor and detect abuse.
     #[inline]
     pub fn user(mut self, user: impl Into<Str<'a>>) -> Self {
@@ -88,14 +185,16 @@ impl<'a> GenerateBuilder<'a> {
     }
 
     /// Sends the request
-    pub async fn build(self, client: impl AsRef<Client>) -> Result<Images> {
+    pub async fn build(mut self, client: impl AsRef<Client>) -> Result<Images> {
+        self.validate()?;
+        client.as_ref().default_user(&mut self.user);
         let resp = client
             .as_ref()
-            .post("https://api.openai.com/v1/images/generations")
-            .json(&self)
+            .post(client.as_ref().url(Endpoint::Images, "/generations"))
+            .json_body(&self)
             .send()
             .await?
-            .json::<FallibleResponse<Images>>()
+            .json_body::<FallibleResponse<Images>>()
             .await?
             .into_result()?;
 
@@ -104,4 +203,132 @@ impl<'a> GenerateBuilder<'a> {
 
         return Ok(resp);
     }
+
+    /// Sends the stream request, which yields the partial images of the generation as they're rendered, and then the final image.
+    ///
+    /// Streaming is only supported by `gpt-image-1`.
+    pub async fn build_stream(mut self, client: impl AsRef<Client>) -> Result<ImageStream> {
+        self.validate()?;
+        let client = client.as_ref();
+        client.default_user(&mut self.user);
+        self.stream = true;
+        let resp = client
+            .post(client.url(Endpoint::Images, "/generations"))
+            .json_body(&self)
+            .send()
+            .await?
+            .check_status()
+            .await?;
+
+        return Ok(ImageStream::from_bytes_stream(resp.bytes_stream()));
+    }
+}
+
+impl GenerateBuilder<'_> {
+    /// Checks that the prompt and the parameters aren't known to be unsupported by the model
+    fn validate(&self) -> Result<()> {
+        let model = self.model.as_deref().unwrap_or("dall-e-2");
+        let max_prompt_chars = match model {
+            "dall-e-2" => 1000,
+            "dall-e-3" => 4000,
+            _ => 32000,
+        };
+        if self.prompt.chars().count() > max_prompt_chars {
+            return Err(Error::msg(format!(
+                "the prompt exceeds the character limit of '{model}' ({max_prompt_chars})"
+            )));
+        }
+
+        // Unknown models (e.g. deployments) may support any parameter
+        let (dall_e_2, gpt_image) = (model == "dall-e-2", model.starts_with("gpt-image"));
+        let supported = match self.quality {
+            Some(ImageQuality::Standard | ImageQuality::Hd) => !dall_e_2 && !gpt_image,
+            Some(ImageQuality::Low | ImageQuality::Medium | ImageQuality::High) => {
+                !model.starts_with("dall-e")
+            }
+            _ => true,
+        };
+        if !supported || (self.style.is_some() && (dall_e_2 || gpt_image)) {
+            return Err(Error::msg(format!(
+                "the quality or style of the images isn't supported by the model '{model}'"
+            )));
+        }
+        return Size::validate(self.size, model);
+    }
+}
+
+/// A [`Stream`](futures::Stream) of [`ImageEvent`]s, emitted while an image is generated
+pub type ImageStream = OpenAiStream<ImageEvent>;
+
+/// An event emitted while an image is generated
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+#[non_exhaustive]
+pub enum ImageEvent {
+    /// A preview of the image, rendered partway through its generation
+    #[serde(rename = "image_generation.partial_image")]
+    PartialImage {
+        /// Base64-encoded image data
+        b64_json: Arc<String>,
+        /// Position of the preview among the partial images, starting at 0
+        partial_image_index: u64,
+        #[serde(with = "chrono::serde::ts_seconds")]
+        created_at: DateTime<Utc>,
+    },
+    /// The final image
+    #[serde(rename = "image_generation.completed")]
+    Completed {
+        /// Base64-encoded image data
+        b64_json: Arc<String>,
+        #[serde(with = "chrono::serde::ts_seconds")]
+        created_at: DateTime<Utc>,
+        /// Tokens used by the generation, as reported by the API
+        #[serde(default)]
+        usage: Option<ResponseUsage>,
+    },
+    /// Event type not supported by the library
+    #[serde(other)]
+    Unknown,
+}
+
+impl ImageEvent {
+    /// Returns the image of the event (partial or final), if any
+    #[inline]
+    pub fn image(&self) -> Option<ImageData> {
+        return match self {
+            ImageEvent::PartialImage { b64_json, .. } | ImageEvent::Completed { b64_json, .. } => {
+                Some(ImageData::B64Json(b64_json.clone()))
+            }
+            ImageEvent::Unknown => None,
+        };
+    }
+}
+
+impl ImageStream {
+    /// Consumes the stream, skipping the partial images and returning the final ones
+    pub async fn into_images(mut self) -> Result<Images> {
+        let mut created = None;
+        let mut data = Vec::new();
+        while let Some(event) = self.try_next().await? {
+            if let ImageEvent::Completed {
+                b64_json,
+                created_at,
+                ..
+            } = event
+            {
+                created.get_or_insert(created_at);
+                data.push(ImageData::B64Json(b64_json));
+            }
+        }
+
+        return match created {
+            Some(created) => Ok(Images {
+                created,
+                revised_prompts: vec![None; data.len()],
+                data,
+                post_processing: Default::default(),
+            }),
+            None => Err(Error::msg("Stream ended before any image was completed")),
+        };
+    }
 }
@@ -0,0 +1,205 @@
+use crate::{
+    error::{Error, Result},
+    multipart::FilePart,
+    rt,
+};
+use bytes::Bytes;
+use image::{
+    codecs::png::PngDecoder, imageops::FilterType, io::Reader as ImageReader, DynamicImage,
+    ExtendedColorType, GenericImage, ImageDecoder, ImageFormat, ImageOutputFormat, RgbaImage,
+};
+use reqwest::Body;
+use std::{
+    io::{BufReader, Cursor, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// Maximum size, in bytes, of the images accepted by the edit and variation endpoints
+pub const MAX_IMAGE_BYTES: usize = 4_000_000;
+
+/// Dimensions of an image before and after it's preprocessed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Resized {
+    pub original: (u32, u32),
+    pub processed: (u32, u32),
+}
+
+/// Adapts images to the requirements of the edit and variation endpoints: RGBA PNGs, square and under a size limit.
+///
+/// The images are processed on a thread where blocking is acceptable, and those already valid are sent as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImagePreprocessor {
+    max_bytes: usize,
+    square: bool,
+}
+
+impl ImagePreprocessor {
+    /// Creates the default preprocessor, which makes images square and shrinks them under [`MAX_IMAGE_BYTES`]
+    #[inline]
+    pub fn new() -> Self {
+        return Self {
+            max_bytes: MAX_IMAGE_BYTES,
+            square: true,
+        };
+    }
+
+    /// Maximum size of the encoded images, in bytes. Larger images are downscaled until they fit. Defaults to [`MAX_IMAGE_BYTES`].
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Whether to make images square, by centering them on a transparent background. Defaults to `true`.
+    pub fn square(mut self, square: bool) -> Self {
+        self.square = square;
+        self
+    }
+
+    /// Loads the image at `path`, adapting it if needed
+    pub async fn load(&self, path: impl Into<PathBuf>) -> Result<Body> {
+        let (this, path) = (*self, path.into());
+        return rt::spawn_blocking(move || this.load_blocking(&path)).await;
+    }
+
+    /// Loads the image at `path` into a file of a multipart request, adapting it if needed, and returns its dimensions before and after.
+    ///
+    /// Images that are already valid are read from `path` every time the request is sent.
+    pub(crate) async fn load_part(&self, path: impl Into<PathBuf>) -> Result<(FilePart, Resized)> {
+        let (this, path) = (*self, path.into());
+        return rt::spawn_blocking(move || {
+            let (image, resized) = this.adapt_blocking(&path)?;
+            let part = match image {
+                Some(image) => FilePart::bytes(image),
+                None => FilePart::file(path),
+            };
+            return Ok((part, resized));
+        })
+        .await;
+    }
+
+    /// Adapts the encoded `image` if needed
+    pub async fn process(&self, image: impl Into<Bytes>) -> Result<Bytes> {
+        let (this, image) = (*self, image.into());
+        return rt::spawn_blocking(move || {
+            if this.is_valid(Cursor::new(&image), image.len())?.is_some() {
+                return Ok(image);
+            }
+            let image = image::load_from_memory(&image)?;
+            return Ok(Bytes::from(this.encode(image)?.0));
+        })
+        .await;
+    }
+
+    /// Loads the image at `path`, adapting it if needed
+    ///
+    /// > **Note**: This is a **blocking** method and should not be used in async contexts
+    pub(crate) fn load_blocking(&self, path: &Path) -> Result<Body> {
+        // Valid images are streamed from the file
+        return match self.adapt_blocking(path)?.0 {
+            Some(image) => Ok(Body::from(image)),
+            None => Ok(rt::std_file_body(std::fs::File::open(path)?)),
+        };
+    }
+
+    /// Adapts the image at `path`, returning `None` if it can be sent as is, along with its dimensions before and after
+    ///
+    /// > **Note**: This is a **blocking** method and should not be used in async contexts
+    fn adapt_blocking(&self, path: &Path) -> Result<(Option<Vec<u8>>, Resized)> {
+        let mut file = std::fs::File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+
+        if let Some(dimensions) = self.is_valid(&mut file, len)? {
+            let resized = Resized {
+                original: dimensions,
+                processed: dimensions,
+            };
+            return Ok((None, resized));
+        }
+
+        file.seek(SeekFrom::Start(0))?;
+        let image = ImageReader::new(BufReader::new(file))
+            .with_guessed_format()?
+            .decode()?;
+        let (image, resized) = self.encode(image)?;
+        return Ok((Some(image), resized));
+    }
+
+    /// Returns the dimensions of an image of `len` bytes if it can be sent as is
+    fn is_valid<R: Read + Seek>(&self, mut image: R, len: usize) -> Result<Option<(u32, u32)>> {
+        if len > self.max_bytes {
+            return Ok(None);
+        }
+
+        // Read file magic number and seek back to start
+        let mut magic = [0; 8];
+        if image.read_exact(&mut magic).is_err() {
+            return Ok(None);
+        }
+        image.seek(SeekFrom::Start(0))?;
+        if image::guess_format(&magic).ok() != Some(ImageFormat::Png) {
+            return Ok(None);
+        }
+
+        let decoder = PngDecoder::new(image)?;
+        let (width, height) = decoder.dimensions();
+        let valid = (!self.square || width == height)
+            && decoder.original_color_type() == ExtendedColorType::Rgba8;
+        return Ok(valid.then_some((width, height)));
+    }
+
+    /// Encodes the image into an RGBA PNG, squaring it and downscaling it under the size limit
+    ///
+    /// > **Note**: This is a **blocking** method and should not be used in async contexts
+    fn encode(&self, image: DynamicImage) -> Result<(Vec<u8>, Resized)> {
+        let mut image = image.into_rgba8();
+        let original = image.dimensions();
+
+        // Make image square (by adding transparent background)
+        if self.square && image.width() != image.height() {
+            let size = u32::max(image.width(), image.height());
+            let mut extended = RgbaImage::new(size, size);
+            extended.copy_from(
+                &image,
+                (size - image.width()) / 2,
+                (size - image.height()) / 2,
+            )?;
+            image = extended;
+        }
+
+        loop {
+            let mut result = Cursor::new(Vec::new());
+            image.write_to(&mut result, ImageOutputFormat::Png)?;
+            let result = result.into_inner();
+            if result.len() <= self.max_bytes {
+                let resized = Resized {
+                    original,
+                    processed: image.dimensions(),
+                };
+                return Ok((result, resized));
+            }
+            if image.width() <= 1 && image.height() <= 1 {
+                return Err(Error::msg(format!(
+                    "the image can't be shrunk under {} bytes",
+                    self.max_bytes
+                )));
+            }
+
+            // The size of the encoding is roughly proportional to the number of pixels
+            let scale = f64::sqrt(self.max_bytes as f64 / result.len() as f64).min(0.9);
+            let resize = |x: u32| ((x as f64 * scale) as u32).clamp(1, x);
+            image = image::imageops::resize(
+                &image,
+                resize(image.width()),
+                resize(image.height()),
+                FilterType::Triangle,
+            );
+        }
+    }
+}
+
+impl Default for ImagePreprocessor {
+    #[inline]
+    fn default() -> Self {
+        return Self::new();
+    }
+}
@@ -1,18 +1,17 @@
-use super::{load_image, ImageResponseFormat, Images, Size};
+use super::{preprocess::ImagePreprocessor, ImageResponseFormat, Images, Size};
 use crate::{
-    error::{BuilderError, Error, FallibleResponse, Result},
+    endpoint::Endpoint,
+    error::{BuilderError, Result},
+    multipart::{FilePart, MultipartRequest},
     Client,
 };
 use bytes::Bytes;
 use futures::TryStream;
 use rand::random;
-use reqwest::{
-    multipart::{Form, Part},
-    Body,
-};
+use reqwest::{multipart::Part, Body};
 use std::path::PathBuf;
 use std::{ffi::OsStr, ops::RangeInclusive};
-use tokio::task::spawn_blocking;
+#[cfg(feature = "tokio")]
 use tokio_util::io::ReaderStream;
 
 #[derive(Debug, Clone)]
@@ -21,6 +20,7 @@ pub struct VariationBuilder {
     size: Option<Size>,
     response_format: Option<ImageResponseFormat>,
     user: Option<String>,
+    preprocessor: ImagePreprocessor,
 }
 
 impl Images {
@@ -39,6 +39,7 @@ impl VariationBuilder {
             size: None,
             response_format: None,
             user: None,
+            preprocessor: ImagePreprocessor::new(),
         };
     }
 
@@ -58,7 +59,7 @@ impl VariationBuilder {
         };
     }
 
-    /// The size of the generated images.
+    /// The size of the generated images. Variations are made by `dall-e-2`, which only supports square sizes up to 1024-by-1024 pixels.
     #[inline]
     pub fn size(mut self, size: Size) -> Self {
         self.size = Some(size);
@@ -79,6 +80,13 @@ impl VariationBuilder {
         self
     }
 
+    /// How the files sent with [`with_file`](VariationBuilder::with_file) are adapted to the requirements of the API.
+    #[inline]
+    pub fn preprocessor(mut self, preprocessor: ImagePreprocessor) -> Self {
+        self.preprocessor = preprocessor;
+        self
+    }
+
     /// Sends the request with the specified file
     ///
     /// If the images do not conform to OpenAI's requirements, they will be adapted before they are sent
@@ -88,22 +96,19 @@ impl VariationBuilder {
         client: impl AsRef<Client>,
     ) -> Result<Images> {
         let image_path: PathBuf = image.into();
-        let my_image_path = image_path.clone();
-
-        let image = spawn_blocking(move || load_image(my_image_path))
-            .await
-            .unwrap()?;
+        let (image, _) = self.preprocessor.load_part(image_path.clone()).await?;
 
         let name = match image_path.file_name().map(OsStr::to_string_lossy) {
             Some(x) => x.into_owned(),
             None => format!("{}.png", random::<u64>()),
         };
 
-        let image = Part::stream(image).file_name(name);
-        return self.with_part(image, client).await;
+        return self.with_part(image.file_name(name), client).await;
     }
 
     /// Sends the request with the specified file.
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    #[cfg(feature = "tokio")]
     pub async fn with_tokio_reader<I>(self, image: I, client: impl AsRef<Client>) -> Result<Images>
     where
         I: 'static + Send + Sync + tokio::io::AsyncRead,
@@ -136,47 +141,35 @@ impl VariationBuilder {
     }
 
     /// Sends the request with the specified file.
-    pub async fn with_part(self, image: Part, client: impl AsRef<Client>) -> Result<Images> {
-        let mut body = Form::new().part("image", image);
-
-        if let Some(n) = self.n {
-            body = body.text("n", format!("{n}"))
-        }
-        if let Some(size) = self.size {
-            body = body.text(
-                "size",
-                match serde_json::to_value(&size)? {
-                    serde_json::Value::String(x) => x,
-                    _ => return Err(Error::msg("Unexpected error")),
-                },
-            )
-        }
-        if let Some(response_format) = self.response_format {
-            body = body.text(
-                "response_format",
-                match serde_json::to_value(&response_format)? {
-                    serde_json::Value::String(x) => x,
-                    _ => return Err(Error::msg("Unexpected error")),
-                },
-            )
-        }
-        if let Some(user) = self.user {
-            body = body.text("user", user)
-        }
-
-        let resp = client
-            .as_ref()
-            .post("https://api.openai.com/v1/images/variations")
-            .multipart(body)
-            .send()
-            .await?
-            .json::<FallibleResponse<Images>>()
-            .await?
-            .into_result()?;
+    ///
+    /// Requests sent with a [`Part`] aren't retried, since it can only be sent once (see [`FilePart`]).
+    pub async fn with_part(
+        mut self,
+        image: impl Into<FilePart>,
+        client: impl AsRef<Client>,
+    ) -> Result<Images> {
+        client.as_ref().default_user(&mut self.user);
+        let resp = self
+            .into_request(image)?
+            .send_json::<Images>(client)
+            .await?;
 
         #[cfg(feature = "tracing")]
         tracing::info!("Images generated");
 
         return Ok(resp);
     }
+
+    /// Creates the request with the specified file, to add fields to it before it's sent.
+    ///
+    /// The client's default user isn't applied to the request, so it must be set with [`user`](VariationBuilder::user).
+    pub fn into_request(self, image: impl Into<FilePart>) -> Result<MultipartRequest> {
+        Size::validate(self.size, "dall-e-2")?;
+        return MultipartRequest::new(Endpoint::Images, "/variations")
+            .part("image", image)
+            .field_opt("n", self.n.as_ref())?
+            .field_opt("size", self.size.as_ref())?
+            .field_opt("response_format", self.response_format.as_ref())?
+            .field_opt("user", self.user.as_ref());
+    }
 }
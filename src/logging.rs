@@ -0,0 +1,236 @@
+use crate::error::{Error, Result};
+use crate::request::RequestBuilder;
+use futures::future::BoxFuture;
+use reqwest::{Response, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Sets the JSON body of a request, logging it when the `debug-logging` feature is enabled
+pub(crate) trait RequestBuilderExt {
+    fn json_body<T: Serialize + ?Sized>(self, body: &T) -> Self;
+}
+
+/// Parses the JSON body of a response, logging it when the `debug-logging` feature is enabled
+pub(crate) trait ResponseExt: Sized {
+    fn json_body<T: 'static + DeserializeOwned>(self) -> BoxFuture<'static, Result<T>>;
+
+    /// Returns the error of a failed response, reading its whole body so that the connection can be reused
+    fn check_status(self) -> BoxFuture<'static, Result<Self>>;
+}
+
+impl RequestBuilderExt for RequestBuilder {
+    #[inline]
+    fn json_body<T: Serialize + ?Sized>(self, body: &T) -> Self {
+        #[cfg(feature = "debug-logging")]
+        if tracing::enabled!(tracing::Level::TRACE) {
+            if let Ok(body) = serde_json::to_value(body) {
+                tracing::trace!(body = %redact(body), "OpenAI request");
+            }
+        }
+
+        return self.json(body);
+    }
+}
+
+impl ResponseExt for Response {
+    #[cfg(not(feature = "debug-logging"))]
+    #[inline]
+    fn json_body<T: 'static + DeserializeOwned>(self) -> BoxFuture<'static, Result<T>> {
+        return Box::pin(read_json(self));
+    }
+
+    #[cfg(feature = "debug-logging")]
+    fn json_body<T: 'static + DeserializeOwned>(self) -> BoxFuture<'static, Result<T>> {
+        return Box::pin(async move {
+            if !tracing::enabled!(tracing::Level::TRACE) {
+                return read_json(self).await;
+            }
+
+            let status = self.status();
+            let url = self.url().clone();
+            let body = self.bytes().await?;
+            log_response_body(status.as_u16(), url.as_str(), &body);
+
+            return parse_body::<T>(status, &body);
+        });
+    }
+
+    fn check_status(self) -> BoxFuture<'static, Result<Self>> {
+        return Box::pin(async move {
+            let status = self.status();
+            if status.is_success() {
+                return Ok(self);
+            }
+
+            #[cfg(feature = "debug-logging")]
+            let url = self.url().clone();
+            let body = self.bytes().await?;
+
+            #[cfg(feature = "debug-logging")]
+            if tracing::enabled!(tracing::Level::TRACE) {
+                log_response_body(status.as_u16(), url.as_str(), &body);
+            }
+            return Err(Error::from_status(status, &body));
+        });
+    }
+}
+
+/// Reads the body of a response and deserializes it
+async fn read_json<T: DeserializeOwned>(response: Response) -> Result<T> {
+    let status = response.status();
+    // The whole body is read before parsing it, so that the connection can be reused even if it isn't valid
+    let body = response.bytes().await?;
+    return parse_body::<T>(status, &body);
+}
+
+/// Deserializes the body of a response, falling back to an error with its status if it fails with one (e.g. with an HTML error page from a proxy)
+fn parse_body<T: DeserializeOwned>(status: StatusCode, body: &[u8]) -> Result<T> {
+    // simd-json needs a mutable copy of the body
+    #[cfg(feature = "simd-json")]
+    let result = crate::from_json_slice::<T>(&mut body.to_vec());
+    #[cfg(not(feature = "simd-json"))]
+    let result = serde_json::from_slice::<T>(body).map_err(Error::from);
+
+    return match result {
+        Err(_) if !status.is_success() => Err(Error::from_status(status, body)),
+        result => result,
+    };
+}
+
+/// Logs the raw body of a response (or a server-sent event)
+#[cfg(feature = "debug-logging")]
+pub(crate) fn log_response_body(status: u16, url: &str, body: &[u8]) {
+    match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(json) => tracing::trace!(status, url, body = %redact(json), "OpenAI response"),
+        Err(_) => {
+            let body = String::from_utf8_lossy(body);
+            tracing::trace!(status, url, body = %elide(&body), "OpenAI response")
+        }
+    }
+}
+
+/// Logs the data of a server-sent event
+#[cfg(feature = "debug-logging")]
+pub(crate) fn log_event(data: &[u8]) {
+    match serde_json::from_slice::<serde_json::Value>(data) {
+        Ok(json) => tracing::trace!(data = %redact(json), "OpenAI event"),
+        Err(_) => tracing::trace!(data = %String::from_utf8_lossy(data), "OpenAI event"),
+    }
+}
+
+/// Strings longer than this (in bytes) are elided from the logs if they look like base64 data
+#[cfg(feature = "debug-logging")]
+const MAX_BASE64_LEN: usize = 256;
+
+/// Redacts API keys and elides long base64 strings (e.g. images and audio) from a JSON value
+#[cfg(feature = "debug-logging")]
+pub(crate) fn redact(mut value: serde_json::Value) -> serde_json::Value {
+    fn visit(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(s) => {
+                if s.starts_with("sk-") {
+                    *s = String::from("sk-***");
+                } else {
+                    let elided = elide(s);
+                    if elided.len() != s.len() {
+                        *s = elided.into_owned();
+                    }
+                }
+            }
+            serde_json::Value::Array(values) => values.iter_mut().for_each(visit),
+            serde_json::Value::Object(values) => values.values_mut().for_each(visit),
+            _ => {}
+        }
+    }
+
+    visit(&mut value);
+    return value;
+}
+
+#[cfg(feature = "debug-logging")]
+fn elide(s: &str) -> std::borrow::Cow<'_, str> {
+    let data = match s.split_once(";base64,") {
+        Some((prefix, data)) if prefix.starts_with("data:") => data,
+        _ => s,
+    };
+
+    let is_base64 = data
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'=' | b'-' | b'_'));
+
+    if data.len() > MAX_BASE64_LEN && is_base64 {
+        return std::borrow::Cow::Owned(format!("<{} bytes of base64 elided>", data.len()));
+    }
+    return std::borrow::Cow::Borrowed(s);
+}
+
+/// Without the `tracing` feature, streams aren't traced
+#[cfg(not(feature = "tracing"))]
+pub(crate) enum StreamTrace {}
+
+/// Lifecycle of a stream, reported as `tracing` events: its start, its first chunk and how it finished
+#[cfg(feature = "tracing")]
+pub(crate) struct StreamTrace {
+    endpoint: &'static str,
+    model: String,
+    start: std::time::Instant,
+    chunks: u64,
+    finished: bool,
+}
+
+#[cfg(feature = "tracing")]
+impl StreamTrace {
+    /// Reports the start of a stream, whose request was sent at `start`
+    pub(crate) fn start(endpoint: &'static str, model: &str, start: std::time::Instant) -> Self {
+        tracing::debug!(
+            endpoint,
+            model,
+            latency_ms = start.elapsed().as_millis() as u64,
+            "OpenAI stream started"
+        );
+
+        return Self {
+            endpoint,
+            model: model.to_string(),
+            start,
+            chunks: 0,
+            finished: false,
+        };
+    }
+
+    /// Counts a chunk of the stream, reporting the time to the first one
+    pub(crate) fn chunk(&mut self) {
+        if self.chunks == 0 {
+            tracing::debug!(
+                endpoint = self.endpoint,
+                model = self.model,
+                time_to_first_token_ms = self.start.elapsed().as_millis() as u64,
+                "OpenAI stream first chunk"
+            );
+        }
+        self.chunks += 1;
+    }
+
+    /// Reports the end of the stream (i.e. `done`, `eof`, `error` or `dropped`), unless it has already been reported
+    pub(crate) fn finish(&mut self, reason: &'static str) {
+        if std::mem::replace(&mut self.finished, true) {
+            return;
+        }
+
+        tracing::info!(
+            endpoint = self.endpoint,
+            model = self.model,
+            chunks = self.chunks,
+            duration_ms = self.start.elapsed().as_millis() as u64,
+            reason,
+            "OpenAI stream finished"
+        );
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for StreamTrace {
+    #[inline]
+    fn drop(&mut self) {
+        self.finish("dropped");
+    }
+}
@@ -1,8 +1,12 @@
+use self::info::{KnownModel, ModelInfo};
 use super::error::Result;
-use crate::{error::FallibleResponse, Client};
+use crate::{common::ListResponse, endpoint::Endpoint, Client};
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
+/// Static capabilities, limits and pricing of well-known models.
+pub mod info;
+
 /// OpenAI module. Each module has different capabilities and price points.
 #[derive(Debug, Clone, Deserialize)]
 #[non_exhaustive]
@@ -11,6 +15,7 @@ pub struct Model {
     #[serde(rename = "created", with = "chrono::serde::ts_seconds")]
     pub created_at: DateTime<Utc>,
     pub owned_by: String,
+    #[serde(default)]
     pub permission: Vec<Permission>,
     #[serde(default)]
     pub root: Option<String>,
@@ -40,35 +45,35 @@ impl Model {
     pub async fn get(model: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Model> {
         let models = client
             .as_ref()
-            .get(format!(
-                "https://api.openai.com/v1/models/{}",
-                model.as_ref()
-            ))
-            .send()
-            .await?
-            .json::<FallibleResponse<Model>>()
-            .await?
-            .into_result()?;
+            .get_json::<Model>(
+                client
+                    .as_ref()
+                    .url(Endpoint::Models, format!("/{}", model.as_ref())),
+            )
+            .await?;
 
         return Ok(models);
     }
+
+    /// Returns the static capabilities of the model, if it's known by the library.
+    #[inline]
+    pub fn capabilities(&self) -> Option<&'static ModelInfo> {
+        return ModelInfo::of(&self.id).or_else(|| self.root.as_deref().and_then(ModelInfo::of));
+    }
+
+    /// Returns the [`KnownModel`] this model corresponds to, if any.
+    #[inline]
+    pub fn known(&self) -> Option<KnownModel> {
+        return KnownModel::from_id(&self.id);
+    }
 }
 
 /// Lists the currently available models, and provides basic information about each one such as the owner and availability.
 pub async fn models(client: impl AsRef<Client>) -> Result<Vec<Model>> {
-    #[derive(Debug, Clone, Deserialize)]
-    pub struct Models {
-        data: Vec<Model>,
-    }
-
     let models = client
         .as_ref()
-        .get("https://api.openai.com/v1/models")
-        .send()
-        .await?
-        .json::<FallibleResponse<Models>>()
-        .await?
-        .into_result()?;
+        .get_json::<ListResponse<Model>>(client.as_ref().url(Endpoint::Models, ""))
+        .await?;
 
     return Ok(models.data);
 }
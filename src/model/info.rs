@@ -0,0 +1,289 @@
+use crate::error::{Error, Result};
+use std::{collections::HashMap, sync::RwLock};
+
+/// Prices set at runtime, taking precedence over the ones known by the library
+static PRICING_OVERRIDES: RwLock<Option<HashMap<String, Pricing>>> = RwLock::new(None);
+
+/// Price of a model, in US dollars per 1K tokens
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[non_exhaustive]
+pub struct Pricing {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+/// Static capabilities and limits of a [`KnownModel`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct ModelInfo {
+    /// Maximum number of tokens (prompt plus completion) the model can attend to
+    pub context_window: u64,
+    /// Maximum number of tokens the model can generate in a single response
+    pub max_output_tokens: u64,
+    pub supports_vision: bool,
+    pub supports_tools: bool,
+    pub supports_json_mode: bool,
+    pub pricing: Pricing,
+}
+
+/// Models whose capabilities are known ahead of time by the library
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum KnownModel {
+    Gpt35Turbo,
+    Gpt35TurboInstruct,
+    Gpt4,
+    Gpt4_32k,
+    Gpt4Turbo,
+    Gpt4o,
+    Gpt4oMini,
+    Gpt41,
+    Gpt41Mini,
+    Gpt41Nano,
+    O1,
+    O1Mini,
+    O3Mini,
+    TextDavinci003,
+    TextEmbeddingAda002,
+    TextEmbedding3Small,
+    TextEmbedding3Large,
+}
+
+impl KnownModel {
+    /// Every model known by the library
+    pub const ALL: &'static [KnownModel] = &[
+        Self::Gpt35Turbo,
+        Self::Gpt35TurboInstruct,
+        Self::Gpt4,
+        Self::Gpt4_32k,
+        Self::Gpt4Turbo,
+        Self::Gpt4o,
+        Self::Gpt4oMini,
+        Self::Gpt41,
+        Self::Gpt41Mini,
+        Self::Gpt41Nano,
+        Self::O1,
+        Self::O1Mini,
+        Self::O3Mini,
+        Self::TextDavinci003,
+        Self::TextEmbeddingAda002,
+        Self::TextEmbedding3Small,
+        Self::TextEmbedding3Large,
+    ];
+
+    /// Returns the model's id, as used by the API
+    pub const fn id(self) -> &'static str {
+        match self {
+            Self::Gpt35Turbo => "gpt-3.5-turbo",
+            Self::Gpt35TurboInstruct => "gpt-3.5-turbo-instruct",
+            Self::Gpt4 => "gpt-4",
+            Self::Gpt4_32k => "gpt-4-32k",
+            Self::Gpt4Turbo => "gpt-4-turbo",
+            Self::Gpt4o => "gpt-4o",
+            Self::Gpt4oMini => "gpt-4o-mini",
+            Self::Gpt41 => "gpt-4.1",
+            Self::Gpt41Mini => "gpt-4.1-mini",
+            Self::Gpt41Nano => "gpt-4.1-nano",
+            Self::O1 => "o1",
+            Self::O1Mini => "o1-mini",
+            Self::O3Mini => "o3-mini",
+            Self::TextDavinci003 => "text-davinci-003",
+            Self::TextEmbeddingAda002 => "text-embedding-ada-002",
+            Self::TextEmbedding3Small => "text-embedding-3-small",
+            Self::TextEmbedding3Large => "text-embedding-3-large",
+        }
+    }
+
+    /// Returns the static capabilities of the model
+    pub const fn info(self) -> &'static ModelInfo {
+        const fn info(
+            context_window: u64,
+            max_output_tokens: u64,
+            (supports_vision, supports_tools, supports_json_mode): (bool, bool, bool),
+            (prompt, completion): (f64, f64),
+        ) -> ModelInfo {
+            return ModelInfo {
+                context_window,
+                max_output_tokens,
+                supports_vision,
+                supports_tools,
+                supports_json_mode,
+                pricing: Pricing { prompt, completion },
+            };
+        }
+
+        const GPT_35_TURBO: ModelInfo = info(16_385, 4_096, (false, true, true), (0.0005, 0.0015));
+        const GPT_35_TURBO_INSTRUCT: ModelInfo =
+            info(4_096, 4_096, (false, false, false), (0.0015, 0.002));
+        const GPT_4: ModelInfo = info(8_192, 8_192, (false, true, false), (0.03, 0.06));
+        const GPT_4_32K: ModelInfo = info(32_768, 32_768, (false, true, false), (0.06, 0.12));
+        const GPT_4_TURBO: ModelInfo = info(128_000, 4_096, (true, true, true), (0.01, 0.03));
+        const GPT_4O: ModelInfo = info(128_000, 16_384, (true, true, true), (0.0025, 0.01));
+        const GPT_4O_MINI: ModelInfo = info(128_000, 16_384, (true, true, true), (0.00015, 0.0006));
+        const GPT_41: ModelInfo = info(1_047_576, 32_768, (true, true, true), (0.002, 0.008));
+        const GPT_41_MINI: ModelInfo =
+            info(1_047_576, 32_768, (true, true, true), (0.0004, 0.0016));
+        const GPT_41_NANO: ModelInfo =
+            info(1_047_576, 32_768, (true, true, true), (0.0001, 0.0004));
+        const O1: ModelInfo = info(200_000, 100_000, (true, true, true), (0.015, 0.06));
+        const O1_MINI: ModelInfo = info(128_000, 65_536, (false, false, false), (0.0011, 0.0044));
+        const O3_MINI: ModelInfo = info(200_000, 100_000, (false, true, true), (0.0011, 0.0044));
+        const TEXT_DAVINCI_003: ModelInfo = info(4_097, 4_097, (false, false, false), (0.02, 0.02));
+        const TEXT_EMBEDDING_ADA_002: ModelInfo =
+            info(8_191, 0, (false, false, false), (0.0001, 0.0));
+        const TEXT_EMBEDDING_3_SMALL: ModelInfo =
+            info(8_191, 0, (false, false, false), (0.00002, 0.0));
+        const TEXT_EMBEDDING_3_LARGE: ModelInfo =
+            info(8_191, 0, (false, false, false), (0.00013, 0.0));
+
+        match self {
+            Self::Gpt35Turbo => &GPT_35_TURBO,
+            Self::Gpt35TurboInstruct => &GPT_35_TURBO_INSTRUCT,
+            Self::Gpt4 => &GPT_4,
+            Self::Gpt4_32k => &GPT_4_32K,
+            Self::Gpt4Turbo => &GPT_4_TURBO,
+            Self::Gpt4o => &GPT_4O,
+            Self::Gpt4oMini => &GPT_4O_MINI,
+            Self::Gpt41 => &GPT_41,
+            Self::Gpt41Mini => &GPT_41_MINI,
+            Self::Gpt41Nano => &GPT_41_NANO,
+            Self::O1 => &O1,
+            Self::O1Mini => &O1_MINI,
+            Self::O3Mini => &O3_MINI,
+            Self::TextDavinci003 => &TEXT_DAVINCI_003,
+            Self::TextEmbeddingAda002 => &TEXT_EMBEDDING_ADA_002,
+            Self::TextEmbedding3Small => &TEXT_EMBEDDING_3_SMALL,
+            Self::TextEmbedding3Large => &TEXT_EMBEDDING_3_LARGE,
+        }
+    }
+
+    /// Finds the known model that corresponds to the specified model id.
+    ///
+    /// Dated snapshots (`gpt-4o-2024-08-06`) and fine-tuned models (`ft:gpt-3.5-turbo:org::id`) resolve to their base model.
+    pub fn from_id(id: &str) -> Option<Self> {
+        let id = base_id(id);
+
+        // Legacy GPT-4 Turbo previews don't share a common prefix
+        if id.starts_with("gpt-4-") && id.ends_with("-preview") {
+            return Some(Self::Gpt4Turbo);
+        }
+
+        // Find the longest known id that is a prefix of `id`
+        return Self::ALL
+            .iter()
+            .copied()
+            .filter(|model| match id.strip_prefix(model.id()) {
+                Some(rest) => rest.is_empty() || rest.starts_with('-'),
+                None => false,
+            })
+            .max_by_key(|model| model.id().len());
+    }
+}
+
+impl Pricing {
+    /// Creates a new price, in US dollars per 1K tokens
+    #[inline]
+    pub const fn new(prompt: f64, completion: f64) -> Self {
+        return Self { prompt, completion };
+    }
+
+    /// Returns the price of the model with the specified id, if known.
+    ///
+    /// Prices set with [`Pricing::set`] take precedence over the ones known by the library, and the ones of a base model (`gpt-4o`) apply to its snapshots (`gpt-4o-2024-08-06`) and fine-tunes, unless they have their own.
+    /// Overrides of models unknown by the library apply to their dated snapshots and fine-tunes too.
+    pub fn of(model: impl AsRef<str>) -> Option<Pricing> {
+        let model = model.as_ref();
+        if let Some(overrides) = PRICING_OVERRIDES
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+        {
+            // Overrides of the base model apply to its snapshots and fine-tunes
+            let known = KnownModel::from_id(model).map(KnownModel::id);
+            let base = base_id(model);
+            for id in [Some(model), Some(base), Some(undated_id(base)), known]
+                .into_iter()
+                .flatten()
+            {
+                if let Some(pricing) = overrides.get(id) {
+                    return Some(*pricing);
+                }
+            }
+        }
+
+        return ModelInfo::of(model).map(|info| info.pricing);
+    }
+
+    /// Overrides the price of a model at runtime (e.g. for custom or negotiated pricing, or models unknown by the library)
+    pub fn set(model: impl Into<String>, pricing: Pricing) {
+        PRICING_OVERRIDES
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_or_insert_with(HashMap::new)
+            .insert(model.into(), pricing);
+    }
+
+    /// Removes the runtime override of a model's price, returning it
+    pub fn reset(model: impl AsRef<str>) -> Option<Pricing> {
+        return PRICING_OVERRIDES
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_mut()
+            .and_then(|overrides| overrides.remove(model.as_ref()));
+    }
+
+    /// Returns the cost, in US dollars, of the specified amount of tokens
+    #[inline]
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        return (prompt_tokens as f64 * self.prompt + completion_tokens as f64 * self.completion)
+            / 1000f64;
+    }
+}
+
+impl ModelInfo {
+    /// Returns the static capabilities of the model with the specified id, if known
+    #[inline]
+    pub fn of(model: impl AsRef<str>) -> Option<&'static ModelInfo> {
+        return KnownModel::from_id(model.as_ref()).map(KnownModel::info);
+    }
+
+    /// Checks that `max_tokens` doesn't exceed the model's output limit
+    pub fn check_max_tokens(&self, max_tokens: u64) -> Result<()> {
+        if max_tokens > self.max_output_tokens {
+            return Err(Error::msg(format!(
+                "max_tokens ({max_tokens}) exceeds the model's output limit of {}",
+                self.max_output_tokens
+            )));
+        }
+        return Ok(());
+    }
+}
+
+/// Returns the id of a dated snapshot (`acme-model-2024-09-12`) without its date, or the id itself
+fn undated_id(id: &str) -> &str {
+    // A date is 11 ASCII characters long, so the split is always at a character boundary
+    let split = match id.len().checked_sub("-2024-09-12".len()) {
+        Some(split) => split,
+        None => return id,
+    };
+    let dated = id.as_bytes()[split..]
+        .iter()
+        .enumerate()
+        .all(|(i, c)| match i {
+            0 | 5 | 8 => *c == b'-',
+            _ => c.is_ascii_digit(),
+        });
+    return match dated {
+        true => &id[..split],
+        false => id,
+    };
+}
+
+/// Returns the id of the base model of a fine-tuned model (`ft:gpt-3.5-turbo:org::id`), or the id itself
+fn base_id(id: &str) -> &str {
+    // Fine-tuned models are prefixed by "ft:" and followed by the base model
+    return match id.strip_prefix("ft:") {
+        Some(ft) => ft.split(':').next().unwrap_or(ft),
+        None => id,
+    };
+}
@@ -1,5 +1,10 @@
 use super::error::Result;
-use crate::{error::FallibleResponse, Client};
+use crate::{
+    endpoint::Endpoint,
+    error::FallibleResponse,
+    logging::{RequestBuilderExt, ResponseExt},
+    Client,
+};
 use serde::{Deserialize, Serialize};
 
 /// Given a input text, outputs if the model classifies it as violating OpenAI's content policy.
@@ -48,14 +53,14 @@ impl Moderation {
 
         let resp = client
             .as_ref()
-            .post("https://api.openai.com/v1/moderations")
-            .json(&Body {
+            .post(client.as_ref().url(Endpoint::Moderations, ""))
+            .json_body(&Body {
                 input: input.as_ref(),
                 model,
             })
             .send()
             .await?
-            .json::<FallibleResponse<Self>>()
+            .json_body::<FallibleResponse<Self>>()
             .await?
             .into_result()?;
 
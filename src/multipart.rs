@@ -0,0 +1,244 @@
+use crate::{
+    endpoint::Endpoint,
+    error::{Error, FallibleResponse, Result},
+    logging::ResponseExt,
+    request::RequestBuilder,
+    rt, Client,
+};
+use bytes::Bytes;
+use futures::Future;
+use reqwest::{
+    multipart::{Form, Part},
+    Body,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{borrow::Cow, ffi::OsStr, path::PathBuf};
+
+/// A `multipart/form-data` request to an endpoint of the API, sent with the authentication and error handling of a [`Client`].
+///
+/// The requests of the image, audio and file builders can be turned into one (e.g. with [`TranscriptionBuilder::into_request`](crate::audio::transcription::TranscriptionBuilder::into_request)) to add fields the library doesn't support yet.
+///
+/// Requests are retried following the client's [retry policy](crate::Client::with_retry_policy), unless one of their files is a [`Part`] (see [`FilePart`]).
+#[derive(Debug)]
+pub struct MultipartRequest {
+    endpoint: Endpoint,
+    path: Cow<'static, str>,
+    fields: Vec<(Cow<'static, str>, Field)>,
+}
+
+/// The file of a field of a [`MultipartRequest`].
+///
+/// Files and bytes are sent again if the request is retried, but a [`Part`] (e.g. a stream) can only be sent once, so requests with one aren't retried.
+#[derive(Debug)]
+pub struct FilePart {
+    source: Source,
+    file_name: Option<String>,
+}
+
+#[derive(Debug)]
+enum Source {
+    Bytes(Bytes),
+    File(PathBuf),
+    /// Taken when the request is first sent. Boxed, since it's much larger than the other sources
+    Part(Option<Box<Part>>),
+}
+
+#[derive(Debug)]
+enum Field {
+    Text(Cow<'static, str>),
+    File(FilePart),
+}
+
+impl FilePart {
+    /// Creates a file with the contents of `data`
+    #[inline]
+    pub fn bytes(data: impl Into<Bytes>) -> Self {
+        return Self {
+            source: Source::Bytes(data.into()),
+            file_name: None,
+        };
+    }
+
+    /// Creates a file with the contents of the file at `path`, named like it. The file is opened (again) every time the request is sent.
+    #[inline]
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        let path: PathBuf = path.into();
+        return Self {
+            file_name: path
+                .file_name()
+                .map(OsStr::to_string_lossy)
+                .map(Cow::into_owned),
+            source: Source::File(path),
+        };
+    }
+
+    /// Sets the name of the file, which the API may use to tell its format (e.g. `audio.mp3`)
+    #[inline]
+    pub fn file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Returns a copy of the file to send, or `None` if it can only be sent once and already has been
+    fn take(&mut self) -> Option<Self> {
+        let source = match self.source {
+            Source::Bytes(ref data) => Source::Bytes(data.clone()),
+            Source::File(ref path) => Source::File(path.clone()),
+            Source::Part(ref mut part) => Source::Part(Some(part.take()?)),
+        };
+        return Some(Self {
+            source,
+            file_name: self.file_name.clone(),
+        });
+    }
+
+    async fn into_part(self) -> Result<Part> {
+        let part = match self.source {
+            Source::Bytes(data) => Part::stream(Body::from(data)),
+            Source::File(path) => Part::stream(rt::file_body(path).await?),
+            Source::Part(part) => *part.ok_or_else(|| Error::msg("the part was already sent"))?,
+        };
+        return Ok(match self.file_name {
+            Some(file_name) => part.file_name(file_name),
+            None => part,
+        });
+    }
+}
+
+impl From<Part> for FilePart {
+    #[inline]
+    fn from(part: Part) -> Self {
+        return Self {
+            source: Source::Part(Some(Box::new(part))),
+            file_name: None,
+        };
+    }
+}
+
+impl MultipartRequest {
+    /// Creates an empty request to `path`, relative to the path of `endpoint` (e.g. `/transcriptions` for [`Endpoint::Audio`])
+    #[inline]
+    pub fn new(endpoint: Endpoint, path: impl Into<Cow<'static, str>>) -> Self {
+        return Self {
+            endpoint,
+            path: path.into(),
+            fields: Vec::new(),
+        };
+    }
+
+    /// Adds a text field
+    #[inline]
+    pub fn text(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        value: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        self.fields.push((name.into(), Field::Text(value.into())));
+        self
+    }
+
+    /// Adds a field with the value of `value`, as it would be serialized into JSON. Strings are sent without quotes.
+    pub fn field(
+        self,
+        name: impl Into<Cow<'static, str>>,
+        value: &(impl ?Sized + Serialize),
+    ) -> Result<Self> {
+        let value = match serde_json::to_value(value)? {
+            serde_json::Value::String(x) => x,
+            other => other.to_string(),
+        };
+        return Ok(self.text(name, value));
+    }
+
+    /// Adds a field with the value of `value`, if any
+    #[inline]
+    pub fn field_opt(
+        self,
+        name: impl Into<Cow<'static, str>>,
+        value: Option<&(impl ?Sized + Serialize)>,
+    ) -> Result<Self> {
+        return match value {
+            Some(value) => self.field(name, value),
+            None => Ok(self),
+        };
+    }
+
+    /// Adds a file field
+    #[inline]
+    pub fn part(mut self, name: impl Into<Cow<'static, str>>, part: impl Into<FilePart>) -> Self {
+        self.fields.push((name.into(), Field::File(part.into())));
+        self
+    }
+
+    /// Returns the endpoint the request is sent to
+    #[inline]
+    pub fn endpoint(&self) -> Endpoint {
+        return self.endpoint;
+    }
+
+    /// Sends the request, returning the response if its status is successful
+    pub async fn send(self, client: impl AsRef<Client>) -> Result<reqwest::Response> {
+        return self
+            .send_unchecked(client.as_ref())
+            .await?
+            .check_status()
+            .await;
+    }
+
+    /// Sends the request, parsing its JSON response
+    pub async fn send_json<T: 'static + DeserializeOwned>(
+        self,
+        client: impl AsRef<Client>,
+    ) -> Result<T> {
+        let resp = self
+            .send_unchecked(client.as_ref())
+            .await?
+            .json_body::<FallibleResponse<T>>()
+            .await?
+            .into_result()?;
+
+        return Ok(resp);
+    }
+
+    /// Sends the request, retrying it with the same fields if needed
+    async fn send_unchecked(mut self, client: &Client) -> Result<reqwest::Response> {
+        let url = client.url(self.endpoint, &self.path);
+        let request = self
+            .request(client, &url)
+            .ok_or_else(|| Error::msg("the parts of the multipart request were already sent"))?;
+        return request
+            .await?
+            .send_retrying(|| self.request(client, &url))
+            .await;
+    }
+
+    /// Prepares a request with the fields, or returns `None` if one of its parts can only be sent once and already has been
+    fn request(
+        &mut self,
+        client: &Client,
+        url: &str,
+    ) -> Option<impl 'static + Future<Output = Result<RequestBuilder>>> {
+        let mut fields = Vec::with_capacity(self.fields.len());
+        for (name, field) in self.fields.iter_mut() {
+            fields.push((
+                name.clone(),
+                match field {
+                    Field::Text(value) => Field::Text(value.clone()),
+                    Field::File(file) => Field::File(file.take()?),
+                },
+            ));
+        }
+
+        let request = client.post(url);
+        return Some(async move {
+            let mut form = Form::new();
+            for (name, field) in fields {
+                form = match field {
+                    Field::Text(value) => form.text(name, value),
+                    Field::File(file) => form.part(name, file.into_part().await?),
+                };
+            }
+            return Ok(request.multipart(form));
+        });
+    }
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// Invitations for users to join the organization.
+pub mod invite;
+/// Admin and project API keys.
+pub mod key;
+/// Usage and costs reports of the organization.
+pub mod usage;
+/// Users of the organization.
+pub mod user;
+
+/// Role of a user within an organization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum OrganizationRole {
+    Owner,
+    Reader,
+}
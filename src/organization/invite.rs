@@ -0,0 +1,117 @@
+use super::OrganizationRole;
+use crate::{
+    common::{Delete, ListParams, Paginator},
+    endpoint::Endpoint,
+    error::{FallibleResponse, Result},
+    logging::{RequestBuilderExt, ResponseExt},
+    Client,
+};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+/// An invitation for a user to join the organization.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct Invite {
+    pub id: String,
+    pub email: String,
+    pub role: OrganizationRole,
+    pub status: InviteStatus,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub invited_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub accepted_at: Option<DateTime<Utc>>,
+}
+
+/// Status of an [`Invite`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum InviteStatus {
+    Accepted,
+    Expired,
+    Pending,
+}
+
+impl Invite {
+    /// Creates an invite for a user to the organization. The invite must be accepted by the user before they have access to the organization.
+    pub async fn new(
+        email: impl AsRef<str>,
+        role: OrganizationRole,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        #[derive(Debug, Serialize)]
+        struct Body<'a> {
+            email: &'a str,
+            role: OrganizationRole,
+        }
+
+        let invite = client
+            .as_ref()
+            .post(client.as_ref().url(Endpoint::Organization, "/invites"))
+            .json_body(&Body {
+                email: email.as_ref(),
+                role,
+            })
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(invite);
+    }
+
+    /// Retrieves an invite.
+    pub async fn retreive(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Self> {
+        let invite = client
+            .as_ref()
+            .get_json::<Self>(
+                client
+                    .as_ref()
+                    .url(Endpoint::Organization, format!("/invites/{}", id.as_ref())),
+            )
+            .await?;
+
+        return Ok(invite);
+    }
+}
+
+impl Invite {
+    /// Deletes the invite. If the invite has already been accepted, it cannot be deleted.
+    #[inline]
+    pub async fn delete(self, client: impl AsRef<Client>) -> Result<Delete> {
+        return delete_invite(self.id, client).await;
+    }
+}
+
+/// Deletes an invite. If the invite has already been accepted, it cannot be deleted.
+pub async fn delete_invite(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Delete> {
+    let delete = client
+        .as_ref()
+        .delete(
+            client
+                .as_ref()
+                .url(Endpoint::Organization, format!("/invites/{}", id.as_ref())),
+        )
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Delete>>()
+        .await?
+        .into_result()?;
+
+    return Ok(delete);
+}
+
+/// Returns a list of every invite in the organization, fetched 100 at a time.
+pub async fn invites(client: impl AsRef<Client>) -> Result<Vec<Invite>> {
+    let req = client
+        .as_ref()
+        .get(client.as_ref().url(Endpoint::Organization, "/invites"));
+    return Paginator::new(req, ListParams::new().limit(100))
+        .try_collect()
+        .await;
+}
@@ -0,0 +1,286 @@
+use crate::{
+    common::{Delete, ListParams, Paginator},
+    endpoint::Endpoint,
+    error::{FallibleResponse, Result},
+    logging::{RequestBuilderExt, ResponseExt},
+    Client,
+};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Display};
+
+/// The secret value of an API key.
+///
+/// Its [`Debug`] and [`Display`] implementations only show the start and end of the key, so it doesn't end up in logs by accident.
+#[derive(Clone, PartialEq, Eq, Hash, Deserialize)]
+#[serde(transparent)]
+pub struct ApiKeySecret(String);
+
+/// An admin API key, used to administer the organization.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct AdminApiKey {
+    pub id: String,
+    pub name: String,
+    /// The redacted value of the key, as shown on the dashboard (e.g. `sk-admin...def`)
+    pub redacted_value: String,
+    /// The secret value of the key. Only present on newly created keys.
+    #[serde(default)]
+    pub value: Option<ApiKeySecret>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub owner: Option<ApiKeyOwner>,
+}
+
+/// An API key belonging to a project.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ProjectApiKey {
+    pub id: String,
+    pub name: String,
+    /// The redacted value of the key, as shown on the dashboard (e.g. `sk-proj...def`)
+    pub redacted_value: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default, with = "chrono::serde::ts_seconds_option")]
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub owner: Option<ApiKeyOwner>,
+}
+
+/// An API key created for a project's service account.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ServiceAccountApiKey {
+    pub id: String,
+    pub name: String,
+    pub value: ApiKeySecret,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// The owner of an API key
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ApiKeyOwner {
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+impl ApiKeySecret {
+    /// Returns the unredacted value of the key
+    #[inline]
+    pub fn expose(&self) -> &str {
+        return &self.0;
+    }
+
+    /// Returns the unredacted value of the key
+    #[inline]
+    pub fn into_inner(self) -> String {
+        return self.0;
+    }
+}
+
+impl AdminApiKey {
+    /// Creates a new admin API key. Its secret value is only returned once, on creation.
+    pub async fn new(name: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Self> {
+        #[derive(Debug, Serialize)]
+        struct Body<'a> {
+            name: &'a str,
+        }
+
+        let key = client
+            .as_ref()
+            .post(
+                client
+                    .as_ref()
+                    .url(Endpoint::Organization, "/admin_api_keys"),
+            )
+            .json_body(&Body {
+                name: name.as_ref(),
+            })
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Self>>()
+            .await?
+            .into_result()?;
+
+        return Ok(key);
+    }
+
+    /// Retrieves an admin API key.
+    pub async fn retreive(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Self> {
+        let key = client
+            .as_ref()
+            .get_json::<Self>(client.as_ref().url(
+                Endpoint::Organization,
+                format!("/admin_api_keys/{}", id.as_ref()),
+            ))
+            .await?;
+
+        return Ok(key);
+    }
+}
+
+impl AdminApiKey {
+    /// Deletes the admin API key.
+    #[inline]
+    pub async fn delete(self, client: impl AsRef<Client>) -> Result<Delete> {
+        return delete_admin_api_key(self.id, client).await;
+    }
+}
+
+impl ProjectApiKey {
+    /// Retrieves an API key of the project.
+    pub async fn retreive(
+        project_id: impl AsRef<str>,
+        id: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        let key = client
+            .as_ref()
+            .get_json::<Self>(client.as_ref().url(
+                Endpoint::Organization,
+                format!("/projects/{}/api_keys/{}", project_id.as_ref(), id.as_ref()),
+            ))
+            .await?;
+
+        return Ok(key);
+    }
+}
+
+impl ServiceAccountApiKey {
+    /// Creates a new service account in the project, returning its API key. The secret value of the key is only returned once, on creation.
+    pub async fn new(
+        project_id: impl AsRef<str>,
+        name: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        #[derive(Debug, Serialize)]
+        struct Body<'a> {
+            name: &'a str,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Response {
+            api_key: ServiceAccountApiKey,
+        }
+
+        let account = client
+            .as_ref()
+            .post(client.as_ref().url(
+                Endpoint::Organization,
+                format!("/projects/{}/service_accounts", project_id.as_ref()),
+            ))
+            .json_body(&Body {
+                name: name.as_ref(),
+            })
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Response>>()
+            .await?
+            .into_result()?;
+
+        return Ok(account.api_key);
+    }
+}
+
+/// Deletes an admin API key.
+pub async fn delete_admin_api_key(
+    id: impl AsRef<str>,
+    client: impl AsRef<Client>,
+) -> Result<Delete> {
+    let delete = client
+        .as_ref()
+        .delete(client.as_ref().url(
+            Endpoint::Organization,
+            format!("/admin_api_keys/{}", id.as_ref()),
+        ))
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Delete>>()
+        .await?
+        .into_result()?;
+
+    return Ok(delete);
+}
+
+/// Lists all of the organization's admin API keys, following the pagination cursor until the last one.
+pub async fn admin_api_keys(client: impl AsRef<Client>) -> Result<Vec<AdminApiKey>> {
+    let req = client.as_ref().get(
+        client
+            .as_ref()
+            .url(Endpoint::Organization, "/admin_api_keys"),
+    );
+    return Paginator::new(req, ListParams::new().limit(100))
+        .try_collect()
+        .await;
+}
+
+/// Deletes an API key of a project.
+pub async fn delete_project_api_key(
+    project_id: impl AsRef<str>,
+    id: impl AsRef<str>,
+    client: impl AsRef<Client>,
+) -> Result<Delete> {
+    let delete = client
+        .as_ref()
+        .delete(client.as_ref().url(
+            Endpoint::Organization,
+            format!("/projects/{}/api_keys/{}", project_id.as_ref(), id.as_ref()),
+        ))
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Delete>>()
+        .await?
+        .into_result()?;
+
+    return Ok(delete);
+}
+
+/// Lists all of the API keys of a project, following the pagination cursor until the last one.
+pub async fn project_api_keys(
+    project_id: impl AsRef<str>,
+    client: impl AsRef<Client>,
+) -> Result<Vec<ProjectApiKey>> {
+    let req = client.as_ref().get(client.as_ref().url(
+        Endpoint::Organization,
+        format!("/projects/{}/api_keys", project_id.as_ref()),
+    ));
+    return Paginator::new(req, ListParams::new().limit(100))
+        .try_collect()
+        .await;
+}
+
+impl Debug for ApiKeySecret {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ApiKeySecret")
+            .field(&format_args!("{self}"))
+            .finish()
+    }
+}
+
+impl Display for ApiKeySecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Show at most the first 8 and last 4 characters of the key
+        let chars = self.0.chars().count();
+        if chars <= 12 {
+            return f.write_str("***");
+        }
+
+        let start = self.0.chars().take(8).collect::<String>();
+        let end = self.0.chars().skip(chars - 4).collect::<String>();
+        write!(f, "{start}...{end}")
+    }
+}
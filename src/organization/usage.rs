@@ -0,0 +1,348 @@
+use crate::{
+    endpoint::Endpoint,
+    error::{FallibleResponse, Result},
+    logging::ResponseExt,
+    Client, Str,
+};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// Width of each time bucket in a usage or costs report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum BucketWidth {
+    Minute,
+    Hour,
+    #[default]
+    Day,
+}
+
+/// A time bucket of a usage or costs report
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct Bucket<T> {
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub start_time: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub end_time: DateTime<Utc>,
+    #[serde(default = "Vec::new")]
+    pub results: Vec<T>,
+}
+
+/// Usage of the completions endpoints within a [`Bucket`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct CompletionsUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub input_cached_tokens: u64,
+    #[serde(default)]
+    pub input_audio_tokens: u64,
+    #[serde(default)]
+    pub output_audio_tokens: u64,
+    pub num_model_requests: u64,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub api_key_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub batch: Option<bool>,
+}
+
+/// Usage of the embeddings endpoint within a [`Bucket`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct EmbeddingsUsage {
+    pub input_tokens: u64,
+    pub num_model_requests: u64,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub api_key_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Usage of the images endpoints within a [`Bucket`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ImagesUsage {
+    pub images: u64,
+    pub num_model_requests: u64,
+    /// The endpoint the images were generated by (`image.generation`, `image.edit` or `image.variation`)
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub size: Option<String>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub api_key_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Usage of the speech endpoint within a [`Bucket`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct AudioSpeechesUsage {
+    pub characters: u64,
+    pub num_model_requests: u64,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub api_key_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Usage of the transcription and translation endpoints within a [`Bucket`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct AudioTranscriptionsUsage {
+    pub seconds: u64,
+    pub num_model_requests: u64,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub api_key_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Spend of the organization within a [`Bucket`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct Cost {
+    pub amount: CostAmount,
+    #[serde(default)]
+    pub line_item: Option<String>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+}
+
+/// A monetary amount
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct CostAmount {
+    pub value: f64,
+    pub currency: String,
+}
+
+/// Usage and costs report request builder
+#[derive(Debug, Clone)]
+pub struct UsageBuilder<'a> {
+    start_time: DateTime<Utc>,
+    end_time: Option<DateTime<Utc>>,
+    bucket_width: Option<BucketWidth>,
+    project_ids: Vec<Str<'a>>,
+    models: Vec<Str<'a>>,
+    group_by: Vec<Str<'a>>,
+    limit: Option<u64>,
+}
+
+impl BucketWidth {
+    #[inline]
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Minute => "1m",
+            Self::Hour => "1h",
+            Self::Day => "1d",
+        }
+    }
+}
+
+impl<'a> UsageBuilder<'a> {
+    /// Creates a new report builder, starting at `start_time` (inclusive)
+    pub fn new(start_time: DateTime<Utc>) -> Self {
+        return Self {
+            start_time,
+            end_time: None,
+            bucket_width: None,
+            project_ids: Vec::new(),
+            models: Vec::new(),
+            group_by: Vec::new(),
+            limit: None,
+        };
+    }
+
+    /// End time (exclusive) of the report
+    pub fn end_time(mut self, end_time: DateTime<Utc>) -> Self {
+        self.end_time = Some(end_time);
+        self
+    }
+
+    /// Width of each time bucket in the report. The costs endpoint only supports [`BucketWidth::Day`].
+    pub fn bucket_width(mut self, bucket_width: BucketWidth) -> Self {
+        self.bucket_width = Some(bucket_width);
+        self
+    }
+
+    /// Only return results for this project. May be called multiple times.
+    pub fn project_id(mut self, project_id: impl Into<Str<'a>>) -> Self {
+        self.project_ids.push(project_id.into());
+        self
+    }
+
+    /// Only return results for this model. May be called multiple times. Ignored by the costs endpoint.
+    pub fn model(mut self, model: impl Into<Str<'a>>) -> Self {
+        self.models.push(model.into());
+        self
+    }
+
+    /// Group the results by this field (e.g. `project_id`, `model` or `line_item`). May be called multiple times.
+    pub fn group_by(mut self, field: impl Into<Str<'a>>) -> Self {
+        self.group_by.push(field.into());
+        self
+    }
+
+    /// Number of buckets returned per page. Every page is fetched regardless.
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns the usage of the completions endpoints
+    #[inline]
+    pub async fn completions(
+        &self,
+        client: impl AsRef<Client>,
+    ) -> Result<Vec<Bucket<CompletionsUsage>>> {
+        return self.fetch("usage/completions", true, client).await;
+    }
+
+    /// Returns the usage of the embeddings endpoint
+    #[inline]
+    pub async fn embeddings(
+        &self,
+        client: impl AsRef<Client>,
+    ) -> Result<Vec<Bucket<EmbeddingsUsage>>> {
+        return self.fetch("usage/embeddings", true, client).await;
+    }
+
+    /// Returns the usage of the images endpoints
+    #[inline]
+    pub async fn images(&self, client: impl AsRef<Client>) -> Result<Vec<Bucket<ImagesUsage>>> {
+        return self.fetch("usage/images", true, client).await;
+    }
+
+    /// Returns the usage of the speech endpoint
+    #[inline]
+    pub async fn audio_speeches(
+        &self,
+        client: impl AsRef<Client>,
+    ) -> Result<Vec<Bucket<AudioSpeechesUsage>>> {
+        return self.fetch("usage/audio_speeches", true, client).await;
+    }
+
+    /// Returns the usage of the transcription and translation endpoints
+    #[inline]
+    pub async fn audio_transcriptions(
+        &self,
+        client: impl AsRef<Client>,
+    ) -> Result<Vec<Bucket<AudioTranscriptionsUsage>>> {
+        return self.fetch("usage/audio_transcriptions", true, client).await;
+    }
+
+    /// Returns the spend of the organization
+    #[inline]
+    pub async fn costs(&self, client: impl AsRef<Client>) -> Result<Vec<Bucket<Cost>>> {
+        return self.fetch("costs", false, client).await;
+    }
+
+    async fn fetch<T: 'static + DeserializeOwned>(
+        &self,
+        path: &str,
+        usage: bool,
+        client: impl AsRef<Client>,
+    ) -> Result<Vec<Bucket<T>>> {
+        #[derive(Debug, Deserialize)]
+        struct Response<T> {
+            data: Vec<Bucket<T>>,
+            #[serde(default)]
+            has_more: bool,
+            #[serde(default)]
+            next_page: Option<String>,
+        }
+
+        let mut query = vec![("start_time", self.start_time.timestamp().to_string())];
+        if let Some(end_time) = self.end_time {
+            query.push(("end_time", end_time.timestamp().to_string()));
+        }
+        if let Some(bucket_width) = self.bucket_width {
+            query.push(("bucket_width", bucket_width.as_str().to_string()));
+        }
+        if let Some(limit) = self.limit {
+            query.push(("limit", limit.to_string()));
+        }
+        for project_id in self.project_ids.iter() {
+            query.push(("project_ids[]", project_id.to_string()));
+        }
+        if usage {
+            for model in self.models.iter() {
+                query.push(("models[]", model.to_string()));
+            }
+        }
+        for field in self.group_by.iter() {
+            query.push(("group_by[]", field.to_string()));
+        }
+
+        let client = client.as_ref();
+        let url = client.url(Endpoint::Organization, format!("/{path}"));
+        let mut page = None::<String>;
+        let mut buckets = Vec::new();
+
+        loop {
+            let mut req = client.get(&url).query(&query);
+            if let Some(ref page) = page {
+                req = req.query(&[("page", page)]);
+            }
+
+            let resp = req
+                .send()
+                .await?
+                .json_body::<FallibleResponse<Response<T>>>()
+                .await?
+                .into_result()?;
+
+            buckets.extend(resp.data);
+            match resp.next_page {
+                Some(next) if resp.has_more => page = Some(next),
+                _ => break,
+            }
+        }
+
+        return Ok(buckets);
+    }
+}
+
+impl Cost {
+    /// Returns the total value of the costs across every bucket, assuming they share the same currency
+    #[inline]
+    pub fn total<'a>(buckets: impl IntoIterator<Item = &'a Bucket<Cost>>) -> f64 {
+        return buckets
+            .into_iter()
+            .flat_map(|bucket| bucket.results.iter())
+            .map(|cost| cost.amount.value)
+            .sum();
+    }
+}
+
+/// Creates a new usage and costs report builder, starting at `start_time` (inclusive)
+#[inline]
+pub fn usage<'a>(start_time: DateTime<Utc>) -> UsageBuilder<'a> {
+    return UsageBuilder::new(start_time);
+}
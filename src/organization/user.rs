@@ -0,0 +1,113 @@
+use super::OrganizationRole;
+use crate::{
+    common::{Delete, ListParams, Paginator},
+    endpoint::Endpoint,
+    error::{FallibleResponse, Result},
+    logging::{RequestBuilderExt, ResponseExt},
+    Client,
+};
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+
+/// A member of the organization.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct OrganizationUser {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub role: OrganizationRole,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub added_at: DateTime<Utc>,
+}
+
+impl OrganizationUser {
+    /// Retrieves a user by their identifier.
+    pub async fn retreive(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Self> {
+        let user = client
+            .as_ref()
+            .get_json::<Self>(
+                client
+                    .as_ref()
+                    .url(Endpoint::Organization, format!("/users/{}", id.as_ref())),
+            )
+            .await?;
+
+        return Ok(user);
+    }
+}
+
+impl OrganizationUser {
+    /// Modifies the user's role in the organization.
+    #[inline]
+    pub async fn set_role(
+        self,
+        role: OrganizationRole,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        return modify_user(self.id, role, client).await;
+    }
+
+    /// Removes the user from the organization.
+    #[inline]
+    pub async fn delete(self, client: impl AsRef<Client>) -> Result<Delete> {
+        return delete_user(self.id, client).await;
+    }
+}
+
+/// Modifies a user's role in the organization.
+pub async fn modify_user(
+    id: impl AsRef<str>,
+    role: OrganizationRole,
+    client: impl AsRef<Client>,
+) -> Result<OrganizationUser> {
+    #[derive(Debug, Serialize)]
+    struct Body {
+        role: OrganizationRole,
+    }
+
+    let user = client
+        .as_ref()
+        .post(
+            client
+                .as_ref()
+                .url(Endpoint::Organization, format!("/users/{}", id.as_ref())),
+        )
+        .json_body(&Body { role })
+        .send()
+        .await?
+        .json_body::<FallibleResponse<OrganizationUser>>()
+        .await?
+        .into_result()?;
+
+    return Ok(user);
+}
+
+/// Removes a user from the organization.
+pub async fn delete_user(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Delete> {
+    let delete = client
+        .as_ref()
+        .delete(
+            client
+                .as_ref()
+                .url(Endpoint::Organization, format!("/users/{}", id.as_ref())),
+        )
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Delete>>()
+        .await?
+        .into_result()?;
+
+    return Ok(delete);
+}
+
+/// Lists all of the users in the organization, fetched 100 at a time.
+pub async fn users(client: impl AsRef<Client>) -> Result<Vec<OrganizationUser>> {
+    let req = client
+        .as_ref()
+        .get(client.as_ref().url(Endpoint::Organization, "/users"));
+    return Paginator::new(req, ListParams::new().limit(100))
+        .try_collect()
+        .await;
+}
@@ -0,0 +1,159 @@
+use crate::{
+    chat::{Message, Role},
+    error::Result,
+    Str,
+};
+
+/// Assembles instructions and example input/output pairs into a few-shot prompt.
+///
+/// The prompt can be built either as a [completion](crate::completion) prompt, or as a list of chat [`Message`]s.
+#[derive(Debug, Clone)]
+pub struct FewShot<'a> {
+    instructions: Option<Str<'a>>,
+    examples: Vec<(Str<'a>, Str<'a>)>,
+    input_label: Str<'a>,
+    output_label: Str<'a>,
+    #[cfg(feature = "tokens")]
+    token_budget: Option<(Str<'a>, usize)>,
+}
+
+impl<'a> FewShot<'a> {
+    /// Creates a new few-shot prompt builder with the specified instructions
+    #[inline]
+    pub fn new(instructions: impl Into<Str<'a>>) -> Self {
+        return Self {
+            instructions: Some(instructions.into()),
+            ..Default::default()
+        };
+    }
+
+    /// Appends an example input and its expected output
+    pub fn example(mut self, input: impl Into<Str<'a>>, output: impl Into<Str<'a>>) -> Self {
+        self.examples.push((input.into(), output.into()));
+        self
+    }
+
+    /// Appends example inputs and their expected outputs
+    pub fn examples<I, O, It>(mut self, examples: It) -> Self
+    where
+        I: Into<Str<'a>>,
+        O: Into<Str<'a>>,
+        It: IntoIterator<Item = (I, O)>,
+    {
+        self.examples.extend(
+            examples
+                .into_iter()
+                .map(|(input, output)| (input.into(), output.into())),
+        );
+        self
+    }
+
+    /// Labels that precede the inputs and outputs in a completion prompt. Defaults to `"Input"` and `"Output"`.
+    pub fn labels(mut self, input: impl Into<Str<'a>>, output: impl Into<Str<'a>>) -> Self {
+        self.input_label = input.into();
+        self.output_label = output.into();
+        self
+    }
+
+    /// Maximum number of tokens the prompt may take up with the tokenizer of `model`.
+    ///
+    /// Examples are dropped, starting from the last one, until the prompt fits within the budget. The instructions and the input are always kept.
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokens")))]
+    #[cfg(feature = "tokens")]
+    pub fn token_budget(mut self, model: impl Into<Str<'a>>, max_tokens: usize) -> Self {
+        self.token_budget = Some((model.into(), max_tokens));
+        self
+    }
+
+    /// Builds a completion prompt for `input`, leaving the output for the model to complete
+    pub fn prompt(&self, input: impl AsRef<str>) -> Result<String> {
+        let input = input.as_ref();
+        #[allow(unused_mut)]
+        let mut prompt = self.prompt_with(input, self.examples.len());
+
+        #[cfg(feature = "tokens")]
+        if let Some((ref model, max_tokens)) = self.token_budget {
+            let mut examples = self.examples.len();
+            while examples > 0 && crate::tokens::count_tokens(model, &prompt)? > max_tokens {
+                examples -= 1;
+                prompt = self.prompt_with(input, examples);
+            }
+        }
+
+        return Ok(prompt);
+    }
+
+    /// Builds the chat messages for `input`: a system message with the instructions, a user/assistant message pair for every example, and a user message with the input
+    pub fn messages(&self, input: impl Into<Str<'a>>) -> Result<Vec<Message<'a>>> {
+        let mut messages = Vec::with_capacity(2 + 2 * self.examples.len());
+        if let Some(ref instructions) = self.instructions {
+            messages.push(Message::system(instructions.clone()));
+        }
+        for (example_input, example_output) in self.examples.iter() {
+            messages.push(Message::user(example_input.clone()));
+            messages.push(Message::assistant(example_output.clone()));
+        }
+        messages.push(Message::user(input));
+
+        #[cfg(feature = "tokens")]
+        if let Some((ref model, max_tokens)) = self.token_budget {
+            let first_example = match self.instructions {
+                Some(_) => 1,
+                None => 0,
+            };
+
+            while messages.len() - first_example > 1
+                && crate::tokens::count_message_tokens(model, messages.iter())? > max_tokens
+            {
+                let last_example = messages.len() - 3;
+                messages.drain(last_example..last_example + 2);
+            }
+        }
+
+        return Ok(messages);
+    }
+
+    fn prompt_with(&self, input: &str, examples: usize) -> String {
+        let mut result = String::new();
+        if let Some(ref instructions) = self.instructions {
+            result.push_str(instructions);
+            result.push_str("\n\n");
+        }
+
+        for (example_input, example_output) in self.examples.iter().take(examples) {
+            result.push_str(&format!(
+                "{}: {example_input}\n{}: {example_output}\n\n",
+                self.input_label, self.output_label
+            ));
+        }
+
+        result.push_str(&format!(
+            "{}: {input}\n{}:",
+            self.input_label, self.output_label
+        ));
+        return result;
+    }
+}
+
+impl Default for FewShot<'_> {
+    #[inline]
+    fn default() -> Self {
+        return Self {
+            instructions: None,
+            examples: Vec::new(),
+            input_label: Str::Borrowed("Input"),
+            output_label: Str::Borrowed("Output"),
+            #[cfg(feature = "tokens")]
+            token_budget: None,
+        };
+    }
+}
+
+/// Moves the system messages to the start of the conversation, keeping the order of the rest, so that requests share the longest possible prefix.
+///
+/// OpenAI caches prompts by their prefix, so instructions that stay the same between requests should come before the messages that change.
+/// Hits can be tracked with [`PromptCacheStats`](crate::accounting::PromptCacheStats).
+#[inline]
+pub fn order_for_prompt_cache(messages: &mut [Message<'_>]) {
+    messages.sort_by_key(|message| message.role != Role::System);
+}
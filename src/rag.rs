@@ -0,0 +1,262 @@
+use crate::{
+    chat::{ChatCompletionBuilder, Message},
+    common::Usage,
+    embeddings::Embedding,
+    error::{Error, Result},
+    text::{SplitBy, TextSplitter},
+    Client, Str,
+};
+
+/// Number of chunks [`answer`] grounds its prompt on
+const DEFAULT_TOP_K: usize = 4;
+
+const SYSTEM_PROMPT: &str = "Answer the question using only the numbered sources provided. \
+Cite the sources you use by their number in brackets, like [1]. \
+If the sources don't contain the answer, say that you don't know.";
+
+/// An in-memory index of document chunks and their embeddings, searchable by similarity
+#[derive(Debug, Clone)]
+pub struct EmbeddingIndex {
+    model: String,
+    splitter: TextSplitter,
+    chunks: Vec<Chunk>,
+}
+
+/// A chunk of a document stored in an [`EmbeddingIndex`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Chunk {
+    /// Name of the document the chunk comes from
+    pub source: String,
+    pub text: String,
+    pub embedding: Vec<f64>,
+}
+
+/// A chunk found by [`EmbeddingIndex::search`]
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct SearchResult<'a> {
+    pub chunk: &'a Chunk,
+    /// Cosine similarity between the chunk and the query
+    pub score: f64,
+}
+
+/// Answer generated by [`answer`]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Answer {
+    pub message: Message<'static>,
+    /// The chunks the answer was grounded on, in the order they were numbered in the prompt
+    pub sources: Vec<Source>,
+    /// Usage of the chat completion request
+    pub usage: Option<Usage>,
+}
+
+/// A chunk the [`Answer`] was grounded on
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Source {
+    pub source: String,
+    pub text: String,
+    pub score: f64,
+    /// Whether the answer cites this source
+    pub cited: bool,
+}
+
+impl EmbeddingIndex {
+    /// Creates an empty index, whose embeddings are created by `model`.
+    ///
+    /// By default, documents are split into chunks of paragraphs of up to 2000 characters.
+    #[inline]
+    pub fn new(model: impl Into<String>) -> Self {
+        return Self {
+            model: model.into(),
+            splitter: TextSplitter::new(SplitBy::Paragraphs, 2000),
+            chunks: Vec::new(),
+        };
+    }
+
+    /// Splitter used to split documents into chunks
+    pub fn splitter(mut self, splitter: TextSplitter) -> Self {
+        self.splitter = splitter;
+        self
+    }
+
+    /// Splits a document into chunks, and adds them to the index with their embeddings
+    pub async fn add_document(
+        &mut self,
+        source: impl Into<String>,
+        text: impl AsRef<str>,
+        client: impl AsRef<Client>,
+    ) -> Result<()> {
+        let source = source.into();
+        let client = client.as_ref();
+
+        for text in self.splitter.split(text.as_ref()) {
+            let embedding = self.embed(&text, client).await?;
+            self.chunks.push(Chunk {
+                source: source.clone(),
+                text,
+                embedding,
+            });
+        }
+        return Ok(());
+    }
+
+    /// Splits documents into chunks, and adds them to the index with their embeddings
+    pub async fn add_documents<S, T, I>(
+        &mut self,
+        documents: I,
+        client: impl AsRef<Client>,
+    ) -> Result<()>
+    where
+        S: Into<String>,
+        T: AsRef<str>,
+        I: IntoIterator<Item = (S, T)>,
+    {
+        let client = client.as_ref();
+        for (source, text) in documents {
+            self.add_document(source, text, client).await?;
+        }
+        return Ok(());
+    }
+
+    /// Returns the `k` chunks most similar to `query`
+    pub async fn search(
+        &self,
+        query: impl AsRef<str>,
+        k: usize,
+        client: impl AsRef<Client>,
+    ) -> Result<Vec<SearchResult<'_>>> {
+        let embedding = self.embed(query.as_ref(), client.as_ref()).await?;
+        return Ok(self.search_embedding(&embedding, k));
+    }
+
+    /// Returns the `k` chunks most similar to `embedding`
+    pub fn search_embedding(&self, embedding: &[f64], k: usize) -> Vec<SearchResult<'_>> {
+        let mut results = self
+            .chunks
+            .iter()
+            .map(|chunk| SearchResult {
+                chunk,
+                score: cosine_similarity(&chunk.embedding, embedding),
+            })
+            .collect::<Vec<_>>();
+
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        results.truncate(k);
+        return results;
+    }
+
+    /// Returns the chunks of the index
+    #[inline]
+    pub fn chunks(&self) -> &[Chunk] {
+        return &self.chunks;
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        return self.chunks.len();
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        return self.chunks.is_empty();
+    }
+
+    async fn embed(&self, input: &str, client: &Client) -> Result<Vec<f64>> {
+        return Embedding::new(&self.model, input, client)
+            .await?
+            .data
+            .into_iter()
+            .next()
+            .map(|embedding| embedding.embedding)
+            .ok_or_else(|| Error::msg("the embedding result is empty"));
+    }
+}
+
+/// Answers `question` with `model`, grounding the prompt on the most relevant chunks of `index`.
+pub async fn answer(
+    question: impl AsRef<str>,
+    index: &EmbeddingIndex,
+    model: impl AsRef<str>,
+    client: impl AsRef<Client>,
+) -> Result<Answer> {
+    return answer_with(
+        question,
+        index,
+        ChatCompletionBuilder::new(model.as_ref(), []),
+        DEFAULT_TOP_K,
+        client,
+    )
+    .await;
+}
+
+/// Answers `question` by sending `builder`, with the prompt grounded on the `k` most relevant chunks of `index` appended to its messages.
+pub async fn answer_with(
+    question: impl AsRef<str>,
+    index: &EmbeddingIndex,
+    builder: ChatCompletionBuilder<'_>,
+    k: usize,
+    client: impl AsRef<Client>,
+) -> Result<Answer> {
+    let question = question.as_ref();
+    let client = client.as_ref();
+    let results = index.search(question, k, client).await?;
+
+    let mut prompt = String::from("Sources:\n\n");
+    for (i, result) in results.iter().enumerate() {
+        prompt.push_str(&format!(
+            "[{}] ({})\n{}\n\n",
+            i + 1,
+            result.chunk.source,
+            result.chunk.text
+        ));
+    }
+    prompt.push_str(&format!("Question: {question}"));
+
+    let completion = builder
+        .message(Message::system(SYSTEM_PROMPT))
+        .message(Message::user(Str::Owned(prompt)))
+        .build(client)
+        .await?;
+
+    let usage = completion.usage.clone();
+    let message = completion
+        .into_first()
+        .map(|choice| choice.message)
+        .ok_or_else(|| Error::msg("the chat completion has no choices"))?;
+
+    let sources = results
+        .into_iter()
+        .enumerate()
+        .map(|(i, result)| Source {
+            source: result.chunk.source.clone(),
+            text: result.chunk.text.clone(),
+            score: result.score,
+            cited: message.content.contains(&format!("[{}]", i + 1)),
+        })
+        .collect();
+
+    return Ok(Answer {
+        message,
+        sources,
+        usage,
+    });
+}
+
+fn cosine_similarity(a: &[f64], b: &[f64]) -> f64 {
+    let mut dot = 0f64;
+    let mut norm_a = 0f64;
+    let mut norm_b = 0f64;
+    for (a, b) in a.iter().zip(b) {
+        dot += a * b;
+        norm_a += a * a;
+        norm_b += b * b;
+    }
+
+    return match norm_a * norm_b {
+        norm if norm > 0f64 => dot / norm.sqrt(),
+        _ => 0f64,
+    };
+}
@@ -0,0 +1,214 @@
+use crate::{
+    error::{Error, Result},
+    retry::{retry_after, RetryPolicy},
+    rt,
+    shutdown::Lifecycle,
+    snapshot::Snapshots,
+    Client,
+};
+use futures::Future;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    multipart::Form,
+    Body, Response, ResponseBuilderExt, StatusCode,
+};
+use serde::Serialize;
+use std::{future::ready, sync::Arc, time::Duration};
+
+/// A request to the API, started by [`Client::request`](crate::Client::request).
+///
+/// Unlike a [`reqwest::RequestBuilder`], it's sent through the client's [snapshots](crate::Client::with_snapshots), if any, retried following its [retry policy](crate::Client::with_retry_policy), and aborted when the client is [shut down](crate::Client::shutdown).
+#[derive(Debug)]
+pub struct RequestBuilder {
+    inner: reqwest::RequestBuilder,
+    snapshots: Option<Arc<Snapshots>>,
+    lifecycle: Arc<Lifecycle>,
+    retry: Option<RetryPolicy>,
+}
+
+impl RequestBuilder {
+    #[inline]
+    pub(crate) fn new(inner: reqwest::RequestBuilder, client: &Client) -> Self {
+        return Self {
+            inner,
+            snapshots: client.snapshots.clone(),
+            lifecycle: client.lifecycle.clone(),
+            retry: client.retry,
+        };
+    }
+
+    /// Retries the request following `policy` instead of the client's retry policy. `None` sends it only once.
+    #[inline]
+    pub fn retry_policy(mut self, policy: Option<RetryPolicy>) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Adds a header to the request
+    #[inline]
+    pub fn header<K, V>(mut self, key: K, value: V) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: Into<http::Error>,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: Into<http::Error>,
+    {
+        self.inner = self.inner.header(key, value);
+        self
+    }
+
+    /// Adds a set of headers to the request
+    #[inline]
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.inner = self.inner.headers(headers);
+        self
+    }
+
+    /// Appends query parameters to the URL of the request
+    #[inline]
+    pub fn query<T: Serialize + ?Sized>(mut self, query: &T) -> Self {
+        self.inner = self.inner.query(query);
+        self
+    }
+
+    /// Sets the JSON body of the request
+    #[inline]
+    pub fn json<T: Serialize + ?Sized>(mut self, json: &T) -> Self {
+        self.inner = self.inner.json(json);
+        self
+    }
+
+    /// Sets a multipart body on the request
+    #[inline]
+    pub fn multipart(mut self, multipart: Form) -> Self {
+        self.inner = self.inner.multipart(multipart);
+        self
+    }
+
+    /// Sets the body of the request
+    #[inline]
+    pub fn body<T: Into<Body>>(mut self, body: T) -> Self {
+        self.inner = self.inner.body(body);
+        self
+    }
+
+    /// Timeout of the request, from when it starts connecting until the response body has finished
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.inner = self.inner.timeout(timeout);
+        self
+    }
+
+    /// Clones the request, returning `None` if its body is a stream
+    #[inline]
+    pub fn try_clone(&self) -> Option<Self> {
+        return Some(Self {
+            inner: self.inner.try_clone()?,
+            snapshots: self.snapshots.clone(),
+            lifecycle: self.lifecycle.clone(),
+            retry: self.retry,
+        });
+    }
+
+    /// Sends the request, or replays its snapshot.
+    ///
+    /// The request is sent again whenever it fails with a transient error (e.g. a rate limit or a server error), as long as its retry policy allows it and its body isn't a stream.
+    /// Fails with [`Error::ShutDown`] if the client has been shut down.
+    pub async fn send(self) -> Result<Response> {
+        let template = self.try_clone();
+        return self
+            .send_retrying(|| Some(ready(Ok(template.as_ref()?.try_clone()?))))
+            .await;
+    }
+
+    /// Sends the request, retrying it with the ones built by `next` as long as its retry policy allows it.
+    ///
+    /// The next attempt is prepared before each one is sent, since sending consumes the request. If `next` returns `None`, the request isn't retried.
+    pub(crate) async fn send_retrying<F, Fut>(mut self, mut next: F) -> Result<Response>
+    where
+        F: FnMut() -> Option<Fut>,
+        Fut: Future<Output = Result<Self>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let (retry, policy) = match self.retry {
+                Some(policy) if policy.delay(attempt).is_some() => match next() {
+                    Some(retry) => (retry, policy),
+                    None => return self.send_once().await,
+                },
+                _ => return self.send_once().await,
+            };
+
+            let (delay, _error) = match self.send_once().await {
+                Ok(resp) if is_retryable(resp.status()) => {
+                    let retry_after = retry_after(resp.headers());
+                    let status = resp.status();
+                    let (resp, error) = read_error(resp).await?;
+                    // Rate limits are told apart from exhausted quotas by their error
+                    if status == StatusCode::TOO_MANY_REQUESTS && !error.is_transient() {
+                        return Ok(resp);
+                    }
+                    (retry_after.or(policy.delay(attempt)), error)
+                }
+                Ok(resp) => return Ok(resp),
+                Err(e) if e.is_transient() => (policy.delay(attempt), e),
+                Err(e) => return Err(e),
+            };
+            let delay = policy.clamp(delay.unwrap_or_default());
+
+            #[cfg(feature = "tracing")]
+            tracing::warn!("attempt {attempt} failed ({_error}), retrying in {delay:?}");
+            rt::sleep(delay).await;
+            self = retry.await?;
+            attempt += 1;
+        }
+    }
+
+    async fn send_once(self) -> Result<Response> {
+        let in_flight = self.lifecycle.begin()?;
+        let response = in_flight
+            .run(async {
+                return match self.snapshots {
+                    Some(snapshots) => {
+                        let (client, request) = self.inner.build_split();
+                        snapshots.send(&client, request?).await
+                    }
+                    None => Ok(self.inner.send().await?),
+                };
+            })
+            .await??;
+        return in_flight.track(response);
+    }
+}
+
+/// Whether a response with `status` may be retried: rate limits (429) and server errors (500, 502 and 503).
+///
+/// Gateway timeouts (504) are retried too, since proxies in front of the API answer with them when it's slow to respond, and a second attempt usually succeeds.
+#[inline]
+fn is_retryable(status: StatusCode) -> bool {
+    return matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    );
+}
+
+/// Reads the error of an unsuccessful response, returning it alongside an identical response
+async fn read_error(resp: Response) -> Result<(Response, Error)> {
+    let mut builder = http::Response::builder()
+        .status(resp.status())
+        .version(resp.version())
+        .url(resp.url().clone());
+    for (name, value) in resp.headers() {
+        builder = builder.header(name, value);
+    }
+
+    let status = resp.status();
+    let body = resp.bytes().await?;
+    let error = Error::from_status(status, &body);
+    let resp = builder.body(body).map_err(|e| Error::Other(e.into()))?;
+    return Ok((Response::from(resp), error));
+}
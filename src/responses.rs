@@ -0,0 +1,483 @@
+use self::{
+    input::Input,
+    output::{OutputContent, OutputItem},
+    stream::{ResponseEvent, ResponseStream},
+    tool::Tool,
+};
+use crate::{
+    common::{Delete, PromptTokensDetails, Usage},
+    endpoint::Endpoint,
+    error::{BuilderError, FallibleResponse, Result},
+    logging::{RequestBuilderExt, ResponseExt},
+    model::info::Pricing,
+    Client, Str,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ops::RangeInclusive, time::Duration};
+
+/// Inputs to the model.
+pub mod input;
+/// Items generated by the model.
+pub mod output;
+/// Events emitted while a response is streamed.
+pub mod stream;
+/// Built-in and custom tools the model may call.
+pub mod tool;
+
+/// A model response, generated from text, image or file inputs.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct Response {
+    pub id: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub created_at: DateTime<Utc>,
+    pub model: String,
+    pub status: ResponseStatus,
+    #[serde(default)]
+    pub output: Vec<OutputItem>,
+    #[serde(default)]
+    pub previous_response_id: Option<String>,
+    #[serde(default)]
+    pub error: Option<ResponseError>,
+    #[serde(default)]
+    pub incomplete_details: Option<IncompleteDetails>,
+    #[serde(default)]
+    pub usage: Option<ResponseUsage>,
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Status of a [`Response`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ResponseStatus {
+    Completed,
+    Failed,
+    InProgress,
+    Incomplete,
+    /// A background response waiting to be processed
+    Queued,
+    Cancelled,
+}
+
+/// Error returned when the model fails to generate a [`Response`]
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ResponseError {
+    pub code: String,
+    pub message: String,
+}
+
+/// Details about why a [`Response`] is incomplete
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct IncompleteDetails {
+    pub reason: String,
+}
+
+/// Token usage of a [`Response`], or of an image generated with a stream (see [`ImageEvent::Completed`](crate::image::generate::ImageEvent::Completed))
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct ResponseUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+    /// Breakdown of the input tokens, if returned by the API
+    #[serde(default)]
+    pub input_tokens_details: Option<PromptTokensDetails>,
+}
+
+/// [`Response`] request builder
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseBuilder<'a> {
+    model: Str<'a>,
+    input: Input<'a>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instructions: Option<Str<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous_response_id: Option<Str<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Tool<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    store: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<HashMap<Str<'a>, Str<'a>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<Str<'a>>,
+    /// Parameters not supported by the builder, sent as is
+    #[serde(flatten)]
+    extra: HashMap<Str<'a>, serde_json::Value>,
+}
+
+impl Response {
+    /// Creates a model response for the provided input
+    #[inline]
+    pub async fn new<'a>(
+        model: impl Into<Str<'a>>,
+        input: impl Into<Input<'a>>,
+        client: impl AsRef<Client>,
+    ) -> Result<Self> {
+        return Self::builder(model, input).build(client).await;
+    }
+
+    /// Creates a model response for the provided input, streaming the generation events
+    #[inline]
+    pub async fn new_stream<'a>(
+        model: impl Into<Str<'a>>,
+        input: impl Into<Input<'a>>,
+        client: impl AsRef<Client>,
+    ) -> Result<ResponseStream> {
+        return Self::builder(model, input).build_stream(client).await;
+    }
+
+    /// Creates a new response request builder
+    #[inline]
+    pub fn builder<'a>(
+        model: impl Into<Str<'a>>,
+        input: impl Into<Input<'a>>,
+    ) -> ResponseBuilder<'a> {
+        return ResponseBuilder::new(model, input);
+    }
+
+    /// Retrieves a stored model response.
+    pub async fn retreive(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Self> {
+        let resp = client
+            .as_ref()
+            .get_json::<Self>(
+                client
+                    .as_ref()
+                    .url(Endpoint::Responses, format!("/{}", id.as_ref())),
+            )
+            .await?;
+
+        return Ok(resp);
+    }
+}
+
+impl Response {
+    /// Returns `true` if the response has reached a final state
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        return !matches!(
+            self.status,
+            ResponseStatus::Queued | ResponseStatus::InProgress
+        );
+    }
+
+    /// Fetches the current state of the response.
+    #[inline]
+    pub async fn refresh(&self, client: impl AsRef<Client>) -> Result<Self> {
+        return Self::retreive(&self.id, client).await;
+    }
+
+    /// Polls a background response every `poll_interval` until it reaches a final state.
+    pub async fn wait(self, poll_interval: Duration, client: impl AsRef<Client>) -> Result<Self> {
+        let client = client.as_ref();
+        let mut resp = self;
+        while !resp.is_finished() {
+            crate::rt::sleep(poll_interval).await;
+            resp = resp.refresh(client).await?;
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::info!("Response {} finished as {:?}", resp.id, resp.status);
+        return Ok(resp);
+    }
+
+    /// Cancels a background response.
+    #[inline]
+    pub async fn cancel(self, client: impl AsRef<Client>) -> Result<Self> {
+        return cancel_response(self.id, client).await;
+    }
+
+    /// Returns the text generated by the model, concatenating every output text
+    pub fn output_text(&self) -> String {
+        let mut result = String::new();
+        for item in self.output.iter() {
+            if let OutputItem::Message { content, .. } = item {
+                for content in content.iter() {
+                    if let OutputContent::OutputText { text, .. } = content {
+                        result.push_str(text);
+                    }
+                }
+            }
+        }
+        return result;
+    }
+
+    /// Returns an iterator over the function calls requested by the model, as `(call_id, name, arguments)`
+    pub fn function_calls(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        return self.output.iter().filter_map(|item| match item {
+            OutputItem::FunctionCall {
+                call_id,
+                name,
+                arguments,
+                ..
+            } => Some((call_id.as_str(), name.as_str(), arguments.as_str())),
+            _ => None,
+        });
+    }
+
+    /// Creates a builder for a response that continues this one
+    #[inline]
+    pub fn follow_up<'a>(
+        &'a self,
+        model: impl Into<Str<'a>>,
+        input: impl Into<Input<'a>>,
+    ) -> ResponseBuilder<'a> {
+        return ResponseBuilder::new(model, input).previous_response_id(self.id.as_str());
+    }
+
+    /// Deletes the stored response.
+    #[inline]
+    pub async fn delete(self, client: impl AsRef<Client>) -> Result<Delete> {
+        return delete_response(self.id, client).await;
+    }
+}
+
+impl ResponseUsage {
+    /// Returns the estimated cost of the tokens used, in US dollars.
+    ///
+    /// Returns zero if the model's [`Pricing`] isn't known.
+    #[inline]
+    pub fn estimated_cost(&self, model: impl AsRef<str>) -> f64 {
+        return Pricing::of(model)
+            .map(|pricing| pricing.cost(self.input_tokens, self.output_tokens))
+            .unwrap_or_default();
+    }
+}
+
+impl From<&ResponseUsage> for Usage {
+    #[inline]
+    fn from(value: &ResponseUsage) -> Self {
+        return Self {
+            prompt_tokens: value.input_tokens,
+            completion_tokens: value.output_tokens,
+            total_tokens: value.total_tokens,
+            prompt_tokens_details: value.input_tokens_details.clone(),
+            extra: HashMap::new(),
+        };
+    }
+}
+
+impl<'a> ResponseBuilder<'a> {
+    /// Creates a new response request builder
+    pub fn new(model: impl Into<Str<'a>>, input: impl Into<Input<'a>>) -> Self {
+        return Self {
+            model: model.into(),
+            input: input.into(),
+            stream: false,
+            background: None,
+            instructions: None,
+            previous_response_id: None,
+            tools: Vec::new(),
+            max_output_tokens: None,
+            temperature: None,
+            top_p: None,
+            store: None,
+            metadata: None,
+            user: None,
+            extra: HashMap::new(),
+        };
+    }
+
+    /// Adds a parameter the builder doesn't have yet to the response request
+    #[inline]
+    pub fn extra_param(
+        mut self,
+        key: impl Into<Str<'a>>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
+    /// Inserts a system (or developer) message as the first item in the model's context.
+    pub fn instructions(mut self, instructions: impl Into<Str<'a>>) -> Self {
+        self.instructions = Some(instructions.into());
+        self
+    }
+
+    /// The unique ID of the previous response to the model. Use this to create multi-turn conversations.
+    pub fn previous_response_id(mut self, previous_response_id: impl Into<Str<'a>>) -> Self {
+        self.previous_response_id = Some(previous_response_id.into());
+        self
+    }
+
+    /// Adds a tool the model may call while generating a response.
+    pub fn tool(mut self, tool: Tool<'a>) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// An array of tools the model may call while generating a response.
+    pub fn tools<I: IntoIterator<Item = Tool<'a>>>(mut self, tools: I) -> Self {
+        self.tools.extend(tools);
+        self
+    }
+
+    /// An upper bound for the number of tokens that can be generated for a response, including visible output tokens and reasoning tokens.
+    pub fn max_output_tokens(mut self, max_output_tokens: u64) -> Self {
+        self.max_output_tokens = Some(max_output_tokens);
+        self
+    }
+
+    /// What sampling temperature to use, between 0 and 2. Higher values like 0.8 will make the output more random, while lower values like 0.2 will make it more focused and deterministic.
+    ///
+    /// We generally recommend altering this or `top_p` but not both.
+    pub fn temperature(mut self, temperature: f64) -> Result<Self, BuilderError<Self>> {
+        const RANGE: RangeInclusive<f64> = 0f64..=2f64;
+        return match RANGE.contains(&temperature) {
+            true => {
+                self.temperature = Some(temperature);
+                Ok(self)
+            }
+            false => Err(BuilderError::msg(
+                self,
+                format!("temperature out of range ({RANGE:?})"),
+            )),
+        };
+    }
+
+    /// An alternative to sampling with temperature, called nucleus sampling, where the model considers the results of the tokens with top_p probability mass. So 0.1 means only the tokens comprising the top 10% probability mass are considered.
+    ///
+    /// We generally recommend altering this or `temperature` but not both.
+    pub fn top_p(mut self, top_p: f64) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// Whether to store the generated model response for later retrieval via API.
+    pub fn store(mut self, store: bool) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Set of key-value pairs that can be attached to the response.
+    pub fn metadata<K, V, I>(mut self, metadata: I) -> Self
+    where
+        K: Into<Str<'a>>,
+        V: Into<Str<'a>>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.metadata = Some(
+            metadata
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// A unique identifier representing your end-user, which can help OpenAI to monitor and detect abuse.
+    pub fn user(mut self, user: impl Into<Str<'a>>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Whether to run the response in the background, returning it while it's still [`Queued`](ResponseStatus::Queued).
+    ///
+    /// Background responses can be awaited with [`Response::wait`] and stopped with [`Response::cancel`]. They require [`store`](ResponseBuilder::store) to not be disabled.
+    #[inline]
+    pub fn background(mut self, background: bool) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    /// Sends the request
+    pub async fn build(mut self, client: impl AsRef<Client>) -> Result<Response> {
+        let client = client.as_ref();
+        client.default_user(&mut self.user);
+        client.check_budget(self.max_output_tokens)?;
+        client.throttle(&self.model, self.max_output_tokens).await;
+        let resp = client
+            .post(client.url(Endpoint::Responses, ""))
+            .json_body(&self)
+            .send()
+            .await?
+            .json_body::<FallibleResponse<Response>>()
+            .await?
+            .into_result()?;
+
+        let usage = resp.usage.as_ref().map(Usage::from);
+        client.record("/v1/responses", &self.model, usage.as_ref());
+        return Ok(resp);
+    }
+
+    /// Sends the stream request
+    pub async fn build_stream(mut self, client: impl AsRef<Client>) -> Result<ResponseStream> {
+        let client = client.as_ref();
+        client.default_user(&mut self.user);
+        client.check_budget(self.max_output_tokens)?;
+        client.throttle(&self.model, self.max_output_tokens).await;
+        self.stream = true;
+        #[cfg(feature = "tracing")]
+        let start = std::time::Instant::now();
+        let resp = client
+            .post(client.url(Endpoint::Responses, ""))
+            .json_body(&self)
+            .send()
+            .await?
+            .check_status()
+            .await?;
+
+        let mut stream = ResponseStream::create(resp);
+        stream.inspect =
+            client.record_stream("/v1/responses", &self.model, |event: &ResponseEvent| {
+                event
+                    .final_response()
+                    .and_then(|resp| resp.usage.as_ref())
+                    .map(Usage::from)
+            });
+        #[cfg(feature = "tracing")]
+        stream.trace("/v1/responses", &self.model, start);
+        return Ok(stream);
+    }
+}
+
+/// Deletes a stored model response.
+pub async fn delete_response(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Delete> {
+    let delete = client
+        .as_ref()
+        .delete(
+            client
+                .as_ref()
+                .url(Endpoint::Responses, format!("/{}", id.as_ref())),
+        )
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Delete>>()
+        .await?
+        .into_result()?;
+
+    return Ok(delete);
+}
+
+/// Cancels a response running in the background.
+pub async fn cancel_response(id: impl AsRef<str>, client: impl AsRef<Client>) -> Result<Response> {
+    let resp = client
+        .as_ref()
+        .post(
+            client
+                .as_ref()
+                .url(Endpoint::Responses, format!("/{}/cancel", id.as_ref())),
+        )
+        .send()
+        .await?
+        .json_body::<FallibleResponse<Response>>()
+        .await?
+        .into_result()?;
+
+    return Ok(resp);
+}
@@ -0,0 +1,189 @@
+use crate::{chat::Role, Str};
+use serde::Serialize;
+
+/// Text, image or file inputs to the model, used to generate a response.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Input<'a> {
+    /// A text input to the model, equivalent to a text input with the [`User`](Role::User) role.
+    Text(Str<'a>),
+    /// A list of one or many input items to the model, containing different content types.
+    Items(Vec<InputItem<'a>>),
+}
+
+/// An item of the model's [`Input`]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum InputItem<'a> {
+    /// A message input to the model
+    Message {
+        role: Role,
+        content: MessageContent<'a>,
+    },
+    /// The output of a function tool call
+    FunctionCallOutput { call_id: Str<'a>, output: Str<'a> },
+    /// A reference to an existing item, by id
+    ItemReference { id: Str<'a> },
+}
+
+/// Content of an input message
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum MessageContent<'a> {
+    /// A text input to the model
+    Text(Str<'a>),
+    /// A list of one or many content parts
+    Parts(Vec<InputContent<'a>>),
+}
+
+/// A content part of an input message
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum InputContent<'a> {
+    /// A text input to the model
+    InputText { text: Str<'a> },
+    /// An image input to the model, either by URL or by file id
+    InputImage {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        image_url: Option<Str<'a>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        file_id: Option<Str<'a>>,
+        detail: ImageDetail,
+    },
+    /// A file input to the model
+    InputFile { file_id: Str<'a> },
+}
+
+/// The level of detail used by the model to understand an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageDetail {
+    Low,
+    High,
+    #[default]
+    Auto,
+}
+
+impl<'a> InputItem<'a> {
+    /// Creates a new message
+    #[inline]
+    pub fn message(role: Role, content: impl Into<MessageContent<'a>>) -> Self {
+        return Self::Message {
+            role,
+            content: content.into(),
+        };
+    }
+
+    /// Creates a new message with a role of [`User`](Role::User)
+    #[inline]
+    pub fn user(content: impl Into<MessageContent<'a>>) -> Self {
+        return Self::message(Role::User, content);
+    }
+
+    /// Creates a new message with a role of [`System`](Role::System)
+    #[inline]
+    pub fn system(content: impl Into<MessageContent<'a>>) -> Self {
+        return Self::message(Role::System, content);
+    }
+
+    /// Creates a new message with a role of [`Assistant`](Role::Assistant)
+    #[inline]
+    pub fn assistant(content: impl Into<MessageContent<'a>>) -> Self {
+        return Self::message(Role::Assistant, content);
+    }
+
+    /// Returns the output of a function tool call to the model
+    #[inline]
+    pub fn function_call_output(call_id: impl Into<Str<'a>>, output: impl Into<Str<'a>>) -> Self {
+        return Self::FunctionCallOutput {
+            call_id: call_id.into(),
+            output: output.into(),
+        };
+    }
+
+    /// References an existing item by its id
+    #[inline]
+    pub fn item_reference(id: impl Into<Str<'a>>) -> Self {
+        return Self::ItemReference { id: id.into() };
+    }
+}
+
+impl<'a> InputContent<'a> {
+    /// Creates a new text content part
+    #[inline]
+    pub fn text(text: impl Into<Str<'a>>) -> Self {
+        return Self::InputText { text: text.into() };
+    }
+
+    /// Creates a new image content part from a URL (or base64-encoded data URL)
+    #[inline]
+    pub fn image_url(image_url: impl Into<Str<'a>>, detail: ImageDetail) -> Self {
+        return Self::InputImage {
+            image_url: Some(image_url.into()),
+            file_id: None,
+            detail,
+        };
+    }
+
+    /// Creates a new image content part from an uploaded file
+    #[inline]
+    pub fn image_file(file_id: impl Into<Str<'a>>, detail: ImageDetail) -> Self {
+        return Self::InputImage {
+            image_url: None,
+            file_id: Some(file_id.into()),
+            detail,
+        };
+    }
+
+    /// Creates a new file content part
+    #[inline]
+    pub fn file(file_id: impl Into<Str<'a>>) -> Self {
+        return Self::InputFile {
+            file_id: file_id.into(),
+        };
+    }
+}
+
+impl<'a> From<&'a str> for Input<'a> {
+    #[inline]
+    fn from(value: &'a str) -> Self {
+        Self::Text(Str::Borrowed(value))
+    }
+}
+
+impl From<String> for Input<'_> {
+    #[inline]
+    fn from(value: String) -> Self {
+        Self::Text(Str::Owned(value))
+    }
+}
+
+impl<'a> From<Vec<InputItem<'a>>> for Input<'a> {
+    #[inline]
+    fn from(value: Vec<InputItem<'a>>) -> Self {
+        Self::Items(value)
+    }
+}
+
+impl<'a> From<&'a str> for MessageContent<'a> {
+    #[inline]
+    fn from(value: &'a str) -> Self {
+        Self::Text(Str::Borrowed(value))
+    }
+}
+
+impl From<String> for MessageContent<'_> {
+    #[inline]
+    fn from(value: String) -> Self {
+        Self::Text(Str::Owned(value))
+    }
+}
+
+impl<'a> From<Vec<InputContent<'a>>> for MessageContent<'a> {
+    #[inline]
+    fn from(value: Vec<InputContent<'a>>) -> Self {
+        Self::Parts(value)
+    }
+}
@@ -0,0 +1,93 @@
+use crate::chat::Role;
+use serde::Deserialize;
+
+/// An item generated by the model
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum OutputItem {
+    /// A message generated by the model
+    Message {
+        id: String,
+        role: Role,
+        content: Vec<OutputContent>,
+    },
+    /// A call to a function tool
+    FunctionCall {
+        id: String,
+        call_id: String,
+        name: String,
+        arguments: String,
+    },
+    /// A call to the web search tool
+    WebSearchCall { id: String, status: String },
+    /// A call to the file search tool
+    FileSearchCall {
+        id: String,
+        status: String,
+        #[serde(default)]
+        queries: Vec<String>,
+        #[serde(default)]
+        results: Option<Vec<serde_json::Value>>,
+    },
+    /// A call to the computer use tool
+    ComputerCall {
+        id: String,
+        call_id: String,
+        status: String,
+        action: serde_json::Value,
+    },
+    /// A call to the code interpreter tool. Files it generates are stored in the [`Container`](crate::container::Container) identified by `container_id`.
+    CodeInterpreterCall {
+        id: String,
+        status: String,
+        #[serde(default)]
+        container_id: Option<String>,
+        #[serde(default)]
+        code: Option<String>,
+    },
+    /// The reasoning performed by the model
+    Reasoning {
+        id: String,
+        #[serde(default)]
+        summary: Vec<serde_json::Value>,
+    },
+    /// Item type not supported by the library
+    #[serde(other)]
+    Unknown,
+}
+
+/// Content of an [`OutputItem::Message`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum OutputContent {
+    /// Text generated by the model
+    OutputText {
+        text: String,
+        #[serde(default)]
+        annotations: Vec<serde_json::Value>,
+    },
+    /// The model refused to answer
+    Refusal { refusal: String },
+    /// Content type not supported by the library
+    #[serde(other)]
+    Unknown,
+}
+
+impl OutputItem {
+    /// Returns the id of the item, if known
+    #[inline]
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            OutputItem::Message { id, .. }
+            | OutputItem::FunctionCall { id, .. }
+            | OutputItem::WebSearchCall { id, .. }
+            | OutputItem::FileSearchCall { id, .. }
+            | OutputItem::ComputerCall { id, .. }
+            | OutputItem::CodeInterpreterCall { id, .. }
+            | OutputItem::Reasoning { id, .. } => Some(id),
+            OutputItem::Unknown => None,
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use super::{output::OutputItem, Response};
+use crate::{
+    error::{Error, Result},
+    OpenAiStream,
+};
+use futures::{future::ready, Stream, TryStreamExt};
+use serde::Deserialize;
+
+/// A [`Stream`] of [`ResponseEvent`]s, emitted while a model response is generated
+pub type ResponseStream = OpenAiStream<ResponseEvent>;
+
+/// An event emitted while a model response is generated
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type")]
+#[non_exhaustive]
+pub enum ResponseEvent {
+    /// The response was created
+    #[serde(rename = "response.created")]
+    Created { response: Response },
+    /// The response is in progress
+    #[serde(rename = "response.in_progress")]
+    InProgress { response: Response },
+    /// The response was completed
+    #[serde(rename = "response.completed")]
+    Completed { response: Response },
+    /// The response failed
+    #[serde(rename = "response.failed")]
+    Failed { response: Response },
+    /// The response finished as incomplete
+    #[serde(rename = "response.incomplete")]
+    Incomplete { response: Response },
+
+    /// A new output item was added
+    #[serde(rename = "response.output_item.added")]
+    OutputItemAdded { output_index: u64, item: OutputItem },
+    /// An output item was marked done
+    #[serde(rename = "response.output_item.done")]
+    OutputItemDone { output_index: u64, item: OutputItem },
+
+    /// There is an additional text delta
+    #[serde(rename = "response.output_text.delta")]
+    OutputTextDelta {
+        item_id: String,
+        output_index: u64,
+        content_index: u64,
+        delta: String,
+    },
+    /// A text content is finalized
+    #[serde(rename = "response.output_text.done")]
+    OutputTextDone {
+        item_id: String,
+        output_index: u64,
+        content_index: u64,
+        text: String,
+    },
+    /// There is a partial refusal text
+    #[serde(rename = "response.refusal.delta")]
+    RefusalDelta {
+        item_id: String,
+        output_index: u64,
+        content_index: u64,
+        delta: String,
+    },
+    /// A refusal text is finalized
+    #[serde(rename = "response.refusal.done")]
+    RefusalDone {
+        item_id: String,
+        output_index: u64,
+        content_index: u64,
+        refusal: String,
+    },
+
+    /// There is a partial function call arguments delta
+    #[serde(rename = "response.function_call_arguments.delta")]
+    FunctionCallArgumentsDelta {
+        item_id: String,
+        output_index: u64,
+        delta: String,
+    },
+    /// The function call arguments are finalized
+    #[serde(rename = "response.function_call_arguments.done")]
+    FunctionCallArgumentsDone {
+        item_id: String,
+        output_index: u64,
+        arguments: String,
+    },
+
+    /// A web search call was initiated
+    #[serde(rename = "response.web_search_call.in_progress")]
+    WebSearchCallInProgress { item_id: String, output_index: u64 },
+    /// A web search call is executing
+    #[serde(rename = "response.web_search_call.searching")]
+    WebSearchCallSearching { item_id: String, output_index: u64 },
+    /// A web search call was completed
+    #[serde(rename = "response.web_search_call.completed")]
+    WebSearchCallCompleted { item_id: String, output_index: u64 },
+
+    /// A file search call was initiated
+    #[serde(rename = "response.file_search_call.in_progress")]
+    FileSearchCallInProgress { item_id: String, output_index: u64 },
+    /// A file search call is searching
+    #[serde(rename = "response.file_search_call.searching")]
+    FileSearchCallSearching { item_id: String, output_index: u64 },
+    /// A file search call was completed
+    #[serde(rename = "response.file_search_call.completed")]
+    FileSearchCallCompleted { item_id: String, output_index: u64 },
+
+    /// An error occurred
+    #[serde(rename = "error")]
+    Error {
+        #[serde(default)]
+        code: Option<String>,
+        message: String,
+        #[serde(default)]
+        param: Option<String>,
+    },
+
+    /// Event type not supported by the library
+    #[serde(other)]
+    Unknown,
+}
+
+impl ResponseEvent {
+    /// Returns the final response, if this event marks the end of the stream
+    #[inline]
+    pub fn final_response(&self) -> Option<&Response> {
+        match self {
+            ResponseEvent::Completed { response }
+            | ResponseEvent::Failed { response }
+            | ResponseEvent::Incomplete { response } => Some(response),
+            _ => None,
+        }
+    }
+}
+
+impl ResponseStream {
+    #[inline]
+    pub(super) fn create(resp: reqwest::Response) -> Self {
+        return Self::from_bytes_stream(resp.bytes_stream());
+    }
+}
+
+impl ResponseStream {
+    /// Converts [`Stream<Item = Result<ResponseEvent>>`] into [`Stream<Item = Result<String>>`] with the text deltas generated by the model
+    pub fn into_text_stream(self) -> impl Stream<Item = Result<String>> {
+        return self.try_filter_map(|event| {
+            ready(match event {
+                ResponseEvent::OutputTextDelta { delta, .. } => Ok(Some(delta)),
+                ResponseEvent::Error { message, .. } => Err(Error::msg(message)),
+                _ => Ok(None),
+            })
+        });
+    }
+
+    /// Consumes the stream, returning the final response
+    pub async fn into_response(mut self) -> Result<Response> {
+        while let Some(event) = self.try_next().await? {
+            match event {
+                ResponseEvent::Completed { response }
+                | ResponseEvent::Failed { response }
+                | ResponseEvent::Incomplete { response } => return Ok(response),
+                ResponseEvent::Error { message, .. } => return Err(Error::msg(message)),
+                _ => {}
+            }
+        }
+        return Err(Error::msg("Stream ended before the response was completed"));
+    }
+}
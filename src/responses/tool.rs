@@ -0,0 +1,138 @@
+use crate::Str;
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+/// A tool the model may call while generating a response
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum Tool<'a> {
+    /// Search the Internet for sources related to the prompt.
+    WebSearchPreview {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        search_context_size: Option<SearchContextSize>,
+    },
+    /// Search the contents of uploaded files.
+    FileSearch {
+        vector_store_ids: Vec<Str<'a>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_num_results: Option<u64>,
+    },
+    /// Control a virtual computer.
+    ComputerUsePreview {
+        display_width: u64,
+        display_height: u64,
+        environment: Str<'a>,
+    },
+    /// Write and run Python code inside a sandboxed [`Container`](crate::container::Container).
+    CodeInterpreter { container: ToolContainer<'a> },
+    /// A custom function defined by you.
+    Function {
+        name: Str<'a>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<Str<'a>>,
+        parameters: serde_json::Value,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
+}
+
+/// Container used by the code interpreter tool
+#[derive(Debug, Clone)]
+pub enum ToolContainer<'a> {
+    /// An existing container, identified by its id
+    Id(Str<'a>),
+    /// A new container, created automatically with the specified files
+    Auto { file_ids: Vec<Str<'a>> },
+}
+
+/// Amount of context window space used for a web search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchContextSize {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl<'a> Tool<'a> {
+    /// Creates a web search tool with the default context size
+    #[inline]
+    pub fn web_search() -> Self {
+        return Self::WebSearchPreview {
+            search_context_size: None,
+        };
+    }
+
+    /// Creates a file search tool over the specified vector stores
+    #[inline]
+    pub fn file_search<I: IntoIterator>(vector_store_ids: I) -> Self
+    where
+        I::Item: Into<Str<'a>>,
+    {
+        return Self::FileSearch {
+            vector_store_ids: vector_store_ids.into_iter().map(Into::into).collect(),
+            max_num_results: None,
+        };
+    }
+
+    /// Creates a computer use tool
+    #[inline]
+    pub fn computer_use(
+        display_width: u64,
+        display_height: u64,
+        environment: impl Into<Str<'a>>,
+    ) -> Self {
+        return Self::ComputerUsePreview {
+            display_width,
+            display_height,
+            environment: environment.into(),
+        };
+    }
+
+    /// Creates a code interpreter tool running in the specified container
+    #[inline]
+    pub fn code_interpreter(container: impl Into<ToolContainer<'a>>) -> Self {
+        return Self::CodeInterpreter {
+            container: container.into(),
+        };
+    }
+
+    /// Creates a function tool, whose parameters are described by a JSON schema
+    #[inline]
+    pub fn function(
+        name: impl Into<Str<'a>>,
+        description: Option<Str<'a>>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        return Self::Function {
+            name: name.into(),
+            description,
+            parameters,
+            strict: None,
+        };
+    }
+}
+
+impl<'a, T: Into<Str<'a>>> From<T> for ToolContainer<'a> {
+    #[inline]
+    fn from(id: T) -> Self {
+        Self::Id(id.into())
+    }
+}
+
+impl Serialize for ToolContainer<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Id(id) => serializer.serialize_str(id),
+            Self::Auto { file_ids } => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "auto")?;
+                if !file_ids.is_empty() {
+                    map.serialize_entry("file_ids", file_ids)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::time::Duration;
+
+/// How a [`Client`](crate::Client) retries requests that fail with a transient error (see [`Error::is_transient`](crate::error::Error::is_transient)), waiting longer after each attempt.
+///
+/// Every request is retried (including the start of streams, e.g. [`ChatCompletionBuilder::build_stream`](crate::chat::ChatCompletionBuilder::build_stream)), except for the ones with a streamed body, such as uploads of streams or of [`Part`](reqwest::multipart::Part)s (see [`FilePart`](crate::multipart::FilePart)).
+/// When the API tells how long to wait (with a `Retry-After` header), it's waited instead, up to [`max_delay`](RetryPolicy::max_delay).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: Duration,
+}
+
+impl RetryPolicy {
+    /// Creates a policy of up to 3 attempts, waiting 500 milliseconds before the first retry
+    #[inline]
+    pub fn new() -> Self {
+        return Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            jitter: Duration::ZERO,
+        };
+    }
+
+    /// Maximum number of times a request is sent, counting the first one. A value of 1 (or 0) disables retries.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Time to wait before the first retry, which doubles after each of them
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Maximum time to wait before a retry, even if the API asks to wait longer. Defaults to 60 seconds.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Adds a random delay of up to `jitter` before every retry, so that requests that failed at once aren't retried at once. Defaults to none.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Returns the time to wait before the retry that follows the failed `attempt` (starting at 1), or `None` if the request shouldn't be retried
+    #[inline]
+    pub(crate) fn delay(&self, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        return Some(delay.saturating_add(self.jitter.mul_f64(rand::random::<f64>())));
+    }
+
+    /// Limits `delay` to the maximum delay of the policy
+    #[inline]
+    pub(crate) fn clamp(&self, delay: Duration) -> Duration {
+        return delay.min(self.max_delay);
+    }
+}
+
+impl Default for RetryPolicy {
+    #[inline]
+    fn default() -> Self {
+        return Self::new();
+    }
+}
+
+/// Returns how long the API asks to wait before retrying, either in the `retry-after-ms` header (sent by OpenAI and Azure) or in the standard `Retry-After` one (in seconds, or as an HTTP date)
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name)?.to_str().ok().map(str::trim);
+
+    if let Some(millis) = header("retry-after-ms").and_then(|x| x.parse::<f64>().ok()) {
+        return Duration::try_from_secs_f64(millis / 1000.0).ok();
+    }
+
+    let retry_after = header(RETRY_AFTER.as_str())?;
+    if let Ok(secs) = retry_after.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).ok();
+    }
+    let date = DateTime::parse_from_rfc2822(retry_after).ok()?;
+    return Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    );
+}
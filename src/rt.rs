@@ -0,0 +1,184 @@
+use futures::Future;
+use reqwest::Body;
+use std::{io, path::Path, time::Duration};
+
+#[cfg(not(any(feature = "tokio", feature = "async-std")))]
+compile_error!("either the `tokio` or the `async-std` feature must be enabled");
+
+/// Spawns a task, returning a future that resolves to its output (and resumes its panic, if it panics).
+///
+/// The task keeps running if the future is dropped. If the task is cancelled (e.g. by the runtime shutting down), the future never resolves.
+#[inline]
+pub(crate) fn spawn<F>(fut: F) -> impl Future<Output = F::Output>
+where
+    F: 'static + Send + Future,
+    F::Output: 'static + Send,
+{
+    #[cfg(feature = "tokio")]
+    return join(tokio::spawn(fut));
+    #[cfg(not(feature = "tokio"))]
+    return async_std::task::spawn(fut);
+}
+
+/// Spawns a task, without waiting for it to finish
+#[inline]
+pub(crate) fn detach<F>(fut: F)
+where
+    F: 'static + Send + Future,
+    F::Output: 'static + Send,
+{
+    #[cfg(feature = "tokio")]
+    tokio::spawn(fut);
+    #[cfg(not(feature = "tokio"))]
+    async_std::task::spawn(fut);
+}
+
+/// Runs a blocking function on a thread where blocking is acceptable, returning a future that resolves to its output (and resumes its panic, if it panics)
+#[inline]
+pub(crate) fn spawn_blocking<F, T>(f: F) -> impl Future<Output = T>
+where
+    F: 'static + Send + FnOnce() -> T,
+    T: 'static + Send,
+{
+    #[cfg(feature = "tokio")]
+    return join(tokio::task::spawn_blocking(f));
+    #[cfg(not(feature = "tokio"))]
+    return async_std::task::spawn_blocking(f);
+}
+
+#[cfg(feature = "tokio")]
+async fn join<T>(handle: tokio::task::JoinHandle<T>) -> T {
+    match handle.await {
+        Ok(output) => output,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        // Tasks are only cancelled when the runtime shuts down, which cancels the awaiting task too
+        Err(_) => futures::future::pending().await,
+    }
+}
+
+#[inline]
+pub(crate) async fn sleep(duration: Duration) {
+    #[cfg(feature = "tokio")]
+    tokio::time::sleep(duration).await;
+    #[cfg(not(feature = "tokio"))]
+    async_std::task::sleep(duration).await;
+}
+
+/// Opens a file, to stream its contents as a request body
+pub(crate) async fn file_body(path: impl AsRef<Path>) -> io::Result<Body> {
+    #[cfg(feature = "tokio")]
+    return Ok(Body::from(tokio::fs::File::open(path.as_ref()).await?));
+    #[cfg(not(feature = "tokio"))]
+    return Ok(Body::wrap_stream(reader_stream(
+        async_std::fs::File::open(path.as_ref()).await?,
+    )));
+}
+
+/// Streams the contents of an open file as a request body
+#[inline]
+pub(crate) fn std_file_body(file: std::fs::File) -> Body {
+    #[cfg(feature = "tokio")]
+    return Body::from(tokio::fs::File::from_std(file));
+    #[cfg(not(feature = "tokio"))]
+    return Body::wrap_stream(reader_stream(async_std::fs::File::from(file)));
+}
+
+/// Creates (or truncates) a file to write into
+pub(crate) async fn create_file(
+    path: impl AsRef<Path>,
+) -> io::Result<impl futures::io::AsyncWrite + Unpin> {
+    #[cfg(feature = "tokio")]
+    return Ok(tokio_util::compat::TokioAsyncWriteCompatExt::compat_write(
+        tokio::fs::File::create(path.as_ref()).await?,
+    ));
+    #[cfg(not(feature = "tokio"))]
+    return async_std::fs::File::create(path.as_ref()).await;
+}
+
+/// Creates a file to write into, failing if it already exists
+pub(crate) async fn create_new_file(
+    path: impl AsRef<Path>,
+) -> io::Result<impl futures::io::AsyncWrite + Unpin> {
+    #[cfg(feature = "tokio")]
+    return Ok(tokio_util::compat::TokioAsyncWriteCompatExt::compat_write(
+        tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path.as_ref())
+            .await?,
+    ));
+    #[cfg(not(feature = "tokio"))]
+    return async_std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path.as_ref())
+        .await;
+}
+
+#[inline]
+pub(crate) async fn read(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    #[cfg(feature = "tokio")]
+    return tokio::fs::read(path.as_ref()).await;
+    #[cfg(not(feature = "tokio"))]
+    return async_std::fs::read(path.as_ref()).await;
+}
+
+#[inline]
+pub(crate) async fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    #[cfg(feature = "tokio")]
+    return tokio::fs::write(path.as_ref(), contents).await;
+    #[cfg(not(feature = "tokio"))]
+    return async_std::fs::write(path.as_ref(), contents).await;
+}
+
+#[inline]
+pub(crate) async fn create_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    #[cfg(feature = "tokio")]
+    return tokio::fs::create_dir_all(path.as_ref()).await;
+    #[cfg(not(feature = "tokio"))]
+    return async_std::fs::create_dir_all(path.as_ref()).await;
+}
+
+#[inline]
+pub(crate) async fn rename(from: impl AsRef<Path>, to: impl AsRef<Path>) -> io::Result<()> {
+    #[cfg(feature = "tokio")]
+    return tokio::fs::rename(from.as_ref(), to.as_ref()).await;
+    #[cfg(not(feature = "tokio"))]
+    return async_std::fs::rename(from.as_ref(), to.as_ref()).await;
+}
+
+#[inline]
+pub(crate) async fn remove_file(path: impl AsRef<Path>) -> io::Result<()> {
+    #[cfg(feature = "tokio")]
+    return tokio::fs::remove_file(path.as_ref()).await;
+    #[cfg(not(feature = "tokio"))]
+    return async_std::fs::remove_file(path.as_ref()).await;
+}
+
+#[inline]
+pub(crate) async fn remove_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    #[cfg(feature = "tokio")]
+    return tokio::fs::remove_dir_all(path.as_ref()).await;
+    #[cfg(not(feature = "tokio"))]
+    return async_std::fs::remove_dir_all(path.as_ref()).await;
+}
+
+/// Reads a [`futures::io::AsyncRead`] in chunks
+#[cfg(not(feature = "tokio"))]
+pub(crate) fn reader_stream<R>(reader: R) -> impl futures::Stream<Item = io::Result<bytes::Bytes>>
+where
+    R: futures::io::AsyncRead + Unpin,
+{
+    const CHUNK_SIZE: usize = 8 * 1024;
+
+    return futures::stream::try_unfold(reader, |mut reader| async move {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        let len = futures::io::AsyncReadExt::read(&mut reader, &mut chunk).await?;
+        if len == 0 {
+            return Ok(None);
+        }
+
+        chunk.truncate(len);
+        return Ok(Some((bytes::Bytes::from(chunk), reader)));
+    });
+}
@@ -0,0 +1,163 @@
+use crate::{
+    accounting::UsageReport,
+    error::{Error, Result},
+    rt,
+};
+use futures::{
+    future::{AbortHandle, AbortRegistration, Abortable},
+    Future, StreamExt,
+};
+use reqwest::{Body, Response, ResponseBuilderExt};
+use std::{
+    collections::HashMap,
+    io,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+/// How often [`Lifecycle::shutdown`] checks whether the in-flight requests have finished
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Outcome of [`Client::shutdown`](crate::Client::shutdown)
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Shutdown {
+    /// Number of requests (and response streams) that hadn't finished before the timeout, and were aborted
+    pub aborted: usize,
+    /// Usage of the client, including the requests that finished during the shutdown, if it tracks it
+    pub usage: Option<UsageReport>,
+}
+
+/// Requests (and background tasks) of a client and its clones, which can be shut down
+#[derive(Debug, Default)]
+pub(crate) struct Lifecycle {
+    closed: AtomicBool,
+    next_id: AtomicU64,
+    // Abort handles of the in-flight requests, removed once their responses have been read (or dropped)
+    in_flight: Mutex<HashMap<u64, Vec<AbortHandle>>>,
+    tasks: Mutex<Vec<AbortHandle>>,
+}
+
+/// A request that is in flight until it's dropped
+#[derive(Debug)]
+pub(crate) struct InFlight {
+    id: u64,
+    lifecycle: Arc<Lifecycle>,
+}
+
+impl Lifecycle {
+    /// Starts a request, failing if the client has been shut down
+    pub fn begin(self: &Arc<Self>) -> Result<InFlight> {
+        if self.is_closed() {
+            return Err(Error::ShutDown);
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.lock().insert(id, Vec::new());
+        return Ok(InFlight {
+            id,
+            lifecycle: self.clone(),
+        });
+    }
+
+    #[inline]
+    pub fn is_closed(&self) -> bool {
+        return self.closed.load(Ordering::Acquire);
+    }
+
+    /// Registers a background task (e.g. a health check), which is aborted when the client is shut down
+    pub fn register_task(&self, abort: AbortHandle) {
+        match self.is_closed() {
+            true => abort.abort(),
+            false => self.tasks.lock().unwrap().push(abort),
+        }
+    }
+
+    /// Stops accepting requests and waits up to `timeout` for the in-flight ones to finish, aborting the rest. Returns the number of aborted requests.
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        self.closed.store(true, Ordering::Release);
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+
+        let start = Instant::now();
+        while !self.lock().is_empty() && start.elapsed() < timeout {
+            rt::sleep(POLL_INTERVAL).await;
+        }
+
+        let in_flight = core::mem::take(&mut *self.lock());
+        for abort in in_flight.values().flatten() {
+            abort.abort();
+        }
+        return in_flight.len();
+    }
+
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, HashMap<u64, Vec<AbortHandle>>> {
+        return self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+    }
+}
+
+impl InFlight {
+    /// Runs `fut` until it completes or the client aborts the request
+    pub async fn run<F: Future>(&self, fut: F) -> Result<F::Output> {
+        return Abortable::new(fut, self.registration())
+            .await
+            .map_err(|_| Error::ShutDown);
+    }
+
+    /// Returns `response` with a body that keeps the request in flight until it has been read (or dropped), and fails if the client aborts it
+    pub fn track(self, response: Response) -> Result<Response> {
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version())
+            .url(response.url().clone());
+        for (name, value) in response.headers() {
+            builder = builder.header(name, value);
+        }
+
+        let stream = Abortable::new(response.bytes_stream(), self.registration());
+        let body =
+            futures::stream::unfold((Some(stream), self), |(stream, in_flight)| async move {
+                let mut stream = stream?;
+                return match stream.next().await {
+                    Some(chunk) => {
+                        Some((chunk.map_err(io::Error::other), (Some(stream), in_flight)))
+                    }
+                    None if stream.is_aborted() => Some((
+                        Err(io::Error::new(
+                            io::ErrorKind::Interrupted,
+                            Error::ShutDown.to_string(),
+                        )),
+                        (None, in_flight),
+                    )),
+                    None => None,
+                };
+            });
+
+        let response = builder
+            .body(Body::wrap_stream(body))
+            .map_err(|e| Error::Other(e.into()))?;
+        return Ok(Response::from(response));
+    }
+
+    fn registration(&self) -> AbortRegistration {
+        let (abort, registration) = AbortHandle::new_pair();
+        match self.lifecycle.lock().get_mut(&self.id) {
+            Some(handles) => handles.push(abort),
+            // The request has already been aborted
+            None => abort.abort(),
+        }
+        return registration;
+    }
+}
+
+impl Drop for InFlight {
+    #[inline]
+    fn drop(&mut self) {
+        self.lifecycle.lock().remove(&self.id);
+    }
+}
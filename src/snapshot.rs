@@ -0,0 +1,267 @@
+use crate::{
+    error::{Error, Result},
+    rt,
+};
+use base64::Engine;
+use bytes::{Bytes, BytesMut};
+use futures::StreamExt;
+use reqwest::{Body, Request, Response};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+/// What a client does with the snapshots of its requests. See [`Client::with_snapshots`](crate::Client::with_snapshots)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SnapshotMode {
+    /// Sends every request, saving a snapshot of it and its response
+    Record,
+    /// Replays the snapshot of every request, failing the requests that have none
+    Replay,
+    /// Replays the snapshots of the requests that have one, recording the rest
+    Auto,
+}
+
+/// Snapshots of a client's requests and responses, stored as JSON files in a directory
+#[derive(Debug)]
+pub(crate) struct Snapshots {
+    dir: PathBuf,
+    mode: SnapshotMode,
+    // Number of requests with the same key sent so far, so that repeated requests replay their responses in order
+    counters: Mutex<HashMap<String, usize>>,
+}
+
+/// Body of a response being recorded, which is saved with its snapshot once it has been read to its end
+struct Recording {
+    snapshot: Option<Snapshot>,
+    body: BytesMut,
+    path: PathBuf,
+    event_stream: bool,
+}
+
+/// A request and its response, as stored in the snapshot directory
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    method: String,
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request: Option<serde_json::Value>,
+    status: u16,
+    headers: Vec<(String, String)>,
+    /// Body of the response, if it's valid UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    /// Base64-encoded body of the response, if it isn't valid UTF-8
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+impl Snapshots {
+    #[inline]
+    pub fn new(dir: PathBuf, mode: SnapshotMode) -> Self {
+        return Self {
+            dir,
+            mode,
+            counters: Mutex::default(),
+        };
+    }
+
+    /// Sends `request` with `client`, or replays its snapshot
+    pub async fn send(&self, client: &reqwest::Client, request: Request) -> Result<Response> {
+        let path = match request.url().query() {
+            Some(query) => format!("{}?{query}", request.url().path()),
+            None => request.url().path().to_string(),
+        };
+        let body = request.body().and_then(Body::as_bytes);
+        let file = self.file_name(request.method().as_str(), &path, body);
+
+        let snapshot_path = self.dir.join(&file);
+        if self.mode != SnapshotMode::Record {
+            match rt::read(&snapshot_path).await {
+                Ok(snapshot) => {
+                    return serde_json::from_slice::<Snapshot>(&snapshot)?.into_response();
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    if self.mode == SnapshotMode::Replay {
+                        return Err(Error::msg(format!(
+                            "No snapshot of {} {path} in '{}' (expected '{file}')",
+                            request.method(),
+                            self.dir.display()
+                        )));
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let snapshot = Snapshot {
+            method: request.method().to_string(),
+            request: body.map(|body| match serde_json::from_slice(body) {
+                Ok(json) => json,
+                Err(_) => serde_json::Value::String(String::from_utf8_lossy(body).into_owned()),
+            }),
+            path,
+            status: 0,
+            headers: Vec::new(),
+            body: None,
+            body_base64: None,
+        };
+        rt::create_dir_all(&self.dir).await?;
+        return snapshot.record(client.execute(request).await?, snapshot_path);
+    }
+
+    /// Returns the name of the snapshot of the next request with the specified method, path and body
+    fn file_name(&self, method: &str, path: &str, body: Option<&[u8]>) -> String {
+        // FNV-1a, which (unlike the standard library's hasher) is stable across Rust versions
+        let mut hash = 0xcbf29ce484222325u64;
+        for byte in [
+            method.as_bytes(),
+            b" ",
+            path.as_bytes(),
+            b"\n",
+            body.unwrap_or_default(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+
+        let slug = path
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        let key = format!("{}-{slug}-{hash:016x}", method.to_ascii_lowercase());
+
+        let mut counters = self.counters.lock().unwrap_or_else(PoisonError::into_inner);
+        let counter = counters.entry(key.clone()).or_default();
+        *counter += 1;
+        return format!("{key}-{}.json", *counter);
+    }
+}
+
+impl Snapshot {
+    /// Returns a response with the status, headers and body of the snapshot
+    fn into_response(self) -> Result<Response> {
+        let body = match (self.body, self.body_base64) {
+            (Some(body), _) => Bytes::from(body),
+            (None, Some(body)) => Bytes::from(
+                base64::engine::general_purpose::STANDARD
+                    .decode(body)
+                    .map_err(|e| Error::Other(e.into()))?,
+            ),
+            (None, None) => Bytes::new(),
+        };
+
+        let mut response = http::Response::builder().status(self.status);
+        for (name, value) in self.headers {
+            response = response.header(name, value);
+        }
+        let response = response.body(body).map_err(|e| Error::Other(e.into()))?;
+        return Ok(Response::from(response));
+    }
+
+    /// Returns `response` with a body that saves the snapshot at `path` once it has been read to its end
+    fn record(mut self, response: Response, path: PathBuf) -> Result<Response> {
+        self.status = response.status().as_u16();
+        self.headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        for (name, value) in response.headers() {
+            builder = builder.header(name, value);
+        }
+
+        let recording = Recording {
+            snapshot: Some(self),
+            body: BytesMut::new(),
+            path,
+            event_stream: match response.headers().get(reqwest::header::CONTENT_TYPE) {
+                Some(content_type) => content_type.as_bytes().starts_with(b"text/event-stream"),
+                None => false,
+            },
+        };
+        // Responses whose body isn't read to its end (e.g. because it fails, or is dropped) aren't recorded
+        let body = futures::stream::unfold(
+            (response.bytes_stream(), recording),
+            |(mut stream, mut recording)| async move {
+                let chunk = match stream.next().await {
+                    Some(chunk) => chunk,
+                    None => {
+                        recording.save().await;
+                        return None;
+                    }
+                };
+                match chunk {
+                    Ok(ref chunk) => {
+                        recording.body.extend_from_slice(chunk);
+                        if recording.is_done() {
+                            recording.save().await;
+                        }
+                    }
+                    Err(_) => recording.snapshot = None,
+                }
+                return Some((chunk, (stream, recording)));
+            },
+        );
+
+        let response = builder
+            .body(Body::wrap_stream(body))
+            .map_err(|e| Error::Other(e.into()))?;
+        return Ok(Response::from(response));
+    }
+
+    fn save(mut self, body: &[u8], path: &Path) -> Result<()> {
+        match std::str::from_utf8(body) {
+            Ok(body) => self.body = Some(body.to_string()),
+            Err(_) => {
+                self.body_base64 = Some(base64::engine::general_purpose::STANDARD.encode(body))
+            }
+        }
+
+        std::fs::write(path, serde_json::to_vec_pretty(&self)?)?;
+        return Ok(());
+    }
+}
+
+impl Recording {
+    /// Returns `true` if the body is an event stream that has ended with `[DONE]`, after which streams stop being read
+    fn is_done(&self) -> bool {
+        if !self.event_stream || !self.body.ends_with(b"\n") {
+            return false;
+        }
+        let last_line = crate::trim_ascii_end(&self.body)
+            .rsplit(|&b| b == b'\n')
+            .next();
+        return match last_line.and_then(crate::sse_data) {
+            Some(data) => crate::is_done(&data),
+            None => false,
+        };
+    }
+
+    /// Saves the snapshot with the body read so far, unless it has already been saved
+    async fn save(&mut self) {
+        let snapshot = match self.snapshot.take() {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+        let (body, path) = (self.body.split().freeze(), self.path.clone());
+        if let Err(_e) = rt::spawn_blocking(move || snapshot.save(&body, &path)).await {
+            #[cfg(feature = "tracing")]
+            tracing::error!("Error saving snapshot '{}': {_e}", self.path.display());
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use crate::{error::Result, Client};
+use serde::Serialize;
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+/// API key sent by the clients of a [`MockOpenAi`]
+pub const TEST_API_KEY: &str = "sk-test";
+/// Model reported by the fixtures
+pub const TEST_MODEL: &str = "gpt-4o-mini";
+/// Creation timestamp reported by the fixtures
+const TEST_CREATED: i64 = 1700000000;
+
+/// A mock OpenAI API server, for tests that don't need an API key or network access.
+///
+/// Mocks for its endpoints can be mounted with the helpers of this struct, or with the [`Mock`]s returned by this module's functions.
+#[derive(Debug)]
+pub struct MockOpenAi {
+    server: MockServer,
+}
+
+impl MockOpenAi {
+    /// Starts a new mock server, listening on a random local port
+    #[inline]
+    pub async fn start() -> Self {
+        return Self {
+            server: MockServer::start().await,
+        };
+    }
+
+    /// Returns the base URL of the mock API
+    #[inline]
+    pub fn uri(&self) -> String {
+        return self.server.uri();
+    }
+
+    /// Returns the underlying [`MockServer`]
+    #[inline]
+    pub fn server(&self) -> &MockServer {
+        return &self.server;
+    }
+
+    /// Creates a client that sends its requests to the mock server
+    pub fn client(&self) -> Result<Client> {
+        let client = Client::from_builder(reqwest::ClientBuilder::new(), Some(TEST_API_KEY), None)?;
+        return Ok(client.with_base_url(self.uri()));
+    }
+
+    /// Mounts a mock on the server
+    #[inline]
+    pub async fn mount(&self, mock: Mock) {
+        mock.mount(&self.server).await
+    }
+
+    /// Responds to chat completion requests with an assistant message with the specified content
+    #[inline]
+    pub async fn mock_chat(&self, content: &str) {
+        self.mount(chat_completion_mock(content)).await
+    }
+
+    /// Responds to completion requests with the specified text
+    #[inline]
+    pub async fn mock_completion(&self, text: &str) {
+        self.mount(completion_mock(text)).await
+    }
+
+    /// Responds to embedding requests with the specified vector
+    #[inline]
+    pub async fn mock_embeddings(&self, embedding: &[f64]) {
+        self.mount(embeddings_mock(embedding)).await
+    }
+
+    /// Returns the JSON bodies of the requests received by the server to `endpoint`, in the order they were received
+    pub async fn received_json(&self, endpoint: &str) -> Vec<Value> {
+        return self
+            .server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|request| request.url.path() == endpoint)
+            .filter_map(|request| serde_json::from_slice(&request.body).ok())
+            .collect();
+    }
+}
+
+/// Returns a mock that responds to `method` requests to `endpoint` (e.g. `/chat/completions`) with a JSON body
+pub fn json_mock(method_name: &str, endpoint: &str, body: impl Serialize) -> Mock {
+    return Mock::given(method(method_name))
+        .and(path(endpoint))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body));
+}
+
+/// Returns a mock that responds to `method` requests to `endpoint` with a server-sent event stream of `events`, terminated with `data: [DONE]`
+pub fn sse_mock<T: Serialize>(
+    method_name: &str,
+    endpoint: &str,
+    events: impl IntoIterator<Item = T>,
+) -> Mock {
+    return Mock::given(method(method_name))
+        .and(path(endpoint))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(sse_body(events), "text/event-stream"),
+        );
+}
+
+/// Returns a mock that responds to `method` requests to `endpoint` with an API error
+pub fn error_mock(method_name: &str, endpoint: &str, status: u16, message: &str) -> Mock {
+    return Mock::given(method(method_name))
+        .and(path(endpoint))
+        .respond_with(ResponseTemplate::new(status).set_body_json(error(message)));
+}
+
+/// Returns a mock that responds to chat completion requests with an assistant message with the specified content
+#[inline]
+pub fn chat_completion_mock(content: &str) -> Mock {
+    return json_mock("POST", "/chat/completions", chat_completion(content));
+}
+
+/// Returns a mock that responds to completion requests with the specified text
+#[inline]
+pub fn completion_mock(text: &str) -> Mock {
+    return json_mock("POST", "/completions", completion(text));
+}
+
+/// Returns a mock that responds to completion stream requests with a chunk for every one of `texts`
+#[inline]
+pub fn completion_stream_mock<'a>(texts: impl IntoIterator<Item = &'a str>) -> Mock {
+    return sse_mock("POST", "/completions", texts.into_iter().map(completion));
+}
+
+/// Returns a mock that responds to chat completion stream requests with a chunk for every one of `contents`
+#[inline]
+pub fn chat_completion_stream_mock<'a>(contents: impl IntoIterator<Item = &'a str>) -> Mock {
+    return sse_mock(
+        "POST",
+        "/chat/completions",
+        contents.into_iter().map(chat_completion_chunk),
+    );
+}
+
+/// Returns a mock that responds to embedding requests with the specified vector
+#[inline]
+pub fn embeddings_mock(embedding: &[f64]) -> Mock {
+    return json_mock("POST", "/embeddings", embeddings([embedding]));
+}
+
+/// Builds the body of a chat completion with an assistant message with the specified content
+pub fn chat_completion(content: &str) -> Value {
+    return json!({
+        "id": "chatcmpl-test",
+        "object": "chat.completion",
+        "created": TEST_CREATED,
+        "model": TEST_MODEL,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": content },
+            "finish_reason": "stop"
+        }],
+        "usage": usage(10, 10)
+    });
+}
+
+/// Builds a chunk of a chat completion stream, whose delta has the specified content
+pub fn chat_completion_chunk(content: &str) -> Value {
+    return json!({
+        "id": "chatcmpl-test",
+        "object": "chat.completion.chunk",
+        "created": TEST_CREATED,
+        "model": TEST_MODEL,
+        "choices": [{
+            "index": 0,
+            "delta": { "content": content },
+            "finish_reason": null
+        }]
+    });
+}
+
+/// Builds the body of a completion with the specified text
+pub fn completion(text: &str) -> Value {
+    return json!({
+        "id": "cmpl-test",
+        "object": "text_completion",
+        "created": TEST_CREATED,
+        "model": TEST_MODEL,
+        "choices": [{
+            "text": text,
+            "index": 0,
+            "logprobs": null,
+            "finish_reason": "stop"
+        }],
+        "usage": usage(10, 10)
+    });
+}
+
+/// Builds the body of an embedding result with the specified vectors
+pub fn embeddings<'a>(embeddings: impl IntoIterator<Item = &'a [f64]>) -> Value {
+    let data = embeddings
+        .into_iter()
+        .enumerate()
+        .map(|(index, embedding)| {
+            json!({
+                "object": "embedding",
+                "index": index,
+                "embedding": embedding
+            })
+        })
+        .collect::<Vec<_>>();
+
+    return json!({
+        "object": "list",
+        "data": data,
+        "model": "text-embedding-3-small",
+        "usage": usage(10, 0)
+    });
+}
+
+/// Builds the body of a model response with an assistant message with the specified text
+pub fn response(text: &str) -> Value {
+    return json!({
+        "id": "resp-test",
+        "object": "response",
+        "created_at": TEST_CREATED,
+        "model": TEST_MODEL,
+        "status": "completed",
+        "output": [{
+            "type": "message",
+            "id": "msg-test",
+            "role": "assistant",
+            "content": [{ "type": "output_text", "text": text, "annotations": [] }]
+        }],
+        "usage": {
+            "input_tokens": 10,
+            "output_tokens": 10,
+            "total_tokens": 20
+        }
+    });
+}
+
+/// Builds the body of a file object
+pub fn file(id: &str, filename: &str, purpose: &str) -> Value {
+    return json!({
+        "id": id,
+        "object": "file",
+        "bytes": 128,
+        "created_at": TEST_CREATED,
+        "filename": filename,
+        "purpose": purpose
+    });
+}
+
+/// Builds the body of a model object
+pub fn model(id: &str) -> Value {
+    return json!({
+        "id": id,
+        "object": "model",
+        "created": TEST_CREATED,
+        "owned_by": "openai"
+    });
+}
+
+/// Builds the body of a list of objects, as returned by list endpoints
+pub fn list<T: Serialize>(data: impl IntoIterator<Item = T>) -> Value {
+    return json!({
+        "object": "list",
+        "data": data.into_iter().collect::<Vec<_>>(),
+        "has_more": false
+    });
+}
+
+/// Builds the body of one page of a list, whose cursors are the ids of its first and last objects
+pub fn page<T: Serialize>(data: impl IntoIterator<Item = T>, has_more: bool) -> Value {
+    let data = data
+        .into_iter()
+        .map(|x| serde_json::to_value(x).unwrap())
+        .collect::<Vec<_>>();
+    return json!({
+        "object": "list",
+        "first_id": data.first().map(|x| x["id"].clone()),
+        "last_id": data.last().map(|x| x["id"].clone()),
+        "data": data,
+        "has_more": has_more
+    });
+}
+
+/// Builds the body of a deletion
+pub fn deleted(id: &str, object: &str) -> Value {
+    return json!({
+        "id": id,
+        "object": object,
+        "deleted": true
+    });
+}
+
+/// Builds the body of an API error
+pub fn error(message: &str) -> Value {
+    return json!({
+        "error": {
+            "message": message,
+            "type": "invalid_request_error",
+            "param": null,
+            "code": null
+        }
+    });
+}
+
+/// Builds a usage object
+pub fn usage(prompt_tokens: u64, completion_tokens: u64) -> Value {
+    return json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens
+    });
+}
+
+/// Builds a server-sent event stream body, with a `data` event for every one of `events` followed by `data: [DONE]`
+pub fn sse_body<T: Serialize>(events: impl IntoIterator<Item = T>) -> String {
+    let mut result = String::new();
+    for event in events {
+        let event = serde_json::to_string(&event).expect("event should serialize into JSON");
+        result.push_str(&format!("data: {event}\n\n"));
+    }
+    result.push_str("data: [DONE]\n\n");
+    return result;
+}
@@ -0,0 +1,196 @@
+/// Unit a [`TextSplitter`] splits text by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum SplitBy {
+    /// See [`sentences`]
+    Sentences,
+    /// See [`paragraphs`]
+    #[default]
+    Paragraphs,
+    /// See [`markdown_sections`]
+    MarkdownSections,
+}
+
+/// Splits text into chunks of at most a maximum length, without breaking its sentences, paragraphs or sections apart.
+///
+/// Useful to prepare long texts for embeddings, summarization or moderation, which have limits on their input size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextSplitter {
+    by: SplitBy,
+    max_len: usize,
+    overlap: usize,
+}
+
+impl TextSplitter {
+    /// Creates a new splitter, with chunks of at most `max_len` characters
+    #[inline]
+    pub fn new(by: SplitBy, max_len: usize) -> Self {
+        return Self {
+            by,
+            max_len,
+            overlap: 0,
+        };
+    }
+
+    /// Number of units (sentences, paragraphs or sections) at the end of a chunk that are repeated at the start of the next one. Defaults to 0.
+    pub fn overlap(mut self, overlap: usize) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    /// Splits `text` into chunks.
+    ///
+    /// Units longer than the maximum length are returned as a chunk of their own.
+    pub fn split(&self, text: &str) -> Vec<String> {
+        let units = match self.by {
+            SplitBy::Sentences => sentences(text).collect::<Vec<_>>(),
+            SplitBy::Paragraphs => paragraphs(text).collect::<Vec<_>>(),
+            SplitBy::MarkdownSections => markdown_sections(text).collect::<Vec<_>>(),
+        };
+        let separator = match self.by {
+            SplitBy::Sentences => " ",
+            SplitBy::Paragraphs | SplitBy::MarkdownSections => "\n\n",
+        };
+
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        let mut prev_end = 0;
+        while start < units.len() {
+            let mut end = start;
+            let mut len = 0;
+            while end < units.len() {
+                let unit_len =
+                    units[end].chars().count() + if end > start { separator.len() } else { 0 };
+                if end > start && len + unit_len > self.max_len {
+                    break;
+                }
+                len += unit_len;
+                end += 1;
+            }
+
+            // Drop the overlap if it leaves no room for new units
+            if end <= prev_end {
+                start = prev_end;
+                continue;
+            }
+
+            chunks.push(units[start..end].join(separator));
+            if end == units.len() {
+                break;
+            }
+            start = usize::max(start + 1, end.saturating_sub(self.overlap));
+            prev_end = end;
+        }
+
+        return chunks;
+    }
+}
+
+/// Returns an iterator over the sentences of `text`.
+///
+/// A sentence ends with a `.`, `!` or `?` (optionally followed by closing quotes or brackets) that's followed by whitespace, or at a blank line.
+pub fn sentences(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    return std::iter::from_fn(move || loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+
+        let end = sentence_end(rest).unwrap_or(rest.len());
+        let (sentence, next) = rest.split_at(end);
+        rest = next;
+
+        let sentence = sentence.trim();
+        if !sentence.is_empty() {
+            return Some(sentence);
+        }
+    });
+}
+
+/// Returns an iterator over the paragraphs of `text`, separated by blank lines
+pub fn paragraphs(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    return std::iter::from_fn(move || {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+
+        let mut end = rest.len();
+        let mut offset = 0;
+        for line in rest.split_inclusive('\n') {
+            if offset > 0 && line.trim().is_empty() {
+                end = offset;
+                break;
+            }
+            offset += line.len();
+        }
+
+        let (paragraph, next) = rest.split_at(end);
+        rest = next;
+        return Some(paragraph.trim_end());
+    });
+}
+
+/// Returns an iterator over the sections of a markdown `text`. Every heading (a line starting with `#`) starts a new section.
+///
+/// Headings inside fenced code blocks are ignored.
+pub fn markdown_sections(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    return std::iter::from_fn(move || {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+
+        let mut end = rest.len();
+        let mut offset = 0;
+        let mut in_code = false;
+        for line in rest.split_inclusive('\n') {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") {
+                in_code = !in_code;
+            } else if offset > 0 && !in_code && trimmed.starts_with('#') {
+                end = offset;
+                break;
+            }
+            offset += line.len();
+        }
+
+        let (section, next) = rest.split_at(end);
+        rest = next;
+        return Some(section.trim_end());
+    });
+}
+
+/// Returns the byte index at which the first sentence of `text` ends
+fn sentence_end(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\n' && matches!(chars.peek(), Some((_, '\n' | '\r'))) {
+            return Some(i);
+        }
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+
+        let mut end = i + c.len_utf8();
+        while let Some(&(j, c)) = chars.peek() {
+            match c {
+                '.' | '!' | '?' | '"' | '\'' | ')' | ']' | '»' | '”' | '’' => {
+                    end = j + c.len_utf8();
+                    chars.next();
+                }
+                _ => break,
+            }
+        }
+
+        match chars.peek() {
+            Some((_, c)) if c.is_whitespace() => return Some(end),
+            None => return Some(end),
+            _ => {}
+        }
+    }
+    return None;
+}
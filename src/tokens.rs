@@ -0,0 +1,128 @@
+use crate::{
+    chat::Message,
+    error::{BuilderError, Error, Result},
+    model::info::{KnownModel, ModelInfo},
+};
+use tiktoken_rs::{
+    cl100k_base_singleton, o200k_base_singleton, p50k_base_singleton, p50k_edit_singleton,
+    r50k_base_singleton,
+    tokenizer::{get_tokenizer, Tokenizer},
+    CoreBPE,
+};
+
+/// Tokens added by the chat format to every message of a conversation
+const TOKENS_PER_MESSAGE: usize = 3;
+/// Tokens added by the chat format to prime the assistant's reply
+const TOKENS_PER_REPLY: usize = 3;
+
+/// Returns the number of tokens `text` is encoded into by the tokenizer of `model`
+pub fn count_tokens(model: impl AsRef<str>, text: impl AsRef<str>) -> Result<usize> {
+    return with_bpe(model.as_ref(), |bpe| {
+        bpe.encode_with_special_tokens(text.as_ref()).len()
+    });
+}
+
+/// Returns the IDs of the tokens `text` is encoded into by the tokenizer of `model`
+pub fn encode(model: impl AsRef<str>, text: impl AsRef<str>) -> Result<Vec<u32>> {
+    return with_bpe(model.as_ref(), |bpe| {
+        bpe.encode_with_special_tokens(text.as_ref())
+    });
+}
+
+/// Returns the number of tokens `model` can generate after a prompt of `prompt_tokens` tokens, limited by both its context window and its output limit.
+///
+/// Fails if the model isn't known by the library (see [`ModelInfo`]), or if the prompt doesn't leave room for any token.
+pub fn remaining_tokens(model: impl AsRef<str>, prompt_tokens: usize) -> Result<u64> {
+    let model = model.as_ref();
+    let info = ModelInfo::of(model)
+        .ok_or_else(|| Error::msg(format!("unknown context window for model '{model}'")))?;
+
+    return match info.context_window.checked_sub(prompt_tokens as u64) {
+        Some(remaining) if remaining > 0 => Ok(remaining.min(info.max_output_tokens)),
+        _ => Err(Error::msg(format!(
+            "the prompt ({prompt_tokens} tokens) doesn't fit the context window of '{model}' ({} tokens)",
+            info.context_window
+        ))),
+    };
+}
+
+/// Sets the `max_tokens` of a request builder with `set` to the tokens its model has left after its prompt, as computed by `remaining`
+pub(crate) fn max_tokens_auto<B>(
+    builder: B,
+    remaining: impl FnOnce(&B) -> Result<u64>,
+    set: impl FnOnce(B, u64) -> B,
+) -> Result<B, BuilderError<B>> {
+    return match remaining(&builder) {
+        Ok(remaining) => Ok(set(builder, remaining)),
+        Err(e) => Err(BuilderError::new(builder, e)),
+    };
+}
+
+/// Returns the number of prompt tokens a chat conversation is encoded into by the tokenizer of `model`, including the tokens that prime the assistant's reply
+pub fn count_message_tokens<'a, I: IntoIterator<Item = &'a Message<'a>>>(
+    model: impl AsRef<str>,
+    messages: I,
+) -> Result<usize> {
+    return with_bpe(model.as_ref(), |bpe| {
+        let mut tokens = TOKENS_PER_REPLY;
+        for message in messages {
+            tokens += message_tokens(bpe, message);
+        }
+        tokens
+    });
+}
+
+impl Message<'_> {
+    /// Returns the number of tokens this message takes up in the prompt of `model`
+    pub fn token_len(&self, model: impl AsRef<str>) -> Result<usize> {
+        return with_bpe(model.as_ref(), |bpe| message_tokens(bpe, self));
+    }
+}
+
+fn message_tokens(bpe: &CoreBPE, message: &Message<'_>) -> usize {
+    let role = serde_json::to_value(message.role)
+        .ok()
+        .and_then(|role| role.as_str().map(|role| bpe.encode_ordinary(role).len()))
+        .unwrap_or_default();
+
+    let name = message
+        .name
+        .as_deref()
+        .map(|name| bpe.encode_ordinary(name).len())
+        .unwrap_or_default();
+
+    return TOKENS_PER_MESSAGE
+        + role
+        + name
+        + bpe.encode_with_special_tokens(&message.content).len();
+}
+
+fn with_bpe<T>(model: &str, f: impl FnOnce(&CoreBPE) -> T) -> Result<T> {
+    let tokenizer = match KnownModel::from_id(model) {
+        Some(
+            KnownModel::Gpt4o
+            | KnownModel::Gpt4oMini
+            | KnownModel::Gpt41
+            | KnownModel::Gpt41Mini
+            | KnownModel::Gpt41Nano
+            | KnownModel::O1
+            | KnownModel::O1Mini
+            | KnownModel::O3Mini,
+        ) => Tokenizer::O200kBase,
+        Some(KnownModel::TextDavinci003) => Tokenizer::P50kBase,
+        Some(_) => Tokenizer::Cl100kBase,
+        None => get_tokenizer(model)
+            .ok_or_else(|| Error::msg(format!("No known tokenizer for model '{model}'")))?,
+    };
+
+    let bpe = match tokenizer {
+        Tokenizer::O200kBase => o200k_base_singleton(),
+        Tokenizer::Cl100kBase => cl100k_base_singleton(),
+        Tokenizer::P50kBase => p50k_base_singleton(),
+        Tokenizer::P50kEdit => p50k_edit_singleton(),
+        Tokenizer::R50kBase | Tokenizer::Gpt2 => r50k_base_singleton(),
+    };
+
+    let bpe = bpe.lock();
+    return Ok(f(&bpe));
+}
@@ -0,0 +1,71 @@
+use libopenai::test_utils::{
+    embeddings, file, json_mock, list, MockOpenAi, TEST_API_KEY, TEST_MODEL,
+};
+use serde_json::Value;
+use std::process::Output;
+
+async fn openai(mock: &MockOpenAi, args: &[&str]) -> Output {
+    let mut command = tokio::process::Command::new(env!("CARGO_BIN_EXE_openai"));
+    command
+        .args(["--base-url", &mock.uri(), "--api-key", TEST_API_KEY])
+        .args(args)
+        .env_remove("OPENAI_ORGANIZATION");
+    return command.output().await.unwrap();
+}
+
+fn stdout(output: &Output) -> &str {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    return std::str::from_utf8(&output.stdout).unwrap();
+}
+
+#[tokio::test]
+async fn chats_without_streaming() {
+    let mock = MockOpenAi::start().await;
+    mock.mock_chat("Hello there!").await;
+
+    let output = openai(
+        &mock,
+        &["chat", "Say hello", "-m", TEST_MODEL, "--no-stream"],
+    )
+    .await;
+    assert_eq!(stdout(&output), "Hello there!\n");
+
+    let request = &mock.received_json("/chat/completions").await[0];
+    assert_eq!(request["messages"][0]["content"], "Say hello");
+}
+
+#[tokio::test]
+async fn prints_json() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "GET",
+        "/files",
+        list([file("file-test", "train.jsonl", "fine-tune")]),
+    ))
+    .await;
+    mock.mount(json_mock(
+        "POST",
+        "/embeddings",
+        embeddings([[0.1, 0.2].as_slice(), &[0.3, 0.4]]),
+    ))
+    .await;
+
+    let output = openai(&mock, &["--json", "files", "list"]).await;
+    let files = serde_json::from_str::<Value>(stdout(&output)).unwrap();
+    assert_eq!(files[0]["id"], "file-test");
+
+    let output = openai(&mock, &["embed", "first", "second"]).await;
+    assert_eq!(stdout(&output).lines().count(), 2);
+}
+
+#[tokio::test]
+async fn reports_errors() {
+    let mock = MockOpenAi::start().await;
+    let output = openai(&mock, &["files", "get", "file-missing"]).await;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error: "));
+}
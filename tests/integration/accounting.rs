@@ -0,0 +1,153 @@
+use libopenai::{
+    accounting::{Budget, RateLimit},
+    chat::{ChatCompletion, Message},
+    completion::Completion,
+    error::{BudgetExceeded, Error},
+    model::info::Pricing,
+    test_utils::{MockOpenAi, TEST_MODEL},
+};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn tracks_usage_by_endpoint() {
+    let mock = MockOpenAi::start().await;
+    mock.mock_chat("Hello").await;
+    mock.mock_completion("Hello").await;
+    let client = mock.client().unwrap().with_usage_tracking();
+
+    for _ in 0..2 {
+        ChatCompletion::new(TEST_MODEL, [Message::user("Hello")], &client)
+            .await
+            .unwrap();
+    }
+    Completion::new(TEST_MODEL, "Hello", &client).await.unwrap();
+
+    let report = client.usage_report().expect("usage should be tracked");
+    let chat = report
+        .get("/v1/chat/completions", TEST_MODEL)
+        .expect("chat usage should be recorded");
+    assert_eq!(chat.requests, 2);
+    assert_eq!(chat.total_tokens, 40);
+    assert_eq!(report.total().requests, 3);
+}
+
+#[tokio::test]
+async fn estimates_costs_per_model() {
+    let model = "acme-costs-model";
+    Pricing::set(model, Pricing::new(1.0, 2.0));
+
+    let mock = MockOpenAi::start().await;
+    mock.mock_chat("Hello").await;
+    let client = mock.client().unwrap().with_usage_tracking();
+
+    let chat = ChatCompletion::new(model, [Message::user("Hello")], &client)
+        .await
+        .unwrap();
+    let usage = chat.usage.unwrap();
+    assert_eq!(usage.cost(model), Some(0.03));
+    assert_eq!(usage.cost("unknown-model"), None);
+    assert_eq!(usage.estimated_cost("unknown-model"), 0.0);
+
+    ChatCompletion::new(model, [Message::user("Hello")], &client)
+        .await
+        .unwrap();
+    let report = client.usage_report().unwrap();
+    assert_eq!(report.cost_by_model()[model], 0.06);
+    assert_eq!(report.estimated_cost(), 0.06);
+
+    Pricing::reset(model);
+    assert!(client.usage_report().unwrap().cost_by_model().is_empty());
+}
+
+#[tokio::test]
+async fn budget_rejects_requests_before_sending() {
+    let mock = MockOpenAi::start().await;
+    mock.mock_chat("Hello").await;
+    let client = mock
+        .client()
+        .unwrap()
+        .with_budget(Budget::new().max_tokens_per_request(100));
+
+    let err = ChatCompletion::builder(TEST_MODEL, [Message::user("Hello")])
+        .max_tokens(1000)
+        .build(&client)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err,
+        Error::BudgetExceeded(BudgetExceeded::TokensPerRequest { .. })
+    ));
+    assert!(mock.received_json("/chat/completions").await.is_empty());
+}
+
+#[tokio::test]
+async fn throttles_requests_per_model() {
+    let mock = MockOpenAi::start().await;
+    mock.mock_chat("Hello").await;
+    let earlier = mock.client().unwrap();
+    let client = earlier
+        .clone()
+        .with_rate_limit(TEST_MODEL, RateLimit::new().tokens_per_minute(600));
+
+    let chat = |model, max_tokens, client| {
+        ChatCompletion::builder(model, [Message::user("Hello")])
+            .max_tokens(max_tokens)
+            .build(client)
+    };
+
+    // The first request empties the bucket, which refills at 10 tokens per second. Clones taken before the limit was set share it.
+    chat(TEST_MODEL, 600, &client).await.unwrap();
+    let start = Instant::now();
+    chat(TEST_MODEL, 2, &earlier).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(150));
+
+    // Limits of other models don't reset the bucket
+    let limited = client
+        .clone()
+        .with_rate_limit("gpt-3.5-turbo", RateLimit::new().requests_per_minute(60));
+    let start = Instant::now();
+    chat(TEST_MODEL, 2, &limited).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(150));
+
+    // Other models aren't limited
+    chat("gpt-4o", 600, &client).await.unwrap();
+    let start = Instant::now();
+    chat("gpt-4o", 600, &client).await.unwrap();
+    assert!(start.elapsed() < Duration::from_millis(100));
+    assert_eq!(mock.received_json("/chat/completions").await.len(), 5);
+}
+
+#[test]
+fn overrides_prices_of_snapshots_and_fine_tunes() {
+    // Models unique to this test, since prices are global
+    const MODEL: &str = "acme-pricing-test-model";
+    let pricing = Pricing::new(5.0, 10.0);
+    assert_eq!(Pricing::of(MODEL), None);
+    Pricing::set(MODEL, pricing);
+
+    assert_eq!(Pricing::of(MODEL), Some(pricing));
+    assert_eq!(Pricing::of(format!("{MODEL}-2024-09-12")), Some(pricing));
+    assert_eq!(
+        Pricing::of(format!("ft:{MODEL}:acme::abc123")),
+        Some(pricing)
+    );
+    assert_eq!(
+        Pricing::of(format!("ft:{MODEL}-2024-09-12:acme::abc123")),
+        Some(pricing)
+    );
+    assert_eq!(Pricing::of(format!("{MODEL}-preview")), None);
+
+    // Snapshots with their own price keep it
+    let snapshot = Pricing::new(1.0, 2.0);
+    Pricing::set(format!("{MODEL}-2024-09-12"), snapshot);
+    assert_eq!(Pricing::of(format!("{MODEL}-2024-09-12")), Some(snapshot));
+
+    assert_eq!(
+        Pricing::reset(format!("{MODEL}-2024-09-12")),
+        Some(snapshot)
+    );
+    assert_eq!(Pricing::reset(MODEL), Some(pricing));
+    assert_eq!(Pricing::of(MODEL), None);
+    assert_eq!(Pricing::of(format!("ft:{MODEL}:acme::abc123")), None);
+}
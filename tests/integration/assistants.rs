@@ -0,0 +1,231 @@
+use libopenai::{
+    assistants::{ask, message::ThreadMessage, thread::delete_thread},
+    citation::CitationSource,
+    test_utils::{deleted, file, json_mock, list, MockOpenAi},
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const THREAD: &str = "/threads/thread-test";
+
+fn run(status: &str) -> Value {
+    let mut run = json!({
+        "id": "run-test",
+        "object": "thread.run",
+        "created_at": 1700000000,
+        "thread_id": "thread-test",
+        "assistant_id": "asst-test",
+        "status": status
+    });
+
+    if status == "requires_action" {
+        run["required_action"] = json!({
+            "type": "submit_tool_outputs",
+            "submit_tool_outputs": {
+                "tool_calls": [{
+                    "id": "call-test",
+                    "type": "function",
+                    "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" }
+                }]
+            }
+        });
+    }
+    return run;
+}
+
+fn message(role: &str, text: &str) -> Value {
+    return json!({
+        "id": "msg-test",
+        "object": "thread.message",
+        "created_at": 1700000000,
+        "thread_id": "thread-test",
+        "role": role,
+        "content": [{ "type": "text", "text": { "value": text, "annotations": [] } }]
+    });
+}
+
+#[tokio::test]
+async fn ask_runs_tools_until_answered() {
+    let mock = MockOpenAi::start().await;
+    let thread = json!({ "id": "thread-test", "object": "thread", "created_at": 1700000000 });
+    mock.mount(json_mock("POST", "/threads", thread)).await;
+    mock.mount(json_mock(
+        "POST",
+        &format!("{THREAD}/messages"),
+        message("user", "What's the weather in Paris?"),
+    ))
+    .await;
+    mock.mount(json_mock(
+        "POST",
+        &format!("{THREAD}/runs"),
+        run("requires_action"),
+    ))
+    .await;
+    mock.mount(json_mock(
+        "POST",
+        &format!("{THREAD}/runs/run-test/submit_tool_outputs"),
+        run("in_progress"),
+    ))
+    .await;
+    mock.mount(json_mock(
+        "GET",
+        &format!("{THREAD}/runs/run-test"),
+        run("completed"),
+    ))
+    .await;
+    mock.mount(json_mock(
+        "GET",
+        &format!("{THREAD}/messages"),
+        list([message("assistant", "It's sunny in Paris")]),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let answer = ask("asst-test")
+        .tool("get_weather", |args| async move {
+            assert_eq!(args, r#"{"city":"Paris"}"#);
+            Ok("sunny".to_string())
+        })
+        .poll_interval(Duration::from_millis(10))
+        .send("What's the weather in Paris?", &client)
+        .await
+        .expect("the assistant should answer");
+
+    assert_eq!(answer.thread_id, "thread-test");
+    assert_eq!(answer.text(), "It's sunny in Paris");
+
+    let outputs = mock
+        .received_json(&format!("{THREAD}/runs/run-test/submit_tool_outputs"))
+        .await;
+    assert_eq!(
+        outputs[0]["tool_outputs"][0],
+        json!({ "tool_call_id": "call-test", "output": "sunny" })
+    );
+}
+
+#[tokio::test]
+async fn deletes_thread() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "DELETE",
+        THREAD,
+        deleted("thread-test", "thread.deleted"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let delete = delete_thread("thread-test", &client).await.unwrap();
+    assert!(delete.deleted);
+}
+
+#[tokio::test]
+async fn resolves_message_citations() {
+    let mock = MockOpenAi::start().await;
+    let step = json!({
+        "id": "step-test",
+        "object": "thread.run.step",
+        "created_at": 1700000000,
+        "assistant_id": "asst-test",
+        "thread_id": "thread-test",
+        "run_id": "run-test",
+        "status": "completed",
+        "step_details": {
+            "type": "tool_calls",
+            "tool_calls": [{
+                "id": "call-test",
+                "type": "file_search",
+                "file_search": {
+                    "results": [{
+                        "file_id": "file-guide",
+                        "file_name": "guide.pdf",
+                        "score": 0.9,
+                        "content": [{ "type": "text", "text": "Paris is the capital" }]
+                    }, {
+                        "file_id": "file-guide",
+                        "file_name": "guide.pdf",
+                        "score": 0.8,
+                        "content": [{ "type": "text", "text": "France is in Europe" }]
+                    }]
+                }
+            }]
+        }
+    });
+    mock.mount(json_mock(
+        "GET",
+        &format!("{THREAD}/runs/run-test/steps"),
+        list([step]),
+    ))
+    .await;
+    mock.mount(json_mock(
+        "GET",
+        "/files/file-chart",
+        file("file-chart", "chart.png", "assistants_output"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let mut message = message("assistant", "Paris【4:0†source】, see chart");
+    message["run_id"] = json!("run-test");
+    message["content"][0]["text"]["annotations"] = json!([
+        {
+            "type": "file_citation",
+            "text": "【4:0†source】",
+            "start_index": 5,
+            "end_index": 17,
+            "file_citation": { "file_id": "file-guide" }
+        },
+        {
+            "type": "file_path",
+            "text": "chart",
+            "start_index": 23,
+            "end_index": 28,
+            "file_path": { "file_id": "file-chart" }
+        },
+        {
+            "type": "file_citation",
+            "text": "【4:1†source】",
+            "start_index": 28,
+            "end_index": 40,
+            "file_citation": { "file_id": "file-guide" }
+        },
+        {
+            "type": "file_path",
+            "text": "data",
+            "start_index": 40,
+            "end_index": 44,
+            "file_path": { "file_id": "file-deleted" }
+        }
+    ]);
+    let message = serde_json::from_value::<ThreadMessage>(message).unwrap();
+
+    let citations = message.citations(&client).await.unwrap();
+    assert_eq!(citations.len(), 4);
+    assert_eq!(citations[0].span, 5..17);
+    assert_eq!(citations[0].text, "【4:0†source】");
+    assert_eq!(citations[0].quote.as_deref(), Some("Paris is the capital"));
+    assert_eq!(
+        citations[0].source,
+        CitationSource::File {
+            file_id: "file-guide".into(),
+            filename: Some("guide.pdf".into())
+        }
+    );
+    assert_eq!(citations[1].quote, None);
+    assert_eq!(
+        citations[1].source,
+        CitationSource::File {
+            file_id: "file-chart".into(),
+            filename: Some("chart.png".into())
+        }
+    );
+
+    // Quotes are the chunks the markers point at, and files that can't be retrieved have no name
+    assert_eq!(citations[2].quote.as_deref(), Some("France is in Europe"));
+    assert_eq!(
+        citations[3].source,
+        CitationSource::File {
+            file_id: "file-deleted".into(),
+            filename: None
+        }
+    );
+}
@@ -0,0 +1,681 @@
+use futures::{StreamExt, TryStreamExt};
+use libopenai::{
+    audio::{
+        chunking::Chunking,
+        converse,
+        language::Language,
+        parse_audio_response,
+        speech::{SpeechBuilder, SpeechFormat, Voice},
+        transcribe_dir,
+        transcription::{TranscriptEvent, TranscriptionBuilder, TranscriptionPrompt},
+        translation::TranslationBuilder,
+        vtt, AudioResponse, AudioResponseFormat, TranscribeDirOptions, VerboseJsonResponse,
+    },
+    chat::{ChatCompletion, Message},
+    retry::RetryPolicy,
+    test_utils::{
+        chat_completion, chat_completion_mock, json_mock, sse_mock, MockOpenAi, TEST_MODEL,
+    },
+};
+use reqwest::multipart::Part;
+use serde_json::{json, Value};
+use std::{collections::HashMap, ops::Range, time::Duration};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+const AUDIO: &[u8] = b"RIFF\0\0\0\0WAVE";
+
+fn transcription(text: &str) -> Value {
+    return json!({
+        "task": "transcribe",
+        "language": "english",
+        "duration": 1.5,
+        "segments": [],
+        "text": text
+    });
+}
+
+/// Returns 16-bit mono WAV audio at 8kHz, with a loud tone except for the `silent` samples
+fn wav(samples: usize, silent: Range<usize>) -> Vec<u8> {
+    let mut result = Vec::with_capacity(44 + 2 * samples);
+    result.extend_from_slice(b"RIFF");
+    result.extend_from_slice(&(36 + 2 * samples as u32).to_le_bytes());
+    result.extend_from_slice(b"WAVEfmt ");
+    result.extend_from_slice(&16u32.to_le_bytes());
+    for x in [1u16, 1] {
+        result.extend_from_slice(&x.to_le_bytes());
+    }
+    for x in [8_000u32, 16_000] {
+        result.extend_from_slice(&x.to_le_bytes());
+    }
+    for x in [2u16, 16] {
+        result.extend_from_slice(&x.to_le_bytes());
+    }
+    result.extend_from_slice(b"data");
+    result.extend_from_slice(&(2 * samples as u32).to_le_bytes());
+    for i in 0..samples {
+        let sample: i16 = match (silent.contains(&i), i % 2) {
+            (true, _) => 0,
+            (false, 0) => 8_000,
+            (false, _) => -8_000,
+        };
+        result.extend_from_slice(&sample.to_le_bytes());
+    }
+    return result;
+}
+
+fn speech_mock(audio: &'static [u8]) -> Mock {
+    return Mock::given(method("POST"))
+        .and(path("/audio/speech"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(audio, "audio/mpeg"));
+}
+
+#[tokio::test]
+async fn transcribes_multipart_upload() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/audio/transcriptions",
+        transcription("Hello there"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let transcription = TranscriptionBuilder::new()
+        .language("EN".parse().unwrap())
+        .with_part(Part::bytes(AUDIO).file_name("input.wav"), &client)
+        .await
+        .unwrap();
+    assert_eq!(transcription.text(), "Hello there");
+    assert_eq!(transcription.language(), Some(&Language::English));
+
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    let body = String::from_utf8_lossy(&request.body);
+    assert!(body.contains("filename=\"input.wav\""));
+    assert!(body.contains("whisper-1"));
+    assert!(body.contains("name=\"language\"\r\n\r\nen\r\n"));
+
+    assert!("klingon".parse::<Language>().is_err());
+    assert_eq!("Welsh".parse::<Language>().unwrap().code(), "cy");
+}
+
+#[tokio::test]
+async fn retries_multipart_uploads() {
+    let file = std::env::temp_dir().join(format!("libopenai-retry-{}.wav", rand::random::<u64>()));
+    tokio::fs::write(&file, AUDIO).await.unwrap();
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/audio/transcriptions"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "3600"))
+            .up_to_n_times(1)
+            .with_priority(1),
+    )
+    .await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/audio/transcriptions"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .with_priority(2),
+    )
+    .await;
+    mock.mount(json_mock(
+        "POST",
+        "/audio/transcriptions",
+        transcription("Hello there"),
+    ))
+    .await;
+    let client = mock.client().unwrap().with_retry_policy(
+        RetryPolicy::new()
+            .base_delay(Duration::from_millis(10))
+            .max_delay(Duration::from_millis(50)),
+    );
+
+    // The long `Retry-After` is capped by the policy
+    let transcription = tokio::time::timeout(
+        Duration::from_secs(5),
+        TranscriptionBuilder::new().with_file(&file, &client),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(transcription.text(), "Hello there");
+
+    // Every attempt sent the whole file
+    let requests = mock.server().received_requests().await.unwrap();
+    assert_eq!(requests.len(), 3);
+    let name = format!(
+        "filename=\"{}\"",
+        file.file_name().unwrap().to_string_lossy()
+    );
+    for request in requests {
+        let body = String::from_utf8_lossy(&request.body);
+        assert!(body.contains(&name));
+        assert!(body.contains(&*String::from_utf8_lossy(AUDIO)));
+    }
+    tokio::fs::remove_file(&file).await.unwrap();
+
+    // Parts can only be sent once
+    mock.server().reset().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/audio/transcriptions"))
+            .respond_with(ResponseTemplate::new(503)),
+    )
+    .await;
+    let result = TranscriptionBuilder::new()
+        .with_part(Part::bytes(AUDIO).file_name("input.wav"), &client)
+        .await;
+    assert!(result.is_err());
+    assert_eq!(mock.server().received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn selects_transcription_models() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/audio/transcriptions",
+        json!({ "text": "Hello there" }),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let builder = TranscriptionBuilder::new().model("gpt-4o-mini-transcribe");
+    assert_eq!(builder.format(), AudioResponseFormat::Json);
+    let transcription = builder
+        .with_part(Part::bytes(AUDIO).file_name("input.wav"), &client)
+        .await
+        .unwrap();
+    assert_eq!(transcription.text(), "Hello there");
+
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    let body = String::from_utf8_lossy(&request.body);
+    assert!(body.contains("name=\"model\"\r\n\r\ngpt-4o-mini-transcribe\r\n"));
+    assert!(body.contains("name=\"response_format\"\r\n\r\njson\r\n"));
+
+    let err = TranscriptionBuilder::new()
+        .model("gpt-4o-transcribe")
+        .response_format(AudioResponseFormat::Srt)
+        .with_part(Part::bytes(AUDIO).file_name("input.wav"), &client)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("'srt'"));
+    assert_eq!(mock.server().received_requests().await.unwrap().len(), 1);
+    assert!(AudioResponseFormat::Vtt.is_supported_by("whisper-1"));
+}
+
+#[tokio::test]
+async fn streams_transcripts() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(sse_mock(
+        "POST",
+        "/audio/transcriptions",
+        [
+            json!({ "type": "transcript.text.delta", "delta": "Hello", "logprobs": [] }),
+            json!({ "type": "transcript.text.delta", "delta": " there" }),
+            json!({ "type": "transcript.text.done", "text": "Hello there", "usage": { "type": "tokens", "total_tokens": 12 } }),
+        ],
+    ))
+    .await;
+    let client = mock.client().unwrap();
+    let transcribe = || {
+        TranscriptionBuilder::new()
+            .model("gpt-4o-transcribe")
+            .stream_part(Part::bytes(AUDIO).file_name("input.wav"), &client)
+    };
+
+    let events = transcribe()
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    let deltas = events
+        .iter()
+        .filter_map(TranscriptEvent::delta)
+        .collect::<String>();
+    assert_eq!(deltas, "Hello there");
+    assert!(
+        matches!(events.last(), Some(TranscriptEvent::Done { text, .. }) if text == "Hello there")
+    );
+    assert_eq!(
+        transcribe().await.unwrap().into_text().await.unwrap(),
+        "Hello there"
+    );
+
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    let body = String::from_utf8_lossy(&request.body);
+    assert!(body.contains("name=\"stream\"\r\n\r\ntrue\r\n"));
+
+    // Whisper can't stream transcripts
+    assert!(TranscriptionBuilder::new()
+        .stream_part(Part::bytes(AUDIO).file_name("input.wav"), &client)
+        .await
+        .is_err());
+    assert_eq!(mock.server().received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn sends_unsupported_fields() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/audio/transcriptions",
+        transcription("Hello there"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let resp = TranscriptionBuilder::new()
+        .response_format(AudioResponseFormat::VerboseJson)
+        .into_request(Part::bytes(AUDIO).file_name("input.wav"))
+        .unwrap()
+        .text("timestamp_granularities[]", "word")
+        .send(&client)
+        .await
+        .unwrap();
+    let transcription = parse_audio_response(resp, AudioResponseFormat::VerboseJson)
+        .await
+        .unwrap();
+    assert_eq!(transcription.text(), "Hello there");
+
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    let body = String::from_utf8_lossy(&request.body);
+    assert!(body.contains("name=\"timestamp_granularities[]\"\r\n\r\nword"));
+    assert!(body.contains("name=\"response_format\"\r\n\r\nverbose_json"));
+}
+
+#[tokio::test]
+async fn parses_vtt_transcriptions() {
+    const VTT: &str = "WEBVTT\r\n\r\nNOTE generated by whisper\r\n\r\n1\r\n00:00:00.500 --> 00:00:02.000 align:start\r\nHello\r\nthere\r\n\r\n01:02.000 --> 01:03.250\r\nGeneral Kenobi\r\n";
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/audio/transcriptions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(VTT, "text/vtt")),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let transcription = TranscriptionBuilder::new()
+        .response_format(AudioResponseFormat::Vtt)
+        .with_part(Part::bytes(AUDIO).file_name("input.wav"), &client)
+        .await
+        .unwrap();
+    assert_eq!(transcription.text(), "Hello\nthere General Kenobi");
+    assert_eq!(
+        transcription.duration(),
+        Some(Duration::from_millis(62_750))
+    );
+
+    let segments = transcription.segments().unwrap().collect::<Vec<_>>();
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[1].start, Duration::from_secs(62));
+    assert_eq!(segments[1].end, Duration::from_millis(63_250));
+
+    let AudioResponse::Vtt(ref cues) = transcription else {
+        panic!("expected a VTT response")
+    };
+    assert_eq!(cues[0].identifier.as_deref(), Some("1"));
+    assert_eq!(
+        serde_json::to_value(&transcription).unwrap(),
+        "WEBVTT\n\n1\n00:00:00.500 --> 00:00:02.000\nHello\nthere\n\n00:01:02.000 --> 00:01:03.250\nGeneral Kenobi\n"
+    );
+    assert_eq!(vtt::parse(&vtt::format(cues)).unwrap(), *cues);
+    assert!(vtt::parse("00:00.000 --> 00:01.000\nHello").is_err());
+    assert!(vtt::parse("WEBVTT\n\n00:00 --> 00:01.000\nHello").is_err());
+}
+
+#[tokio::test]
+async fn translates_into_every_format() {
+    const SRT: &str = "1\n00:00:00,000 --> 00:00:01,500\nGood morning\n\n2\n00:00:01,500 --> 00:00:03,000\neveryone\n\n";
+
+    let mock = MockOpenAi::start().await;
+    let client = mock.client().unwrap();
+    let translate = |format| {
+        TranslationBuilder::new()
+            .response_format(format)
+            .with_part(Part::bytes(AUDIO).file_name("input.wav"), &client)
+    };
+
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/audio/translations"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SRT, "application/x-subrip")),
+    )
+    .await;
+    let translation = translate(AudioResponseFormat::Srt).await.unwrap();
+    assert_eq!(translation.text(), "Good morning everyone");
+    assert_eq!(translation.duration(), Some(Duration::from_secs(3)));
+    assert_eq!(translation.segments().unwrap().count(), 2);
+
+    mock.server().reset().await;
+    mock.mount(json_mock(
+        "POST",
+        "/audio/translations",
+        json!({ "text": "Good morning" }),
+    ))
+    .await;
+    let translation = translate(AudioResponseFormat::Json).await.unwrap();
+    assert_eq!(translation.text(), "Good morning");
+    assert!(translation.segments().is_none());
+
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    let body = String::from_utf8_lossy(&request.body);
+    assert!(body.contains("name=\"response_format\"\r\n\r\njson"));
+}
+
+#[tokio::test]
+async fn synthesizes_speech() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(speech_mock(b"mp3 bytes")).await;
+    let client = mock.client().unwrap();
+
+    let speech = SpeechBuilder::new("gpt-4o-mini-tts", Voice::Coral)
+        .response_format(SpeechFormat::Opus)
+        .instructions("Speak cheerfully");
+    let audio = speech.build("Hello", &client).await.unwrap();
+    assert_eq!(&audio[..], b"mp3 bytes");
+
+    let request = &mock.received_json("/audio/speech").await[0];
+    assert_eq!(request["input"], "Hello");
+    assert_eq!(request["voice"], "coral");
+    assert_eq!(request["response_format"], "opus");
+}
+
+#[tokio::test]
+async fn writes_speech_into_files() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(speech_mock(b"mp3 bytes")).await;
+    let client = mock.client().unwrap();
+    let speech = SpeechBuilder::default();
+
+    let dir = std::env::temp_dir().join(format!("libopenai-integration-{}", rand::random::<u64>()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+    let path = dir.join("hello.mp3");
+    speech.save_to("Hello", &path, &client).await.unwrap();
+    assert_eq!(tokio::fs::read(&path).await.unwrap(), b"mp3 bytes");
+
+    let mut buf = Vec::new();
+    speech
+        .write_into_tokio("Hello", &mut buf, &client)
+        .await
+        .unwrap();
+    assert_eq!(buf, b"mp3 bytes");
+
+    // Rejected requests don't leave empty files behind
+    let unsupported = dir.join("unsupported.mp3");
+    let speech = SpeechBuilder::new("tts-1", Voice::Verse);
+    assert!(speech
+        .save_to("Hello", &unsupported, &client)
+        .await
+        .is_err());
+    assert!(!unsupported.exists());
+    tokio::fs::remove_dir_all(dir).await.unwrap();
+}
+
+#[tokio::test]
+async fn rejects_unsupported_voice_before_sending() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(speech_mock(b"mp3 bytes")).await;
+    let client = mock.client().unwrap();
+
+    let speech = SpeechBuilder::new("tts-1", Voice::Verse);
+    assert!(speech.build("Hello", &client).await.is_err());
+    assert!(mock.received_json("/audio/speech").await.is_empty());
+}
+
+#[tokio::test]
+async fn converses_through_audio() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/audio/transcriptions",
+        transcription(" What time is it? "),
+    ))
+    .await;
+    mock.mount(chat_completion_mock("It's noon")).await;
+    mock.mount(speech_mock(b"reply")).await;
+    let client = mock.client().unwrap();
+
+    let conversation = ChatCompletion::builder(TEST_MODEL, [Message::system("Be brief")]);
+    let reply = converse(
+        Part::bytes(AUDIO).file_name("input.wav"),
+        conversation,
+        &client,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(reply.transcript, "What time is it?");
+    assert_eq!(reply.reply, "It's noon");
+    assert_eq!(reply.audio.as_deref(), Some(&b"reply"[..]));
+    assert!(reply.tool_calls.is_empty());
+
+    let chat = &mock.received_json("/chat/completions").await[0];
+    assert_eq!(chat["messages"][1]["content"], "What time is it?");
+}
+
+#[tokio::test]
+async fn returns_tool_calls_of_voice_replies() {
+    let mut body = chat_completion("");
+    body["choices"][0]["message"] = json!({
+        "role": "assistant",
+        "content": null,
+        "tool_calls": [{
+            "id": "call_1",
+            "type": "function",
+            "function": { "name": "get_time", "arguments": "{}" }
+        }]
+    });
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/audio/transcriptions",
+        transcription("What time is it?"),
+    ))
+    .await;
+    mock.mount(json_mock("POST", "/chat/completions", body))
+        .await;
+    mock.mount(speech_mock(b"reply")).await;
+    let client = mock.client().unwrap();
+
+    let conversation = ChatCompletion::builder(TEST_MODEL, [Message::system("Be brief")]);
+    let reply = converse(
+        Part::bytes(AUDIO).file_name("input.wav"),
+        conversation,
+        &client,
+    )
+    .await
+    .unwrap();
+
+    assert!(reply.audio.is_none());
+    assert_eq!(reply.tool_calls[0].id, "call_1");
+    assert!(mock.received_json("/audio/speech").await.is_empty());
+
+    // The calls are answered before the conversation continues
+    let conversation = reply.conversation.message(Message::tool("call_1", "12:00"));
+    conversation.build(&client).await.unwrap();
+    let chat = &mock.received_json("/chat/completions").await[1];
+    assert_eq!(chat["messages"][2]["tool_calls"][0]["id"], "call_1");
+    assert_eq!(chat["messages"][3]["content"], "12:00");
+}
+
+#[tokio::test]
+async fn transcribes_directory_with_retries() {
+    let dir = std::env::temp_dir().join(format!("libopenai-integration-{}", rand::random::<u64>()));
+    tokio::fs::create_dir_all(dir.join("nested")).await.unwrap();
+    for name in ["a.wav", "b.MP3", "nested/c.ogg", "notes.txt"] {
+        tokio::fs::write(dir.join(name), AUDIO).await.unwrap();
+    }
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/audio/transcriptions"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .with_priority(1),
+    )
+    .await;
+    mock.mount(json_mock(
+        "POST",
+        "/audio/transcriptions",
+        transcription("Hello"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let opts = TranscribeDirOptions::new()
+        .concurrency(2)
+        .unwrap()
+        .retry_delay(Duration::from_millis(10));
+    let results = transcribe_dir(format!("{}/**/*", dir.display()), opts, &client)
+        .await
+        .unwrap()
+        .collect::<HashMap<_, _>>()
+        .await;
+
+    assert_eq!(results.len(), 3);
+    assert!(!results.contains_key(&dir.join("notes.txt")));
+    for (_, result) in results {
+        assert_eq!(result.unwrap().text(), "Hello");
+    }
+    // The file that got the 503 was sent again
+    assert_eq!(mock.server().received_requests().await.unwrap().len(), 4);
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[tokio::test]
+async fn sends_truncated_prompt_hints() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/audio/transcriptions",
+        transcription("The etcd cluster is healthy"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let context = (0..200)
+        .map(|i| format!("word{i}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let hints = TranscriptionPrompt::new()
+        .vocabulary(["Kubernetes", "etcd"])
+        .context(context)
+        .max_tokens(40);
+
+    let prompt = hints.build();
+    assert!(prompt.starts_with("Glossary: Kubernetes, etcd. "));
+    assert!(prompt.ends_with(" word199"));
+    assert!(!prompt.contains("word0 "));
+    assert!(prompt.len() < 200);
+
+    TranscriptionBuilder::new()
+        .prompt_hints(&hints)
+        .with_part(Part::bytes(AUDIO).file_name("input.wav"), &client)
+        .await
+        .unwrap();
+
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    assert!(String::from_utf8_lossy(&request.body).contains(&prompt));
+}
+
+#[tokio::test]
+async fn transcribes_large_audio_in_chunks() {
+    let mock = MockOpenAi::start().await;
+    let mut response = transcription("Hello");
+    response["segments"] = json!([{
+        "id": 0, "seek": 0, "start": 0.25, "end": 0.75,
+        "text": "Hello", "tokens": [1], "temperature": 0.0,
+        "avg_logprob": -0.1, "compression_ratio": 1.0,
+        "no_speech_prob": 0.0, "transient": false
+    }]);
+    mock.mount(json_mock("POST", "/audio/transcriptions", response))
+        .await;
+    let client = mock.client().unwrap();
+
+    // 3 seconds of audio, silent between 1.1 and 1.15 seconds
+    let audio = wav(24_000, 8_800..9_200);
+    let chunking = Chunking::new().max_bytes(20_000).concurrency(2).unwrap();
+    let transcribe = |chunking| {
+        TranscriptionBuilder::new()
+            .chunking(chunking)
+            .with_bytes(audio.clone(), "wav", &client)
+    };
+
+    let AudioResponse::VerboseJson(transcript) = transcribe(chunking).await.unwrap() else {
+        panic!("expected a verbose JSON response")
+    };
+    assert_eq!(transcript.text, "Hello Hello Hello");
+    let ids = transcript.segments.iter().map(|x| x.id).collect::<Vec<_>>();
+    assert_eq!(ids, [0, 1, 2]);
+    let second_chunk = transcript.segments[1].start - Duration::from_millis(250);
+    assert!(second_chunk > Duration::from_millis(1_100));
+    assert!(second_chunk < Duration::from_millis(1_150));
+
+    let requests = mock.server().received_requests().await.unwrap();
+    assert_eq!(requests.len(), 3);
+    assert!(requests
+        .iter()
+        .all(|x| x.body.len() < 21_000 && String::from_utf8_lossy(&x.body).contains("WAVEfmt ")));
+
+    // Without looking for silences, chunks are cut at the size limit
+    let AudioResponse::VerboseJson(transcript) =
+        transcribe(chunking.split_on_silence(false)).await.unwrap()
+    else {
+        panic!("expected a verbose JSON response")
+    };
+    let second_chunk = transcript.segments[1].start - Duration::from_millis(250);
+    assert!(second_chunk > Duration::from_millis(1_240));
+    assert!(second_chunk < Duration::from_millis(1_250));
+
+    // Only WAV audio can be split
+    let err = TranscriptionBuilder::new()
+        .chunking(chunking)
+        .with_bytes(vec![0u8; 30_000], "mp3", &client)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("WAV"));
+}
+
+#[test]
+fn reports_transcript_quality() {
+    let segment = |id: u64, avg_logprob: f64, compression_ratio: f64, no_speech_prob: f64| {
+        json!({
+            "id": id, "seek": 0, "start": id as f64, "end": id as f64 + 1.0,
+            "text": "Hello", "tokens": [1, 2], "temperature": 0.0,
+            "avg_logprob": avg_logprob, "compression_ratio": compression_ratio,
+            "no_speech_prob": no_speech_prob, "transient": false
+        })
+    };
+    let mut response = transcription("Hello hello hello hello");
+    response["segments"] = json!([
+        segment(0, -0.2, 1.2, 0.01),
+        segment(1, -0.4, 1.4, 0.9),
+        segment(2, -0.3, 3.1, 0.02),
+        segment(3, -0.3, 1.3, 0.05)
+    ]);
+    let response = serde_json::from_value::<VerboseJsonResponse>(response).unwrap();
+
+    let report = response.quality_report();
+    assert_eq!(report.segments, 4);
+    assert!((report.mean_avg_logprob + 0.3).abs() < 1e-9);
+    assert_eq!(report.no_speech_ratio, 0.25);
+    assert_eq!(report.compression_anomaly_ratio, 0.25);
+    assert_eq!(report.flagged_segments, [1, 2]);
+    let expected = (-0.3f64).exp() * 0.75 * 0.75;
+    assert!((response.quality_score() - expected).abs() < 1e-9);
+
+    let empty = serde_json::from_value::<VerboseJsonResponse>(transcription("")).unwrap();
+    assert_eq!(empty.quality_score(), 0.0);
+}
@@ -0,0 +1,69 @@
+use libopenai::{
+    chat::{ChatCompletion, Message},
+    embeddings::Embedding,
+    endpoint::Endpoint,
+    file::files,
+    test_utils::{chat_completion, embeddings, list, MockOpenAi, TEST_API_KEY, TEST_MODEL},
+    Client,
+};
+use wiremock::{
+    matchers::{header, method, path, query_param},
+    Mock, ResponseTemplate,
+};
+
+const API_VERSION: &str = "2024-06-01";
+
+fn azure_mock(method_name: &str, endpoint: &str, body: serde_json::Value) -> Mock {
+    return Mock::given(method(method_name))
+        .and(path(endpoint))
+        .and(query_param("api-version", API_VERSION))
+        .and(header("api-key", TEST_API_KEY))
+        .respond_with(ResponseTemplate::new(200).set_body_json(body));
+}
+
+#[tokio::test]
+async fn sends_requests_to_azure_deployments() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(azure_mock(
+        "POST",
+        "/openai/deployments/gpt-4o/chat/completions",
+        chat_completion("Hi!"),
+    ))
+    .await;
+    mock.mount(azure_mock(
+        "POST",
+        "/openai/deployments/ada/embeddings",
+        embeddings([&[0.5, 0.5][..]]),
+    ))
+    .await;
+    mock.mount(azure_mock(
+        "GET",
+        "/openai/files",
+        list::<serde_json::Value>([]),
+    ))
+    .await;
+
+    let client = Client::builder()
+        .azure(mock.uri(), "gpt-4o", API_VERSION)
+        .api_key(TEST_API_KEY)
+        .build()
+        .unwrap()
+        .with_azure_deployment(Endpoint::Embeddings, "ada");
+
+    let completion = ChatCompletion::new(TEST_MODEL, [Message::user("Hello")], &client)
+        .await
+        .unwrap();
+    assert_eq!(completion.first().unwrap().message.content, "Hi!");
+    let embedding = Embedding::new("text-embedding-3-small", "Hello", &client)
+        .await
+        .unwrap();
+    assert_eq!(embedding.data[0].embedding, [0.5, 0.5]);
+    assert!(files(&client).await.unwrap().is_empty());
+
+    // Azure requests aren't authenticated with bearer tokens
+    let requests = mock.server().received_requests().await.unwrap();
+    assert_eq!(requests.len(), 3);
+    assert!(requests
+        .iter()
+        .all(|request| !request.headers.contains_key(&"authorization".into())));
+}
@@ -0,0 +1,115 @@
+use futures::TryStreamExt;
+use libopenai::{
+    batch::{batches, list_batches, Batch, BatchStatus},
+    chat::{ChatCompletion, Message},
+    common::ListParams,
+    test_utils::{chat_completion, file, json_mock, list, MockOpenAi, TEST_MODEL},
+};
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+fn batch(status: &str) -> Value {
+    return json!({
+        "id": "batch-test",
+        "object": "batch",
+        "endpoint": "/v1/chat/completions",
+        "input_file_id": "file-input",
+        "completion_window": "24h",
+        "status": status,
+        "output_file_id": "file-output",
+        "created_at": 1700000000
+    });
+}
+
+#[tokio::test]
+async fn uploads_streamed_input_and_creates_batch() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/files",
+        file("file-input", "input.jsonl", "batch"),
+    ))
+    .await;
+    mock.mount(json_mock("POST", "/batches", batch("validating")))
+        .await;
+    let client = mock.client().unwrap();
+
+    let mut writer = Batch::writer(Some("input.jsonl".to_string()), &client);
+    for question in ["Hello", "Goodbye"] {
+        let request = ChatCompletion::builder(TEST_MODEL, [Message::user(question)]);
+        writer.push(question, &request).await.unwrap();
+    }
+    assert_eq!(writer.len(), 2);
+
+    let batch = writer.send(&client).await.unwrap();
+    assert_eq!(batch.status, BatchStatus::Validating);
+
+    let requests = mock.server().received_requests().await.unwrap();
+    let upload = String::from_utf8_lossy(&requests[0].body);
+    assert!(upload.contains(r#""custom_id":"Goodbye""#));
+    assert!(upload.contains(r#""url":"/v1/chat/completions""#));
+
+    let create = &mock.received_json("/batches").await[0];
+    assert_eq!(create["input_file_id"], "file-input");
+    assert_eq!(create["endpoint"], "/v1/chat/completions");
+}
+
+#[tokio::test]
+async fn downloads_results() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock("GET", "/batches/batch-test", batch("completed")))
+        .await;
+
+    let output = [
+        json!({
+            "id": "line-1",
+            "custom_id": "hello",
+            "response": { "status_code": 200, "request_id": "req-1", "body": chat_completion("Hi") }
+        }),
+        json!({
+            "id": "line-2",
+            "custom_id": "bad",
+            "error": { "code": "invalid_request", "message": "Bad request" }
+        }),
+    ]
+    .iter()
+    .map(|line| format!("{line}\n"))
+    .collect::<String>();
+
+    mock.mount(
+        Mock::given(method("GET"))
+            .and(path("/files/file-output/content"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(output)),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let batch = Batch::retreive("batch-test", &client).await.unwrap();
+    let mut results = batch.results::<ChatCompletion>(&client).await.unwrap();
+    assert_eq!(results.len(), 2);
+
+    let hello = results.remove("hello").unwrap().unwrap();
+    assert_eq!(hello.first().unwrap().message.content, "Hi");
+    assert!(results.remove("bad").unwrap().is_err());
+}
+
+#[tokio::test]
+async fn lists_batches() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock("GET", "/batches", list([batch("in_progress")])))
+        .await;
+    let client = mock.client().unwrap();
+
+    let batches = batches(&client).await.unwrap();
+    assert_eq!(batches.len(), 1);
+    assert_eq!(batches[0].status, BatchStatus::InProgress);
+
+    let paged = list_batches(ListParams::new().limit(1), &client)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(paged.len(), 1);
+}
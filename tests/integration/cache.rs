@@ -0,0 +1,42 @@
+use libopenai::{
+    cache::MemoryCache,
+    model::Model,
+    test_utils::{model, MockOpenAi},
+};
+use wiremock::{
+    matchers::{header, method, path},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn revalidates_cached_responses() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("GET"))
+            .and(path("/models/gpt-4o"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .with_priority(1),
+    )
+    .await;
+    mock.mount(
+        Mock::given(method("GET"))
+            .and(path("/models/gpt-4o"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v1\"")
+                    .set_body_json(model("gpt-4o")),
+            )
+            .up_to_n_times(1),
+    )
+    .await;
+    let client = mock.client().unwrap().with_cache(MemoryCache::new());
+
+    let first = Model::get("gpt-4o", &client).await.unwrap();
+    let second = Model::get("gpt-4o", &client).await.unwrap();
+    assert_eq!(first.id, second.id);
+
+    // The second request is only answered if it revalidates the cached response
+    let requests = mock.server().received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+}
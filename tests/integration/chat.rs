@@ -0,0 +1,1061 @@
+use futures::TryStreamExt;
+use libopenai::{
+    chat::{
+        conversation::{ConversationStore, JsonFileStore},
+        export::{TranscriptExporter, TranscriptFormat},
+        extract_json, run_agent, self_consistent,
+        tool::{
+            FunctionChoice, FunctionDefinition, Tool, ToolArgs, ToolChoice, ToolRegistry,
+            ToolSchema,
+        },
+        AgentLimits, AgentStop, ChatCompletion, ChatCompletionBuilder, Conversation, Message,
+        ResponseFormat, Role,
+    },
+    common::Truncation,
+    error::Error,
+    file::jsonl::JsonlWriter,
+    prompt::FewShot,
+    test_utils::{
+        chat_completion, chat_completion_chunk, chat_completion_mock, chat_completion_stream_mock,
+        error_mock, sse_mock, MockOpenAi, TEST_API_KEY, TEST_MODEL,
+    },
+};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+use wiremock::{
+    matchers::{bearer_token, method, path},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn sends_conversation() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(bearer_token(TEST_API_KEY))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(chat_completion("Hi! How can I help?")),
+            ),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let completion = ChatCompletion::builder(
+        TEST_MODEL,
+        [Message::system("Be helpful"), Message::user("Hello")],
+    )
+    .temperature(0.5)
+    .unwrap()
+    .extra_param("seed", 42)
+    .build(&client)
+    .await
+    .unwrap();
+    assert_eq!(
+        completion.first().unwrap().message.content,
+        "Hi! How can I help?"
+    );
+
+    let request = &mock.received_json("/chat/completions").await[0];
+    assert_eq!(request["model"], TEST_MODEL);
+    assert_eq!(request["temperature"], 0.5);
+    assert_eq!(request["seed"], 42);
+    assert_eq!(
+        request["messages"],
+        json!([
+            { "role": "system", "content": "Be helpful" },
+            { "role": "user", "content": "Hello" }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn calls_functions() {
+    #[derive(Debug, Deserialize)]
+    struct Weather {
+        city: String,
+    }
+
+    let mut body = chat_completion("");
+    body["choices"][0]["message"] = json!({
+        "role": "assistant",
+        "content": null,
+        "function_call": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" }
+    });
+    body["choices"][0]["finish_reason"] = json!("function_call");
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body)),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let weather = FunctionDefinition::new(
+        "get_weather",
+        Some("Returns the weather of a city".into()),
+        json!({ "type": "object", "properties": { "city": { "type": "string" } } }),
+    );
+    let completion = ChatCompletion::builder(TEST_MODEL, [Message::user("Weather in Paris?")])
+        .functions([weather])
+        .function_call("get_weather")
+        .build(&client)
+        .await
+        .unwrap();
+
+    let choice = completion.first().unwrap();
+    assert_eq!(choice.finish_reason.as_deref(), Some("function_call"));
+    let call = choice.message.function_call.as_ref().unwrap();
+    assert_eq!(call.name, "get_weather");
+    assert_eq!(call.parse_arguments::<Weather>().unwrap().city, "Paris");
+
+    let request = &mock.received_json("/chat/completions").await[0];
+    assert_eq!(request["functions"][0]["name"], "get_weather");
+    assert_eq!(request["function_call"], json!({ "name": "get_weather" }));
+
+    let result = Message::function("get_weather", "Sunny, 24°C");
+    assert_eq!(
+        serde_json::to_value(&result).unwrap(),
+        json!({ "role": "function", "content": "Sunny, 24°C", "name": "get_weather" })
+    );
+    assert_eq!(
+        serde_json::to_value(FunctionChoice::Auto).unwrap(),
+        json!("auto")
+    );
+}
+
+#[tokio::test]
+async fn calls_tools() {
+    let mut body = chat_completion("");
+    body["choices"][0]["message"] = json!({
+        "role": "assistant",
+        "content": null,
+        "tool_calls": [{
+            "id": "call_1",
+            "type": "function",
+            "function": { "name": "get_weather", "arguments": "{\"city\":\"Paris\"}" }
+        }]
+    });
+    body["choices"][0]["finish_reason"] = json!("tool_calls");
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body)),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let completion = ChatCompletion::builder(TEST_MODEL, [Message::user("Weather in Paris?")])
+        .tools([Tool::function(
+            "get_weather",
+            None,
+            json!({ "type": "object", "properties": { "city": { "type": "string" } } }),
+        )])
+        .tool_choice("get_weather")
+        .build(&client)
+        .await
+        .unwrap();
+
+    let choice = completion.first().unwrap();
+    assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+    let call = &choice.message.tool_calls[0];
+    assert_eq!((call.id.as_str(), call.name()), ("call_1", "get_weather"));
+    assert_eq!(
+        call.parse_arguments::<serde_json::Value>().unwrap(),
+        json!({ "city": "Paris" })
+    );
+
+    let request = &mock.received_json("/chat/completions").await[0];
+    assert_eq!(request["tools"][0]["type"], "function");
+    assert_eq!(request["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(
+        request["tool_choice"],
+        json!({ "type": "function", "function": { "name": "get_weather" } })
+    );
+
+    assert_eq!(
+        serde_json::to_value(Message::tool("call_1", "Sunny")).unwrap(),
+        json!({ "role": "tool", "content": "Sunny", "tool_call_id": "call_1" })
+    );
+    assert_eq!(
+        serde_json::to_value(ToolChoice::Required).unwrap(),
+        json!("required")
+    );
+
+    // Agents stopped by a limit answer the last tool calls without executing them, so their transcripts can be resumed
+    let executed = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let registry =
+        ToolRegistry::new().register("get_weather", None, json!({ "type": "object" }), {
+            let executed = executed.clone();
+            move |_: serde_json::Value| {
+                executed.store(true, std::sync::atomic::Ordering::SeqCst);
+                async move { Ok("Sunny") }
+            }
+        });
+    let run = run_agent(
+        ChatCompletion::builder(TEST_MODEL, [Message::user("Weather in Paris?")]),
+        &registry,
+        &client,
+        AgentLimits::new().max_iterations(1),
+    )
+    .await
+    .unwrap();
+    assert_eq!(run.stop, AgentStop::MaxIterations);
+    assert_eq!(run.iterations, 1);
+    let last = run.transcript.last().unwrap();
+    assert_eq!(last.role, Role::Tool);
+    assert_eq!(last.tool_call_id.as_deref(), Some("call_1"));
+    assert_eq!(last.content, "not executed: limit reached");
+    assert!(!executed.load(std::sync::atomic::Ordering::SeqCst));
+    assert_eq!(mock.received_json("/chat/completions").await.len(), 2);
+
+    assert!(run_agent(
+        ChatCompletion::builder(TEST_MODEL, [Message::user("Weather in Paris?")]),
+        &registry,
+        &client,
+        AgentLimits::new().max_iterations(0),
+    )
+    .await
+    .is_err());
+    assert_eq!(mock.received_json("/chat/completions").await.len(), 2);
+}
+
+#[tokio::test]
+async fn votes_on_self_consistent_answers() {
+    let mut body = chat_completion("");
+    body["choices"] = json!(["Answer: 42", "Answer: 41", "answer: 42 ", "I don't know"]
+        .iter()
+        .enumerate()
+        .map(|(index, content)| json!({
+            "index": index,
+            "message": { "role": "assistant", "content": content },
+            "finish_reason": "stop"
+        }))
+        .collect::<Vec<_>>());
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(body)),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let consensus = self_consistent(
+        ChatCompletion::builder(TEST_MODEL, [Message::user("What's 6 times 7?")]),
+        4,
+        |content| {
+            Some(
+                content
+                    .to_lowercase()
+                    .strip_prefix("answer:")?
+                    .trim()
+                    .to_string(),
+            )
+        },
+        &client,
+    )
+    .await
+    .unwrap();
+    assert_eq!(consensus.answer, "42");
+    assert_eq!(consensus.answer_votes(), 2);
+    assert_eq!(consensus.samples, 4);
+    assert_eq!(consensus.agreement(), 0.5);
+
+    // The completions are sampled with a single request
+    let requests = mock.received_json("/chat/completions").await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0]["n"], 4);
+    assert_eq!(requests[0]["temperature"], 0.8);
+}
+
+#[tokio::test]
+async fn derives_tool_schemas() {
+    #[derive(Debug, PartialEq, Deserialize, ToolSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum Unit {
+        Celsius,
+        Fahrenheit,
+    }
+
+    /// Returns the current weather of a city
+    #[derive(Debug, Deserialize, ToolArgs)]
+    struct GetWeather {
+        /// Name of the city
+        city: String,
+        unit: Option<Unit>,
+        #[serde(default, rename = "days")]
+        forecast_days: u32,
+    }
+
+    let definition = GetWeather::definition();
+    assert_eq!(definition.name, "get_weather");
+    assert_eq!(
+        definition.description.as_deref(),
+        Some("Returns the current weather of a city")
+    );
+    assert_eq!(
+        definition.parameters,
+        json!({
+            "type": "object",
+            "properties": {
+                "city": { "type": "string", "description": "Name of the city" },
+                "unit": { "type": "string", "enum": ["celsius", "fahrenheit"] },
+                "days": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["city"]
+        })
+    );
+
+    let args = GetWeather::parse(r#"{ "city": "Paris", "unit": "celsius" }"#).unwrap();
+    assert_eq!(args.city, "Paris");
+    assert_eq!(args.unit, Some(Unit::Celsius));
+    assert_eq!(args.forecast_days, 0);
+
+    let registry = ToolRegistry::new()
+        .register_args(|args: GetWeather| async move { Ok(format!("Sunny in {}", args.city)) });
+    assert_eq!(
+        registry.tools().map(Tool::name).collect::<Vec<_>>(),
+        ["get_weather"]
+    );
+
+    // Tools are sent in the same order, regardless of the order they're registered in
+    let registry = registry.register(
+        "add",
+        None,
+        json!({ "type": "object" }),
+        |_: serde_json::Value| async move { Ok(0) },
+    );
+    assert_eq!(
+        registry.tools().map(Tool::name).collect::<Vec<_>>(),
+        ["add", "get_weather"]
+    );
+}
+
+#[tokio::test]
+async fn loads_presets() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(chat_completion_mock("Bonjour !")).await;
+    let client = mock.client().unwrap();
+
+    let preset = json!({
+        "model": TEST_MODEL,
+        "temperature": 0.2,
+        "top_p": 0.9,
+        "stop": ["\n\n"]
+    });
+    serde_json::from_value::<ChatCompletionBuilder>(preset.clone())
+        .unwrap()
+        .message(Message::user("Say hello in French"))
+        .build(&client)
+        .await
+        .unwrap();
+
+    let request = &mock.received_json("/chat/completions").await[0];
+    assert_eq!(request["model"], TEST_MODEL);
+    assert_eq!(request["temperature"], 0.2);
+    assert_eq!(request["top_p"], 0.9);
+    assert_eq!(request["stop"], json!(["\n\n"]));
+    assert_eq!(request["messages"][0]["content"], "Say hello in French");
+
+    // Invalid presets are rejected before they're sent
+    for (field, value) in [
+        ("temperature", json!(2.5)),
+        ("top_p", json!(1.5)),
+        ("n", json!(0)),
+        ("stop", json!(["a", "b", "c", "d", "e"])),
+    ] {
+        let mut preset = preset.clone();
+        preset[field] = value;
+        let err = serde_json::from_value::<ChatCompletionBuilder>(preset)
+            .unwrap()
+            .message(Message::user("Say hello in French"))
+            .build(&client)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(field), "{err}");
+    }
+    assert_eq!(mock.received_json("/chat/completions").await.len(), 1);
+}
+
+#[tokio::test]
+async fn lays_out_few_shot_messages() {
+    let few_shot = FewShot::new("Classify the sentiment of the review")
+        .example("I loved it", "positive")
+        .example("Never again", "negative");
+
+    let messages = few_shot.messages("Not bad at all").unwrap();
+    let layout = messages
+        .iter()
+        .map(|message| (message.role, message.content.as_ref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        layout,
+        [
+            (Role::System, "Classify the sentiment of the review"),
+            (Role::User, "I loved it"),
+            (Role::Assistant, "positive"),
+            (Role::User, "Never again"),
+            (Role::Assistant, "negative"),
+            (Role::User, "Not bad at all"),
+        ]
+    );
+
+    // Without instructions, the prompt starts with the first example
+    let messages = FewShot::default()
+        .example("I loved it", "positive")
+        .messages("Not bad at all")
+        .unwrap();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[0].role, Role::User);
+
+    // The examples that don't fit the budget are dropped, starting from the last one
+    let kept = &few_shot.messages("Not bad at all").unwrap()[..3];
+    let budget = libopenai::tokens::count_message_tokens(
+        TEST_MODEL,
+        kept.iter().chain([&Message::user("Not bad at all")]),
+    )
+    .unwrap();
+    let messages = few_shot
+        .token_budget(TEST_MODEL, budget)
+        .messages("Not bad at all")
+        .unwrap();
+    let contents = messages
+        .iter()
+        .map(|message| message.content.as_ref())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        contents,
+        [
+            "Classify the sentiment of the review",
+            "I loved it",
+            "positive",
+            "Not bad at all"
+        ]
+    );
+
+    // The messages are sent as they're laid out
+    let mock = MockOpenAi::start().await;
+    mock.mount(chat_completion_mock("positive")).await;
+    let client = mock.client().unwrap();
+    ChatCompletion::builder(TEST_MODEL, messages)
+        .build(&client)
+        .await
+        .unwrap();
+    let request = &mock.received_json("/chat/completions").await[0];
+    let roles = request["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["role"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(roles, ["system", "user", "assistant", "user"]);
+}
+
+#[tokio::test]
+async fn streams_chat_completions() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(chat_completion_stream_mock(["Hel", "lo", "!"]))
+        .await;
+    let client = mock.client().unwrap();
+
+    let text = ChatCompletion::new_stream(TEST_MODEL, [Message::user("Say hello")], &client)
+        .await
+        .unwrap()
+        .into_text_stream()
+        .try_collect::<String>()
+        .await
+        .unwrap();
+    assert_eq!(text, "Hello!");
+}
+
+#[tokio::test]
+async fn reports_stream_usage() {
+    let mut last = chat_completion_chunk("");
+    last["choices"] = json!([]);
+    last["usage"] = json!({ "prompt_tokens": 4, "completion_tokens": 2, "total_tokens": 6 });
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(sse_mock(
+        "POST",
+        "/chat/completions",
+        [chat_completion_chunk("Hi"), last],
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let mut stream = ChatCompletion::builder(TEST_MODEL, [Message::user("Say hi")])
+        .include_usage(true)
+        .build_stream(&client)
+        .await
+        .unwrap();
+    let usage = stream.usage();
+    let chunks = stream.try_collect::<Vec<_>>().await.unwrap();
+    assert_eq!(
+        chunks.last().unwrap().usage.as_ref().unwrap().total_tokens,
+        6
+    );
+    assert_eq!(usage.await.unwrap().total_tokens, 6);
+
+    let request = &mock.received_json("/chat/completions").await[0];
+    assert_eq!(request["stream_options"], json!({ "include_usage": true }));
+
+    // Streams without usage resolve to `None` once they end, or once dropped
+    mock.server().reset().await;
+    mock.mount(sse_mock(
+        "POST",
+        "/chat/completions",
+        [chat_completion_chunk("Hi")],
+    ))
+    .await;
+    let mut stream = ChatCompletion::new_stream(TEST_MODEL, [Message::user("Say hi")], &client)
+        .await
+        .unwrap();
+    let usage = stream.usage();
+    while stream.try_next().await.unwrap().is_some() {}
+    let usage = tokio::time::timeout(std::time::Duration::from_secs(5), usage).await;
+    assert!(usage.unwrap().is_none());
+    assert!(stream.try_next().await.unwrap().is_none());
+
+    let mut stream = ChatCompletion::new_stream(TEST_MODEL, [Message::user("Say hi")], &client)
+        .await
+        .unwrap();
+    let usage = stream.usage();
+    drop(stream);
+    assert!(usage.await.is_none());
+}
+
+#[tokio::test]
+async fn accumulates_stream_deltas() {
+    let chunk = |choices: serde_json::Value| {
+        let mut chunk = chat_completion_chunk("");
+        chunk["choices"] = choices;
+        chunk
+    };
+    let mut usage = chunk(json!([]));
+    usage["usage"] = json!({ "prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15 });
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(sse_mock(
+        "POST",
+        "/chat/completions",
+        [
+            chunk(json!([{ "index": 0, "delta": { "role": "assistant", "content": null } }])),
+            chunk(json!([{ "index": 0, "delta": { "tool_calls": [{
+                "index": 0, "id": "call_1", "type": "function",
+                "function": { "name": "get_weather", "arguments": "" }
+            }] } }])),
+            chunk(json!([{ "index": 0, "delta": { "tool_calls": [{
+                "index": 0, "function": { "arguments": "{\"city\":" }
+            }] } }])),
+            chunk(json!([{ "index": 0, "delta": { "tool_calls": [{
+                "index": 0, "function": { "arguments": "\"Paris\"}" }
+            }] } }])),
+            chunk(json!([{ "index": 0, "delta": {}, "finish_reason": "tool_calls" }])),
+            usage,
+        ],
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let completion = ChatCompletion::new_stream(TEST_MODEL, [Message::user("Weather?")], &client)
+        .await
+        .unwrap()
+        .collect_full()
+        .await
+        .unwrap();
+
+    let choice = completion.first().unwrap();
+    assert_eq!(choice.message.role, Role::Assistant);
+    assert_eq!(choice.message.content, "");
+    assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+    let call = &choice.message.tool_calls[0];
+    assert_eq!((call.id.as_str(), call.name()), ("call_1", "get_weather"));
+    assert_eq!(call.function.arguments, r#"{"city":"Paris"}"#);
+    assert_eq!(completion.usage.unwrap().total_tokens, 15);
+}
+
+#[tokio::test]
+async fn parses_json_answers() {
+    #[derive(Debug, Deserialize)]
+    struct City {
+        name: String,
+        population: u64,
+    }
+
+    let mock = MockOpenAi::start().await;
+    mock.mock_chat("```json\n{ \"name\": \"Paris\", \"population\": 2102650 }\n```")
+        .await;
+    let client = mock.client().unwrap();
+
+    let city = ChatCompletion::builder(TEST_MODEL, [Message::user("Describe Paris as JSON")])
+        .build_json::<City>(false, &client)
+        .await
+        .unwrap();
+    assert_eq!(city.name, "Paris");
+    assert_eq!(city.population, 2102650);
+}
+
+#[test]
+fn extracts_json_from_prose() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct City {
+        name: String,
+    }
+
+    let paris = City {
+        name: "Paris".into(),
+    };
+    let answers = [
+        "Sure! Here it is: {\"name\": \"Paris\"} Let me know if you need more.",
+        "[Answer] {\"name\": \"Paris\"}",
+        "{oops} and then {\"name\": \"Paris\"}",
+        "Unbalanced ] brackets } before {\"name\": \"Paris\"}",
+        "```json\n{\"name\": \"Paris\"}\n```",
+    ];
+    for answer in answers {
+        assert_eq!(extract_json::<City>(answer).unwrap(), paris, "{answer}");
+    }
+
+    // Brackets inside strings don't end the value
+    let city =
+        extract_json::<City>(r#"The city is {"name": "Paris {or [Lutetia]} \"city\""}."#).unwrap();
+    assert_eq!(city.name, r#"Paris {or [Lutetia]} "city""#);
+
+    assert_eq!(
+        extract_json::<Vec<u32>>("Primes: [2, 3, 5]").unwrap(),
+        [2, 3, 5]
+    );
+    assert!(extract_json::<City>("There's no JSON here").is_err());
+    assert!(extract_json::<City>("Cut short: {\"name\": \"Par").is_err());
+}
+
+#[tokio::test]
+async fn retries_invalid_json_answers() {
+    #[derive(Debug, Deserialize)]
+    struct City {
+        name: String,
+    }
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        chat_completion_mock("{\"name\": Paris}")
+            .up_to_n_times(1)
+            .with_priority(1),
+    )
+    .await;
+    mock.mount(chat_completion_mock("{\"name\": \"Paris\"}"))
+        .await;
+    let client = mock.client().unwrap();
+
+    let builder = ChatCompletion::builder(TEST_MODEL, [Message::user("Describe Paris as JSON")]);
+    let city = builder
+        .clone()
+        .build_json::<City>(true, &client)
+        .await
+        .unwrap();
+    assert_eq!(city.name, "Paris");
+
+    let requests = mock.received_json("/chat/completions").await;
+    assert_eq!(requests.len(), 2);
+    let messages = requests[1]["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[1]["content"], "{\"name\": Paris}");
+    assert!(messages[2]["content"]
+        .as_str()
+        .unwrap()
+        .starts_with("Your response couldn't be parsed as JSON"));
+
+    // Without retrying, the invalid answer fails
+    mock.server().reset().await;
+    mock.mount(chat_completion_mock("{\"name\": Paris}")).await;
+    let err = builder
+        .build_json::<City>(false, &client)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Json(_)), "{err}");
+    assert_eq!(mock.received_json("/chat/completions").await.len(), 1);
+}
+
+#[tokio::test]
+async fn sends_response_formats() {
+    #[derive(Debug, Deserialize)]
+    struct City {
+        name: String,
+    }
+
+    let mock = MockOpenAi::start().await;
+    mock.mock_chat(r#"{ "name": "Paris" }"#).await;
+    let client = mock.client().unwrap();
+
+    let schema = json!({
+        "type": "object",
+        "properties": { "name": { "type": "string" } },
+        "required": ["name"],
+        "additionalProperties": false
+    });
+    let city = ChatCompletion::builder(TEST_MODEL, [Message::user("Name a city")])
+        .response_format(ResponseFormat::json_schema("city", schema.clone(), true))
+        .build_json::<City>(false, &client)
+        .await
+        .unwrap();
+    assert_eq!(city.name, "Paris");
+
+    ChatCompletion::builder(TEST_MODEL, [Message::user("Name a city, in JSON")])
+        .response_format(ResponseFormat::JsonObject)
+        .build(&client)
+        .await
+        .unwrap();
+
+    let requests = mock.received_json("/chat/completions").await;
+    assert_eq!(
+        requests[0]["response_format"],
+        json!({
+            "type": "json_schema",
+            "json_schema": { "name": "city", "schema": schema, "strict": true }
+        })
+    );
+    assert_eq!(
+        requests[1]["response_format"],
+        json!({ "type": "json_object" })
+    );
+}
+
+#[tokio::test]
+async fn builds_typed_outputs() {
+    #[derive(Debug, Deserialize, JsonSchema)]
+    struct City {
+        name: String,
+        country: Option<String>,
+    }
+
+    let mock = MockOpenAi::start().await;
+    mock.mock_chat(r#"{ "name": "Paris", "country": null }"#)
+        .await;
+    let client = mock.client().unwrap();
+
+    let city = ChatCompletion::builder(TEST_MODEL, [Message::user("Name a city")])
+        .build_typed::<City>(&client)
+        .await
+        .unwrap();
+    assert_eq!(city.name, "Paris");
+    assert_eq!(city.country, None);
+
+    let request = &mock.received_json("/chat/completions").await[0];
+    let format = &request["response_format"];
+    assert_eq!(format["type"], "json_schema");
+    assert_eq!(format["json_schema"]["name"], "City");
+    assert_eq!(format["json_schema"]["strict"], true);
+
+    let schema = &format["json_schema"]["schema"];
+    assert_eq!(schema["additionalProperties"], false);
+    assert_eq!(schema["required"], json!(["country", "name"]));
+    assert_eq!(
+        schema["properties"]["country"]["type"],
+        json!(["string", "null"])
+    );
+    assert!(schema.get("$schema").is_none());
+}
+
+#[tokio::test]
+async fn surfaces_api_errors() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(error_mock(
+        "POST",
+        "/chat/completions",
+        400,
+        "Invalid model",
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let err = ChatCompletion::new("unknown", [Message::user("Hello")], &client)
+        .await
+        .unwrap_err();
+    match err {
+        Error::OpenAI(e) => assert_eq!(e.message, "Invalid model"),
+        other => panic!("unexpected error: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn truncates_conversation_exceeding_context() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": {
+                    "message": "This model's maximum context length is 8192 tokens. However, you requested 8195 tokens.",
+                    "type": "invalid_request_error",
+                    "param": "messages",
+                    "code": "context_length_exceeded"
+                }
+            })))
+            .up_to_n_times(1)
+            .with_priority(1),
+    )
+    .await;
+    mock.mount(chat_completion_mock("Fine")).await;
+    let client = mock.client().unwrap();
+
+    let truncated = ChatCompletion::builder(
+        TEST_MODEL,
+        [
+            Message::system("Be brief"),
+            Message::user("An old question"),
+            Message::assistant("An old answer"),
+            Message::user("How are you?"),
+        ],
+    )
+    .build_truncating(Truncation::Oldest, &client)
+    .await
+    .unwrap();
+
+    assert_eq!(truncated.retries, 1);
+    assert_eq!(truncated.dropped.len(), 1);
+    assert_eq!(truncated.dropped[0].content, "An old question");
+
+    let requests = mock.received_json("/chat/completions").await;
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1]["messages"].as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn truncates_tool_calls_with_their_results() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": {
+                    "message": "This model's maximum context length is 8192 tokens. However, you requested 9000 tokens.",
+                    "type": "invalid_request_error",
+                    "param": "messages",
+                    "code": "context_length_exceeded"
+                }
+            })))
+            .up_to_n_times(1)
+            .with_priority(1),
+    )
+    .await;
+    mock.mount(chat_completion_mock("Fine")).await;
+    let client = mock.client().unwrap();
+
+    let call = |id: &str| {
+        serde_json::from_value::<Message>(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": id,
+                "type": "function",
+                "function": { "name": "get_weather", "arguments": "{}" }
+            }]
+        }))
+        .unwrap()
+    };
+    let truncated = ChatCompletion::builder(
+        TEST_MODEL,
+        [
+            Message::system("Be brief"),
+            Message::user("Hi"),
+            call("call_1"),
+            Message::tool("call_1", "Rainy"),
+            Message::user("What's the weather like?"),
+            call("call_2"),
+            Message::tool("call_2", "Sunny"),
+        ],
+    )
+    .build_truncating(Truncation::Middle, &client)
+    .await
+    .unwrap();
+
+    // The last call is kept along with its result
+    let dropped = truncated
+        .dropped
+        .iter()
+        .map(|message| (message.role, message.content.as_ref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        dropped,
+        [
+            (Role::Assistant, ""),
+            (Role::Tool, "Rainy"),
+            (Role::User, "What's the weather like?")
+        ]
+    );
+    let requests = mock.received_json("/chat/completions").await;
+    let roles = requests[1]["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["role"].as_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(roles, ["system", "user", "assistant", "tool"]);
+}
+
+#[tokio::test]
+async fn resumes_saved_conversation() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(chat_completion_mock("Nice to meet you")).await;
+    let client = mock.client().unwrap();
+
+    let dir = std::env::temp_dir().join(format!("libopenai-integration-{}", rand::random::<u64>()));
+    let store = JsonFileStore::new(&dir);
+
+    let builder = ChatCompletion::builder(TEST_MODEL, [Message::system("Be helpful")]);
+    let mut conversation = Conversation::resume("session-1", builder.clone(), &store)
+        .await
+        .unwrap();
+    conversation.send("I'm Alex", &client).await.unwrap();
+    conversation.save(&store).await.unwrap();
+
+    let mut resumed = Conversation::resume("session-1", builder, &store)
+        .await
+        .unwrap();
+    assert_eq!(resumed.messages().len(), 3);
+    resumed.send("Who am I?", &client).await.unwrap();
+
+    let request = &mock.received_json("/chat/completions").await[1];
+    assert_eq!(
+        request["messages"],
+        json!([
+            { "role": "system", "content": "Be helpful" },
+            { "role": "user", "content": "I'm Alex" },
+            { "role": "assistant", "content": "Nice to meet you" },
+            { "role": "user", "content": "Who am I?" }
+        ])
+    );
+
+    assert_eq!(store.ids().await.unwrap(), ["session-1"]);
+    assert!(store.delete("session-1").await.unwrap());
+    assert!(Conversation::load("session-1", &store)
+        .await
+        .unwrap()
+        .is_none());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn exports_transcripts() {
+    let builder = ChatCompletion::builder(TEST_MODEL, [Message::system("Be helpful")]);
+    let mut conversation = Conversation::with_id("session-1", builder);
+    conversation.push(Message::user("I'm Alex, alex@example.com"));
+    conversation.push(Message::assistant("Nice to meet you, Alex"));
+
+    let exporter = TranscriptExporter::new(TranscriptFormat::FineTune)
+        .role(Role::System, None)
+        .scrub(|content| content.replace("Alex", "[NAME]"));
+    let mut writer = JsonlWriter::temp_file().await.unwrap();
+    assert_eq!(
+        exporter.export([&conversation], &mut writer).await.unwrap(),
+        1
+    );
+
+    let exporter = TranscriptExporter::new(TranscriptFormat::Evals);
+    assert_eq!(exporter.purpose(), "evals");
+    let completion = serde_json::from_value::<ChatCompletion>(chat_completion("Paris")).unwrap();
+    let line = exporter
+        .completion_line(&[Message::user("Capital of France?")], &completion)
+        .unwrap();
+    writer.write(&line).await.unwrap();
+    // Evals items need a reply to expect
+    assert!(exporter.line(&[Message::user("Hi")]).is_none());
+
+    let path = writer.finish().await.unwrap().path.unwrap();
+    let lines = std::fs::read_to_string(&path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        lines,
+        [
+            json!({ "messages": [
+                { "role": "user", "content": "I'm [NAME], alex@example.com" },
+                { "role": "assistant", "content": "Nice to meet you, [NAME]" }
+            ] }),
+            json!({ "item": {
+                "input": [{ "role": "user", "content": "Capital of France?" }],
+                "ideal": "Paris"
+            } })
+        ]
+    );
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn saves_conversation_into_sqlite() {
+    use libopenai::chat::conversation::SqliteStore;
+
+    let store = SqliteStore::in_memory().await.unwrap();
+    let mut conversation = Conversation::with_id(
+        "session-1",
+        ChatCompletion::builder(TEST_MODEL, [Message::user("Hello")]),
+    );
+    conversation.save(&store).await.unwrap();
+    conversation.push(Message::assistant("Hi!"));
+    conversation.save(&store).await.unwrap();
+
+    let loaded = Conversation::load("session-1", &store)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(loaded.messages().len(), 2);
+    assert_eq!(store.ids().await.unwrap(), ["session-1"]);
+    assert!(store.delete("session-1").await.unwrap());
+    assert!(!store.delete("session-1").await.unwrap());
+}
+
+#[tokio::test]
+async fn round_trips_playground_payloads() {
+    let mock = MockOpenAi::start().await;
+    let client = mock.client().unwrap();
+
+    let builder = ChatCompletion::builder(
+        TEST_MODEL,
+        [Message::system("Be helpful"), Message::user("What's up?")],
+    )
+    .temperature(0.5)
+    .unwrap();
+    let payload =
+        serde_json::from_str::<serde_json::Value>(&builder.to_playground().unwrap()).unwrap();
+    assert_eq!(
+        payload,
+        json!({
+            "model": TEST_MODEL,
+            "messages": [
+                { "role": "system", "content": "Be helpful" },
+                { "role": "user", "content": "What's up?" }
+            ],
+            "temperature": 0.5
+        })
+    );
+
+    let curl = builder.to_curl(&client).unwrap();
+    assert!(curl.starts_with(&format!("curl {}/chat/completions", mock.uri())));
+    let conversation = Conversation::from_playground(&curl).unwrap();
+    assert_eq!(conversation.messages()[1].content, "What's up?");
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&conversation.to_playground().unwrap()).unwrap(),
+        payload
+    );
+
+    // Fields the builder doesn't support are kept, except for the streaming ones
+    let imported = ChatCompletionBuilder::from_playground(
+        r#"curl https://api.openai.com/v1/chat/completions -d "{\"model\": \"gpt-4o\", \"messages\": [], \"stream\": true, \"seed\": 1}""#,
+    )
+    .unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&imported.to_playground().unwrap()).unwrap(),
+        json!({ "model": "gpt-4o", "messages": [], "seed": 1 })
+    );
+}
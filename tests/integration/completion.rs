@@ -0,0 +1,397 @@
+use futures::TryStreamExt;
+use libopenai::{
+    completion::{Completion, DEADLINE_FINISH_REASON},
+    error::Error,
+    retry::RetryPolicy,
+    test_utils::{
+        completion, error, error_mock, sse_body, sse_mock, usage, MockOpenAi, TEST_API_KEY,
+        TEST_MODEL,
+    },
+    tokens::{count_tokens, remaining_tokens},
+    Client,
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+/// A chunk of a completion stream, which only reports usage in its last chunk
+fn chunk(text: &str) -> Value {
+    let mut chunk = completion(text);
+    chunk["usage"] = Value::Null;
+    return chunk;
+}
+
+#[tokio::test]
+async fn completes_prompt() {
+    let mock = MockOpenAi::start().await;
+    mock.mock_completion(" world\n").await;
+    let client = mock.client().unwrap();
+
+    let completion = Completion::builder(TEST_MODEL, "Hello")
+        .max_tokens(16)
+        .build(&client)
+        .await
+        .unwrap();
+    // Choices are trimmed of the whitespace the model surrounds them with
+    assert_eq!(completion.first().unwrap().text, "world");
+
+    let request = &mock.received_json("/completions").await[0];
+    assert_eq!(request["prompt"], json!(["Hello"]));
+    assert_eq!(request["max_tokens"], 16);
+}
+
+#[test]
+fn sets_max_tokens_from_context_window() {
+    let model = "gpt-3.5-turbo-instruct";
+    let prompt_tokens = count_tokens(model, "Write a long story").unwrap();
+    assert_eq!(
+        remaining_tokens(model, prompt_tokens).unwrap(),
+        4_096 - prompt_tokens as u64
+    );
+    assert!(remaining_tokens(model, 4_096).is_err());
+
+    let builder = Completion::builder(model, "Hi")
+        .prompt(["Write a long story", "Hi"])
+        .max_tokens_auto()
+        .unwrap();
+    let request = serde_json::to_value(&builder).unwrap();
+    assert_eq!(request["max_tokens"], 4_096 - prompt_tokens as u64);
+
+    let err = Completion::builder("unknown-model", "Hi")
+        .max_tokens_auto()
+        .unwrap_err();
+    assert!(err.to_string().contains("unknown-model"));
+}
+
+#[tokio::test]
+async fn streams_text() {
+    let mock = MockOpenAi::start().await;
+    let mut last = completion("");
+    last["choices"] = json!([]);
+    last["usage"] = usage(3, 2);
+
+    mock.mount(sse_mock(
+        "POST",
+        "/completions",
+        [chunk("Hel"), chunk("lo"), chunk("!"), last],
+    ))
+    .await;
+    let client = mock.client().unwrap().with_usage_tracking();
+
+    let text = Completion::new_stream(TEST_MODEL, "Say hello", &client)
+        .await
+        .unwrap()
+        .into_text_stream()
+        .try_collect::<String>()
+        .await
+        .unwrap();
+    assert_eq!(text, "Hello!");
+
+    let request = &mock.received_json("/completions").await[0];
+    assert_eq!(request["stream"], true);
+    assert_eq!(request["stream_options"]["include_usage"], true);
+
+    let report = client.usage_report().unwrap();
+    assert_eq!(report.total().total_tokens, 5);
+}
+
+#[tokio::test]
+async fn collects_full_streams() {
+    let choice = |index: u64, text: &str, finish_reason: Option<&str>| {
+        let mut chunk = chunk(text);
+        chunk["choices"][0]["index"] = json!(index);
+        chunk["choices"][0]["finish_reason"] = json!(finish_reason);
+        return chunk;
+    };
+    let mut last = chunk("");
+    last["choices"] = json!([]);
+    last["usage"] = usage(3, 4);
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(sse_mock(
+        "POST",
+        "/completions",
+        [
+            choice(1, "Hi", None),
+            choice(0, "Hel", None),
+            choice(0, "lo", Some("stop")),
+            choice(1, " there", Some("length")),
+            last,
+        ],
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let completion = Completion::builder(TEST_MODEL, "Say hello")
+        .n(2)
+        .build_stream(&client)
+        .await
+        .unwrap()
+        .collect_full()
+        .await
+        .unwrap();
+
+    let choices = completion
+        .choices
+        .iter()
+        .map(|x| (x.index, x.text.as_str(), x.finish_reason.as_deref()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        choices,
+        [(0, "Hello", Some("stop")), (1, "Hi there", Some("length"))]
+    );
+    assert_eq!(completion.usage.unwrap().total_tokens, 7);
+}
+
+#[tokio::test]
+async fn retries_stream_start() {
+    let mock = MockOpenAi::start().await;
+    let mut rate_limited = error("Rate limit reached");
+    rate_limited["error"]["code"] = json!("rate_limit_exceeded");
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/completions"))
+            .respond_with(ResponseTemplate::new(429).set_body_json(rate_limited))
+            .up_to_n_times(1)
+            .with_priority(1),
+    )
+    .await;
+    mock.mount(sse_mock("POST", "/completions", [chunk("Hi")]))
+        .await;
+
+    let client = mock.client().unwrap().with_retry_policy(
+        RetryPolicy::new()
+            .max_attempts(2)
+            .base_delay(Duration::from_millis(10)),
+    );
+    let text = Completion::new_stream(TEST_MODEL, "Say hi", &client)
+        .await
+        .unwrap()
+        .into_text_stream()
+        .try_collect::<String>()
+        .await
+        .unwrap();
+    assert_eq!(text, "Hi");
+    assert_eq!(mock.received_json("/completions").await.len(), 2);
+}
+
+#[tokio::test]
+async fn fails_stream_start() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(error_mock("POST", "/completions", 400, "Invalid prompt"))
+        .await;
+    let client = mock.client().unwrap().with_retry_policy(RetryPolicy::new());
+
+    match Completion::new_stream(TEST_MODEL, "Say hi", &client).await {
+        Err(Error::OpenAI(e)) => assert_eq!(e.message, "Invalid prompt"),
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("the stream started"),
+    }
+    // Errors that aren't transient aren't retried
+    assert_eq!(mock.received_json("/completions").await.len(), 1);
+}
+
+#[cfg(feature = "tracing")]
+#[tokio::test]
+async fn traces_stream_lifecycle() {
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            return Ok(buf.len());
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            return Ok(());
+        }
+    }
+
+    let logs = Logs::default();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .with_writer({
+            let logs = logs.clone();
+            move || logs.clone()
+        })
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(sse_mock(
+        "POST",
+        "/completions",
+        [chunk("Hel"), chunk("lo")],
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let text = Completion::new_stream(TEST_MODEL, "Say hello", &client)
+        .await
+        .unwrap()
+        .into_text_stream()
+        .try_collect::<String>()
+        .await
+        .unwrap();
+    assert_eq!(text, "Hello");
+
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(logs.contains("OpenAI stream started"));
+    assert!(logs.contains("time_to_first_token_ms="));
+    assert!(logs.contains("OpenAI stream finished"));
+    assert!(logs.contains("chunks=2"));
+    assert!(logs.contains("reason=\"done\""));
+}
+
+#[tokio::test]
+async fn stops_stream_at_deadline() {
+    // Server that sends the first chunk of the stream and then stalls
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = [0; 4096];
+        let _ = socket.read(&mut request).await.unwrap();
+
+        let mut first = chunk("Hel");
+        first["choices"][0]["finish_reason"] = Value::Null;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\ndata: {first}\n\n"
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+        tokio::time::sleep(Duration::from_secs(30)).await;
+    });
+    let client = Client::from_builder(reqwest::ClientBuilder::new(), Some(TEST_API_KEY), None)
+        .unwrap()
+        .with_base_url(format!("http://{addr}"));
+
+    let completion = Completion::builder(TEST_MODEL, "Say hello")
+        .build_stream_with_deadline(Duration::from_millis(300), &client)
+        .await
+        .unwrap();
+    server.abort();
+    assert_eq!(completion.id, "cmpl-test");
+    assert_eq!(completion.choices[0].text, "Hel");
+    assert_eq!(
+        completion.choices[0].finish_reason.as_deref(),
+        Some(DEADLINE_FINISH_REASON)
+    );
+
+    // Streams that end in time are returned whole
+    let mock = MockOpenAi::start().await;
+    let mut first = chunk("Hel");
+    first["choices"][0]["finish_reason"] = Value::Null;
+    mock.mount(sse_mock("POST", "/completions", [first, chunk("lo")]))
+        .await;
+    let client = mock.client().unwrap();
+
+    let completion = Completion::builder(TEST_MODEL, "Say hello")
+        .build_stream_with_deadline(Duration::from_secs(5), &client)
+        .await
+        .unwrap();
+    assert_eq!(completion.choices.len(), 1);
+    assert_eq!(completion.choices[0].text, "Hello");
+    assert_eq!(completion.choices[0].finish_reason.as_deref(), Some("stop"));
+}
+
+#[tokio::test]
+async fn returns_empty_completion_past_deadline() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(sse_body([chunk("Hi")]), "text/event-stream")
+                    .set_delay(Duration::from_secs(5)),
+            ),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let completion = Completion::builder(TEST_MODEL, "Say hello")
+        .n(2)
+        .build_stream_with_deadline(Duration::from_millis(100), &client)
+        .await
+        .unwrap();
+    assert_eq!(completion.model, TEST_MODEL);
+    assert_eq!(completion.choices.len(), 2);
+    for choice in completion.choices {
+        assert_eq!(choice.text, "");
+        assert_eq!(
+            choice.finish_reason.as_deref(),
+            Some(DEADLINE_FINISH_REASON)
+        );
+    }
+}
+
+#[tokio::test]
+async fn retries_transient_errors_honoring_retry_after() {
+    let mock = MockOpenAi::start().await;
+    let mut rate_limited = error("Rate limit reached");
+    rate_limited["error"]["code"] = json!("rate_limit_exceeded");
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/completions"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .set_body_json(rate_limited)
+                    .insert_header("retry-after-ms", "50"),
+            )
+            .up_to_n_times(1)
+            .with_priority(1),
+    )
+    .await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/completions"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("upstream unavailable"))
+            .up_to_n_times(1)
+            .with_priority(2),
+    )
+    .await;
+    mock.mock_completion("Hi").await;
+
+    let client = mock.client().unwrap().with_retry_policy(
+        RetryPolicy::new()
+            .base_delay(Duration::from_millis(10))
+            .jitter(Duration::from_millis(10)),
+    );
+    let start = std::time::Instant::now();
+    let completion = Completion::new(TEST_MODEL, "Say hi", &client)
+        .await
+        .unwrap();
+    assert_eq!(completion.choices[0].text, "Hi");
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(mock.received_json("/completions").await.len(), 3);
+}
+
+#[tokio::test]
+async fn does_not_retry_exhausted_quota() {
+    let mock = MockOpenAi::start().await;
+    let mut quota = error("You exceeded your current quota");
+    quota["error"]["type"] = json!("insufficient_quota");
+    quota["error"]["code"] = json!("insufficient_quota");
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/completions"))
+            .respond_with(ResponseTemplate::new(429).set_body_json(quota)),
+    )
+    .await;
+    let client = mock.client().unwrap().with_retry_policy(RetryPolicy::new());
+
+    match Completion::new(TEST_MODEL, "Say hi", &client).await {
+        Err(Error::OpenAI(e)) => assert_eq!(e.code.as_deref(), Some("insufficient_quota")),
+        Err(e) => panic!("unexpected error: {e}"),
+        Ok(_) => panic!("the request succeeded"),
+    }
+    assert_eq!(mock.received_json("/completions").await.len(), 1);
+}
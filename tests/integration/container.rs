@@ -0,0 +1,100 @@
+use libopenai::{
+    container::{container_files, Container, ContainerFile},
+    test_utils::{deleted, json_mock, list, MockOpenAi},
+};
+use reqwest::multipart::Part;
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+const CONTAINER: &str = "/containers/cntr-test";
+
+fn container_file(id: &str, path: &str) -> Value {
+    return json!({
+        "id": id,
+        "object": "container.file",
+        "container_id": "cntr-test",
+        "path": path,
+        "source": "user",
+        "bytes": 5,
+        "created_at": 1700000000
+    });
+}
+
+#[tokio::test]
+async fn creates_container() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/containers",
+        json!({
+            "id": "cntr-test",
+            "object": "container",
+            "name": "sandbox",
+            "status": "running",
+            "created_at": 1700000000,
+            "expires_after": { "anchor": "last_active_at", "minutes": 20 }
+        }),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let container = Container::builder("sandbox")
+        .file_id("file-test")
+        .expires_after(20)
+        .build(&client)
+        .await
+        .unwrap();
+    assert_eq!(container.id, "cntr-test");
+    assert_eq!(container.expires_after.map(|x| x.minutes), Some(20));
+
+    let request = &mock.received_json("/containers").await[0];
+    assert_eq!(request["name"], "sandbox");
+    assert_eq!(request["file_ids"], json!(["file-test"]));
+}
+
+#[tokio::test]
+async fn uploads_lists_and_downloads_files() {
+    let mock = MockOpenAi::start().await;
+    let files = format!("{CONTAINER}/files");
+    mock.mount(json_mock(
+        "POST",
+        &files,
+        container_file("cfile-test", "/mnt/data/notes.txt"),
+    ))
+    .await;
+    mock.mount(json_mock(
+        "GET",
+        &files,
+        list([container_file("cfile-test", "/mnt/data/notes.txt")]),
+    ))
+    .await;
+    mock.mount(
+        Mock::given(method("GET"))
+            .and(path(format!("{files}/cfile-test/content")))
+            .respond_with(ResponseTemplate::new(200).set_body_string("hello")),
+    )
+    .await;
+    mock.mount(json_mock(
+        "DELETE",
+        &format!("{files}/cfile-test"),
+        deleted("cfile-test", "container.file.deleted"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let part = Part::bytes(&b"hello"[..]).file_name("notes.txt");
+    let file = ContainerFile::upload_part("cntr-test", part, &client)
+        .await
+        .unwrap();
+    assert_eq!(file.filename(), "notes.txt");
+
+    let listed = container_files("cntr-test", &client).await.unwrap();
+    assert_eq!(listed.len(), 1);
+
+    let content = file.content(&client).await.unwrap();
+    assert_eq!(&content[..], b"hello");
+    assert!(file.delete(&client).await.unwrap().deleted);
+}
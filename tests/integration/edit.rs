@@ -0,0 +1,63 @@
+use libopenai::{
+    edit::{Edit, EditBuilder},
+    test_utils::{json_mock, usage, MockOpenAi},
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn edits_input() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/edits",
+        json!({
+            "object": "edit",
+            "created": 1700000000,
+            "model": "text-davinci-edit-001",
+            "choices": [{ "text": "What day is it?", "index": 0 }],
+            "usage": usage(10, 5)
+        }),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let edit = Edit::new(
+        "text-davinci-edit-001",
+        "What day of the wek is it?",
+        "Fix the spelling mistakes",
+        &client,
+    )
+    .await
+    .unwrap();
+    assert_eq!(edit.choices[0].text, "What day is it?");
+    assert_eq!(edit.usage.total_tokens, 15);
+
+    let request = &mock.received_json("/edits").await[0];
+    assert_eq!(request["input"], "What day of the wek is it?");
+    assert_eq!(request["instruction"], "Fix the spelling mistakes");
+}
+
+#[tokio::test]
+async fn validates_presets() {
+    let mock = MockOpenAi::start().await;
+    let client = mock.client().unwrap();
+
+    let preset = json!({ "model": "text-davinci-edit-001", "input": "What day of the wek is it?" });
+    let err = serde_json::from_value::<EditBuilder>(preset.clone())
+        .unwrap()
+        .build(&client)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("instruction is empty"), "{err}");
+
+    let mut preset = preset;
+    preset["top_p"] = json!(1.5);
+    let err = serde_json::from_value::<EditBuilder>(preset)
+        .unwrap()
+        .instruction("Fix the spelling mistakes")
+        .build(&client)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("top_p"), "{err}");
+    assert!(mock.received_json("/edits").await.is_empty());
+}
@@ -0,0 +1,186 @@
+use libopenai::{
+    embeddings::{Embedding, EmbeddingBuilder, EmbeddingPacking},
+    rag::{answer, EmbeddingIndex},
+    test_utils::{chat_completion_mock, embeddings, json_mock, MockOpenAi, TEST_MODEL},
+};
+use serde_json::json;
+use wiremock::{
+    matchers::{body_string_contains, method, path},
+    Mock, ResponseTemplate,
+};
+
+const EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+fn embedding_for(text: &str, embedding: &'static [f64]) -> Mock {
+    return Mock::given(method("POST"))
+        .and(path("/embeddings"))
+        .and(body_string_contains(text))
+        .respond_with(ResponseTemplate::new(200).set_body_json(embeddings([embedding])));
+}
+
+#[tokio::test]
+async fn embeds_input() {
+    let mock = MockOpenAi::start().await;
+    mock.mock_embeddings(&[0.1, 0.2, 0.3]).await;
+    let client = mock.client().unwrap();
+
+    let result = Embedding::new(EMBEDDING_MODEL, "Hello", &client)
+        .await
+        .unwrap();
+    assert_eq!(result.data[0].embedding, [0.1, 0.2, 0.3]);
+    assert_eq!(result.usage.prompt_tokens, 10);
+
+    let request = &mock.received_json("/embeddings").await[0];
+    assert_eq!(request["model"], EMBEDDING_MODEL);
+    assert_eq!(request["input"], "Hello");
+    assert!(request.get("input_type").is_none());
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn sends_extra_fields() {
+    let mock = MockOpenAi::start().await;
+    mock.mock_embeddings(&[0.1, 0.2, 0.3]).await;
+    let client = mock.client().unwrap();
+
+    Embedding::builder(EMBEDDING_MODEL, "What's the capital of France?")
+        .extra_param("input_type", "search_query")
+        .extra("truncate", "END")
+        .build(&client)
+        .await
+        .unwrap();
+
+    let request = &mock.received_json("/embeddings").await[0];
+    assert_eq!(request["input_type"], "search_query");
+    assert_eq!(request["truncate"], "END");
+
+    // Presets keep the extra fields they're deserialized with
+    let preset = serde_json::from_value::<EmbeddingBuilder>(
+        json!({ "model": EMBEDDING_MODEL, "input_type": "search_document" }),
+    )
+    .unwrap();
+    let preset = serde_json::to_value(preset.input("Paris")).unwrap();
+    assert_eq!(preset["input_type"], "search_document");
+}
+
+#[tokio::test]
+async fn rejects_presets_without_input() {
+    let mock = MockOpenAi::start().await;
+    mock.mock_embeddings(&[0.1, 0.2, 0.3]).await;
+    let client = mock.client().unwrap();
+
+    let preset =
+        serde_json::from_value::<EmbeddingBuilder>(json!({ "model": EMBEDDING_MODEL })).unwrap();
+    let err = preset.clone().build(&client).await.unwrap_err();
+    assert!(err.to_string().contains("input is empty"), "{err}");
+    assert!(mock.received_json("/embeddings").await.is_empty());
+
+    preset.input("Paris").build(&client).await.unwrap();
+    let request = &mock.received_json("/embeddings").await[0];
+    assert_eq!(request["input"], "Paris");
+}
+
+#[tokio::test]
+async fn answers_from_indexed_documents() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(embedding_for("Paris", &[1.0, 0.0])).await;
+    mock.mount(embedding_for("Rome", &[0.0, 1.0])).await;
+    mock.mount(embedding_for("capital of France", &[0.9, 0.1]))
+        .await;
+    mock.mount(chat_completion_mock("Paris [1]")).await;
+    let client = mock.client().unwrap();
+
+    let mut index = EmbeddingIndex::new(EMBEDDING_MODEL);
+    index
+        .add_documents(
+            [
+                ("france.txt", "Paris is the capital of France"),
+                ("italy.txt", "Rome is the capital of Italy"),
+            ],
+            &client,
+        )
+        .await
+        .unwrap();
+    assert_eq!(index.len(), 2);
+
+    let answer = answer(
+        "What is the capital of France?",
+        &index,
+        TEST_MODEL,
+        &client,
+    )
+    .await
+    .unwrap();
+    assert_eq!(answer.message.content, "Paris [1]");
+    assert_eq!(answer.sources[0].source, "france.txt");
+    assert!(answer.sources[0].cited);
+    assert!(!answer.sources[1].cited);
+
+    let chat = &mock.received_json("/chat/completions").await[0];
+    let prompt = chat["messages"][1]["content"].as_str().unwrap();
+    assert!(prompt.starts_with("Sources:\n\n[1] (france.txt)"));
+}
+
+#[test]
+fn packs_inputs_by_tokens() {
+    // Without the `tokens` feature, each of these inputs is estimated at 2 tokens
+    let inputs = ["aaaaaaa", "bbbbbbb", "ccccccc", "ddddddd", "eeeeeee"];
+    #[cfg(not(feature = "tokens"))]
+    assert_eq!(
+        EmbeddingPacking::new()
+            .max_request_tokens(5)
+            .pack(EMBEDDING_MODEL, &inputs)
+            .unwrap(),
+        [0..2, 2..4, 4..5]
+    );
+
+    let packing = EmbeddingPacking::new().max_inputs(3);
+    assert_eq!(
+        packing.pack(EMBEDDING_MODEL, &inputs).unwrap(),
+        [0..3, 3..5]
+    );
+
+    let packing = EmbeddingPacking::new().max_input_tokens(1);
+    assert!(packing.pack(EMBEDDING_MODEL, &inputs).is_err());
+}
+
+#[tokio::test]
+async fn embeds_inputs_in_batches() {
+    let mock = MockOpenAi::start().await;
+    let first: [&[f64]; 2] = [&[1.0, 0.0], &[0.0, 1.0]];
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .and(body_string_contains("first"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(embeddings(first))),
+    )
+    .await;
+    mock.mount(json_mock(
+        "POST",
+        "/embeddings",
+        embeddings([[0.5, 0.5].as_slice()]),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let result = Embedding::batched_with(
+        EMBEDDING_MODEL,
+        ["first", "first again", "third"],
+        EmbeddingPacking::new().max_inputs(2),
+        &client,
+    )
+    .await
+    .unwrap();
+    let indices = result.data.iter().map(|e| e.index).collect::<Vec<_>>();
+    assert_eq!(indices, [0, 1, 2]);
+    assert_eq!(result.data[2].embedding, [0.5, 0.5]);
+    assert_eq!(result.usage.prompt_tokens, 20);
+
+    let requests = mock.received_json("/embeddings").await;
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[0]["input"],
+        serde_json::json!(["first", "first again"])
+    );
+    assert_eq!(requests[1]["input"], serde_json::json!(["third"]));
+}
@@ -0,0 +1,181 @@
+use futures::TryStreamExt;
+use libopenai::{
+    batch::BatchOutputLine,
+    file::{delete_file, files, File},
+    finetune::data::TrainingData,
+    test_utils::{deleted, file, json_mock, list, page, MockOpenAi},
+};
+use reqwest::multipart::Part;
+use serde_json::Value;
+use wiremock::{
+    matchers::{method, path, query_param},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn uploads_multipart_file() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/files",
+        file("file-test", "data.jsonl", "fine-tune"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let part = Part::bytes(&b"{\"prompt\":\"a\"}\n"[..]).file_name("data.jsonl");
+    let file = File::upload_part(part, "fine-tune", &client).await.unwrap();
+    assert_eq!(file.id, "file-test");
+    assert_eq!(file.filename, "data.jsonl");
+
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    let body = String::from_utf8_lossy(&request.body);
+    assert!(body.contains("name=\"purpose\""));
+    assert!(body.contains("fine-tune"));
+    assert!(body.contains("{\"prompt\":\"a\"}"));
+}
+
+#[tokio::test]
+async fn lists_files() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "GET",
+        "/files",
+        list([
+            file("file-a", "a.jsonl", "fine-tune"),
+            file("file-b", "b.jsonl", "batch"),
+        ]),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let files = files(&client).await.unwrap();
+    let ids = files.iter().map(|x| x.id.as_str()).collect::<Vec<_>>();
+    assert_eq!(ids, ["file-a", "file-b"]);
+}
+
+#[tokio::test]
+async fn lists_every_page_of_files() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "GET",
+        "/files",
+        page(
+            [
+                file("file-a", "a.jsonl", "fine-tune"),
+                file("file-b", "b.jsonl", "batch"),
+            ],
+            true,
+        ),
+    ))
+    .await;
+    mock.mount(
+        Mock::given(method("GET"))
+            .and(path("/files"))
+            .and(query_param("after", "file-b"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(page([file("file-c", "c.jsonl", "batch")], false)),
+            )
+            .with_priority(1),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let files = files(&client).await.unwrap();
+    let ids = files.iter().map(|x| x.id.as_str()).collect::<Vec<_>>();
+    assert_eq!(ids, ["file-a", "file-b", "file-c"]);
+
+    let requests = mock.server().received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].url.query(), None);
+    assert_eq!(requests[1].url.query(), Some("after=file-b"));
+}
+
+#[tokio::test]
+async fn streams_jsonl_content() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "GET",
+        "/files/file-test",
+        file("file-test", "data.jsonl", "fine-tune"),
+    ))
+    .await;
+    mock.mount(
+        Mock::given(method("GET"))
+            .and(path("/files/file-test/content"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string("{\"n\":1}\n{\"n\":2}\n{\"n\":3}\n"),
+            ),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let file = File::retreive("file-test", &client).await.unwrap();
+    let lines = file
+        .content::<Value>(&client)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+
+    let numbers = lines
+        .iter()
+        .map(|x| x["n"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(numbers, [1, 2, 3]);
+}
+
+#[tokio::test]
+async fn parses_typed_content() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "GET",
+        "/files/file-test",
+        file("file-test", "data.jsonl", "fine-tune"),
+    ))
+    .await;
+    mock.mount(
+        Mock::given(method("GET"))
+            .and(path("/files/file-test/content"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "{\"prompt\":\"a\",\"completion\":\"b\"}\n\n{\"prompt\":\"c\"}\n",
+            )),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let file = File::retreive("file-test", &client).await.unwrap();
+    let mut lines = Box::pin(file.content_as::<TrainingData>(&client).await.unwrap());
+    let first = lines.try_next().await.unwrap().unwrap();
+    assert_eq!(first.completion, "b");
+
+    let err = lines.try_next().await.unwrap_err().to_string();
+    assert!(err.contains("Line 3 of file 'file-test' isn't a valid training example"));
+    assert!(err.contains("missing field `completion`"));
+
+    // Files of other purposes are rejected up front
+    let err = file
+        .content_as::<BatchOutputLine>(&client)
+        .await
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("purpose 'batch_output'"));
+}
+
+#[tokio::test]
+async fn deletes_file() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "DELETE",
+        "/files/file-test",
+        deleted("file-test", "file"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let delete = delete_file("file-test", &client).await.unwrap();
+    assert!(delete.deleted);
+}
@@ -0,0 +1,108 @@
+use futures::TryStreamExt;
+use libopenai::{
+    finetune::{fine_tune_event_stream, fine_tune_events, FineTune},
+    test_utils::{json_mock, list, sse_mock, MockOpenAi},
+};
+use serde_json::{json, Value};
+use wiremock::{
+    matchers::{method, path, query_param},
+    Mock, ResponseTemplate,
+};
+
+const EVENTS: &str = "/fine-tunes/ft-test/events";
+
+fn event(message: &str) -> Value {
+    return json!({
+        "object": "fine-tune-event",
+        "created_at": 1700000000,
+        "level": "info",
+        "message": message
+    });
+}
+
+#[tokio::test]
+async fn creates_fine_tune() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/fine-tunes",
+        json!({
+            "id": "ft-test",
+            "object": "fine-tune",
+            "model": "curie",
+            "created_at": 1700000000,
+            "fine_tuned_model": null,
+            "hyperparams": { "n_epochs": 2, "prompt_loss_weight": 0.01 },
+            "organization_id": "org-test",
+            "result_files": [],
+            "status": "pending",
+            "validation_files": [],
+            "training_files": [],
+            "updated_at": 1700000000
+        }),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let fine_tune = FineTune::builder("file-test")
+        .n_epochs(2)
+        .build(&client)
+        .await
+        .unwrap();
+    assert_eq!(fine_tune.status, "pending");
+    assert!(fine_tune.fine_tuned_model().is_err());
+
+    let request = &mock.received_json("/fine-tunes").await[0];
+    assert_eq!(request["training_file"], "file-test");
+    assert_eq!(request["n_epochs"], 2);
+}
+
+#[tokio::test]
+async fn lists_events() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("GET"))
+            .and(path(EVENTS))
+            .and(query_param("stream", "false"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(list([event("Created"), event("Started")])),
+            ),
+    )
+    .await;
+    let client = mock.client().unwrap();
+
+    let events = fine_tune_events("ft-test", &client).await.unwrap();
+    let messages = events
+        .iter()
+        .map(|x| x.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["Created", "Started"]);
+}
+
+#[tokio::test]
+async fn streams_events() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(sse_mock(
+        "GET",
+        EVENTS,
+        [event("Created"), event("Epoch 1 done"), event("Succeeded")],
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let events = fine_tune_event_stream("ft-test", &client)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    let messages = events
+        .iter()
+        .map(|x| x.message.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(messages, ["Created", "Epoch 1 done", "Succeeded"]);
+
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    assert_eq!(request.url.query(), Some("stream=true"));
+}
@@ -0,0 +1,66 @@
+use libopenai::{
+    health::HealthStatus,
+    test_utils::{error_mock, json_mock, list, model, MockOpenAi},
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[tokio::test]
+async fn pings_api() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock("GET", "/models", list([model("gpt-4o")])))
+        .await;
+    let client = mock.client().unwrap();
+
+    let health = client.ping().await;
+    assert!(health.is_healthy());
+    assert!(health.latency > Duration::ZERO);
+}
+
+#[tokio::test]
+async fn reports_invalid_credentials() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(error_mock("GET", "/models", 401, "Incorrect API key"))
+        .await;
+    let client = mock.client().unwrap();
+    assert_eq!(client.ping().await.status, HealthStatus::Unauthorized);
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(error_mock("GET", "/models", 503, "Overloaded"))
+        .await;
+    let client = mock.client().unwrap();
+    match client.ping().await.status {
+        HealthStatus::Unavailable { reason } => assert!(reason.contains("Overloaded")),
+        status => panic!("unexpected status: {status:?}"),
+    }
+}
+
+#[tokio::test]
+async fn watches_health() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock("GET", "/models", list([model("gpt-4o")])))
+        .await;
+    let client = mock.client().unwrap();
+
+    let statuses = Arc::new(Mutex::new(Vec::new()));
+    let check = client.watch_health(Duration::from_millis(10), {
+        let statuses = statuses.clone();
+        move |health| statuses.lock().unwrap().push(health.status.clone())
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    check.stop();
+
+    let count = statuses.lock().unwrap().len();
+    assert!(count >= 2);
+    assert!(statuses
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|status| *status == HealthStatus::Healthy));
+
+    // No more pings are sent once the check is stopped
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(statuses.lock().unwrap().len(), count);
+}
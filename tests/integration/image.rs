@@ -0,0 +1,583 @@
+use base64::Engine;
+use futures::TryStreamExt;
+use image::{imageops::FilterType, DynamicImage, GrayImage, ImageFormat, RgbImage, RgbaImage};
+use libopenai::{
+    image::{
+        edit::MaskBuilder,
+        generate::{ImageEvent, ImageQuality, ImageStyle},
+        preprocess::ImagePreprocessor,
+        variation::VariationBuilder,
+        ImageResponseFormat, Images, Naming, Size,
+    },
+    test_utils::{json_mock, sse_mock, MockOpenAi},
+};
+use reqwest::multipart::Part;
+use serde_json::{json, Value};
+use std::io::Cursor;
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    DynamicImage::ImageRgb8(RgbImage::new(width, height))
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    return bytes;
+}
+
+fn images(data: impl IntoIterator<Item = Value>) -> Value {
+    return json!({
+        "created": 1700000000,
+        "data": data.into_iter().collect::<Vec<_>>()
+    });
+}
+
+#[tokio::test]
+async fn generates_and_saves_images() {
+    let b64 = base64::engine::general_purpose::STANDARD.encode(png(8, 8));
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/images/generations",
+        images([json!({ "b64_json": b64 }), json!({ "b64_json": b64 })]),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let images = Images::create("A black square")
+        .unwrap()
+        .n(2)
+        .unwrap()
+        .response_format(ImageResponseFormat::B64Json)
+        .build(&client)
+        .await
+        .unwrap();
+    assert_eq!(images.data.len(), 2);
+
+    let request = &mock.received_json("/images/generations").await[0];
+    assert_eq!(request["prompt"], "A black square");
+    assert_eq!(request["response_format"], "b64_json");
+
+    let dir = std::env::temp_dir().join(format!("libopenai-integration-{}", rand::random::<u64>()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    let paths = images
+        .post_process(|image| Ok(image.resize_exact(4, 4, FilterType::Nearest)))
+        .output_format(ImageFormat::Jpeg)
+        .save_at_with(&dir, Naming::Prompt("A black square".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(paths.len(), 2);
+    for (i, path) in paths.iter().enumerate() {
+        let name = path.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with(&format!("a-black-square-{i}-")));
+        assert!(name.ends_with(".jpg"));
+
+        let saved = image::open(path).unwrap();
+        assert_eq!((saved.width(), saved.height()), (4, 4));
+    }
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[tokio::test]
+async fn uploads_image_for_variations() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/images/variations",
+        images([json!({ "url": "https://example.com/variation.png" })]),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let part = Part::bytes(png(8, 8)).file_name("input.png");
+    let images = VariationBuilder::new()
+        .n(1)
+        .unwrap()
+        .with_part(part, &client)
+        .await
+        .unwrap();
+    assert_eq!(images.data[0].as_str(), "https://example.com/variation.png");
+
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    let body = String::from_utf8_lossy(&request.body);
+    assert!(body.contains("name=\"image\"; filename=\"input.png\""));
+    assert!(body.contains("name=\"n\""));
+}
+
+#[tokio::test]
+async fn streams_partial_images() {
+    let (partial, last) = (png(2, 2), png(8, 8));
+    let encode = |png: &[u8]| base64::engine::general_purpose::STANDARD.encode(png);
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(sse_mock(
+        "POST",
+        "/images/generations",
+        [
+            json!({
+                "type": "image_generation.partial_image",
+                "b64_json": encode(&partial),
+                "partial_image_index": 0,
+                "created_at": 1700000000
+            }),
+            json!({
+                "type": "image_generation.completed",
+                "b64_json": encode(&last),
+                "created_at": 1700000001,
+                "usage": {
+                    "total_tokens": 100,
+                    "input_tokens": 50,
+                    "output_tokens": 50,
+                    "input_tokens_details": { "text_tokens": 10, "image_tokens": 40 }
+                }
+            }),
+            json!({
+                "type": "image_generation.completed",
+                "b64_json": encode(&partial),
+                "created_at": 1700000002
+            }),
+        ],
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let builder = Images::create("A black square")
+        .unwrap()
+        .model("gpt-image-1")
+        .partial_images(1)
+        .unwrap();
+    let events = builder
+        .clone()
+        .build_stream(&client)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert!(matches!(
+        events[0],
+        ImageEvent::PartialImage {
+            partial_image_index: 0,
+            ..
+        }
+    ));
+    let preview = events[0].image().unwrap().into_image().await.unwrap();
+    assert_eq!(preview.width(), 2);
+    match &events[1] {
+        ImageEvent::Completed {
+            usage: Some(usage), ..
+        } => assert_eq!((usage.input_tokens, usage.total_tokens), (50, 100)),
+        event => panic!("unexpected event: {event:?}"),
+    }
+
+    let images = builder
+        .build_stream(&client)
+        .await
+        .unwrap()
+        .into_images()
+        .await
+        .unwrap();
+    // Every completed image is kept
+    assert_eq!(images.data.len(), 2);
+    assert_eq!(images.revised_prompts.len(), 2);
+    let image = images.data[0].clone().into_image().await.unwrap();
+    assert_eq!(image.width(), 8);
+    let image = images.data[1].clone().into_image().await.unwrap();
+    assert_eq!(image.width(), 2);
+
+    let request = &mock.received_json("/images/generations").await[0];
+    assert_eq!(request["model"], "gpt-image-1");
+    assert_eq!(request["stream"], true);
+    assert_eq!(request["partial_images"], 1);
+
+    assert!(Images::create("A black square")
+        .unwrap()
+        .partial_images(4)
+        .is_err());
+}
+
+#[tokio::test]
+async fn generates_with_dall_e_3() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/images/generations",
+        json!({
+            "created": 1700000000,
+            "data": [{
+                "url": "https://example.com/lighthouse.png",
+                "revised_prompt": "A lighthouse on a cliff at dusk, in watercolor"
+            }]
+        }),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let prompt = "A lighthouse ".repeat(100);
+    let images = Images::create(prompt.as_str())
+        .unwrap()
+        .model("dall-e-3")
+        .quality(ImageQuality::Hd)
+        .style(ImageStyle::Natural)
+        .build(&client)
+        .await
+        .unwrap();
+    assert_eq!(
+        images.data[0].as_str(),
+        "https://example.com/lighthouse.png"
+    );
+    assert_eq!(
+        images.revised_prompts[0].as_deref(),
+        Some("A lighthouse on a cliff at dusk, in watercolor")
+    );
+    let json = serde_json::to_value(&images).unwrap();
+    assert_eq!(
+        json["data"][0]["revised_prompt"],
+        "A lighthouse on a cliff at dusk, in watercolor"
+    );
+
+    let request = &mock.received_json("/images/generations").await[0];
+    assert_eq!(request["model"], "dall-e-3");
+    assert_eq!(request["quality"], "hd");
+    assert_eq!(request["style"], "natural");
+
+    // The default model has a shorter prompt limit, and neither quality nor style
+    assert!(Images::create(prompt.as_str())
+        .unwrap()
+        .build(&client)
+        .await
+        .is_err());
+    assert!(Images::create("A lighthouse")
+        .unwrap()
+        .style(ImageStyle::Vivid)
+        .build(&client)
+        .await
+        .is_err());
+    assert!(Images::create("A lighthouse")
+        .unwrap()
+        .model("gpt-image-1")
+        .quality(ImageQuality::Hd)
+        .build(&client)
+        .await
+        .is_err());
+    assert_eq!(mock.received_json("/images/generations").await.len(), 1);
+}
+
+#[tokio::test]
+async fn validates_sizes_per_model() {
+    let mock = MockOpenAi::start().await;
+    for path in ["/images/generations", "/images/edits"] {
+        mock.mount(json_mock(
+            "POST",
+            path,
+            images([json!({ "url": "https://example.com/image.png" })]),
+        ))
+        .await;
+    }
+    let client = mock.client().unwrap();
+
+    for (model, size) in [("dall-e-3", Size::P1792x1024), ("gpt-image-1", Size::Auto)] {
+        Images::create("A wide landscape")
+            .unwrap()
+            .model(model)
+            .size(size)
+            .build(&client)
+            .await
+            .unwrap();
+    }
+    let requests = mock.received_json("/images/generations").await;
+    assert_eq!(requests[0]["size"], "1792x1024");
+    assert_eq!(requests[1]["size"], "auto");
+
+    let part = Part::bytes(png(8, 8)).file_name("input.png");
+    Images::edit("A tall tower")
+        .unwrap()
+        .model("gpt-image-1")
+        .size(Size::P1024x1536)
+        .with_part(part, None, &client)
+        .await
+        .unwrap();
+    let request = &mock.server().received_requests().await.unwrap()[2];
+    let body = String::from_utf8_lossy(&request.body);
+    assert!(body.contains("gpt-image-1"));
+    assert!(body.contains("1024x1536"));
+
+    // Sizes unsupported by the model are rejected before the request is sent
+    assert!(Images::create("A wide landscape")
+        .unwrap()
+        .size(Size::P1792x1024)
+        .build(&client)
+        .await
+        .is_err());
+    assert!(Images::create("A wide landscape")
+        .unwrap()
+        .model("dall-e-3")
+        .size(Size::P1536x1024)
+        .build(&client)
+        .await
+        .is_err());
+    assert!(Images::edit("A tall tower")
+        .unwrap()
+        .size(Size::P1024x1792)
+        .into_request(Part::bytes(png(8, 8)), None)
+        .is_err());
+    assert!(VariationBuilder::new()
+        .size(Size::Auto)
+        .into_request(Part::bytes(png(8, 8)))
+        .is_err());
+    assert_eq!(mock.server().received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn names_saved_images_after_their_format() {
+    let encode = |image: DynamicImage, format: ImageFormat| {
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), format)
+            .unwrap();
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    };
+    let (jpeg, png) = (
+        encode(
+            DynamicImage::ImageRgb8(RgbImage::new(8, 8)),
+            ImageFormat::Jpeg,
+        ),
+        encode(
+            DynamicImage::ImageRgba8(RgbaImage::new(8, 8)),
+            ImageFormat::Png,
+        ),
+    );
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/images/generations",
+        images([json!({ "b64_json": jpeg }), json!({ "b64_json": png })]),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+    let images = Images::create("A black square")
+        .unwrap()
+        .n(2)
+        .unwrap()
+        .response_format(ImageResponseFormat::B64Json)
+        .build(&client)
+        .await
+        .unwrap();
+
+    let dir = std::env::temp_dir().join(format!("libopenai-integration-{}", rand::random::<u64>()));
+    tokio::fs::create_dir_all(&dir).await.unwrap();
+
+    // Without post-processing, images are saved as returned, with the extension of their format
+    let paths = images
+        .clone()
+        .save_at_named(&dir, |i, _| format!("square-{i}"))
+        .await
+        .unwrap();
+    assert_eq!(paths[0], dir.join("square-0.jpg"));
+    assert_eq!(paths[1], dir.join("square-1.png"));
+    let saved = tokio::fs::read(&paths[0]).await.unwrap();
+    assert_eq!(
+        base64::engine::general_purpose::STANDARD.encode(saved),
+        jpeg
+    );
+
+    // Transparent images are converted into formats without transparency
+    for (format, extension) in [(ImageFormat::Jpeg, "jpg"), (ImageFormat::WebP, "webp")] {
+        let paths = images
+            .clone()
+            .output_format(format)
+            .save_at_named(&dir, |i, _| format!("transcoded-{i}"))
+            .await
+            .unwrap();
+        for (i, path) in paths.iter().enumerate() {
+            assert_eq!(path, &dir.join(format!("transcoded-{i}.{extension}")));
+            let saved = image::open(path).unwrap();
+            assert_eq!(saved.width(), 8);
+        }
+    }
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+#[tokio::test]
+async fn sends_built_masks() {
+    let mask = MaskBuilder::new(8, 8).rect(2, 2, 4, 100);
+    let encoded = image::load_from_memory(&mask.to_png().unwrap())
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(encoded.dimensions(), (8, 8));
+    assert_eq!(encoded.get_pixel(0, 0)[3], 255);
+    assert_eq!(encoded.get_pixel(3, 7)[3], 0);
+    assert_eq!(encoded.get_pixel(6, 3)[3], 255);
+
+    let gray = GrayImage::from_fn(8, 8, |x, _| image::Luma([(x * 32) as u8]));
+    let encoded = image::load_from_memory(&MaskBuilder::from_gray(gray).to_png().unwrap())
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(encoded.get_pixel(4, 0)[3], 255 - 128);
+
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/images/edits",
+        images([json!({ "url": "https://example.com/edit.png" })]),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let builder = Images::edit("A window").unwrap().mask(mask);
+    builder
+        .clone()
+        .with_part(Part::bytes(png(8, 8)).file_name("house.png"), None, &client)
+        .await
+        .unwrap();
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    let body = String::from_utf8_lossy(&request.body);
+    assert!(body.contains("name=\"mask\"; filename=\"mask.png\""));
+
+    // A mask can't be both built and sent
+    assert!(builder
+        .into_request(Part::bytes(png(8, 8)), Some(Part::bytes(png(8, 8))))
+        .is_err());
+
+    // Masks of files are padded and scaled along with them
+    let path = std::env::temp_dir().join(format!("libopenai-{}.png", rand::random::<u64>()));
+    tokio::fs::write(&path, png(8, 4)).await.unwrap();
+    Images::edit("A window")
+        .unwrap()
+        .mask(MaskBuilder::new(8, 4).rect(0, 0, 8, 4))
+        .with_file(&path, None, &client)
+        .await
+        .unwrap();
+
+    let request = &mock.server().received_requests().await.unwrap()[1];
+    let mask_start = request
+        .body
+        .windows(8)
+        .rposition(|x| x == b"\x89PNG\r\n\x1a\n")
+        .unwrap();
+    let sent = image::load_from_memory(&request.body[mask_start..])
+        .unwrap()
+        .into_rgba8();
+    assert_eq!(sent.dimensions(), (8, 8));
+    assert_eq!(sent.get_pixel(0, 0)[3], 255);
+    assert_eq!(sent.get_pixel(0, 3)[3], 0);
+
+    assert!(Images::edit("A window")
+        .unwrap()
+        .mask(MaskBuilder::new(8, 8))
+        .with_file(&path, None, &client)
+        .await
+        .is_err());
+    tokio::fs::remove_file(&path).await.unwrap();
+}
+
+#[tokio::test]
+async fn preprocesses_images() {
+    let preprocessor = ImagePreprocessor::new();
+
+    // Square RGBA PNGs are sent as they are
+    let valid = {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgba8(RgbaImage::new(8, 8))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    };
+    assert_eq!(preprocessor.process(valid.clone()).await.unwrap(), valid);
+
+    let squared = preprocessor.process(png(8, 4)).await.unwrap();
+    let squared = image::load_from_memory(&squared).unwrap().into_rgba8();
+    assert_eq!(squared.dimensions(), (8, 8));
+    assert_eq!(squared.get_pixel(0, 0)[3], 0);
+    assert_eq!(squared.get_pixel(0, 4)[3], 255);
+
+    let noise = RgbaImage::from_fn(64, 64, |_, _| image::Rgba(rand::random()));
+    let mut noise_png = Vec::new();
+    DynamicImage::ImageRgba8(noise)
+        .write_to(&mut Cursor::new(&mut noise_png), ImageFormat::Png)
+        .unwrap();
+    let shrunk = preprocessor
+        .max_bytes(4000)
+        .process(noise_png.clone())
+        .await
+        .unwrap();
+    assert!(shrunk.len() <= 4000);
+    assert!(image::load_from_memory(&shrunk).unwrap().width() < 64);
+    assert!(preprocessor.max_bytes(10).process(valid).await.is_err());
+
+    // Files are converted before they're uploaded
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/images/variations",
+        images([json!({ "url": "https://example.com/variation.png" })]),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let path = std::env::temp_dir().join(format!("libopenai-{}.jpg", rand::random::<u64>()));
+    DynamicImage::ImageRgb8(RgbImage::new(8, 4))
+        .save_with_format(&path, ImageFormat::Jpeg)
+        .unwrap();
+    VariationBuilder::new()
+        .preprocessor(preprocessor)
+        .with_file(&path, &client)
+        .await
+        .unwrap();
+    tokio::fs::remove_file(&path).await.unwrap();
+
+    let request = &mock.server().received_requests().await.unwrap()[0];
+    let start = request
+        .body
+        .windows(8)
+        .position(|x| x == b"\x89PNG\r\n\x1a\n")
+        .unwrap();
+    let sent = image::load_from_memory(&request.body[start..]).unwrap();
+    assert_eq!((sent.width(), sent.height()), (8, 8));
+
+    // Mask files are scaled along with their image
+    mock.mount(json_mock(
+        "POST",
+        "/images/edits",
+        images([json!({ "url": "https://example.com/edit.png" })]),
+    ))
+    .await;
+    let path = std::env::temp_dir().join(format!("libopenai-{}.png", rand::random::<u64>()));
+    let mask_path = std::env::temp_dir().join(format!("libopenai-{}.png", rand::random::<u64>()));
+    tokio::fs::write(&path, &noise_png).await.unwrap();
+    RgbaImage::from_fn(64, 64, |x, _| {
+        image::Rgba([0, 0, 0, if x < 32 { 0 } else { 255 }])
+    })
+    .save_with_format(&mask_path, ImageFormat::Png)
+    .unwrap();
+    Images::edit("A window")
+        .unwrap()
+        .preprocessor(preprocessor.max_bytes(4000))
+        .with_file(&path, Some(mask_path.clone()), &client)
+        .await
+        .unwrap();
+    tokio::fs::remove_file(&path).await.unwrap();
+    tokio::fs::remove_file(&mask_path).await.unwrap();
+
+    let request = &mock.server().received_requests().await.unwrap()[1];
+    let starts = request
+        .body
+        .windows(8)
+        .enumerate()
+        .filter(|(_, x)| *x == b"\x89PNG\r\n\x1a\n")
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let sent = image::load_from_memory(&request.body[starts[0]..])
+        .unwrap()
+        .into_rgba8();
+    let sent_mask = image::load_from_memory(&request.body[starts[1]..])
+        .unwrap()
+        .into_rgba8();
+    assert!(sent.width() < 64);
+    assert_eq!(sent_mask.dimensions(), sent.dimensions());
+    assert_eq!(sent_mask.get_pixel(0, 0)[3], 0);
+    assert_eq!(sent_mask.get_pixel(sent.width() - 1, 0)[3], 255);
+}
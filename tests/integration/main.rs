@@ -0,0 +1,25 @@
+//! Request/response cycles of every module against a mock of the API, without an API key or network access.
+//!
+//! Run with `cargo test --features integration-tests`.
+
+mod accounting;
+mod assistants;
+mod audio;
+mod azure;
+mod batch;
+mod cache;
+mod chat;
+mod completion;
+mod container;
+mod edit;
+mod embeddings;
+mod file;
+mod finetune;
+mod health;
+mod image;
+mod model;
+mod moderations;
+mod organization;
+mod responses;
+mod shutdown;
+mod snapshot;
@@ -0,0 +1,97 @@
+use libopenai::{
+    endpoint::Endpoint,
+    model::{models, Model},
+    test_utils::{error_mock, json_mock, list, model, MockOpenAi, TEST_API_KEY},
+    Client,
+};
+
+#[tokio::test]
+async fn lists_models() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "GET",
+        "/models",
+        list([model("gpt-4o"), model("whisper-1")]),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let models = models(&client).await.unwrap();
+    let ids = models.iter().map(|x| x.id.as_str()).collect::<Vec<_>>();
+    assert_eq!(ids, ["gpt-4o", "whisper-1"]);
+    assert_eq!(models[0].owned_by, "openai");
+}
+
+#[tokio::test]
+async fn retrieves_model() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock("GET", "/models/gpt-4o", model("gpt-4o")))
+        .await;
+    mock.mount(error_mock(
+        "GET",
+        "/models/unknown",
+        404,
+        "The model 'unknown' does not exist",
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let model = Model::get("gpt-4o", &client).await.unwrap();
+    assert_eq!(model.id, "gpt-4o");
+
+    let err = Model::get("unknown", &client).await.unwrap_err();
+    assert!(err.to_string().contains("does not exist"));
+}
+
+#[tokio::test]
+async fn sends_endpoint_defaults() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock("GET", "/models/gpt-4o", model("gpt-4o")))
+        .await;
+    mock.mount(json_mock("GET", "/models", list([model("gpt-4o")])))
+        .await;
+    let client = mock
+        .client()
+        .unwrap()
+        .with_endpoint_header(Endpoint::Models, "OpenAI-Beta", "models=v2")
+        .unwrap()
+        .with_endpoint_query(Endpoint::Models, "beta", "true")
+        .with_endpoint_header(Endpoint::Files, "OpenAI-Beta", "files=v2")
+        .unwrap();
+
+    Model::get("gpt-4o", &client).await.unwrap();
+    models(&client).await.unwrap();
+
+    let requests = mock.server().received_requests().await.unwrap();
+    assert_eq!(requests.len(), 2);
+    for request in requests {
+        let beta = &request.headers[&"OpenAI-Beta".into()];
+        assert_eq!(
+            beta.iter().map(|x| x.as_str()).collect::<Vec<_>>(),
+            ["models=v2"]
+        );
+        assert_eq!(request.url.query(), Some("beta=true"));
+    }
+
+    let err = client.with_endpoint_header(Endpoint::Models, "Bad Header", "value");
+    assert!(err.is_err());
+}
+
+#[tokio::test]
+async fn sends_requests_to_builder_base_url() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock("GET", "/v1/models", list([model("llama3")])))
+        .await;
+
+    let client = Client::builder()
+        .api_key(TEST_API_KEY)
+        .base_url(format!("{}/v1/", mock.uri()))
+        .build()
+        .unwrap();
+    assert_eq!(models(&client).await.unwrap()[0].id, "llama3");
+
+    // Clients are HTTPS-only unless their base URL says otherwise
+    let client = Client::builder().api_key(TEST_API_KEY).build().unwrap();
+    let client = client.with_base_url(format!("{}/v1", mock.uri()));
+    assert!(models(&client).await.is_err());
+}
@@ -0,0 +1,51 @@
+use libopenai::{
+    moderations::Moderation,
+    test_utils::{json_mock, MockOpenAi},
+};
+use serde_json::{json, Value};
+
+fn categories(violence: Value, other: Value) -> Value {
+    return json!({
+        "hate": other,
+        "hate/threatening": other,
+        "self-harm": other,
+        "sexual": other,
+        "sexual/minors": other,
+        "violence": violence,
+        "violence/graphic": other
+    });
+}
+
+#[tokio::test]
+async fn classifies_input() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/moderations",
+        json!({
+            "id": "modr-test",
+            "model": "omni-moderation-latest",
+            "results": [{
+                "flagged": true,
+                "categories": categories(json!(true), json!(false)),
+                "category_scores": categories(json!(0.9), json!(0.01))
+            }]
+        }),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let moderation = Moderation::new("I will hurt you", Some("omni-moderation-latest"), &client)
+        .await
+        .unwrap();
+
+    let result = &moderation.results[0];
+    assert!(result.flagged);
+    assert!(result.categories.violence);
+    assert!(!result.categories.hate);
+    assert_eq!(result.category_scores.violence, 0.9);
+
+    let request = &mock.received_json("/moderations").await[0];
+    assert_eq!(request["input"], "I will hurt you");
+    assert_eq!(request["model"], "omni-moderation-latest");
+}
@@ -0,0 +1,70 @@
+use libopenai::{
+    organization::{
+        invite::{delete_invite, invites, Invite, InviteStatus},
+        OrganizationRole,
+    },
+    test_utils::{deleted, json_mock, list, MockOpenAi},
+};
+use serde_json::{json, Value};
+
+fn invite(id: &str, email: &str, status: &str) -> Value {
+    return json!({
+        "object": "organization.invite",
+        "id": id,
+        "email": email,
+        "role": "reader",
+        "status": status,
+        "invited_at": 1700000000,
+        "expires_at": 1700604800
+    });
+}
+
+#[tokio::test]
+async fn invites_user() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "POST",
+        "/organization/invites",
+        invite("invite-test", "user@example.com", "pending"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let invite = Invite::new("user@example.com", OrganizationRole::Reader, &client)
+        .await
+        .unwrap();
+    assert_eq!(invite.status, InviteStatus::Pending);
+
+    let request = &mock.received_json("/organization/invites").await[0];
+    assert_eq!(
+        request,
+        &json!({ "email": "user@example.com", "role": "reader" })
+    );
+}
+
+#[tokio::test]
+async fn lists_and_deletes_invites() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "GET",
+        "/organization/invites",
+        list([
+            invite("invite-a", "a@example.com", "accepted"),
+            invite("invite-b", "b@example.com", "pending"),
+        ]),
+    ))
+    .await;
+    mock.mount(json_mock(
+        "DELETE",
+        "/organization/invites/invite-b",
+        deleted("invite-b", "organization.invite.deleted"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let invites = invites(&client).await.unwrap();
+    assert_eq!(invites.len(), 2);
+    assert_eq!(invites[0].status, InviteStatus::Accepted);
+
+    assert!(delete_invite("invite-b", &client).await.unwrap().deleted);
+}
@@ -0,0 +1,209 @@
+use futures::TryStreamExt;
+use libopenai::{
+    citation::CitationSource,
+    responses::{stream::ResponseEvent, Response, ResponseStatus},
+    test_utils::{file, json_mock, response, sse_mock, MockOpenAi, TEST_MODEL},
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+#[tokio::test]
+async fn creates_response() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock("POST", "/responses", response("Hello!")))
+        .await;
+    let client = mock.client().unwrap();
+
+    let response = Response::builder(TEST_MODEL, "Say hello")
+        .instructions("Be cheerful")
+        .build(&client)
+        .await
+        .unwrap();
+    assert_eq!(response.output_text(), "Hello!");
+
+    let request = &mock.received_json("/responses").await[0];
+    assert_eq!(request["input"], "Say hello");
+    assert_eq!(request["instructions"], "Be cheerful");
+}
+
+#[tokio::test]
+async fn streams_response_events() {
+    let mock = MockOpenAi::start().await;
+    let delta = |delta: &str| {
+        json!({
+            "type": "response.output_text.delta",
+            "item_id": "msg-test",
+            "output_index": 0,
+            "content_index": 0,
+            "delta": delta
+        })
+    };
+    mock.mount(sse_mock(
+        "POST",
+        "/responses",
+        [
+            json!({ "type": "response.created", "response": response("") }),
+            delta("Hello"),
+            delta(" there"),
+            json!({ "type": "response.completed", "response": response("Hello there") }),
+        ],
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let events = Response::new_stream(TEST_MODEL, "Say hello", &client)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 4);
+    assert!(matches!(events[0], ResponseEvent::Created { .. }));
+
+    let text = events
+        .iter()
+        .filter_map(|event| match event {
+            ResponseEvent::OutputTextDelta { delta, .. } => Some(delta.as_str()),
+            _ => None,
+        })
+        .collect::<String>();
+    assert_eq!(text, "Hello there");
+
+    let last = events.last().unwrap().final_response().unwrap();
+    assert_eq!(last.output_text(), "Hello there");
+
+    let request = &mock.received_json("/responses").await[0];
+    assert_eq!(request["stream"], true);
+}
+
+#[tokio::test]
+async fn resolves_citations() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "GET",
+        "/files/file-notes",
+        file("file-notes", "notes.md", "assistants"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let mut body = response("Paris is the capital of France.");
+    body["output"][0]["content"][0]["annotations"] = json!([
+        { "type": "file_citation", "index": 31, "file_id": "file-guide", "filename": "guide.pdf" },
+        { "type": "file_citation", "index": 31, "file_id": "file-notes" },
+        { "type": "url_citation", "start_index": 0, "end_index": 5, "url": "https://paris.fr", "title": "Paris" },
+        { "type": "file_citation", "index": 31, "file_id": "file-atlas", "filename": "atlas.pdf" },
+        { "type": "file_citation", "index": 31, "file_id": "file-deleted" }
+    ]);
+    body["output"].as_array_mut().unwrap().insert(
+        0,
+        json!({
+            "type": "file_search_call",
+            "id": "fs-test",
+            "status": "completed",
+            "results": [
+                { "file_id": "file-guide", "filename": "guide.pdf", "text": "Paris is the capital" },
+                { "file_id": "file-atlas", "filename": "atlas.pdf", "text": "Paris" },
+                { "file_id": "file-atlas", "filename": "atlas.pdf", "text": "France" }
+            ]
+        }),
+    );
+    let response = serde_json::from_value::<Response>(body).unwrap();
+
+    let citations = response.citations(&client).await.unwrap();
+    assert_eq!(citations.len(), 5);
+    assert_eq!(citations[0].span, 31..31);
+    assert_eq!(citations[0].quote.as_deref(), Some("Paris is the capital"));
+    assert_eq!(
+        citations[1].source,
+        CitationSource::File {
+            file_id: "file-notes".into(),
+            filename: Some("notes.md".into())
+        }
+    );
+    assert_eq!(citations[1].quote, None);
+    assert_eq!(citations[2].text, "Paris");
+    assert!(
+        matches!(citations[2].source, CitationSource::Url { ref url, .. } if url == "https://paris.fr")
+    );
+
+    // Files with many chunks found can't be quoted, and files that can't be retrieved have no name
+    assert_eq!(citations[3].quote, None);
+    assert_eq!(
+        citations[4].source,
+        CitationSource::File {
+            file_id: "file-deleted".into(),
+            filename: None
+        }
+    );
+}
+
+fn with_status(status: &str) -> Value {
+    let mut body = response(if status == "completed" { "Done!" } else { "" });
+    body["status"] = json!(status);
+    return body;
+}
+
+#[tokio::test]
+async fn polls_background_response() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock("POST", "/responses", with_status("queued")))
+        .await;
+    mock.mount(
+        json_mock("GET", "/responses/resp-test", with_status("in_progress"))
+            .up_to_n_times(2)
+            .with_priority(1),
+    )
+    .await;
+    mock.mount(json_mock(
+        "GET",
+        "/responses/resp-test",
+        with_status("completed"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let response = Response::builder(TEST_MODEL, "Think hard")
+        .background(true)
+        .build(&client)
+        .await
+        .unwrap();
+    assert_eq!(response.status, ResponseStatus::Queued);
+    assert!(!response.is_finished());
+
+    let response = response
+        .wait(Duration::from_millis(10), &client)
+        .await
+        .unwrap();
+    assert_eq!(response.status, ResponseStatus::Completed);
+    assert_eq!(response.output_text(), "Done!");
+    assert_eq!(
+        mock.received_json("/responses").await[0]["background"],
+        true
+    );
+}
+
+#[tokio::test]
+async fn cancels_background_response() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock("POST", "/responses", with_status("queued")))
+        .await;
+    mock.mount(json_mock(
+        "POST",
+        "/responses/resp-test/cancel",
+        with_status("cancelled"),
+    ))
+    .await;
+    let client = mock.client().unwrap();
+
+    let response = Response::builder(TEST_MODEL, "Think hard")
+        .background(true)
+        .build(&client)
+        .await
+        .unwrap()
+        .cancel(&client)
+        .await
+        .unwrap();
+    assert_eq!(response.status, ResponseStatus::Cancelled);
+    assert!(response.is_finished());
+}
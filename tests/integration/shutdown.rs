@@ -0,0 +1,66 @@
+use libopenai::{
+    completion::Completion,
+    error::Error,
+    test_utils::{completion, MockOpenAi, TEST_MODEL},
+};
+use std::time::Duration;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn slow_mock(delay: Duration) -> MockOpenAi {
+    let mock = MockOpenAi::start().await;
+    mock.mount(
+        Mock::given(method("POST"))
+            .and(path("/completions"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(completion("Hi"))
+                    .set_delay(delay),
+            ),
+    )
+    .await;
+    return mock;
+}
+
+#[tokio::test]
+async fn waits_for_in_flight_requests() {
+    let mock = slow_mock(Duration::from_millis(200)).await;
+    let client = mock.client().unwrap().with_usage_tracking();
+
+    let request = tokio::spawn({
+        let client = client.clone();
+        async move { Completion::new(TEST_MODEL, "Hello", &client).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let shutdown = client.shutdown(Duration::from_secs(5)).await;
+    assert!(client.is_shut_down());
+    assert_eq!(shutdown.aborted, 0);
+    assert_eq!(shutdown.usage.unwrap().total().total_tokens, 20);
+    assert_eq!(request.await.unwrap().unwrap().choices[0].text, "Hi");
+
+    let err = Completion::new(TEST_MODEL, "Hello", &client)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::ShutDown));
+    assert_eq!(mock.received_json("/completions").await.len(), 1);
+}
+
+#[tokio::test]
+async fn aborts_requests_after_timeout() {
+    let mock = slow_mock(Duration::from_secs(5)).await;
+    let client = mock.client().unwrap();
+
+    let request = tokio::spawn({
+        let client = client.clone();
+        async move { Completion::new(TEST_MODEL, "Hello", &client).await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let shutdown = client.shutdown(Duration::from_millis(100)).await;
+    assert_eq!(shutdown.aborted, 1);
+    assert!(shutdown.usage.is_none());
+    assert!(matches!(request.await.unwrap(), Err(Error::ShutDown)));
+}
@@ -0,0 +1,135 @@
+use futures::TryStreamExt;
+use libopenai::{
+    chat::{ChatCompletion, Message},
+    completion::Completion,
+    snapshot::SnapshotMode,
+    test_utils::{completion, json_mock, sse_mock, MockOpenAi, TEST_MODEL},
+};
+
+#[tokio::test]
+async fn replays_recorded_requests() {
+    let dir = std::env::temp_dir().join(format!("libopenai-integration-{}", rand::random::<u64>()));
+    let mock = MockOpenAi::start().await;
+    mock.mock_chat("Hello there!").await;
+    mock.mount(sse_mock(
+        "POST",
+        "/completions",
+        [completion("Once"), completion(" upon")],
+    ))
+    .await;
+
+    let recorder = mock
+        .client()
+        .unwrap()
+        .with_snapshots(&dir, SnapshotMode::Record);
+    let chat = ChatCompletion::new(TEST_MODEL, [Message::user("Say hello")], &recorder)
+        .await
+        .unwrap();
+    let stream = Completion::new_stream(TEST_MODEL, "Tell a story", &recorder)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+
+    // Replays against a server that answers nothing
+    let offline = MockOpenAi::start().await;
+    let replayer = offline
+        .client()
+        .unwrap()
+        .with_snapshots(&dir, SnapshotMode::Replay);
+    let replayed_chat = ChatCompletion::new(TEST_MODEL, [Message::user("Say hello")], &replayer)
+        .await
+        .unwrap();
+    assert_eq!(replayed_chat.id, chat.id);
+    assert_eq!(
+        replayed_chat.first().unwrap().message.content,
+        "Hello there!"
+    );
+
+    let replayed_stream = Completion::new_stream(TEST_MODEL, "Tell a story", &replayer)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(replayed_stream.len(), stream.len());
+    assert_eq!(replayed_stream[1].first().unwrap().text, " upon");
+    assert!(offline
+        .server()
+        .received_requests()
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Different requests, and repetitions beyond the recorded ones, have no snapshot
+    let err = ChatCompletion::new(TEST_MODEL, [Message::user("Say bye")], &replayer)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("No snapshot of POST"));
+    let err = ChatCompletion::new(TEST_MODEL, [Message::user("Say hello")], &replayer)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("No snapshot of POST"));
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn records_missing_snapshots_in_auto_mode() {
+    let dir = std::env::temp_dir().join(format!("libopenai-integration-{}", rand::random::<u64>()));
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "GET",
+        "/models/gpt-4o",
+        libopenai::test_utils::model("gpt-4o"),
+    ))
+    .await;
+
+    for _ in 0..2 {
+        let client = mock
+            .client()
+            .unwrap()
+            .with_snapshots(&dir, SnapshotMode::Auto);
+        let model = libopenai::model::Model::get("gpt-4o", &client)
+            .await
+            .unwrap();
+        assert_eq!(model.id, "gpt-4o");
+    }
+    assert_eq!(mock.server().received_requests().await.unwrap().len(), 1);
+
+    let files = std::fs::read_dir(&dir).unwrap().count();
+    assert_eq!(files, 1);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn skips_responses_not_read_to_their_end() {
+    let dir = std::env::temp_dir().join(format!("libopenai-integration-{}", rand::random::<u64>()));
+    let mock = MockOpenAi::start().await;
+    mock.mount(json_mock(
+        "GET",
+        "/models/gpt-4o",
+        libopenai::test_utils::model("gpt-4o"),
+    ))
+    .await;
+
+    let client = mock
+        .client()
+        .unwrap()
+        .with_snapshots(&dir, SnapshotMode::Record);
+    let response = client
+        .get(format!("{}/models/gpt-4o", mock.uri()))
+        .send()
+        .await
+        .unwrap();
+    drop(response);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+    // Read bodies are saved by the time they're returned
+    libopenai::model::Model::get("gpt-4o", &client)
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,70 @@
+use futures::TryStreamExt;
+use libopenai::{
+    chat::{ChatCompletion, Message},
+    completion::Completion,
+    embeddings::Embedding,
+    error::Error,
+    test_utils::{completion_stream_mock, error_mock, MockOpenAi, TEST_MODEL},
+};
+
+#[tokio::test]
+async fn chat_completion_from_mock() {
+    let mock = MockOpenAi::start().await;
+    mock.mock_chat("Hello from the mock").await;
+    let client = mock.client().unwrap();
+
+    let completion = ChatCompletion::new(TEST_MODEL, [Message::user("Hello")], &client)
+        .await
+        .expect("chat completion should succeed");
+
+    let choice = completion.first().expect("completion should have a choice");
+    assert_eq!(choice.message.content, "Hello from the mock");
+    assert_eq!(completion.usage.map(|usage| usage.total_tokens), Some(20));
+}
+
+#[tokio::test]
+async fn completion_stream_from_mock() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(completion_stream_mock(["Hello", " world"]))
+        .await;
+    let client = mock.client().unwrap();
+
+    let chunks = Completion::new_stream(TEST_MODEL, "Say hello", &client)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .expect("stream should be parsed");
+
+    assert_eq!(chunks.len(), 2);
+}
+
+#[tokio::test]
+async fn embeddings_from_mock() {
+    let mock = MockOpenAi::start().await;
+    mock.mock_embeddings(&[0.5, -0.5]).await;
+    let client = mock.client().unwrap();
+
+    let result = Embedding::new("text-embedding-3-small", "Hello", &client)
+        .await
+        .unwrap();
+
+    assert_eq!(result.data[0].embedding, [0.5, -0.5]);
+}
+
+#[tokio::test]
+async fn api_error_from_mock() {
+    let mock = MockOpenAi::start().await;
+    mock.mount(error_mock("POST", "/chat/completions", 400, "Bad request"))
+        .await;
+    let client = mock.client().unwrap();
+
+    let err = ChatCompletion::new(TEST_MODEL, [Message::user("Hello")], &client)
+        .await
+        .unwrap_err();
+
+    match err {
+        Error::OpenAI(e) => assert_eq!(e.message, "Bad request"),
+        other => panic!("unexpected error: {other}"),
+    }
+}
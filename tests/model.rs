@@ -0,0 +1,26 @@
+use libopenai::model::{info::KnownModel, Model};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct Models {
+    data: Vec<Model>,
+}
+
+#[test]
+fn deserializes_current_model_list() {
+    let models = serde_json::from_str::<Models>(include_str!("fixtures/models.json"))
+        .expect("model list should deserialize")
+        .data;
+
+    assert_eq!(models.len(), 3);
+    assert_eq!(models[0].id, "gpt-4o-mini");
+    assert_eq!(models[0].created_at.timestamp(), 1721172741);
+    assert!(models[0].permission.is_empty());
+    assert!(models[0].root.is_none());
+    assert!(models[0].parent.is_none());
+
+    assert_eq!(models[1].known(), Some(KnownModel::Gpt4oMini));
+
+    assert_eq!(models[2].permission.len(), 1);
+    assert_eq!(models[2].root.as_deref(), Some("text-davinci-003"));
+}
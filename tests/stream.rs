@@ -0,0 +1,80 @@
+use bytes::Bytes;
+use futures::{executor::block_on, stream, TryStreamExt};
+use libopenai::{completion::Completion, error::Error, OpenAiStream};
+
+/// A completion stream as sent by the API, ending with `data: [DONE]`
+const CAPTURE: &str = include_str!("fixtures/completion_stream.sse");
+
+/// Parses `body`, received in the specified network reads, returning the text of every chunk
+fn parse_reads<'a>(reads: impl IntoIterator<Item = &'a [u8]>) -> Vec<String> {
+    let reads = reads
+        .into_iter()
+        .map(|read| Ok::<_, Error>(Bytes::copy_from_slice(read)))
+        .collect::<Vec<_>>();
+
+    let stream = OpenAiStream::<Completion>::from_bytes_stream(stream::iter(reads));
+    return block_on(
+        stream
+            .map_ok(|completion| completion.choices[0].text.clone())
+            .try_collect(),
+    )
+    .expect("stream should be parsed");
+}
+
+/// Parses `body` received in reads of every size, checking they all yield the chunks of the capture
+fn assert_parses(body: &str) {
+    let expected = ["\n\n", "Hello", " there", "!", ""];
+    for size in 1..=body.len() {
+        assert_eq!(
+            parse_reads(body.as_bytes().chunks(size)),
+            expected,
+            "reads of {size} bytes"
+        );
+    }
+}
+
+#[test]
+fn parses_captured_stream() {
+    assert_parses(CAPTURE);
+}
+
+#[test]
+fn parses_crlf_line_endings() {
+    assert_parses(&CAPTURE.replace('\n', "\r\n"));
+}
+
+#[test]
+fn ends_without_trailing_newline() {
+    assert_parses(CAPTURE.trim_end());
+    assert_parses(CAPTURE.trim_end().strip_suffix("data: [DONE]").unwrap());
+    assert_parses(&format!("{}\n \n", CAPTURE.trim_end()));
+}
+
+#[test]
+fn detects_done_variants() {
+    for done in [
+        "data:[DONE]",
+        "data: [done]",
+        "data:  [DONE] \t",
+        "data: [Done]\r",
+    ] {
+        let body = CAPTURE.replace("data: [DONE]", done);
+        assert_parses(&body);
+    }
+}
+
+#[test]
+fn ignores_events_after_done() {
+    let body = format!("{CAPTURE}data: {{\"not\": \"a completion\"}}\n\n");
+    assert_eq!(parse_reads([body.as_bytes()]).len(), 5);
+}
+
+#[test]
+fn parses_data_and_done_in_one_read() {
+    // The last chunk and `[DONE]` usually arrive in the same network read
+    let (head, tail) = CAPTURE.split_at(CAPTURE.rfind("data: {").unwrap());
+    assert_eq!(
+        parse_reads([head.as_bytes(), tail.as_bytes()]),
+        ["\n\n", "Hello", " there", "!", ""]
+    );
+}